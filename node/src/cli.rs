@@ -1,4 +1,5 @@
 use sc_cli::RunCmd;
+use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -43,4 +44,32 @@ pub enum Subcommand {
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Proof of history utilities that work without a running node.
+	#[command(subcommand)]
+	Poh(PohSubcommand),
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum PohSubcommand {
+	/// Verify an exported proof chain offline.
+	Verify(PohVerifyCmd),
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct PohVerifyCmd {
+	/// File holding the exported proof records, oldest first.
+	pub path: PathBuf,
+
+	/// Encoding of the file.
+	#[arg(long, value_enum, default_value_t = PohBundleFormat::Json)]
+	pub format: PohBundleFormat,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum PohBundleFormat {
+	/// A JSON array of proof records.
+	Json,
+	/// A SCALE encoded list of proof records.
+	Scale,
 }
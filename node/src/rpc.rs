@@ -9,22 +9,26 @@ use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
 use sc_transaction_pool_api::TransactionPool;
-use healer_network_runtime::{opaque::Block, AccountId, Balance, Nonce};
+use healer_network_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Nonce, Runtime, Signature};
+use pallet_medical_record::{ChangeHistory, ClinicalTest, PatientInfo};
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_core::offchain::OffchainStorage;
 
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, S> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
+	/// Offchain database, if the backend has one.
+	pub offchain_storage: Option<S>,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, S>(
+	deps: FullDeps<C, P, S>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
 	C: ProvideRuntimeApi<Block>,
@@ -32,17 +36,39 @@ where
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: pallet_medical_record_rpc::MedicalRecordRuntimeApi<
+		Block,
+		AccountId,
+		BlockNumber,
+		PatientInfo<Runtime>,
+		ClinicalTest<Runtime>,
+		ChangeHistory<Runtime>,
+	>,
+	C::Api: pallet_medical_record_rpc::MedicalDocumentsRuntimeApi<Block, AccountId>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
+	S: OffchainStorage + 'static,
 {
+	use pallet_medical_record_rpc::{
+		MedicalDocuments, MedicalDocumentsApiServer, MedicalRecord, MedicalRecordApiServer,
+	};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool } = deps;
+	let FullDeps { client, pool, offchain_storage } = deps;
 
 	module.merge(System::new(client.clone(), pool).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(MedicalRecord::new(client.clone()).into_rpc())?;
+	// Documents are kept in the offchain database once their uploader hands them to this node
+	if let Some(storage) = offchain_storage {
+		module.merge(
+			MedicalDocumentsApiServer::<AccountId, BlockNumber, Signature>::into_rpc(
+				MedicalDocuments::new(client, storage),
+			),
+		)?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -1,15 +1,214 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-ai-prediction
 
 use super::*;
 
 #[allow(unused)]
 use crate::Pallet as AiPrediction;
 use frame_benchmarking::v2::*;
+use frame_support::{
+	sp_runtime::{traits::Hash, Permill},
+	traits::{EnsureOrigin, Get},
+	BoundedVec,
+};
 use frame_system::RawOrigin;
 
+// Register a model owned by `owner` and approve it
+fn approved_model<T: Config>(owner: T::AccountId) -> u32 {
+	let model_id = NextModelId::<T>::get();
+	AiPrediction::<T>::register_model(RawOrigin::Signed(owner).into(), T::Hash::default(), T::Hash::default())
+		.unwrap();
+	let admin = T::AdminOrigin::try_successful_origin().unwrap();
+	AiPrediction::<T>::approve_model(admin, model_id).unwrap();
+	model_id
+}
+
+// Request prediction 0 and store it as disputed, with conflicting results from `s` workers
+fn disputed_prediction<T: Config>(s: u32) {
+	let caller: T::AccountId = whitelisted_caller();
+	let model_id = approved_model::<T>(account("owner", 0, 0));
+	T::PatientRecords::register_patient(0, &caller);
+	AiPrediction::<T>::request_prediction(RawOrigin::Signed(caller).into(), model_id, 0).unwrap();
+
+	let submissions: Vec<_> = (0..s)
+		.map(|i| {
+			let worker: T::AccountId = account("worker", i, 0);
+			Workers::<T>::insert(&worker, ());
+			Submission {
+				worker,
+				result_hash: T::Hashing::hash_of(&i),
+				confidence: Permill::from_percent(90),
+				submitted_at: frame_system::Pallet::<T>::block_number(),
+			}
+		})
+		.collect();
+	Submissions::<T>::insert(0, BoundedVec::truncate_from(submissions));
+	Predictions::<T>::mutate(0, |prediction| {
+		prediction.as_mut().unwrap().status = PredictionStatus::Disputed
+	});
+}
+
 #[benchmarks]
 mod benchmarks {
 	use super::*;
 
+	#[benchmark]
+	fn register_model() {
+		let caller: T::AccountId = whitelisted_caller();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), T::Hash::default(), T::Hash::default());
+
+		assert!(Models::<T>::contains_key(0));
+	}
+
+	#[benchmark]
+	fn register_model_version() {
+		let caller: T::AccountId = whitelisted_caller();
+		let model_id = approved_model::<T>(caller.clone());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), model_id, T::Hash::default(), T::Hash::default());
+
+		assert_eq!(Models::<T>::get(model_id).unwrap().version, 2);
+	}
+
+	#[benchmark]
+	fn approve_model() -> Result<(), BenchmarkError> {
+		let caller: T::AccountId = whitelisted_caller();
+		AiPrediction::<T>::register_model(
+			RawOrigin::Signed(caller).into(),
+			T::Hash::default(),
+			T::Hash::default(),
+		)
+		.unwrap();
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, 0);
+
+		assert_eq!(Models::<T>::get(0).unwrap().status, ModelStatus::Approved);
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn retire_model() -> Result<(), BenchmarkError> {
+		let caller: T::AccountId = whitelisted_caller();
+		let model_id = approved_model::<T>(caller);
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, model_id);
+
+		assert_eq!(Models::<T>::get(model_id).unwrap().status, ModelStatus::Retired);
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn add_worker() -> Result<(), BenchmarkError> {
+		let worker: T::AccountId = account("worker", 0, 0);
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, worker.clone());
+
+		assert!(Workers::<T>::contains_key(worker));
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn remove_worker() -> Result<(), BenchmarkError> {
+		let worker: T::AccountId = account("worker", 0, 0);
+		Workers::<T>::insert(&worker, ());
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, worker.clone());
+
+		assert!(!Workers::<T>::contains_key(worker));
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn request_prediction() {
+		let caller: T::AccountId = whitelisted_caller();
+		let model_id = approved_model::<T>(account("owner", 0, 0));
+		T::PatientRecords::register_patient(0, &caller);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), model_id, 0);
+
+		assert!(Predictions::<T>::contains_key(0));
+	}
+
+	// Worst case: the submission reaches the quorum with `s` earlier results, the ones that do
+	// not match it are penalised
+	#[benchmark]
+	fn submit_prediction(
+		s: Linear<
+			{ T::QuorumThreshold::get() - 1 },
+			{ if T::QuorumThreshold::get() > 1 { T::MaxWorkers::get() - 1 } else { 0 } },
+		>,
+	) {
+		let caller: T::AccountId = whitelisted_caller();
+		let model_id = approved_model::<T>(account("owner", 0, 0));
+		T::PatientRecords::register_patient(0, &caller);
+		AiPrediction::<T>::request_prediction(RawOrigin::Signed(caller.clone()).into(), model_id, 0)
+			.unwrap();
+
+		for i in 0..s {
+			let worker: T::AccountId = account("worker", i, 0);
+			Workers::<T>::insert(&worker, ());
+			let result_hash = if i < T::QuorumThreshold::get() - 1 {
+				T::Hash::default()
+			} else {
+				T::Hashing::hash_of(&i)
+			};
+			AiPrediction::<T>::submit_prediction(
+				RawOrigin::Signed(worker).into(),
+				0,
+				result_hash,
+				Permill::from_percent(90),
+			)
+			.unwrap();
+		}
+		Workers::<T>::insert(&caller, ());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), 0, T::Hash::default(), Permill::from_percent(90));
+
+		assert_eq!(Predictions::<T>::get(0).unwrap().status, PredictionStatus::Finalised);
+	}
+
+	// Worst case: every worker submitted a result and none matches the settled one
+	#[benchmark]
+	fn resolve_prediction(s: Linear<0, { T::MaxWorkers::get() }>) -> Result<(), BenchmarkError> {
+		disputed_prediction::<T>(s);
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, 0, T::Hash::default(), Permill::from_percent(90));
+
+		assert_eq!(Predictions::<T>::get(0).unwrap().status, PredictionStatus::Finalised);
+
+		Ok(())
+	}
+
+	#[benchmark]
+	fn reject_prediction() -> Result<(), BenchmarkError> {
+		disputed_prediction::<T>(2);
+		let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, 0);
+
+		assert_eq!(Predictions::<T>::get(0).unwrap().status, PredictionStatus::Rejected);
+
+		Ok(())
+	}
+
 	impl_benchmark_test_suite!(AiPrediction, crate::mock::new_test_ext(), crate::mock::Test);
 }
@@ -19,16 +19,65 @@ mod tests;
 // for each dispatchable and generates this pallet's weight.rs file. Learn more about benchmarking here: https://docs.substrate.io/test/benchmark/
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod offchain;
 pub mod weights;
 pub use weights::*;
 
+use frame_support::sp_runtime::KeyTypeId;
+use scale_info::prelude::vec::Vec;
+
+/// Key type of the keystore keys inference workers sign prediction results with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"aipr");
+
+/// Application crypto for inference worker keys.
+pub mod crypto {
+	use super::KEY_TYPE;
+	use sp_runtime::{
+		app_crypto::{app_crypto, sr25519},
+		MultiSignature, MultiSigner,
+	};
+
+	app_crypto!(sr25519, KEY_TYPE);
+
+	/// Signs `submit_prediction` transactions with an `aipr` key from the node's keystore.
+	pub struct AiWorkerAuthId;
+
+	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for AiWorkerAuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+}
+
+/// The patient records predictions are run against.
+///
+/// Implemented by the runtime on top of the medical record pallet, so this pallet only needs to
+/// know whether a patient exists and whether an account may use their records.
+pub trait PatientRecords<AccountId> {
+	/// Whether a patient with this id is registered.
+	fn patient_exists(patient_id: u32) -> bool;
+	/// Whether `who` may run a prediction over the patient's records.
+	fn can_request_prediction(patient_id: u32, who: &AccountId) -> bool;
+	/// The encoded records of a patient, sent to the inference endpoint by the offchain worker.
+	fn record_payload(patient_id: u32) -> Option<Vec<u8>>;
+	/// Register a patient `who` may request predictions for.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn register_patient(patient_id: u32, who: &AccountId);
+}
+
 // All pallet logic is defined in its own module and must be annotated by the `pallet` attribute.
 #[frame_support::pallet]
 pub mod pallet {
 	// Import various useful types required by all FRAME pallets.
 	use super::*;
-	use frame_support::pallet_prelude::*;
-	use frame_system::pallet_prelude::*;
+	use frame_support::{
+		pallet_prelude::*,
+		sp_runtime::Permill,
+	};
+	use frame_system::{
+		offchain::{AppCrypto, CreateSignedTransaction},
+		pallet_prelude::*,
+	};
 
 	// The `Pallet` struct serves as a placeholder to implement traits, methods and dispatchables
 	// (`Call`s) in this pallet.
@@ -41,124 +90,750 @@ pub mod pallet {
 	/// These types are defined generically and made concrete when the pallet is declared in the
 	/// `runtime/src/lib.rs` file of your chain.
 	#[pallet::config]
-	pub trait Config: frame_system::Config {
+	pub trait Config: CreateSignedTransaction<Call<Self>> + frame_system::Config {
 		/// The overarching runtime event type.
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		/// A type representing the weights required by the dispatchables of this pallet.
 		type WeightInfo: WeightInfo;
+		/// Origin allowed to approve and retire models and to manage inference workers.
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Patient records that predictions reference.
+		type PatientRecords: PatientRecords<Self::AccountId>;
+		/// Crypto the offchain worker signs prediction results with.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+
+		/// Maximum number of pending predictions the offchain worker runs in a single block.
+		#[pallet::constant]
+		type MaxPredictionsPerBlock: Get<u32>;
+		/// Maximum number of registered inference workers.
+		#[pallet::constant]
+		type MaxWorkers: Get<u32>;
+		/// Number of workers that must submit matching results before a prediction is finalised.
+		#[pallet::constant]
+		type QuorumThreshold: Get<u32>;
+		/// How far apart the confidences of two results with the same hash may be and still
+		/// count as matching.
+		#[pallet::constant]
+		type ConfidenceTolerance: Get<Permill>;
 	}
 
-	/// A storage item for this pallet.
-	///
-	/// In this template, we are declaring a storage item called `Something` that stores a single
-	/// `u32` value. Learn more about runtime storage here: <https://docs.substrate.io/build/runtime-storage/>
+	// Approval status of a registered model
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+		DecodeWithMemTracking,
+	)]
+	pub enum ModelStatus {
+		/// Registered or updated, waiting for review.
+		Pending,
+		/// Approved for predictions.
+		Approved,
+		/// Withdrawn from use, no new predictions can be requested.
+		Retired,
+	}
+
+	// A model in the registry. Only the current version is kept on-chain; predictions record the
+	// version they were requested against.
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct ModelInfo<T: Config> {
+		pub model_id: u32,
+		pub version: u32,
+		pub weights_hash: T::Hash,
+		pub input_schema_hash: T::Hash,
+		pub owner: T::AccountId,
+		pub status: ModelStatus,
+		pub registered_at: BlockNumberFor<T>,
+		pub last_modified_at: BlockNumberFor<T>,
+	}
+
+	// Where a prediction is in the attestation process
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+		DecodeWithMemTracking,
+	)]
+	pub enum PredictionStatus {
+		/// Waiting for workers to submit results.
+		Pending,
+		/// Workers have submitted conflicting results, or every worker has answered, and no
+		/// quorum has been reached yet.
+		Disputed,
+		/// A quorum of workers agreed on the result, or the admin settled a dispute on it.
+		Finalised,
+		/// The admin rejected a disputed prediction, no result will be accepted for it.
+		Rejected,
+	}
+
+	// Result submitted by a single inference worker
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Submission<T: Config> {
+		pub worker: T::AccountId,
+		pub result_hash: T::Hash,
+		pub confidence: Permill,
+		pub submitted_at: BlockNumberFor<T>,
+	}
+
+	// Result a quorum of workers agreed on
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct PredictionResult<T: Config> {
+		pub result_hash: T::Hash,
+		/// Mean confidence of the matching submissions.
+		pub confidence: Permill,
+		/// Number of workers that submitted a matching result.
+		pub attestations: u32,
+		pub finalised_at: BlockNumberFor<T>,
+	}
+
+	// Track record of an inference worker
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+	)]
+	pub struct WorkerReputation {
+		/// Finalised predictions the worker's result matched.
+		pub attested: u32,
+		/// Finalised predictions the worker's result did not match.
+		pub outliers: u32,
+	}
+
+	// A prediction requested for a patient, with its result once a worker has submitted one
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Prediction<T: Config> {
+		pub prediction_id: u32,
+		pub model_id: u32,
+		pub model_version: u32,
+		pub patient_id: u32,
+		pub requested_by: T::AccountId,
+		pub requested_at: BlockNumberFor<T>,
+		pub status: PredictionStatus,
+		pub result: Option<PredictionResult<T>>,
+	}
+
+	// Storage for the model registry
+	#[pallet::storage]
+	#[pallet::getter(fn models)]
+	pub type Models<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // model_id
+		ModelInfo<T>,
+		OptionQuery
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn next_model_id)]
+	pub type NextModelId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	// Storage for the prediction ledger
+	#[pallet::storage]
+	#[pallet::getter(fn predictions)]
+	pub type Predictions<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // prediction_id
+		Prediction<T>,
+		OptionQuery
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn next_prediction_id)]
+	pub type NextPredictionId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	// Predictions still waiting for a result, picked up by the offchain worker
+	#[pallet::storage]
+	#[pallet::getter(fn pending_predictions)]
+	pub type PendingPredictions<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // prediction_id
+		(),
+		OptionQuery
+	>;
+
+	// Results submitted so far for each prediction
+	#[pallet::storage]
+	#[pallet::getter(fn submissions)]
+	pub type Submissions<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // prediction_id
+		BoundedVec<Submission<T>, T::MaxWorkers>,
+		ValueQuery
+	>;
+
+	// Accounts allowed to submit prediction results
+	#[pallet::storage]
+	#[pallet::getter(fn workers)]
+	pub type Workers<T: Config> = CountedStorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+	// Reputation of every account that has taken part in a finalised prediction
 	#[pallet::storage]
-	pub type Something<T> = StorageValue<_, u32>;
+	#[pallet::getter(fn worker_reputation)]
+	pub type WorkerReputations<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		WorkerReputation,
+		ValueQuery
+	>;
 
 	/// Events that functions in this pallet can emit.
-	///
-	/// Events are a simple means of indicating to the outside world (such as dApps, chain explorers
-	/// or other users) that some notable update in the runtime has occurred. In a FRAME pallet, the
-	/// documentation for each event field and its parameters is added to a node's metadata so it
-	/// can be used by external interfaces or tools.
-	///
-	///	The `generate_deposit` macro generates a function on `Pallet` called `deposit_event` which
-	/// will convert the event type of your pallet into `RuntimeEvent` (declared in the pallet's
-	/// [`Config`] trait) and deposit it using [`frame_system::Pallet::deposit_event`].
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// A user has successfully set a new value.
-		SomethingStored {
-			/// The new value set.
-			something: u32,
-			/// The account who set the new value.
-			who: T::AccountId,
+		/// A model has been added to the registry and awaits approval.
+		ModelRegistered {
+			model_id: u32,
+			owner: T::AccountId,
+			weights_hash: T::Hash,
+		},
+		/// The owner has published a new version of a model, which awaits approval.
+		ModelVersionRegistered {
+			model_id: u32,
+			version: u32,
+			weights_hash: T::Hash,
+		},
+		/// A model version has been approved for predictions.
+		ModelApproved {
+			model_id: u32,
+			version: u32,
+		},
+		/// A model has been retired.
+		ModelRetired {
+			model_id: u32,
+		},
+		/// An account has been allowed to submit prediction results.
+		WorkerAdded {
+			worker: T::AccountId,
+		},
+		/// An account is no longer allowed to submit prediction results.
+		WorkerRemoved {
+			worker: T::AccountId,
+		},
+		/// A prediction has been requested for a patient.
+		PredictionRequested {
+			prediction_id: u32,
+			model_id: u32,
+			model_version: u32,
+			patient_id: u32,
+			requested_by: T::AccountId,
+		},
+		/// A worker has submitted the result of a prediction.
+		PredictionSubmitted {
+			prediction_id: u32,
+			result_hash: T::Hash,
+			confidence: Permill,
+			worker: T::AccountId,
+		},
+		/// A quorum of workers submitted matching results and the prediction is final.
+		PredictionFinalised {
+			prediction_id: u32,
+			result_hash: T::Hash,
+			confidence: Permill,
+			attestations: u32,
+		},
+		/// Workers have submitted conflicting results for a prediction.
+		PredictionDisputed {
+			prediction_id: u32,
+		},
+		/// The admin rejected a disputed prediction.
+		PredictionRejected {
+			prediction_id: u32,
+		},
+		/// A worker's result did not match the finalised result and its reputation was lowered.
+		WorkerPenalised {
+			worker: T::AccountId,
+			prediction_id: u32,
 		},
 	}
 
 	/// Errors that can be returned by this pallet.
-	///
-	/// Errors tell users that something went wrong so it's important that their naming is
-	/// informative. Similar to events, error documentation is added to a node's metadata so it's
-	/// equally important that they have helpful documentation associated with them.
-	///
-	/// This type of runtime error can be up to 4 bytes in size should you want to return additional
-	/// information.
 	#[pallet::error]
 	pub enum Error<T> {
-		/// The value retrieved was `None` as no value was previously set.
-		NoneValue,
-		/// There was an attempt to increment the value in storage over `u32::MAX`.
+		/// There was an attempt to increment an id counter over `u32::MAX`.
 		StorageOverflow,
+		/// The model is not in the registry.
+		ModelNotFound,
+		/// Only the owner of a model can publish new versions of it.
+		NotModelOwner,
+		/// The model is not approved for predictions.
+		ModelNotApproved,
+		/// The model has been retired.
+		ModelRetired,
+		/// The model version is already approved.
+		ModelAlreadyApproved,
+		/// The patient does not exist.
+		PatientNotFound,
+		/// The caller may not run predictions over this patient's records.
+		NotAuthorized,
+		/// The account is already a worker.
+		WorkerAlreadyAdded,
+		/// The account is not a worker.
+		NotWorker,
+		/// The prediction does not exist.
+		PredictionNotFound,
+		/// The worker has already submitted a result for this prediction.
+		PredictionAlreadySubmitted,
+		/// The prediction has already been finalised.
+		PredictionAlreadyFinalised,
+		/// The prediction has been rejected.
+		PredictionAlreadyRejected,
+		/// Only disputed predictions can be settled by the admin.
+		PredictionNotDisputed,
+		/// The maximum number of workers has been reached.
+		TooManyWorkers,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		/// Run pending predictions against the local inference endpoint and submit the results.
+		/// Only does anything on nodes holding a worker key in their keystore.
+		fn offchain_worker(block_number: BlockNumberFor<T>) {
+			Self::process_pending_predictions(block_number);
+		}
+
+		fn integrity_test() {
+			assert!(
+				(1..=T::MaxWorkers::get()).contains(&T::QuorumThreshold::get()),
+				"QuorumThreshold must be between 1 and MaxWorkers"
+			);
+		}
 	}
 
 	/// The pallet's dispatchable functions ([`Call`]s).
-	///
-	/// Dispatchable functions allows users to interact with the pallet and invoke state changes.
-	/// These functions materialize as "extrinsics", which are often compared to transactions.
-	/// They must always return a `DispatchResult` and be annotated with a weight and call index.
-	///
-	/// The [`call_index`] macro is used to explicitly
-	/// define an index for calls in the [`Call`] enum. This is useful for pallets that may
-	/// introduce new dispatchables over time. If the order of a dispatchable changes, its index
-	/// will also change which will break backwards compatibility.
-	///
-	/// The [`weight`] macro is used to assign a weight to each call.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		/// An example dispatchable that takes a single u32 value as a parameter, writes the value
-		/// to storage and emits an event.
-		///
-		/// It checks that the _origin_ for this call is _Signed_ and returns a dispatch
-		/// error if it isn't. Learn more about origins here: <https://docs.substrate.io/build/origins/>
+		/// Register a new model. The caller becomes its owner and the model starts at version 1,
+		/// pending approval.
 		#[pallet::call_index(0)]
-		#[pallet::weight(T::WeightInfo::do_something())]
-		pub fn do_something(origin: OriginFor<T>, something: u32) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			let who = ensure_signed(origin)?;
+		#[pallet::weight(T::WeightInfo::register_model())]
+		pub fn register_model(
+			origin: OriginFor<T>,
+			weights_hash: T::Hash,
+			input_schema_hash: T::Hash,
+		) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+			let block_number = <frame_system::Pallet<T>>::block_number();
 
-			// Update storage.
-			Something::<T>::put(something);
+			let model_id = NextModelId::<T>::get();
+			NextModelId::<T>::put(model_id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?);
 
-			// Emit an event.
-			Self::deposit_event(Event::SomethingStored { something, who });
+			Models::<T>::insert(
+				model_id,
+				ModelInfo {
+					model_id,
+					version: 1,
+					weights_hash,
+					input_schema_hash,
+					owner: owner.clone(),
+					status: ModelStatus::Pending,
+					registered_at: block_number,
+					last_modified_at: block_number,
+				},
+			);
+
+			Self::deposit_event(Event::ModelRegistered { model_id, owner, weights_hash });
 
-			// Return a successful `DispatchResult`
 			Ok(())
 		}
 
-		/// An example dispatchable that may throw a custom error.
-		///
-		/// It checks that the caller is a signed origin and reads the current value from the
-		/// `Something` storage item. If a current value exists, it is incremented by 1 and then
-		/// written back to storage.
-		///
-		/// ## Errors
-		///
-		/// The function will return an error under the following conditions:
-		///
-		/// - If no value has been set ([`Error::NoneValue`])
-		/// - If incrementing the value in storage causes an arithmetic overflow
-		///   ([`Error::StorageOverflow`])
+		/// Publish a new version of a model. The version number is bumped and the model goes back
+		/// to pending until the new weights are approved.
 		#[pallet::call_index(1)]
-		#[pallet::weight(T::WeightInfo::cause_error())]
-		pub fn cause_error(origin: OriginFor<T>) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
-
-			// Read a value from storage.
-			match Something::<T>::get() {
-				// Return an error if the value has not been set.
-				None => Err(Error::<T>::NoneValue.into()),
-				Some(old) => {
-					// Increment the value read from storage. This will cause an error in the event
-					// of overflow.
-					let new = old.checked_add(1).ok_or(Error::<T>::StorageOverflow)?;
-					// Update the value in storage with the incremented result.
-					Something::<T>::put(new);
-					Ok(())
+		#[pallet::weight(T::WeightInfo::register_model_version())]
+		pub fn register_model_version(
+			origin: OriginFor<T>,
+			model_id: u32,
+			weights_hash: T::Hash,
+			input_schema_hash: T::Hash,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let version = Models::<T>::try_mutate(model_id, |model_opt| -> Result<u32, DispatchError> {
+				let model = model_opt.as_mut().ok_or(Error::<T>::ModelNotFound)?;
+				ensure!(model.owner == who, Error::<T>::NotModelOwner);
+				ensure!(model.status != ModelStatus::Retired, Error::<T>::ModelRetired);
+
+				model.version = model.version.checked_add(1).ok_or(Error::<T>::StorageOverflow)?;
+				model.weights_hash = weights_hash;
+				model.input_schema_hash = input_schema_hash;
+				model.status = ModelStatus::Pending;
+				model.last_modified_at = <frame_system::Pallet<T>>::block_number();
+
+				Ok(model.version)
+			})?;
+
+			Self::deposit_event(Event::ModelVersionRegistered { model_id, version, weights_hash });
+
+			Ok(())
+		}
+
+		/// Approve the current version of a model for predictions.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::approve_model())]
+		pub fn approve_model(origin: OriginFor<T>, model_id: u32) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let version = Models::<T>::try_mutate(model_id, |model_opt| -> Result<u32, DispatchError> {
+				let model = model_opt.as_mut().ok_or(Error::<T>::ModelNotFound)?;
+				ensure!(model.status != ModelStatus::Retired, Error::<T>::ModelRetired);
+				ensure!(model.status != ModelStatus::Approved, Error::<T>::ModelAlreadyApproved);
+
+				model.status = ModelStatus::Approved;
+				model.last_modified_at = <frame_system::Pallet<T>>::block_number();
+
+				Ok(model.version)
+			})?;
+
+			Self::deposit_event(Event::ModelApproved { model_id, version });
+
+			Ok(())
+		}
+
+		/// Retire a model, pending or approved. Existing predictions are kept, but no new ones can
+		/// be requested.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::retire_model())]
+		pub fn retire_model(origin: OriginFor<T>, model_id: u32) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Models::<T>::try_mutate(model_id, |model_opt| -> DispatchResult {
+				let model = model_opt.as_mut().ok_or(Error::<T>::ModelNotFound)?;
+				ensure!(model.status != ModelStatus::Retired, Error::<T>::ModelRetired);
+
+				model.status = ModelStatus::Retired;
+				model.last_modified_at = <frame_system::Pallet<T>>::block_number();
+
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ModelRetired { model_id });
+
+			Ok(())
+		}
+
+		/// Allow an account to submit prediction results.
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::add_worker())]
+		pub fn add_worker(origin: OriginFor<T>, worker: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(!Workers::<T>::contains_key(&worker), Error::<T>::WorkerAlreadyAdded);
+			ensure!(Workers::<T>::count() < T::MaxWorkers::get(), Error::<T>::TooManyWorkers);
+			Workers::<T>::insert(&worker, ());
+
+			Self::deposit_event(Event::WorkerAdded { worker });
+
+			Ok(())
+		}
+
+		/// Stop an account from submitting prediction results.
+		#[pallet::call_index(5)]
+		#[pallet::weight(T::WeightInfo::remove_worker())]
+		pub fn remove_worker(origin: OriginFor<T>, worker: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ensure!(Workers::<T>::contains_key(&worker), Error::<T>::NotWorker);
+			Workers::<T>::remove(&worker);
+
+			Self::deposit_event(Event::WorkerRemoved { worker });
+
+			Ok(())
+		}
+
+		/// Request a prediction from an approved model over a patient's records. The caller must
+		/// be allowed to use the patient's records.
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::request_prediction())]
+		pub fn request_prediction(
+			origin: OriginFor<T>,
+			model_id: u32,
+			patient_id: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let model = Models::<T>::get(model_id).ok_or(Error::<T>::ModelNotFound)?;
+			ensure!(model.status == ModelStatus::Approved, Error::<T>::ModelNotApproved);
+			ensure!(T::PatientRecords::patient_exists(patient_id), Error::<T>::PatientNotFound);
+			ensure!(
+				T::PatientRecords::can_request_prediction(patient_id, &who),
+				Error::<T>::NotAuthorized
+			);
+
+			let prediction_id = NextPredictionId::<T>::get();
+			NextPredictionId::<T>::put(prediction_id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?);
+
+			Predictions::<T>::insert(
+				prediction_id,
+				Prediction {
+					prediction_id,
+					model_id,
+					model_version: model.version,
+					patient_id,
+					requested_by: who.clone(),
+					requested_at: <frame_system::Pallet<T>>::block_number(),
+					status: PredictionStatus::Pending,
+					result: None,
 				},
+			);
+			PendingPredictions::<T>::insert(prediction_id, ());
+
+			Self::deposit_event(Event::PredictionRequested {
+				prediction_id,
+				model_id,
+				model_version: model.version,
+				patient_id,
+				requested_by: who,
+			});
+
+			Ok(())
+		}
+
+		/// Submit the result of a pending prediction. Only the hash of the result is stored; the
+		/// result itself stays off-chain with the rest of the patient's data.
+		///
+		/// The prediction is finalised once `QuorumThreshold` workers have submitted the same
+		/// result hash with confidences within `ConfidenceTolerance` of this one. Workers whose
+		/// results do not match the finalised one are recorded as outliers in their reputation.
+		/// A result that conflicts with an earlier one marks the prediction as disputed. Results
+		/// of accounts that have stopped being workers since no longer count.
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::submit_prediction(T::MaxWorkers::get()))]
+		pub fn submit_prediction(
+			origin: OriginFor<T>,
+			prediction_id: u32,
+			result_hash: T::Hash,
+			confidence: Permill,
+		) -> DispatchResult {
+			let worker = ensure_signed(origin)?;
+			ensure!(Workers::<T>::contains_key(&worker), Error::<T>::NotWorker);
+			let block_number = <frame_system::Pallet<T>>::block_number();
+
+			let mut prediction = Predictions::<T>::get(prediction_id).ok_or(Error::<T>::PredictionNotFound)?;
+			Self::ensure_open(&prediction)?;
+
+			let mut submissions = Submissions::<T>::get(prediction_id);
+			// Removed workers are no longer trusted, and would take up the room of new ones
+			submissions.retain(|earlier| Workers::<T>::contains_key(&earlier.worker));
+			ensure!(
+				!submissions.iter().any(|submission| submission.worker == worker),
+				Error::<T>::PredictionAlreadySubmitted
+			);
+			let submission = Submission { worker: worker.clone(), result_hash, confidence, submitted_at: block_number };
+			let conflicting = submissions.iter().any(|earlier| !Self::results_match(earlier, &submission));
+			submissions.try_push(submission.clone()).map_err(|_| Error::<T>::TooManyWorkers)?;
+
+			Self::deposit_event(Event::PredictionSubmitted {
+				prediction_id,
+				result_hash,
+				confidence,
+				worker,
+			});
+
+			let (matching, outliers): (Vec<_>, Vec<_>) =
+				submissions.iter().partition(|other| Self::results_match(other, &submission));
+
+			if matching.len() as u32 >= T::QuorumThreshold::get() {
+				let confidence = Permill::from_parts(
+					(matching.iter().map(|m| m.confidence.deconstruct() as u64).sum::<u64>() /
+						matching.len() as u64) as u32,
+				);
+				Self::finalise(&mut prediction, result_hash, confidence, matching, outliers);
+			} else {
+				// Every worker has answered and there is still no quorum, stop offering it to them
+				// and leave it to the admin, even if they all agree
+				let stalled = submissions.len() as u32 >= Workers::<T>::count();
+				if stalled {
+					PendingPredictions::<T>::remove(prediction_id);
+				}
+				if (conflicting || stalled) && prediction.status == PredictionStatus::Pending {
+					prediction.status = PredictionStatus::Disputed;
+					Self::deposit_event(Event::PredictionDisputed { prediction_id });
+				}
 			}
+
+			Submissions::<T>::insert(prediction_id, submissions);
+			Predictions::<T>::insert(prediction_id, prediction);
+
+			Ok(())
+		}
+
+		/// Settle a disputed prediction on a result. Workers whose results match it are credited,
+		/// the others are recorded as outliers, as if a quorum had agreed on it.
+		#[pallet::call_index(8)]
+		#[pallet::weight(T::WeightInfo::resolve_prediction(T::MaxWorkers::get()))]
+		pub fn resolve_prediction(
+			origin: OriginFor<T>,
+			prediction_id: u32,
+			result_hash: T::Hash,
+			confidence: Permill,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let mut prediction = Predictions::<T>::get(prediction_id).ok_or(Error::<T>::PredictionNotFound)?;
+			Self::ensure_disputed(&prediction)?;
+
+			let submissions = Submissions::<T>::get(prediction_id);
+			let (matching, outliers): (Vec<_>, Vec<_>) = submissions
+				.iter()
+				.filter(|submission| Workers::<T>::contains_key(&submission.worker))
+				.partition(|submission| {
+					submission.result_hash == result_hash &&
+						Self::confidences_match(submission.confidence, confidence)
+				});
+			Self::finalise(&mut prediction, result_hash, confidence, matching, outliers);
+			Predictions::<T>::insert(prediction_id, prediction);
+
+			Ok(())
+		}
+
+		/// Reject a disputed prediction. It is taken off the queue and no result is accepted for
+		/// it any more; nobody's reputation changes.
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::WeightInfo::reject_prediction())]
+		pub fn reject_prediction(origin: OriginFor<T>, prediction_id: u32) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Predictions::<T>::try_mutate(prediction_id, |prediction_opt| -> DispatchResult {
+				let prediction = prediction_opt.as_mut().ok_or(Error::<T>::PredictionNotFound)?;
+				Self::ensure_disputed(prediction)?;
+				prediction.status = PredictionStatus::Rejected;
+				Ok(())
+			})?;
+			PendingPredictions::<T>::remove(prediction_id);
+
+			Self::deposit_event(Event::PredictionRejected { prediction_id });
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		// Two results match when they have the same hash and their confidences are within
+		// `ConfidenceTolerance` of each other
+		pub fn results_match(a: &Submission<T>, b: &Submission<T>) -> bool {
+			a.result_hash == b.result_hash && Self::confidences_match(a.confidence, b.confidence)
+		}
+
+		fn confidences_match(a: Permill, b: Permill) -> bool {
+			a.deconstruct().abs_diff(b.deconstruct()) <= T::ConfidenceTolerance::get().deconstruct()
+		}
+
+		// Results can still be submitted until the prediction is finalised or rejected
+		fn ensure_open(prediction: &Prediction<T>) -> DispatchResult {
+			match prediction.status {
+				PredictionStatus::Finalised => Err(Error::<T>::PredictionAlreadyFinalised.into()),
+				PredictionStatus::Rejected => Err(Error::<T>::PredictionAlreadyRejected.into()),
+				PredictionStatus::Pending | PredictionStatus::Disputed => Ok(()),
+			}
+		}
+
+		fn ensure_disputed(prediction: &Prediction<T>) -> DispatchResult {
+			Self::ensure_open(prediction)?;
+			ensure!(prediction.status == PredictionStatus::Disputed, Error::<T>::PredictionNotDisputed);
+			Ok(())
+		}
+
+		// Finalise a prediction on a result, crediting the workers behind `matching` and
+		// penalising the `outliers`
+		fn finalise(
+			prediction: &mut Prediction<T>,
+			result_hash: T::Hash,
+			confidence: Permill,
+			matching: Vec<&Submission<T>>,
+			outliers: Vec<&Submission<T>>,
+		) {
+			let prediction_id = prediction.prediction_id;
+			let attestations = matching.len() as u32;
+
+			for m in matching {
+				WorkerReputations::<T>::mutate(&m.worker, |reputation| {
+					reputation.attested = reputation.attested.saturating_add(1)
+				});
+			}
+			for outlier in outliers {
+				WorkerReputations::<T>::mutate(&outlier.worker, |reputation| {
+					reputation.outliers = reputation.outliers.saturating_add(1)
+				});
+				Self::deposit_event(Event::WorkerPenalised {
+					worker: outlier.worker.clone(),
+					prediction_id,
+				});
+			}
+
+			prediction.status = PredictionStatus::Finalised;
+			prediction.result = Some(PredictionResult {
+				result_hash,
+				confidence,
+				attestations,
+				finalised_at: <frame_system::Pallet<T>>::block_number(),
+			});
+			PendingPredictions::<T>::remove(prediction_id);
+
+			Self::deposit_event(Event::PredictionFinalised {
+				prediction_id,
+				result_hash,
+				confidence,
+				attestations,
+			});
 		}
 	}
 }
@@ -1,6 +1,13 @@
 use crate as pallet_ai_prediction;
-use frame_support::derive_impl;
-use sp_runtime::BuildStorage;
+use frame_support::{derive_impl, parameter_types, traits::ConstU32};
+use frame_system::{
+	offchain::{AppCrypto, CreateSignedTransaction, CreateTransactionBase, SigningTypes},
+	EnsureRoot,
+};
+use sp_runtime::{
+	testing::{TestSignature, TestXt, UintAuthorityId},
+	BuildStorage, Permill,
+};
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -35,12 +42,85 @@ impl frame_system::Config for Test {
 	type Block = Block;
 }
 
+// Patients 0 to 9 exist, and every account but `STRANGER` may use their records
+pub const STRANGER: u64 = 99;
+
+pub struct MockPatients;
+impl crate::PatientRecords<u64> for MockPatients {
+	fn patient_exists(patient_id: u32) -> bool {
+		patient_id < 10
+	}
+
+	fn can_request_prediction(_patient_id: u32, who: &u64) -> bool {
+		*who != STRANGER
+	}
+
+	fn record_payload(patient_id: u32) -> Option<Vec<u8>> {
+		Self::patient_exists(patient_id).then(|| vec![patient_id as u8; 4])
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn register_patient(_patient_id: u32, _who: &u64) {}
+}
+
+parameter_types! {
+	pub const ConfidenceTolerance: Permill = Permill::from_percent(5);
+}
+
+pub type Extrinsic = TestXt<RuntimeCall, ()>;
+
+impl SigningTypes for Test {
+	type Public = UintAuthorityId;
+	type Signature = TestSignature;
+}
+
+impl<LocalCall> CreateTransactionBase<LocalCall> for Test
+where
+	RuntimeCall: From<LocalCall>,
+{
+	type Extrinsic = Extrinsic;
+	type RuntimeCall = RuntimeCall;
+}
+
+impl<LocalCall> CreateSignedTransaction<LocalCall> for Test
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_signed_transaction<C: AppCrypto<Self::Public, Self::Signature>>(
+		call: RuntimeCall,
+		_public: UintAuthorityId,
+		account: u64,
+		_nonce: Self::Nonce,
+	) -> Option<Extrinsic> {
+		Some(Extrinsic::new_signed(call, account, (), ()))
+	}
+}
+
+// Worker keys are `UintAuthorityId`s, set with `UintAuthorityId::set_all_keys`
+pub struct TestAuthId;
+impl AppCrypto<UintAuthorityId, TestSignature> for TestAuthId {
+	type RuntimeAppPublic = UintAuthorityId;
+	type GenericPublic = UintAuthorityId;
+	type GenericSignature = TestSignature;
+}
+
 impl pallet_ai_prediction::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type AdminOrigin = EnsureRoot<u64>;
+	type PatientRecords = MockPatients;
+	type AuthorityId = TestAuthId;
+	type MaxPredictionsPerBlock = ConstU32<2>;
+	type MaxWorkers = ConstU32<4>;
+	type QuorumThreshold = ConstU32<2>;
+	type ConfidenceTolerance = ConfidenceTolerance;
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	// Events are not deposited at genesis
+	ext.execute_with(|| System::set_block_number(1));
+	ext
 }
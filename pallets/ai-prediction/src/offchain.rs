@@ -0,0 +1,202 @@
+//! Offchain worker that runs pending predictions against a local inference endpoint.
+//!
+//! On every block, a node holding an `aipr` key of a registered worker in its keystore picks up
+//! to `MaxPredictionsPerBlock` pending predictions and POSTs each of them to the inference
+//! endpoint as JSON:
+//!
+//! ```json
+//! {"prediction_id":0,"model_id":0,"model_version":1,"patient_id":4,"payload":"0x…"}
+//! ```
+//!
+//! `payload` is the hex encoded record payload returned by [`crate::PatientRecords`]. The
+//! endpoint answers with a JSON object holding the `result` as a string and its `confidence` in
+//! parts per million:
+//!
+//! ```json
+//! {"result":"benign","confidence":870000}
+//! ```
+//!
+//! The hash of the `result` string is submitted with the confidence in a signed
+//! `submit_prediction` transaction. Workers running the same model over the same records produce
+//! the same hash, which is what the on-chain quorum compares.
+//!
+//! The endpoint defaults to [`DEFAULT_ENDPOINT`] and can be changed per node by writing the URL to
+//! the persistent offchain storage key [`ENDPOINT_KEY`], e.g. with the `offchain_localStorageSet`
+//! RPC.
+
+use crate::{
+	Call, Config, Pallet, PatientRecords, PendingPredictions, Predictions, Submissions, Workers,
+};
+use codec::Encode;
+use frame_support::{
+	sp_runtime::{
+		offchain::{
+			http,
+			storage::{StorageRetrievalError, StorageValueRef},
+			Duration, StorageKind,
+		},
+		traits::{Hash, Saturating},
+		PerThing, Permill,
+	},
+	traits::Get,
+};
+use frame_system::{
+	offchain::{SendSignedTransaction, Signer},
+	pallet_prelude::BlockNumberFor,
+};
+use scale_info::prelude::{format, string::String, vec, vec::Vec};
+use sp_core::hexdisplay::HexDisplay;
+
+const LOG_TARGET: &str = "runtime::ai-prediction";
+
+/// Inference endpoint used when none is configured in offchain storage.
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:8000/predict";
+/// Persistent offchain storage key holding the inference endpoint URL of this node.
+pub const ENDPOINT_KEY: &[u8] = b"ai-prediction::endpoint";
+// Prefix of the keys recording when this node last submitted a result for a prediction
+const SUBMITTED_PREFIX: &[u8] = b"ai-prediction::submitted::";
+// How long to wait for the inference endpoint
+const HTTP_TIMEOUT_MS: u64 = 10_000;
+// Blocks to wait for a submitted result to be included before running the prediction again
+const RESUBMIT_AFTER: u32 = 10;
+
+// The part of the endpoint's response the worker reads
+#[derive(serde::Deserialize)]
+struct InferenceResponse {
+	result: String,
+	confidence: u32,
+}
+
+enum WorkerError {
+	/// The request could not be sent or timed out.
+	Http(http::Error),
+	/// The endpoint answered with a non-200 status.
+	Status(u16),
+	/// The response is not JSON with a `result` and a valid `confidence`.
+	InvalidResponse,
+	/// The prediction or the patient's records are gone.
+	MissingRecords,
+	/// The transaction could not be signed or submitted.
+	Submission,
+}
+
+impl core::fmt::Display for WorkerError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			WorkerError::Http(e) => write!(f, "inference request failed: {:?}", e),
+			WorkerError::Status(code) => write!(f, "inference endpoint answered with status {}", code),
+			WorkerError::InvalidResponse => write!(f, "invalid inference response"),
+			WorkerError::MissingRecords => write!(f, "prediction or patient records not found"),
+			WorkerError::Submission => write!(f, "could not submit the result"),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Run the pending predictions of this block and submit their results.
+	pub(crate) fn process_pending_predictions(block_number: BlockNumberFor<T>) {
+		// Only nodes holding the key of a registered worker take part
+		let Some(account) = Signer::<T, T::AuthorityId>::keystore_accounts()
+			.find(|account| Workers::<T>::contains_key(&account.id))
+		else {
+			return;
+		};
+		let endpoint = Self::inference_endpoint();
+
+		// Predictions this worker answered or another run is still working on do not count
+		// towards the per-block cap, so they cannot hold back the rest of the queue
+		let claimed = PendingPredictions::<T>::iter_keys()
+			.filter(|&prediction_id| {
+				let already_submitted = Submissions::<T>::get(prediction_id)
+					.iter()
+					.any(|submission| submission.worker == account.id);
+				!already_submitted && Self::claim_prediction(prediction_id, block_number)
+			})
+			.take(T::MaxPredictionsPerBlock::get() as usize);
+
+		for prediction_id in claimed {
+			let submitted = Self::run_prediction(prediction_id, &endpoint).and_then(
+				|(result_hash, confidence)| {
+					Signer::<T, T::AuthorityId>::any_account()
+						.send_single_signed_transaction(
+							&account,
+							Call::submit_prediction { prediction_id, result_hash, confidence },
+						)
+						.and_then(|result| result.ok())
+						.ok_or(WorkerError::Submission)
+				},
+			);
+
+			match submitted {
+				Ok(()) => log::info!(
+					target: LOG_TARGET,
+					"submitted result of prediction {} from {:?}",
+					prediction_id,
+					account.id
+				),
+				Err(e) =>
+					log::warn!(target: LOG_TARGET, "prediction {} failed: {}", prediction_id, e),
+			}
+		}
+	}
+
+	/// The inference endpoint configured for this node, or [`DEFAULT_ENDPOINT`].
+	pub fn inference_endpoint() -> String {
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, ENDPOINT_KEY)
+			.and_then(|url| String::from_utf8(url).ok())
+			.unwrap_or_else(|| DEFAULT_ENDPOINT.into())
+	}
+
+	// Record that this node is running a prediction, unless it already did in the last
+	// `RESUBMIT_AFTER` blocks. This keeps overlapping offchain worker runs from sending the same
+	// result twice, and backs off after a failed attempt.
+	fn claim_prediction(prediction_id: u32, block_number: BlockNumberFor<T>) -> bool {
+		let key = [SUBMITTED_PREFIX, &prediction_id.encode()].concat();
+		let claimed = StorageValueRef::persistent(&key).mutate(
+			|last: Result<Option<BlockNumberFor<T>>, StorageRetrievalError>| match last {
+				Ok(Some(at)) if block_number < at.saturating_add(RESUBMIT_AFTER.into()) => Err(()),
+				_ => Ok(block_number),
+			},
+		);
+		claimed.is_ok()
+	}
+
+	// Send a prediction to the inference endpoint and return its result hash and confidence
+	fn run_prediction(prediction_id: u32, endpoint: &str) -> Result<(T::Hash, Permill), WorkerError> {
+		let prediction = Predictions::<T>::get(prediction_id).ok_or(WorkerError::MissingRecords)?;
+		let payload = T::PatientRecords::record_payload(prediction.patient_id)
+			.ok_or(WorkerError::MissingRecords)?;
+
+		let body = format!(
+			"{{\"prediction_id\":{},\"model_id\":{},\"model_version\":{},\"patient_id\":{},\"payload\":\"0x{}\"}}",
+			prediction_id,
+			prediction.model_id,
+			prediction.model_version,
+			prediction.patient_id,
+			HexDisplay::from(&payload),
+		);
+
+		let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(HTTP_TIMEOUT_MS));
+		let pending = http::Request::post(endpoint, vec![body.as_bytes()])
+			.add_header("Content-Type", "application/json")
+			.deadline(deadline)
+			.send()
+			.map_err(|_| WorkerError::Http(http::Error::IoError))?;
+		let response = pending
+			.try_wait(deadline)
+			.map_err(|_| WorkerError::Http(http::Error::DeadlineReached))?
+			.map_err(WorkerError::Http)?;
+		if response.code != 200 {
+			return Err(WorkerError::Status(response.code));
+		}
+		let body = response.body().collect::<Vec<u8>>();
+
+		let parsed: InferenceResponse =
+			serde_json::from_slice(&body).map_err(|_| WorkerError::InvalidResponse)?;
+		if parsed.confidence > Permill::ACCURACY {
+			return Err(WorkerError::InvalidResponse);
+		}
+
+		Ok((T::Hashing::hash(parsed.result.as_bytes()), Permill::from_parts(parsed.confidence)))
+	}
+}
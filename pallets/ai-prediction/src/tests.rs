@@ -1,2 +1,682 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+	mock::*,
+	offchain::{DEFAULT_ENDPOINT, ENDPOINT_KEY},
+	Call, Config, Error, Event, ModelStatus, PendingPredictions, PredictionResult, PredictionStatus,
+	WorkerReputation,
+};
+use codec::Decode;
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Get, Hooks},
+};
+use sp_core::{
+	offchain::{testing, OffchainDbExt, OffchainWorkerExt, StorageKind, TransactionPoolExt},
+	H256,
+};
+use sp_runtime::{
+	generic::Preamble,
+	testing::UintAuthorityId,
+	traits::{BlakeTwo256, Hash},
+	DispatchError, Permill,
+};
+
+const OWNER: u64 = 1;
+const DOCTOR: u64 = 2;
+const WORKER: u64 = 3;
+
+// Register a model owned by `OWNER` and approve it, returning its id
+fn approved_model() -> u32 {
+	let model_id = AiPrediction::next_model_id();
+	assert_ok!(AiPrediction::register_model(
+		RuntimeOrigin::signed(OWNER),
+		H256::repeat_byte(1),
+		H256::repeat_byte(2),
+	));
+	assert_ok!(AiPrediction::approve_model(RuntimeOrigin::root(), model_id));
+	model_id
+}
+
+#[test]
+fn register_model_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(AiPrediction::register_model(
+			RuntimeOrigin::signed(OWNER),
+			H256::repeat_byte(1),
+			H256::repeat_byte(2),
+		));
+
+		let model = AiPrediction::models(0).unwrap();
+		assert_eq!(model.version, 1);
+		assert_eq!(model.weights_hash, H256::repeat_byte(1));
+		assert_eq!(model.input_schema_hash, H256::repeat_byte(2));
+		assert_eq!(model.owner, OWNER);
+		assert_eq!(model.status, ModelStatus::Pending);
+		assert_eq!(AiPrediction::next_model_id(), 1);
+
+		System::assert_last_event(
+			Event::ModelRegistered { model_id: 0, owner: OWNER, weights_hash: H256::repeat_byte(1) }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn only_admin_approves_and_retires_models() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(AiPrediction::register_model(
+			RuntimeOrigin::signed(OWNER),
+			H256::repeat_byte(1),
+			H256::repeat_byte(2),
+		));
+
+		assert_noop!(
+			AiPrediction::approve_model(RuntimeOrigin::signed(OWNER), 0),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(AiPrediction::approve_model(RuntimeOrigin::root(), 1), Error::<Test>::ModelNotFound);
+
+		assert_ok!(AiPrediction::approve_model(RuntimeOrigin::root(), 0));
+		assert_eq!(AiPrediction::models(0).unwrap().status, ModelStatus::Approved);
+		System::assert_last_event(Event::ModelApproved { model_id: 0, version: 1 }.into());
+		assert_noop!(
+			AiPrediction::approve_model(RuntimeOrigin::root(), 0),
+			Error::<Test>::ModelAlreadyApproved
+		);
+
+		assert_noop!(
+			AiPrediction::retire_model(RuntimeOrigin::signed(OWNER), 0),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(AiPrediction::retire_model(RuntimeOrigin::root(), 0));
+		assert_eq!(AiPrediction::models(0).unwrap().status, ModelStatus::Retired);
+		assert_noop!(AiPrediction::approve_model(RuntimeOrigin::root(), 0), Error::<Test>::ModelRetired);
+		assert_noop!(AiPrediction::retire_model(RuntimeOrigin::root(), 0), Error::<Test>::ModelRetired);
+	});
+}
+
+#[test]
+fn new_model_version_needs_approval() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+
+		assert_noop!(
+			AiPrediction::register_model_version(
+				RuntimeOrigin::signed(DOCTOR),
+				model_id,
+				H256::repeat_byte(3),
+				H256::repeat_byte(2),
+			),
+			Error::<Test>::NotModelOwner
+		);
+
+		assert_ok!(AiPrediction::register_model_version(
+			RuntimeOrigin::signed(OWNER),
+			model_id,
+			H256::repeat_byte(3),
+			H256::repeat_byte(2),
+		));
+		let model = AiPrediction::models(model_id).unwrap();
+		assert_eq!(model.version, 2);
+		assert_eq!(model.weights_hash, H256::repeat_byte(3));
+		assert_eq!(model.status, ModelStatus::Pending);
+
+		// Predictions cannot be requested until the new weights are approved
+		assert_noop!(
+			AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0),
+			Error::<Test>::ModelNotApproved
+		);
+		assert_ok!(AiPrediction::approve_model(RuntimeOrigin::root(), model_id));
+		System::assert_last_event(Event::ModelApproved { model_id, version: 2 }.into());
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		assert_eq!(AiPrediction::predictions(0).unwrap().model_version, 2);
+	});
+}
+
+#[test]
+fn request_prediction_works() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 4));
+
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.model_id, model_id);
+		assert_eq!(prediction.model_version, 1);
+		assert_eq!(prediction.patient_id, 4);
+		assert_eq!(prediction.requested_by, DOCTOR);
+		assert_eq!(prediction.requested_at, 1);
+		assert_eq!(prediction.result, None);
+		assert_eq!(AiPrediction::next_prediction_id(), 1);
+
+		System::assert_last_event(
+			Event::PredictionRequested {
+				prediction_id: 0,
+				model_id,
+				model_version: 1,
+				patient_id: 4,
+				requested_by: DOCTOR,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn request_prediction_checks_model_and_patient() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), 0, 0),
+			Error::<Test>::ModelNotFound
+		);
+
+		let model_id = approved_model();
+		assert_noop!(
+			AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 10),
+			Error::<Test>::PatientNotFound
+		);
+		assert_noop!(
+			AiPrediction::request_prediction(RuntimeOrigin::signed(STRANGER), model_id, 0),
+			Error::<Test>::NotAuthorized
+		);
+
+		assert_ok!(AiPrediction::retire_model(RuntimeOrigin::root(), model_id));
+		assert_noop!(
+			AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0),
+			Error::<Test>::ModelNotApproved
+		);
+	});
+}
+
+// Submit a result for prediction 0
+fn submit(worker: u64, result: u8, confidence: u32) -> frame_support::dispatch::DispatchResult {
+	AiPrediction::submit_prediction(
+		RuntimeOrigin::signed(worker),
+		0,
+		H256::repeat_byte(result),
+		Permill::from_percent(confidence),
+	)
+}
+
+#[test]
+fn prediction_is_finalised_once_quorum_agrees() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		for worker in [WORKER, 4, 5] {
+			assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+		}
+
+		System::set_block_number(5);
+		assert_ok!(submit(WORKER, 9, 87));
+		System::assert_last_event(
+			Event::PredictionSubmitted {
+				prediction_id: 0,
+				result_hash: H256::repeat_byte(9),
+				confidence: Permill::from_percent(87),
+				worker: WORKER,
+			}
+			.into(),
+		);
+		// A single result is not enough
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Pending);
+		assert_eq!(prediction.result, None);
+		assert!(AiPrediction::pending_predictions(0).is_some());
+		assert_noop!(submit(WORKER, 9, 87), Error::<Test>::PredictionAlreadySubmitted);
+
+		// A matching hash with a confidence within the tolerance completes the quorum
+		assert_ok!(submit(4, 9, 90));
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Finalised);
+		assert_eq!(
+			prediction.result,
+			Some(PredictionResult {
+				result_hash: H256::repeat_byte(9),
+				confidence: Permill::from_parts(885_000),
+				attestations: 2,
+				finalised_at: 5,
+			})
+		);
+		assert!(AiPrediction::pending_predictions(0).is_none());
+		assert_eq!(AiPrediction::submissions(0).len(), 2);
+		System::assert_last_event(
+			Event::PredictionFinalised {
+				prediction_id: 0,
+				result_hash: H256::repeat_byte(9),
+				confidence: Permill::from_parts(885_000),
+				attestations: 2,
+			}
+			.into(),
+		);
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation { attested: 1, outliers: 0 });
+		assert_eq!(AiPrediction::worker_reputation(4), WorkerReputation { attested: 1, outliers: 0 });
+
+		assert_noop!(submit(5, 9, 88), Error::<Test>::PredictionAlreadyFinalised);
+	});
+}
+
+#[test]
+fn conflicting_results_dispute_the_prediction_and_penalise_outliers() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		for worker in [WORKER, 4, 5, 6] {
+			assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+		}
+
+		assert_ok!(submit(WORKER, 9, 87));
+		assert_ok!(submit(4, 8, 87));
+		assert_eq!(AiPrediction::predictions(0).unwrap().status, PredictionStatus::Disputed);
+		System::assert_has_event(Event::PredictionDisputed { prediction_id: 0 }.into());
+
+		// Same hash but a confidence outside the tolerance does not match either
+		assert_ok!(submit(5, 9, 60));
+		assert_eq!(AiPrediction::predictions(0).unwrap().status, PredictionStatus::Disputed);
+		let disputes = System::events()
+			.iter()
+			.filter(|record| {
+				record.event == RuntimeEvent::AiPrediction(Event::PredictionDisputed { prediction_id: 0 })
+			})
+			.count();
+		assert_eq!(disputes, 1);
+
+		assert_ok!(submit(6, 9, 85));
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Finalised);
+		assert_eq!(prediction.result.unwrap().confidence, Permill::from_percent(86));
+
+		System::assert_has_event(Event::WorkerPenalised { worker: 4, prediction_id: 0 }.into());
+		System::assert_has_event(Event::WorkerPenalised { worker: 5, prediction_id: 0 }.into());
+		assert_eq!(AiPrediction::worker_reputation(4), WorkerReputation { attested: 0, outliers: 1 });
+		assert_eq!(AiPrediction::worker_reputation(5), WorkerReputation { attested: 0, outliers: 1 });
+		assert_eq!(AiPrediction::worker_reputation(6), WorkerReputation { attested: 1, outliers: 0 });
+	});
+}
+
+#[test]
+fn prediction_without_quorum_leaves_the_queue_once_every_worker_answered() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		for worker in [WORKER, 4] {
+			assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+		}
+
+		assert_ok!(submit(WORKER, 9, 87));
+		assert!(AiPrediction::pending_predictions(0).is_some());
+		assert_ok!(submit(4, 8, 87));
+
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Disputed);
+		assert_eq!(prediction.result, None);
+		assert!(AiPrediction::pending_predictions(0).is_none());
+		// Nobody is penalised while the prediction is unresolved
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation::default());
+	});
+}
+
+#[test]
+fn results_of_removed_workers_do_not_count() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		for worker in [WORKER, 4] {
+			assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+		}
+
+		assert_ok!(submit(WORKER, 9, 87));
+		assert_ok!(AiPrediction::remove_worker(RuntimeOrigin::root(), WORKER));
+		// The removed worker's result does not complete the quorum, and the only worker left
+		// has answered
+		assert_ok!(submit(4, 9, 87));
+		assert_eq!(AiPrediction::predictions(0).unwrap().status, PredictionStatus::Disputed);
+		assert_eq!(AiPrediction::submissions(0).len(), 1);
+
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), 5));
+		assert_ok!(submit(5, 9, 88));
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Finalised);
+		assert_eq!(prediction.result.unwrap().attestations, 2);
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation::default());
+	});
+}
+
+#[test]
+fn all_workers_agreeing_below_quorum_leave_the_prediction_to_the_admin() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		// A single worker can never make up the quorum of two
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+
+		assert_ok!(submit(WORKER, 9, 87));
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Disputed);
+		assert_eq!(prediction.result, None);
+		assert!(AiPrediction::pending_predictions(0).is_none());
+		System::assert_last_event(Event::PredictionDisputed { prediction_id: 0 }.into());
+
+		assert_ok!(AiPrediction::resolve_prediction(
+			RuntimeOrigin::root(),
+			0,
+			H256::repeat_byte(9),
+			Permill::from_percent(87),
+		));
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Finalised);
+		assert_eq!(prediction.result.unwrap().attestations, 1);
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation { attested: 1, outliers: 0 });
+	});
+}
+
+// Have two workers submit conflicting results for prediction 0, leaving it disputed and off the
+// queue
+fn disputed() {
+	let model_id = approved_model();
+	assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+	for worker in [WORKER, 4] {
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+	}
+	assert_ok!(submit(WORKER, 9, 87));
+	assert_ok!(submit(4, 8, 87));
+	assert!(AiPrediction::pending_predictions(0).is_none());
+}
+
+#[test]
+fn admin_settles_disputed_predictions() {
+	new_test_ext().execute_with(|| {
+		disputed();
+		System::set_block_number(5);
+
+		assert_noop!(
+			AiPrediction::resolve_prediction(
+				RuntimeOrigin::signed(OWNER),
+				0,
+				H256::repeat_byte(9),
+				Permill::from_percent(90),
+			),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(AiPrediction::resolve_prediction(
+			RuntimeOrigin::root(),
+			0,
+			H256::repeat_byte(9),
+			Permill::from_percent(90),
+		));
+
+		let prediction = AiPrediction::predictions(0).unwrap();
+		assert_eq!(prediction.status, PredictionStatus::Finalised);
+		assert_eq!(
+			prediction.result,
+			Some(PredictionResult {
+				result_hash: H256::repeat_byte(9),
+				confidence: Permill::from_percent(90),
+				attestations: 1,
+				finalised_at: 5,
+			})
+		);
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation { attested: 1, outliers: 0 });
+		assert_eq!(AiPrediction::worker_reputation(4), WorkerReputation { attested: 0, outliers: 1 });
+		System::assert_has_event(Event::WorkerPenalised { worker: 4, prediction_id: 0 }.into());
+
+		assert_noop!(
+			AiPrediction::reject_prediction(RuntimeOrigin::root(), 0),
+			Error::<Test>::PredictionAlreadyFinalised
+		);
+	});
+}
+
+#[test]
+fn admin_rejects_disputed_predictions() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+		// Only disputes can be settled
+		assert_noop!(
+			AiPrediction::reject_prediction(RuntimeOrigin::root(), 0),
+			Error::<Test>::PredictionNotDisputed
+		);
+		assert_noop!(
+			AiPrediction::reject_prediction(RuntimeOrigin::root(), 1),
+			Error::<Test>::PredictionNotFound
+		);
+	});
+
+	new_test_ext().execute_with(|| {
+		disputed();
+
+		assert_ok!(AiPrediction::reject_prediction(RuntimeOrigin::root(), 0));
+		assert_eq!(AiPrediction::predictions(0).unwrap().status, PredictionStatus::Rejected);
+		System::assert_last_event(Event::PredictionRejected { prediction_id: 0 }.into());
+		assert_eq!(AiPrediction::worker_reputation(WORKER), WorkerReputation::default());
+
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), 5));
+		assert_noop!(submit(5, 9, 87), Error::<Test>::PredictionAlreadyRejected);
+		assert_noop!(
+			AiPrediction::resolve_prediction(
+				RuntimeOrigin::root(),
+				0,
+				H256::repeat_byte(9),
+				Permill::from_percent(90),
+			),
+			Error::<Test>::PredictionAlreadyRejected
+		);
+	});
+}
+
+#[test]
+fn number_of_workers_is_bounded() {
+	new_test_ext().execute_with(|| {
+		for worker in 3..7 {
+			assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), worker));
+		}
+		assert_noop!(AiPrediction::add_worker(RuntimeOrigin::root(), 7), Error::<Test>::TooManyWorkers);
+
+		assert_ok!(AiPrediction::remove_worker(RuntimeOrigin::root(), 3));
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), 7));
+	});
+}
+
+#[test]
+fn only_workers_submit_predictions() {
+	new_test_ext().execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 0));
+
+		assert_noop!(
+			AiPrediction::submit_prediction(
+				RuntimeOrigin::signed(WORKER),
+				0,
+				H256::repeat_byte(9),
+				Permill::one(),
+			),
+			Error::<Test>::NotWorker
+		);
+
+		assert_noop!(
+			AiPrediction::add_worker(RuntimeOrigin::signed(WORKER), WORKER),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+		assert_noop!(
+			AiPrediction::add_worker(RuntimeOrigin::root(), WORKER),
+			Error::<Test>::WorkerAlreadyAdded
+		);
+		assert_noop!(
+			AiPrediction::submit_prediction(
+				RuntimeOrigin::signed(WORKER),
+				1,
+				H256::repeat_byte(9),
+				Permill::one(),
+			),
+			Error::<Test>::PredictionNotFound
+		);
+
+		assert_ok!(AiPrediction::remove_worker(RuntimeOrigin::root(), WORKER));
+		assert_noop!(AiPrediction::remove_worker(RuntimeOrigin::root(), WORKER), Error::<Test>::NotWorker);
+		assert_noop!(
+			AiPrediction::submit_prediction(
+				RuntimeOrigin::signed(WORKER),
+				0,
+				H256::repeat_byte(9),
+				Permill::one(),
+			),
+			Error::<Test>::NotWorker
+		);
+	});
+}
+
+// The request the offchain worker sends for prediction 0 of patient 4 under model 0, version 1
+fn expected_request(uri: &str, response: &[u8]) -> testing::PendingRequest {
+	prediction_request(0, uri, response)
+}
+
+// The request the offchain worker sends for the given prediction of patient 4 under model 0, version 1
+fn prediction_request(prediction_id: u32, uri: &str, response: &[u8]) -> testing::PendingRequest {
+	testing::PendingRequest {
+		method: "POST".into(),
+		uri: uri.into(),
+		headers: vec![("Content-Type".into(), "application/json".into())],
+		body: format!(
+			r#"{{"prediction_id":{},"model_id":0,"model_version":1,"patient_id":4,"payload":"0x04040404"}}"#,
+			prediction_id
+		)
+		.into_bytes(),
+		response: Some(response.to_vec()),
+		sent: true,
+		..Default::default()
+	}
+}
+
+#[test]
+fn offchain_worker_submits_prediction_results() {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	ext.register_extension(OffchainDbExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	UintAuthorityId::set_all_keys(vec![WORKER]);
+
+	let response = br#"{"result":"benign","confidence":870000,"model":"cxr-v1"}"#;
+	offchain_state.write().expect_request(expected_request(DEFAULT_ENDPOINT, response));
+
+	ext.execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 4));
+		assert!(AiPrediction::pending_predictions(0).is_some());
+
+		AiPrediction::offchain_worker(1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.preamble, Preamble::Signed(WORKER, (), ()));
+		assert_eq!(
+			tx.function,
+			RuntimeCall::AiPrediction(Call::submit_prediction {
+				prediction_id: 0,
+				result_hash: BlakeTwo256::hash(b"benign"),
+				confidence: Permill::from_percent(87),
+			})
+		);
+
+		// The prediction is not run again while the result is on its way
+		AiPrediction::offchain_worker(2);
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn offchain_worker_uses_configured_endpoint() {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	ext.register_extension(OffchainDbExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	UintAuthorityId::set_all_keys(vec![WORKER]);
+
+	let endpoint = "http://10.0.0.5:9000/infer";
+	offchain_state
+		.write()
+		.expect_request(expected_request(endpoint, br#"{"result":"benign","confidence":500000}"#));
+
+	ext.execute_with(|| {
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, ENDPOINT_KEY, endpoint.as_bytes());
+		assert_eq!(AiPrediction::inference_endpoint(), endpoint);
+
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 4));
+
+		// Keys of accounts that are not registered workers are ignored
+		AiPrediction::offchain_worker(1);
+		assert!(pool_state.read().transactions.is_empty());
+
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+		AiPrediction::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+	});
+}
+
+#[test]
+fn offchain_worker_rejects_invalid_responses() {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	ext.register_extension(OffchainDbExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	UintAuthorityId::set_all_keys(vec![WORKER]);
+
+	// Confidence above 100%
+	offchain_state
+		.write()
+		.expect_request(expected_request(DEFAULT_ENDPOINT, br#"{"result":"benign","confidence":1000001}"#));
+
+	ext.execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+		assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 4));
+
+		AiPrediction::offchain_worker(1);
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn offchain_worker_moves_on_past_claimed_predictions() {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+	ext.register_extension(OffchainDbExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	UintAuthorityId::set_all_keys(vec![WORKER]);
+
+	ext.execute_with(|| {
+		let model_id = approved_model();
+		assert_ok!(AiPrediction::add_worker(RuntimeOrigin::root(), WORKER));
+		// One more prediction than a worker runs per block
+		let max = <Test as Config>::MaxPredictionsPerBlock::get();
+		for _ in 0..=max {
+			assert_ok!(AiPrediction::request_prediction(RuntimeOrigin::signed(DOCTOR), model_id, 4));
+		}
+
+		// Requests are sent in the order the queue is walked
+		let response = br#"{"result":"benign","confidence":870000}"#;
+		for prediction_id in PendingPredictions::<Test>::iter_keys() {
+			offchain_state
+				.write()
+				.expect_request(prediction_request(prediction_id, DEFAULT_ENDPOINT, response));
+		}
+
+		AiPrediction::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), max as usize);
+
+		// The claimed predictions are skipped rather than filling the next run
+		AiPrediction::offchain_worker(2);
+		assert_eq!(pool_state.read().transactions.len(), max as usize + 1);
+	});
+}
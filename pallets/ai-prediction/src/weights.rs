@@ -1,5 +1,5 @@
 
-//! Autogenerated weights for pallet_template
+//! Autogenerated weights for pallet_ai_prediction
 //!
 //! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
 //! DATE: 2023-04-06, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
@@ -32,15 +32,98 @@
 use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
 use core::marker::PhantomData;
 
-/// Weight functions needed for pallet_template.
+/// Weight functions needed for pallet_ai_prediction.
 pub trait WeightInfo {
+	fn register_model() -> Weight;
+	fn register_model_version() -> Weight;
+	fn approve_model() -> Weight;
+	fn retire_model() -> Weight;
+	fn add_worker() -> Weight;
+	fn remove_worker() -> Weight;
+	fn request_prediction() -> Weight;
+	fn submit_prediction(s: u32) -> Weight;
+	fn resolve_prediction(s: u32) -> Weight;
+	fn reject_prediction() -> Weight;
 }
 
-/// Weights for pallet_template using the Substrate node and recommended hardware.
+/// Weights for pallet_ai_prediction using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn register_model() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1)) // counter
+			.saturating_add(T::DbWeight::get().writes(2)) // model + counter
+	}
+
+	fn register_model_version() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn approve_model() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn retire_model() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn add_worker() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2)) // worker + worker count
+			.saturating_add(T::DbWeight::get().writes(2)) // worker + worker count
+	}
+
+	fn remove_worker() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2)) // worker + worker count
+			.saturating_add(T::DbWeight::get().writes(2)) // worker + worker count
+	}
+
+	fn request_prediction() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2)) // model + counter, patient lookups are counted by the provider
+			.saturating_add(T::DbWeight::get().writes(3)) // prediction + pending entry + counter
+	}
+
+	fn submit_prediction(s: u32) -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(Weight::from_parts(2_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(4)) // worker + prediction + submissions + worker count
+			.saturating_add(T::DbWeight::get().writes(3)) // prediction + submissions + pending entry
+			.saturating_add(T::DbWeight::get().reads_writes((2 * s).into(), s.into())) // worker entry and reputation of every submitter
+	}
+
+	fn resolve_prediction(s: u32) -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(Weight::from_parts(2_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(2)) // prediction + submissions
+			.saturating_add(T::DbWeight::get().writes(2)) // prediction + pending entry
+			.saturating_add(T::DbWeight::get().reads_writes((2 * s).into(), s.into())) // worker entry and reputation of every submitter
+	}
+
+	fn reject_prediction() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1)) // prediction
+			.saturating_add(T::DbWeight::get().writes(2)) // prediction + pending entry
+	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
+	fn register_model() -> Weight { Weight::from_parts(10_000, 0) }
+	fn register_model_version() -> Weight { Weight::from_parts(10_000, 0) }
+	fn approve_model() -> Weight { Weight::from_parts(10_000, 0) }
+	fn retire_model() -> Weight { Weight::from_parts(10_000, 0) }
+	fn add_worker() -> Weight { Weight::from_parts(10_000, 0) }
+	fn remove_worker() -> Weight { Weight::from_parts(10_000, 0) }
+	fn request_prediction() -> Weight { Weight::from_parts(10_000, 0) }
+	fn submit_prediction(_s: u32) -> Weight { Weight::from_parts(10_000, 0) }
+	fn resolve_prediction(_s: u32) -> Weight { Weight::from_parts(10_000, 0) }
+	fn reject_prediction() -> Weight { Weight::from_parts(10_000, 0) }
 }
@@ -1,15 +1,330 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-medical-appointment
 
 use super::*;
 
 #[allow(unused)]
 use crate::Pallet as MedicalAppointment;
 use frame_benchmarking::v2::*;
+use frame_support::{
+	traits::{
+		fungible::{Inspect, Mutate, MutateHold},
+		Get, Hooks, UnixTime,
+	},
+	sp_runtime::Saturating,
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use scale_info::prelude::vec;
+
+// Length of the slots in the benchmark calendars
+const SLOT: u64 = 1_800;
+
+fn now<T: Config>() -> u64 {
+	T::TimeProvider::now().as_secs()
+}
+
+// 01:00 UTC tomorrow, the slot the benchmarks book
+fn target_slot<T: Config>() -> u64 {
+	(now::<T>() / SECONDS_PER_DAY + 1) * SECONDS_PER_DAY + 3_600
+}
+
+// Fill the doctor's calendar so checking a booking at `target_slot` is as expensive as it gets:
+// every window but the last one misses it, and all unavailable periods and other bookings are
+// around it
+fn busy_calendar<T: Config>(doctor: &T::AccountId) {
+	let target = target_slot::<T>();
+	let target_weekday = ((target / SECONDS_PER_DAY + 3) % 7) as u8;
+	let mut windows = vec![];
+	for i in 1..T::MaxAvailabilityWindows::get() {
+		windows.push(AvailabilityWindow { weekday: (i % 7) as u8, start: 0, end: SLOT as u32 });
+	}
+	windows.push(AvailabilityWindow {
+		weekday: target_weekday,
+		start: 0,
+		end: SECONDS_PER_DAY as u32,
+	});
+	DoctorAvailability::<T>::insert(
+		doctor,
+		Availability { windows: BoundedVec::truncate_from(windows), slot_length: SLOT as u32 },
+	);
+
+	let periods = (1..=T::MaxUnavailablePeriods::get() as u64)
+		.map(|i| UnavailablePeriod { start: target + i * SECONDS_PER_DAY, end: target + i * SECONDS_PER_DAY + 1 })
+		.collect::<Vec<_>>();
+	DoctorUnavailability::<T>::insert(doctor, BoundedVec::truncate_from(periods));
+
+	let bookings = (1..T::MaxBookingsPerDoctor::get())
+		.map(|i| Booking {
+			appointment_id: 1_000 + i,
+			start: target + i as u64 * SLOT,
+			end: target + (i as u64 + 1) * SLOT,
+		})
+		.collect::<Vec<_>>();
+	DoctorBookings::<T>::insert(doctor, BoundedVec::truncate_from(bookings));
+}
+
+// Give `who` enough to pay a few deposits and fees, and have `doctor` charge a fee
+fn fund<T: Config>(who: &T::AccountId, doctor: &T::AccountId) {
+	let fee = T::BookingDeposit::get().max(T::Currency::minimum_balance());
+	ConsultationFees::<T>::insert(doctor, fee);
+	let amount = T::Currency::minimum_balance()
+		.saturating_add(T::BookingDeposit::get())
+		.saturating_add(fee)
+		.saturating_mul(100u32.into());
+	T::Currency::set_balance(who, amount);
+	T::Currency::set_balance(doctor, T::Currency::minimum_balance());
+}
+
+// Hold the deposit and fee of appointment `appointment_id` from `patient`
+fn hold_deposit<T: Config>(appointment_id: u32, patient: &T::AccountId, doctor: &T::AccountId) {
+	let deposit = T::BookingDeposit::get();
+	let fee = ConsultationFees::<T>::get(doctor);
+	T::Currency::hold(&HoldReason::AppointmentDeposit.into(), patient, deposit.saturating_add(fee))
+		.unwrap();
+	Escrows::<T>::insert(appointment_id, Escrow { payer: patient.clone(), deposit, fee });
+}
+
+// Store appointment 0 between `patient` and `doctor` with the given status and time, holding its
+// slot in the doctor's calendar and the patient's funds
+fn insert_appointment<T: Config>(
+	patient: &T::AccountId,
+	doctor: &T::AccountId,
+	status: AppointmentStatus,
+	scheduled_time: u64,
+) {
+	fund::<T>(patient, doctor);
+	hold_deposit::<T>(0, patient, doctor);
+	Appointments::<T>::insert(
+		0,
+		Appointment {
+			appointment_id: 0,
+			patient_id: patient.clone(),
+			doctor_id: doctor.clone(),
+			scheduled_time,
+			status,
+			created_at: now::<T>(),
+			updated_at: now::<T>(),
+		},
+	);
+	PatientAppointments::<T>::insert(patient, 0, ());
+	DoctorAppointments::<T>::insert(doctor, 0, ());
+	NextAppointmentId::<T>::put(1);
+	DoctorBookings::<T>::mutate(doctor, |bookings| {
+		bookings.pop();
+		bookings
+			.try_push(Booking { appointment_id: 0, start: scheduled_time, end: scheduled_time + SLOT })
+			.unwrap();
+	});
+}
 
 #[benchmarks]
 mod benchmarks {
 	use super::*;
 
+	#[benchmark]
+	fn create_medical_appointment() {
+		let patient: T::AccountId = whitelisted_caller();
+		let doctor: T::AccountId = account("doctor", 0, 0);
+		busy_calendar::<T>(&doctor);
+		fund::<T>(&patient, &doctor);
+		let scheduled_time = target_slot::<T>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(patient.clone()), patient.clone(), doctor.clone(), scheduled_time);
+
+		assert!(Appointments::<T>::contains_key(0));
+		assert!(Escrows::<T>::contains_key(0));
+		assert!(PatientAppointments::<T>::contains_key(patient, 0));
+		assert_eq!(DoctorBookings::<T>::get(doctor).len() as u32, T::MaxBookingsPerDoctor::get());
+	}
+
+	#[benchmark]
+	fn confirm_appointment() {
+		let patient: T::AccountId = account("patient", 0, 0);
+		let doctor: T::AccountId = whitelisted_caller();
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::Scheduled, target_slot::<T>());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor), 0);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().status, AppointmentStatus::Confirmed);
+	}
+
+	#[benchmark]
+	fn reschedule_appointment() {
+		let patient: T::AccountId = whitelisted_caller();
+		let doctor: T::AccountId = account("doctor", 0, 0);
+		busy_calendar::<T>(&doctor);
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::Confirmed, target_slot::<T>());
+		// The tasks for the current time are the last ones in full agenda blocks
+		let block = frame_system::Pallet::<T>::block_number() + 1u32.into();
+		for (at, task) in [(block, AppointmentTask::Remind), (block + 1u32.into(), AppointmentTask::NoShowCheck)] {
+			let mut tasks = (1..T::MaxTasksPerBlock::get())
+				.map(|i| ScheduledTask { appointment_id: 1_000 + i, task, scheduled_time: 0 })
+				.collect::<Vec<_>>();
+			tasks.push(ScheduledTask { appointment_id: 0, task, scheduled_time: target_slot::<T>() });
+			Agenda::<T>::insert(at, BoundedVec::truncate_from(tasks));
+			QueuedTasks::<T>::insert(0, task, at);
+		}
+		let scheduled_time = target_slot::<T>() - SLOT;
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(patient), 0, scheduled_time);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().scheduled_time, scheduled_time);
+		assert_eq!(Agenda::<T>::get(block).len() as u32, T::MaxTasksPerBlock::get() - 1);
+	}
+
+	#[benchmark]
+	fn cancel_appointment() {
+		let patient: T::AccountId = whitelisted_caller();
+		let doctor: T::AccountId = account("doctor", 0, 0);
+		busy_calendar::<T>(&doctor);
+		// Late, so part of the deposit goes to the doctor
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::Confirmed, now::<T>() + SLOT);
+		let reason: BoundedVec<u8, T::MaxReasonLength> =
+			BoundedVec::truncate_from(vec![b'x'; T::MaxReasonLength::get() as usize]);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(patient), 0, reason);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().status, AppointmentStatus::Cancelled);
+		assert!(!Escrows::<T>::contains_key(0));
+	}
+
+	#[benchmark]
+	fn check_in() {
+		let patient: T::AccountId = whitelisted_caller();
+		let doctor: T::AccountId = account("doctor", 0, 0);
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::Confirmed, target_slot::<T>());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(patient), 0);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().status, AppointmentStatus::CheckedIn);
+	}
+
+	#[benchmark]
+	fn complete_appointment() {
+		let patient: T::AccountId = account("patient", 0, 0);
+		let doctor: T::AccountId = whitelisted_caller();
+		busy_calendar::<T>(&doctor);
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::CheckedIn, target_slot::<T>());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor), 0);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().status, AppointmentStatus::Completed);
+		assert!(!Escrows::<T>::contains_key(0));
+	}
+
+	#[benchmark]
+	fn mark_no_show() {
+		let patient: T::AccountId = account("patient", 0, 0);
+		let doctor: T::AccountId = whitelisted_caller();
+		busy_calendar::<T>(&doctor);
+		insert_appointment::<T>(&patient, &doctor, AppointmentStatus::Confirmed, now::<T>());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor), 0);
+
+		assert_eq!(Appointments::<T>::get(0).unwrap().status, AppointmentStatus::NoShow);
+		assert!(!Escrows::<T>::contains_key(0));
+	}
+
+	#[benchmark]
+	fn set_availability() {
+		let doctor: T::AccountId = whitelisted_caller();
+		let windows = (0..T::MaxAvailabilityWindows::get())
+			.map(|i| AvailabilityWindow { weekday: (i % 7) as u8, start: 0, end: SECONDS_PER_DAY as u32 })
+			.collect::<Vec<_>>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor.clone()), BoundedVec::truncate_from(windows), SLOT as u32);
+
+		assert!(DoctorAvailability::<T>::contains_key(doctor));
+	}
+
+	#[benchmark]
+	fn add_unavailability() {
+		let doctor: T::AccountId = whitelisted_caller();
+		busy_calendar::<T>(&doctor);
+		DoctorUnavailability::<T>::mutate(&doctor, |periods| periods.pop());
+		let start = target_slot::<T>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor.clone()), start, start + SECONDS_PER_DAY);
+
+		assert_eq!(
+			DoctorUnavailability::<T>::get(doctor).len() as u32,
+			T::MaxUnavailablePeriods::get()
+		);
+	}
+
+	#[benchmark]
+	fn remove_unavailability() {
+		let doctor: T::AccountId = whitelisted_caller();
+		busy_calendar::<T>(&doctor);
+		let start = DoctorUnavailability::<T>::get(&doctor).last().unwrap().start;
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor.clone()), start);
+
+		assert!(!DoctorUnavailability::<T>::get(doctor).iter().any(|period| period.start == start));
+	}
+
+	// Worst case: every task is a no-show check that closes its appointment. The check is only
+	// due once `NoShowGracePeriod` has passed since the appointment, on a clock that starts
+	// earlier the tasks are queued again instead.
+	#[benchmark]
+	fn on_initialize(s: Linear<0, { T::MaxTasksPerBlock::get() }>) {
+		let doctor: T::AccountId = account("doctor", 0, 0);
+		busy_calendar::<T>(&doctor);
+		let scheduled_time = now::<T>().saturating_sub(T::NoShowGracePeriod::get());
+		let block = frame_system::Pallet::<T>::block_number() + 1u32.into();
+		let mut tasks = vec![];
+		for appointment_id in 0..s {
+			let patient: T::AccountId = account("patient", appointment_id, 0);
+			fund::<T>(&patient, &doctor);
+			hold_deposit::<T>(appointment_id, &patient, &doctor);
+			Appointments::<T>::insert(
+				appointment_id,
+				Appointment {
+					appointment_id,
+					patient_id: patient,
+					doctor_id: doctor.clone(),
+					scheduled_time,
+					status: AppointmentStatus::Confirmed,
+					created_at: scheduled_time,
+					updated_at: scheduled_time,
+				},
+			);
+			tasks.push(ScheduledTask {
+				appointment_id,
+				task: AppointmentTask::NoShowCheck,
+				scheduled_time,
+			});
+		}
+		Agenda::<T>::insert(block, BoundedVec::truncate_from(tasks));
+
+		#[block]
+		{
+			MedicalAppointment::<T>::on_initialize(block);
+		}
+
+		assert!(!Agenda::<T>::contains_key(block));
+	}
+
+	#[benchmark]
+	fn set_consultation_fee() {
+		let doctor: T::AccountId = whitelisted_caller();
+		let fee = T::BookingDeposit::get();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(doctor.clone()), fee);
+
+		assert_eq!(ConsultationFees::<T>::get(doctor), fee);
+	}
+
 	impl_benchmark_test_suite!(MedicalAppointment, crate::mock::new_test_ext(), crate::mock::Test);
 }
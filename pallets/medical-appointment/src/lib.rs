@@ -22,16 +22,29 @@ mod benchmarking;
 pub mod weights;
 pub use weights::*;
 
+use scale_info::prelude::vec::Vec;
+
 // All pallet logic is defined in its own module and must be annotated by the `pallet` attribute.
-#[frame_support::pallet(dev_mode)]
+#[frame_support::pallet]
 pub mod pallet {
 	// Import various useful types required by all FRAME pallets.
 	use super::*;
 	use frame_support::{
 		pallet_prelude::*,
-		traits::UnixTime,
+		traits::{
+			fungible::{Inspect, Mutate, MutateHold},
+			tokens::{Fortitude, Precision, Restriction},
+			Contains, UnixTime,
+		},
 	};
 	use frame_system::pallet_prelude::*;
+	use frame_support::sp_runtime::{
+		traits::{One, Saturating, Zero},
+		Perbill, SaturatedConversion,
+	};
+
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Inspect<<T as frame_system::Config>::AccountId>>::Balance;
 
 	// The `Pallet` struct serves as a placeholder to implement traits, methods and dispatchables
 	// (`Call`s) in this pallet.
@@ -54,25 +67,121 @@ pub mod pallet {
 
 		/// Appointment Status.
 		type RuntimeAppointmentStatus: From<AppointmentStatus> + Into<AppointmentStatus>;
+
+		/// Maximum length of a cancellation reason.
+		#[pallet::constant]
+		type MaxReasonLength: Get<u32>;
+		/// Maximum number of weekly availability windows a doctor can publish.
+		#[pallet::constant]
+		type MaxAvailabilityWindows: Get<u32>;
+		/// Maximum number of upcoming unavailable periods (holidays, leave) per doctor.
+		#[pallet::constant]
+		type MaxUnavailablePeriods: Get<u32>;
+		/// Maximum number of upcoming appointments a doctor can have booked.
+		#[pallet::constant]
+		type MaxBookingsPerDoctor: Get<u32>;
+		/// Expected time between blocks in milliseconds, used to turn appointment times into the
+		/// blocks their reminders and no-show checks run at.
+		#[pallet::constant]
+		type ExpectedBlockTime: Get<u64>;
+		/// How many seconds before an appointment `AppointmentReminder` is emitted.
+		#[pallet::constant]
+		type ReminderLeadTime: Get<u64>;
+		/// How many seconds after its scheduled time an appointment the patient did not check in
+		/// for becomes a no-show.
+		#[pallet::constant]
+		type NoShowGracePeriod: Get<u64>;
+		/// Maximum number of reminders and no-show checks run in a single block.
+		#[pallet::constant]
+		type MaxTasksPerBlock: Get<u32>;
+
+		/// Currency the booking deposits and consultation fees are held and paid in.
+		type Currency: Mutate<Self::AccountId>
+			+ MutateHold<Self::AccountId, Reason = Self::RuntimeHoldReason>;
+		/// The overarching hold reason.
+		type RuntimeHoldReason: From<HoldReason>;
+		/// Deposit held from a patient booking an appointment, on top of the consultation fee.
+		#[pallet::constant]
+		type BookingDeposit: Get<BalanceOf<Self>>;
+		/// How many seconds before an appointment a cancellation by the patient counts as late.
+		#[pallet::constant]
+		type LateCancellationPeriod: Get<u64>;
+		/// Share of the deposit a patient forfeits to the doctor for a late cancellation.
+		#[pallet::constant]
+		type LateCancellationPenalty: Get<Perbill>;
+		/// Accounts that may book appointments on behalf of their patients, e.g. those holding the
+		/// doctor role in the hospital registry.
+		type Doctors: Contains<Self::AccountId>;
 	}
 
-	#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo)]
+	/// Reasons the pallet holds funds for.
+	#[pallet::composite_enum]
+	pub enum HoldReason {
+		/// Booking deposit and consultation fee of an appointment.
+		AppointmentDeposit,
+	}
+
+	/// Seconds in a day. Availability is expressed in UTC.
+	pub const SECONDS_PER_DAY: u64 = 86_400;
+	// How many blocks after the intended one a task is placed when that block's agenda is full
+	const AGENDA_LOOKAHEAD: u32 = 16;
+
+	#[derive(
+		Encode, Decode, DecodeWithMemTracking, Clone, Copy, PartialEq, Eq, Debug, Default, MaxEncodedLen,
+		TypeInfo,
+	)]
 	pub enum AppointmentStatus {
-		/// The appointment is scheduled and pending.
+		/// The appointment is booked and waits for the doctor to confirm it.
+		#[default]
 		Scheduled,
-		/// The appointment is completed.
+		/// The patient was seen and the appointment is closed.
 		Completed,
-		/// The appointment is cancelled.
+		/// The appointment was cancelled by the patient or the doctor.
 		Cancelled,
+		/// The doctor confirmed the appointment.
+		Confirmed,
+		/// The patient arrived for the appointment.
+		CheckedIn,
+		/// The patient did not turn up for an appointment they had not checked in for.
+		NoShow,
 	}
-	impl Default for AppointmentStatus {
-		fn default() -> Self {
-			AppointmentStatus::Scheduled
+	impl AppointmentStatus {
+		/// Whether the appointment can still be rescheduled or cancelled.
+		pub fn is_open(&self) -> bool {
+			matches!(self, AppointmentStatus::Scheduled | AppointmentStatus::Confirmed)
+		}
+
+		/// Whether the appointment keeps its slot in the doctor's calendar.
+		pub fn holds_slot(&self) -> bool {
+			self.is_open() || *self == AppointmentStatus::CheckedIn
+		}
+
+		/// Whether the appointment lifecycle allows moving from this status to `next`.
+		///
+		/// ```text
+		/// Scheduled -> Confirmed -> CheckedIn -> Completed
+		///     |            |
+		///     +------------+------> NoShow
+		///     +------------+------> Cancelled
+		/// ```
+		///
+		/// Only open appointments can be missed or cancelled: once the patient checked in, the
+		/// appointment can only be completed. Rescheduling keeps an open appointment open and is
+		/// not a transition.
+		pub fn can_transition_to(&self, next: AppointmentStatus) -> bool {
+			use AppointmentStatus::*;
+			match (self, next) {
+				(Scheduled, Confirmed) => true,
+				(Confirmed, CheckedIn) => true,
+				(CheckedIn, Completed) => true,
+				(Scheduled | Confirmed, NoShow | Cancelled) => true,
+				_ => false,
+			}
 		}
 	}
 
 	#[derive(
-		Encode, Decode, MaxEncodedLen, TypeInfo, CloneNoBound, PartialEqNoBound,
+		Encode, Decode, MaxEncodedLen, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound,
 	)]
 	#[scale_info(skip_type_params(T))]
 	pub struct Appointment<T: Config> {
@@ -80,11 +189,127 @@ pub mod pallet {
 		pub(crate) patient_id: T::AccountId,
 		pub(crate) doctor_id: T::AccountId,
 		pub(crate) scheduled_time: u64,
-		pub(crate) status: Option<AppointmentStatus>,
+		pub(crate) status: AppointmentStatus,
 		pub(crate) created_at: u64,
 		pub(crate) updated_at: u64,
 	}
-	
+
+	impl<T: Config> Appointment<T> {
+		/// The account of the patient.
+		pub fn patient_id(&self) -> &T::AccountId {
+			&self.patient_id
+		}
+
+		/// The account of the doctor.
+		pub fn doctor_id(&self) -> &T::AccountId {
+			&self.doctor_id
+		}
+
+		/// Unix time of the appointment, in seconds.
+		pub fn scheduled_time(&self) -> u64 {
+			self.scheduled_time
+		}
+
+		/// Where the appointment is in its lifecycle.
+		pub fn status(&self) -> AppointmentStatus {
+			self.status
+		}
+	}
+
+	/// A recurring weekly period in which a doctor takes appointments.
+	#[derive(
+		Encode, Decode, DecodeWithMemTracking, Clone, Copy, PartialEq, Eq, Debug, MaxEncodedLen,
+		TypeInfo,
+	)]
+	pub struct AvailabilityWindow {
+		/// Day of the week, `0` for Monday to `6` for Sunday.
+		pub weekday: u8,
+		/// Start of the window in seconds since midnight UTC.
+		pub start: u32,
+		/// End of the window in seconds since midnight UTC, at most [`SECONDS_PER_DAY`].
+		pub end: u32,
+	}
+
+	// Weekly calendar published by a doctor
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Availability<T: Config> {
+		pub windows: BoundedVec<AvailabilityWindow, T::MaxAvailabilityWindows>,
+		/// Length of an appointment in seconds. Appointments start every `slot_length` seconds
+		/// from the start of a window.
+		pub slot_length: u32,
+	}
+
+	impl<T: Config> Availability<T> {
+		/// Whether an appointment starting at the unix time `start` falls on one of the slots.
+		pub fn offers(&self, start: u64) -> bool {
+			// 1970-01-01 was a Thursday
+			let weekday = ((start / SECONDS_PER_DAY + 3) % 7) as u8;
+			let time_of_day = (start % SECONDS_PER_DAY) as u32;
+			self.windows.iter().any(|window| {
+				window.weekday == weekday &&
+					time_of_day >= window.start &&
+					time_of_day.saturating_add(self.slot_length) <= window.end &&
+					(time_of_day - window.start).is_multiple_of(self.slot_length)
+			})
+		}
+	}
+
+	/// A period in which a doctor takes no appointments, e.g. a holiday.
+	#[derive(
+		Encode, Decode, DecodeWithMemTracking, Clone, Copy, PartialEq, Eq, Debug, MaxEncodedLen,
+		TypeInfo,
+	)]
+	pub struct UnavailablePeriod {
+		/// Unix time the period starts at, in seconds.
+		pub start: u64,
+		/// Unix time the period ends at, in seconds (exclusive).
+		pub end: u64,
+	}
+
+	// Time taken in a doctor's calendar by an appointment
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo)]
+	pub struct Booking {
+		pub appointment_id: u32,
+		pub start: u64,
+		pub end: u64,
+	}
+
+	/// Work done for an appointment when the block it is queued for starts.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo)]
+	pub enum AppointmentTask {
+		/// Emit `AppointmentReminder` if the appointment is still open.
+		Remind,
+		/// Mark the appointment as a no-show if the patient has not checked in.
+		NoShowCheck,
+	}
+
+	// A task queued in the agenda
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo)]
+	pub struct ScheduledTask {
+		pub appointment_id: u32,
+		pub task: AppointmentTask,
+		/// Scheduled time of the appointment when the task was queued. The task is dropped if the
+		/// appointment has been rescheduled since, e.g. when it was queued before rescheduling
+		/// removed the old tasks from the agenda.
+		pub scheduled_time: u64,
+	}
+
+	// Funds held from the patient for an appointment
+	#[derive(
+		Encode, Decode, MaxEncodedLen, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Escrow<T: Config> {
+		pub payer: T::AccountId,
+		/// Booking deposit still held. Released when the patient checks in.
+		pub deposit: BalanceOf<T>,
+		/// Consultation fee held. Paid to the doctor when the appointment is completed.
+		pub fee: BalanceOf<T>,
+	}
+
 	#[pallet::storage]
 	#[pallet::getter(fn appointments)]
 	pub type Appointments<T> = StorageMap<
@@ -99,6 +324,111 @@ pub mod pallet {
 	#[pallet::getter(fn next_appointment_id)]
 	pub type NextAppointmentId<T: Config> = StorageValue<_, u32, ValueQuery>;
 
+	// Storage for mapping patient to their appointments
+	#[pallet::storage]
+	#[pallet::getter(fn patient_appointments)]
+	pub type PatientAppointments<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // patient_id
+		Blake2_128Concat,
+		u32, // appointment_id
+		(),
+		OptionQuery
+	>;
+
+	// Storage for mapping doctor to their appointments
+	#[pallet::storage]
+	#[pallet::getter(fn doctor_appointments)]
+	pub type DoctorAppointments<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // doctor_id
+		Blake2_128Concat,
+		u32, // appointment_id
+		(),
+		OptionQuery
+	>;
+
+	// Storage for the weekly calendar of each doctor
+	#[pallet::storage]
+	#[pallet::getter(fn doctor_availability)]
+	pub type DoctorAvailability<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // doctor_id
+		Availability<T>,
+		OptionQuery
+	>;
+
+	// Storage for the holidays and other periods a doctor takes no appointments
+	#[pallet::storage]
+	#[pallet::getter(fn doctor_unavailability)]
+	pub type DoctorUnavailability<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // doctor_id
+		BoundedVec<UnavailablePeriod, T::MaxUnavailablePeriods>,
+		ValueQuery
+	>;
+
+	// Storage for the upcoming appointments holding a slot in a doctor's calendar
+	#[pallet::storage]
+	#[pallet::getter(fn doctor_bookings)]
+	pub type DoctorBookings<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // doctor_id
+		BoundedVec<Booking, T::MaxBookingsPerDoctor>,
+		ValueQuery
+	>;
+
+	// Storage for the consultation fee each doctor charges
+	#[pallet::storage]
+	#[pallet::getter(fn consultation_fee)]
+	pub type ConsultationFees<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, // doctor_id
+		BalanceOf<T>,
+		ValueQuery
+	>;
+
+	// Storage for the funds held for each open appointment
+	#[pallet::storage]
+	#[pallet::getter(fn escrows)]
+	pub type Escrows<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // appointment_id
+		Escrow<T>,
+		OptionQuery
+	>;
+
+	// Storage for the reminders and no-show checks to run at the start of each block
+	#[pallet::storage]
+	#[pallet::getter(fn agenda)]
+	pub type Agenda<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<ScheduledTask, T::MaxTasksPerBlock>,
+		ValueQuery
+	>;
+
+	// Storage for the block each queued task of an appointment is in the agenda for
+	#[pallet::storage]
+	#[pallet::getter(fn queued_tasks)]
+	pub type QueuedTasks<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		u32, // appointment_id
+		Twox64Concat,
+		AppointmentTask,
+		BlockNumberFor<T>,
+		OptionQuery
+	>;
+
 	/// Events that functions in this pallet can emit.
 	///
 	/// Events are a simple means of indicating to the outside world (such as dApps, chain explorers
@@ -128,16 +458,113 @@ pub mod pallet {
 		AppointmentUpdated {
 			/// The ID of the appointment.
 			appointment_id: u32,
-			// / The new status of the appointment.
-			// status: AppointmentStatus,
+			/// The new status of the appointment.
+			status: AppointmentStatus,
+		},
+
+		/// An appointment has been moved to another time.
+		AppointmentRescheduled {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The new scheduled time of the appointment.
+			scheduled_time: u64,
+			/// The status of the appointment after the change.
+			status: AppointmentStatus,
 		},
 
 		/// A appointment has been cancelled.
 		AppointmentCancelled {
 			/// The ID of the appointment.
 			appointment_id: u32,
-			/// The account who cancelled the appointment.
+			/// The patient or doctor who cancelled the appointment.
+			cancelled_by: T::AccountId,
+			/// Why the appointment was cancelled.
+			reason: BoundedVec<u8, T::MaxReasonLength>,
+		},
+
+		/// An appointment is coming up. Notification services pick this up to remind the patient
+		/// and the doctor.
+		AppointmentReminder {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The account of the patient.
 			patient_id: T::AccountId,
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// The scheduled time of the appointment.
+			scheduled_time: u64,
+		},
+
+		/// A doctor set the fee they charge for an appointment.
+		ConsultationFeeSet {
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// The new fee.
+			fee: BalanceOf<T>,
+		},
+
+		/// Funds have been held from the patient for an appointment.
+		DepositHeld {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The account the funds are held from.
+			patient_id: T::AccountId,
+			/// The booking deposit.
+			deposit: BalanceOf<T>,
+			/// The consultation fee.
+			fee: BalanceOf<T>,
+		},
+
+		/// Held funds have been returned to the patient.
+		DepositReleased {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The amount released.
+			amount: BalanceOf<T>,
+		},
+
+		/// Part of the deposit has been paid to the doctor for a late cancellation or a no-show.
+		DepositSlashed {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The amount paid to the doctor.
+			amount: BalanceOf<T>,
+		},
+
+		/// The consultation fee has been paid to the doctor.
+		FeePaid {
+			/// The ID of the appointment.
+			appointment_id: u32,
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// The amount paid.
+			amount: BalanceOf<T>,
+		},
+
+		/// A doctor published their weekly availability.
+		AvailabilitySet {
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// Length of an appointment in seconds.
+			slot_length: u32,
+		},
+
+		/// A doctor blocked a period in their calendar.
+		UnavailabilityAdded {
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// Start of the period.
+			start: u64,
+			/// End of the period.
+			end: u64,
+		},
+
+		/// A doctor reopened a blocked period.
+		UnavailabilityRemoved {
+			/// The account of the doctor.
+			doctor_id: T::AccountId,
+			/// Start of the period.
+			start: u64,
 		},
 	}
 
@@ -153,7 +580,7 @@ pub mod pallet {
 	pub enum Error<T> {
 		/// The value retrieved was `None` as no value was previously set.
 		NoneValue,
-		
+
 		/// There was an attempt to increment the value in storage over `u32::MAX`.
 		StorageOverflow,
 
@@ -168,6 +595,71 @@ pub mod pallet {
 
 		/// Invalid appointment time.
 		InvalidAppointmentTime,
+
+		/// The caller is neither the patient nor the doctor of the appointment.
+		NotParticipant,
+
+		/// Only the patient of the appointment can do this.
+		NotPatient,
+
+		/// Only the doctor of the appointment can do this.
+		NotDoctor,
+
+		/// The patient and the doctor are the same account.
+		PatientIsDoctor,
+
+		/// Only registered doctors can book appointments on behalf of a patient.
+		NotRegisteredDoctor,
+
+		/// The appointment's current status does not allow this change.
+		InvalidStatusTransition,
+
+		/// The appointment's scheduled time has not been reached yet.
+		AppointmentNotDue,
+
+		/// The doctor does not take appointments at that time.
+		DoctorUnavailable,
+
+		/// The doctor already has an appointment at that time.
+		SlotTaken,
+
+		/// The doctor has too many upcoming appointments.
+		TooManyBookings,
+
+		/// An availability window is out of range or shorter than a slot.
+		InvalidAvailability,
+
+		/// The unavailable period ends before it starts or is already over.
+		InvalidPeriod,
+
+		/// The doctor has too many upcoming unavailable periods.
+		TooManyUnavailablePeriods,
+
+		/// The doctor has no unavailable period starting at that time.
+		PeriodNotFound,
+
+		/// Too many reminders are already queued around the appointment's time.
+		AgendaFull,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let tasks = Agenda::<T>::take(n);
+			let count = tasks.len() as u32;
+			// The timestamp of this block is not set yet, so this is the time of the previous one
+			let now = <T as Config>::TimeProvider::now().as_secs();
+			for task in tasks {
+				QueuedTasks::<T>::remove(task.appointment_id, task.task);
+				Self::run_task(task, now);
+			}
+			T::WeightInfo::on_initialize(count)
+		}
+
+		fn integrity_test() {
+			assert!(T::ExpectedBlockTime::get() > 0, "ExpectedBlockTime must not be zero");
+			assert!(T::MaxTasksPerBlock::get() > 0, "MaxTasksPerBlock must not be zero");
+		}
 	}
 
 	/// The pallet's dispatchable functions ([`Call`]s).
@@ -184,20 +676,221 @@ pub mod pallet {
 	/// The [`weight`] macro is used to assign a weight to each call.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(10_000)]
+		/// Book an appointment between a patient and a doctor, for a free slot in the doctor's
+		/// availability. Either of them can book it, the doctor only if they are one of `Doctors`.
+		///
+		/// When the patient books, `BookingDeposit` and the doctor's consultation fee are held from
+		/// their balance until the appointment is settled.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::create_medical_appointment())]
 		pub fn create_medical_appointment(
 			origin: OriginFor<T>,
 			patient_id: T::AccountId,
 			doctor_id: T::AccountId,
 			scheduled_time: u64,
 		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_create_appointment(&who, patient_id, doctor_id, scheduled_time).map(|_| ())
+		}
+
+		/// Confirm a scheduled appointment. Only the doctor can confirm.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::confirm_appointment())]
+		pub fn confirm_appointment(origin: OriginFor<T>, appointment_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::transition(appointment_id, AppointmentStatus::Confirmed, |appointment| {
+				ensure!(appointment.doctor_id == who, Error::<T>::NotDoctor);
+				Ok(())
+			})
+		}
+
+		/// Move an open appointment to another free slot of the doctor. A change made by the patient has to be
+		/// confirmed again by the doctor; a change made by the doctor stays confirmed.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::reschedule_appointment())]
+		pub fn reschedule_appointment(
+			origin: OriginFor<T>,
+			appointment_id: u32,
+			scheduled_time: u64,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::move_appointment(&who, appointment_id, scheduled_time, |appointment| {
+				ensure!(
+					who == appointment.patient_id || who == appointment.doctor_id,
+					Error::<T>::NotParticipant
+				);
+				Ok(())
+			})
+		}
+
+		/// Cancel an open appointment. Either the patient or the doctor can cancel, giving a
+		/// reason. The held funds are returned, except for `LateCancellationPenalty` of the
+		/// deposit when the patient cancels less than `LateCancellationPeriod` before the
+		/// appointment.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::cancel_appointment())]
+		pub fn cancel_appointment(
+			origin: OriginFor<T>,
+			appointment_id: u32,
+			reason: BoundedVec<u8, T::MaxReasonLength>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::cancel(&who, appointment_id, reason, |appointment| {
+				ensure!(
+					who == appointment.patient_id || who == appointment.doctor_id,
+					Error::<T>::NotParticipant
+				);
+				Ok(())
+			})
+		}
+
+		/// Check in for a confirmed appointment. Only the patient can check in.
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::check_in())]
+		pub fn check_in(origin: OriginFor<T>, appointment_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::transition(appointment_id, AppointmentStatus::CheckedIn, |appointment| {
+				ensure!(appointment.patient_id == who, Error::<T>::NotPatient);
+				Ok(())
+			})
+		}
+
+		/// Close the appointment of a checked in patient. Only the doctor can complete it.
+		#[pallet::call_index(5)]
+		#[pallet::weight(T::WeightInfo::complete_appointment())]
+		pub fn complete_appointment(origin: OriginFor<T>, appointment_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::transition(appointment_id, AppointmentStatus::Completed, |appointment| {
+				ensure!(appointment.doctor_id == who, Error::<T>::NotDoctor);
+				Ok(())
+			})
+		}
+
+		/// Record that the patient did not turn up for a scheduled or confirmed appointment. Only
+		/// the doctor can do this, once the scheduled time has passed; a patient who checked in is
+		/// never a no-show. Appointments nobody acted on become no-shows
+		/// automatically `NoShowGracePeriod` after their time.
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::mark_no_show())]
+		pub fn mark_no_show(origin: OriginFor<T>, appointment_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let time: u64 = <T as Config>::TimeProvider::now().as_secs();
+			Self::transition(appointment_id, AppointmentStatus::NoShow, |appointment| {
+				ensure!(appointment.doctor_id == who, Error::<T>::NotDoctor);
+				ensure!(time >= appointment.scheduled_time, Error::<T>::AppointmentNotDue);
+				Ok(())
+			})
+		}
+
+		/// Publish the caller's weekly availability as a doctor, replacing the previous one.
+		/// Appointments already booked are kept.
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::set_availability())]
+		pub fn set_availability(
+			origin: OriginFor<T>,
+			windows: BoundedVec<AvailabilityWindow, T::MaxAvailabilityWindows>,
+			slot_length: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(slot_length > 0, Error::<T>::InvalidAvailability);
+			for window in windows.iter() {
+				ensure!(
+					window.weekday < 7 &&
+						window.start < window.end &&
+						window.end as u64 <= SECONDS_PER_DAY &&
+						window.end - window.start >= slot_length,
+					Error::<T>::InvalidAvailability
+				);
+			}
+
+			DoctorAvailability::<T>::insert(&who, Availability { windows, slot_length });
+
+			Self::deposit_event(Event::AvailabilitySet { doctor_id: who, slot_length });
+
+			Ok(())
+		}
+
+		/// Block a period of the caller's calendar. Appointments already booked in it are kept
+		/// and have to be cancelled or rescheduled.
+		#[pallet::call_index(8)]
+		#[pallet::weight(T::WeightInfo::add_unavailability())]
+		pub fn add_unavailability(origin: OriginFor<T>, start: u64, end: u64) -> DispatchResult {
 			let who = ensure_signed(origin)?;
 			let time: u64 = <T as Config>::TimeProvider::now().as_secs();
 
+			ensure!(start < end && end > time, Error::<T>::InvalidPeriod);
+
+			DoctorUnavailability::<T>::try_mutate(&who, |periods| -> DispatchResult {
+				// Periods that are over no longer matter
+				periods.retain(|period| period.end > time);
+				periods
+					.try_push(UnavailablePeriod { start, end })
+					.map_err(|_| Error::<T>::TooManyUnavailablePeriods)?;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::UnavailabilityAdded { doctor_id: who, start, end });
+
+			Ok(())
+		}
+
+		/// Reopen the period of the caller's calendar starting at `start`.
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::WeightInfo::remove_unavailability())]
+		pub fn remove_unavailability(origin: OriginFor<T>, start: u64) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			DoctorUnavailability::<T>::try_mutate(&who, |periods| -> DispatchResult {
+				let index = periods
+					.iter()
+					.position(|period| period.start == start)
+					.ok_or(Error::<T>::PeriodNotFound)?;
+				periods.remove(index);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::UnavailabilityRemoved { doctor_id: who, start });
+
+			Ok(())
+		}
+
+		/// Set the fee the caller charges as a doctor. It applies to appointments booked from now
+		/// on.
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::set_consultation_fee())]
+		pub fn set_consultation_fee(origin: OriginFor<T>, fee: BalanceOf<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ConsultationFees::<T>::insert(&who, fee);
+
+			Self::deposit_event(Event::ConsultationFeeSet { doctor_id: who, fee });
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Book an appointment on behalf of `who`, who has to be the patient or a registered doctor
+		/// booking with themselves, returning its id. Used by other pallets booking appointments,
+		/// e.g. follow-ups of a visit.
+		pub fn do_create_appointment(
+			who: &T::AccountId,
+			patient_id: T::AccountId,
+			doctor_id: T::AccountId,
+			scheduled_time: u64,
+		) -> Result<u32, DispatchError> {
+			let time: u64 = <T as Config>::TimeProvider::now().as_secs();
+
+			ensure!(*who == patient_id || *who == doctor_id, Error::<T>::NotParticipant);
+			ensure!(patient_id != doctor_id, Error::<T>::PatientIsDoctor);
+			// Anyone can publish availability, so only registered doctors book for other accounts
+			ensure!(*who == patient_id || T::Doctors::contains(who), Error::<T>::NotRegisteredDoctor);
 			// Ensure the appointment time is in the future
 			ensure!(scheduled_time > time, Error::<T>::InvalidAppointmentTime);
 
 			let appointment_id = Self::next_appointment_id();
+			let next_id = appointment_id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?;
 
 			// Check if the appointment already exists
 			ensure!(!Appointments::<T>::contains_key(appointment_id), Error::<T>::AppointmentAlreadyExists);
@@ -207,14 +900,21 @@ pub mod pallet {
 				patient_id: patient_id.clone(),
 				doctor_id: doctor_id.clone(),
 				scheduled_time,
-				status: Some(AppointmentStatus::default()),
+				status: AppointmentStatus::default(),
 				created_at: time,
-				// <frame_system::Pallet<T>>::block_number()
 				updated_at: time,
 			};
 
+			Self::book_slot(&doctor_id, appointment_id, scheduled_time, time)?;
+			Self::schedule_tasks(appointment_id, scheduled_time, time)?;
+			// A doctor booking for a patient does not take their funds
+			if *who == patient_id {
+				Self::hold_deposit(appointment_id, &patient_id, &doctor_id)?;
+			}
 			Appointments::<T>::insert(appointment_id, new_appointment);
-			NextAppointmentId::<T>::put(appointment_id + 1);
+			PatientAppointments::<T>::insert(&patient_id, appointment_id, ());
+			DoctorAppointments::<T>::insert(&doctor_id, appointment_id, ());
+			NextAppointmentId::<T>::put(next_id);
 
 			Self::deposit_event(Event::AppointmentBooked {
 				appointment_id,
@@ -223,7 +923,377 @@ pub mod pallet {
 				scheduled_time,
 			});
 
+			Ok(appointment_id)
+		}
+
+		/// Move an open appointment to another free slot on behalf of `who`, who has to be its
+		/// doctor, so the appointment stays confirmed. Used by other pallets managing the
+		/// appointments they booked.
+		pub fn do_reschedule(
+			who: &T::AccountId,
+			appointment_id: u32,
+			scheduled_time: u64,
+		) -> DispatchResult {
+			Self::move_appointment(who, appointment_id, scheduled_time, |appointment| {
+				ensure!(*who == appointment.doctor_id, Error::<T>::NotDoctor);
+				Ok(())
+			})
+		}
+
+		/// Cancel an open appointment on behalf of `who`, who has to be its doctor, returning the
+		/// held funds in full. Used by other pallets managing the appointments they booked.
+		pub fn do_cancel(
+			who: &T::AccountId,
+			appointment_id: u32,
+			reason: BoundedVec<u8, T::MaxReasonLength>,
+		) -> DispatchResult {
+			Self::cancel(who, appointment_id, reason, |appointment| {
+				ensure!(*who == appointment.doctor_id, Error::<T>::NotDoctor);
+				Ok(())
+			})
+		}
+
+		// Move an open appointment to `scheduled_time` after `check` accepted the caller. A change
+		// made by the doctor stays confirmed, one made by the patient has to be confirmed again.
+		fn move_appointment(
+			who: &T::AccountId,
+			appointment_id: u32,
+			scheduled_time: u64,
+			check: impl FnOnce(&Appointment<T>) -> DispatchResult,
+		) -> DispatchResult {
+			let time: u64 = <T as Config>::TimeProvider::now().as_secs();
+
+			Appointments::<T>::try_mutate(appointment_id, |maybe_appointment| -> DispatchResult {
+				let appointment = maybe_appointment.as_mut().ok_or(Error::<T>::AppointmentNotFound)?;
+				check(appointment)?;
+				Self::ensure_not_completed(appointment)?;
+				ensure!(appointment.status.is_open(), Error::<T>::InvalidStatusTransition);
+				ensure!(scheduled_time > time, Error::<T>::InvalidAppointmentTime);
+				Self::book_slot(&appointment.doctor_id, appointment_id, scheduled_time, time)?;
+				// Tasks queued for the previous time would otherwise take up room in the agenda
+				Self::unschedule_tasks(appointment_id);
+				Self::schedule_tasks(appointment_id, scheduled_time, time)?;
+
+				let status = if *who == appointment.doctor_id {
+					AppointmentStatus::Confirmed
+				} else {
+					AppointmentStatus::Scheduled
+				};
+				appointment.scheduled_time = scheduled_time;
+				appointment.status = status;
+				appointment.updated_at = time;
+
+				Self::deposit_event(Event::AppointmentRescheduled {
+					appointment_id,
+					scheduled_time,
+					status,
+				});
+				Ok(())
+			})
+		}
+
+		// Cancel an open appointment after `check` accepted the caller, settling the held funds
+		fn cancel(
+			who: &T::AccountId,
+			appointment_id: u32,
+			reason: BoundedVec<u8, T::MaxReasonLength>,
+			check: impl FnOnce(&Appointment<T>) -> DispatchResult,
+		) -> DispatchResult {
+			let time: u64 = <T as Config>::TimeProvider::now().as_secs();
+			Self::transition(appointment_id, AppointmentStatus::Cancelled, check)?;
+
+			let appointment = Self::appointments(appointment_id).ok_or(Error::<T>::AppointmentNotFound)?;
+			let late = *who == appointment.patient_id &&
+				time.saturating_add(T::LateCancellationPeriod::get()) > appointment.scheduled_time;
+			let forfeit = if late { T::LateCancellationPenalty::get() } else { Perbill::zero() };
+			Self::settle(appointment_id, &appointment.doctor_id, forfeit, false)?;
+
+			Self::deposit_event(Event::AppointmentCancelled {
+				appointment_id,
+				cancelled_by: who.clone(),
+				reason,
+			});
+
+			Ok(())
+		}
+
+		// Move an appointment to `next` after `check` accepted the caller, emitting
+		// `AppointmentUpdated` for every change other than a cancellation
+		fn transition(
+			appointment_id: u32,
+			next: AppointmentStatus,
+			check: impl FnOnce(&Appointment<T>) -> DispatchResult,
+		) -> DispatchResult {
+			Appointments::<T>::try_mutate(appointment_id, |maybe_appointment| -> DispatchResult {
+				let appointment = maybe_appointment.as_mut().ok_or(Error::<T>::AppointmentNotFound)?;
+				check(appointment)?;
+				Self::ensure_not_completed(appointment)?;
+				ensure!(appointment.status.can_transition_to(next), Error::<T>::InvalidStatusTransition);
+
+				appointment.status = next;
+				appointment.updated_at = <T as Config>::TimeProvider::now().as_secs();
+				if !next.holds_slot() {
+					Self::release_slot(&appointment.doctor_id, appointment_id);
+				}
+				// Tasks of a closed appointment would only take up room in the agenda
+				if !next.is_open() {
+					Self::unschedule_tasks(appointment_id);
+				}
+				// Cancellations are settled by the caller, who knows whether they are late
+				match next {
+					AppointmentStatus::CheckedIn => Self::release_deposit(appointment_id)?,
+					AppointmentStatus::Completed =>
+						Self::settle(appointment_id, &appointment.doctor_id, Perbill::zero(), true)?,
+					AppointmentStatus::NoShow =>
+						Self::settle(appointment_id, &appointment.doctor_id, Perbill::one(), false)?,
+					_ => {},
+				}
+
+				if next != AppointmentStatus::Cancelled {
+					Self::deposit_event(Event::AppointmentUpdated { appointment_id, status: next });
+				}
+				Ok(())
+			})
+		}
+
+		// Take the slot starting at `start` in the doctor's calendar for the appointment, moving it
+		// if the appointment already holds one
+		fn book_slot(
+			doctor_id: &T::AccountId,
+			appointment_id: u32,
+			start: u64,
+			now: u64,
+		) -> DispatchResult {
+			let availability =
+				DoctorAvailability::<T>::get(doctor_id).ok_or(Error::<T>::DoctorUnavailable)?;
+			ensure!(availability.offers(start), Error::<T>::DoctorUnavailable);
+			let end = start.saturating_add(availability.slot_length as u64);
+			ensure!(
+				!DoctorUnavailability::<T>::get(doctor_id)
+					.iter()
+					.any(|period| period.start < end && start < period.end),
+				Error::<T>::DoctorUnavailable
+			);
+
+			DoctorBookings::<T>::try_mutate(doctor_id, |bookings| -> DispatchResult {
+				// Past appointments cannot overlap a new one
+				bookings.retain(|booking| booking.end > now && booking.appointment_id != appointment_id);
+				ensure!(
+					!bookings.iter().any(|booking| booking.start < end && start < booking.end),
+					Error::<T>::SlotTaken
+				);
+				bookings
+					.try_push(Booking { appointment_id, start, end })
+					.map_err(|_| Error::<T>::TooManyBookings)?;
+				Ok(())
+			})
+		}
+
+		// Queue the reminder and the no-show check of an appointment at `scheduled_time`
+		fn schedule_tasks(appointment_id: u32, scheduled_time: u64, now: u64) -> DispatchResult {
+			let remind_at = scheduled_time.saturating_sub(T::ReminderLeadTime::get());
+			let no_show_at = scheduled_time.saturating_add(T::NoShowGracePeriod::get());
+			for (task, at) in [
+				(AppointmentTask::Remind, remind_at),
+				(AppointmentTask::NoShowCheck, no_show_at),
+			] {
+				Self::schedule_task(ScheduledTask { appointment_id, task, scheduled_time }, at, now)?;
+			}
 			Ok(())
 		}
+
+		// Queue a task for the first block expected to start at or after the unix time `at`
+		fn schedule_task(task: ScheduledTask, at: u64, now: u64) -> DispatchResult {
+			let block_time = T::ExpectedBlockTime::get().max(1);
+			let blocks = at.saturating_sub(now).saturating_mul(1_000).div_ceil(block_time).max(1);
+			let mut block = frame_system::Pallet::<T>::block_number()
+				.saturating_add(blocks.saturated_into::<BlockNumberFor<T>>());
+
+			for _ in 0..AGENDA_LOOKAHEAD {
+				if Agenda::<T>::mutate(block, |agenda| agenda.try_push(task.clone()).is_ok()) {
+					QueuedTasks::<T>::insert(task.appointment_id, task.task, block);
+					return Ok(());
+				}
+				block = block.saturating_add(One::one());
+			}
+			Err(Error::<T>::AgendaFull.into())
+		}
+
+		// Remove the queued tasks of an appointment from the agenda
+		fn unschedule_tasks(appointment_id: u32) {
+			for (task, block) in QueuedTasks::<T>::drain_prefix(appointment_id) {
+				Agenda::<T>::mutate_exists(block, |maybe_agenda| {
+					if let Some(agenda) = maybe_agenda {
+						agenda.retain(|queued| queued.appointment_id != appointment_id || queued.task != task);
+						if agenda.is_empty() {
+							*maybe_agenda = None;
+						}
+					}
+				});
+			}
+		}
+
+		// Run a task from the agenda. Tasks of appointments that have been closed or rescheduled
+		// since they were queued do nothing.
+		fn run_task(task: ScheduledTask, now: u64) {
+			let Some(appointment) = Appointments::<T>::get(task.appointment_id) else {
+				return;
+			};
+			if appointment.scheduled_time != task.scheduled_time {
+				return;
+			}
+
+			match task.task {
+				AppointmentTask::Remind =>
+					if appointment.status.is_open() {
+						Self::deposit_event(Event::AppointmentReminder {
+							appointment_id: task.appointment_id,
+							patient_id: appointment.patient_id,
+							doctor_id: appointment.doctor_id,
+							scheduled_time: appointment.scheduled_time,
+						});
+					},
+				AppointmentTask::NoShowCheck => {
+					if !appointment.status.is_open() {
+						return;
+					}
+					let due = appointment.scheduled_time.saturating_add(T::NoShowGracePeriod::get());
+					if now < due {
+						// Blocks came faster than expected, check again later
+						let _ = Self::schedule_task(task, due, now);
+						return;
+					}
+					let _ = frame_support::storage::with_storage_layer(|| {
+						Self::transition(task.appointment_id, AppointmentStatus::NoShow, |_| Ok(()))
+					});
+				},
+			}
+		}
+
+		// Hold the booking deposit and the doctor's consultation fee from the patient
+		fn hold_deposit(
+			appointment_id: u32,
+			patient_id: &T::AccountId,
+			doctor_id: &T::AccountId,
+		) -> DispatchResult {
+			let deposit = T::BookingDeposit::get();
+			let fee = ConsultationFees::<T>::get(doctor_id);
+			let amount = deposit.saturating_add(fee);
+			if amount.is_zero() {
+				return Ok(());
+			}
+
+			T::Currency::hold(&HoldReason::AppointmentDeposit.into(), patient_id, amount)?;
+			Escrows::<T>::insert(appointment_id, Escrow { payer: patient_id.clone(), deposit, fee });
+
+			Self::deposit_event(Event::DepositHeld {
+				appointment_id,
+				patient_id: patient_id.clone(),
+				deposit,
+				fee,
+			});
+			Ok(())
+		}
+
+		// Return the booking deposit to the patient, keeping the fee held
+		fn release_deposit(appointment_id: u32) -> DispatchResult {
+			let Some(mut escrow) = Escrows::<T>::get(appointment_id) else {
+				return Ok(());
+			};
+			Self::release(appointment_id, &escrow.payer, escrow.deposit)?;
+			escrow.deposit = Zero::zero();
+			Escrows::<T>::insert(appointment_id, escrow);
+			Ok(())
+		}
+
+		// Close the escrow of an appointment: `forfeit` of the remaining deposit goes to the doctor,
+		// the fee goes to the doctor if `pay_fee` is set, and the rest goes back to the patient
+		fn settle(
+			appointment_id: u32,
+			doctor_id: &T::AccountId,
+			forfeit: Perbill,
+			pay_fee: bool,
+		) -> DispatchResult {
+			let Some(escrow) = Escrows::<T>::take(appointment_id) else {
+				return Ok(());
+			};
+			let reason = HoldReason::AppointmentDeposit.into();
+
+			let slashed = forfeit * escrow.deposit;
+			if !slashed.is_zero() {
+				let amount = T::Currency::transfer_on_hold(
+					&reason,
+					&escrow.payer,
+					doctor_id,
+					slashed,
+					Precision::BestEffort,
+					Restriction::Free,
+					Fortitude::Polite,
+				)?;
+				Self::deposit_event(Event::DepositSlashed { appointment_id, amount });
+			}
+
+			let mut refund = escrow.deposit.saturating_sub(slashed);
+			if pay_fee {
+				if !escrow.fee.is_zero() {
+					let amount = T::Currency::transfer_on_hold(
+						&reason,
+						&escrow.payer,
+						doctor_id,
+						escrow.fee,
+						Precision::BestEffort,
+						Restriction::Free,
+						Fortitude::Polite,
+					)?;
+					Self::deposit_event(Event::FeePaid {
+						appointment_id,
+						doctor_id: doctor_id.clone(),
+						amount,
+					});
+				}
+			} else {
+				refund = refund.saturating_add(escrow.fee);
+			}
+			Self::release(appointment_id, &escrow.payer, refund)
+		}
+
+		fn release(appointment_id: u32, payer: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+			if amount.is_zero() {
+				return Ok(());
+			}
+			let amount = T::Currency::release(
+				&HoldReason::AppointmentDeposit.into(),
+				payer,
+				amount,
+				Precision::BestEffort,
+			)?;
+			Self::deposit_event(Event::DepositReleased { appointment_id, amount });
+			Ok(())
+		}
+
+		// Free the slot held by the appointment in the doctor's calendar
+		fn release_slot(doctor_id: &T::AccountId, appointment_id: u32) {
+			DoctorBookings::<T>::mutate(doctor_id, |bookings| {
+				bookings.retain(|booking| booking.appointment_id != appointment_id)
+			});
+		}
+
+		fn ensure_not_completed(appointment: &Appointment<T>) -> DispatchResult {
+			ensure!(
+				appointment.status != AppointmentStatus::Completed,
+				Error::<T>::AppointmentAlreadyCompleted
+			);
+			Ok(())
+		}
+
+		/// The IDs of all appointments of a patient.
+		pub fn appointments_of_patient(patient_id: &T::AccountId) -> Vec<u32> {
+			PatientAppointments::<T>::iter_key_prefix(patient_id).collect()
+		}
+
+		/// The IDs of all appointments of a doctor.
+		pub fn appointments_of_doctor(doctor_id: &T::AccountId) -> Vec<u32> {
+			DoctorAppointments::<T>::iter_key_prefix(doctor_id).collect()
+		}
 	}
 }
@@ -1,6 +1,10 @@
 use crate as pallet_medical_appointment;
-use frame_support::derive_impl;
-use sp_runtime::BuildStorage;
+use core::time::Duration;
+use frame_support::{
+	derive_impl, parameter_types,
+	traits::{ConstU32, ConstU64, Contains, UnixTime},
+};
+use sp_runtime::{BuildStorage, Perbill};
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -28,19 +32,79 @@ mod runtime {
 
 	#[runtime::pallet_index(1)]
 	pub type MedicalAppointment = pallet_medical_appointment::Pallet<Test>;
+
+	#[runtime::pallet_index(2)]
+	pub type Balances = pallet_balances::Pallet<Test>;
 }
 
 #[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
 impl frame_system::Config for Test {
 	type Block = Block;
+	type AccountData = pallet_balances::AccountData<u64>;
+}
+
+#[derive_impl(pallet_balances::config_preludes::TestDefaultConfig)]
+impl pallet_balances::Config for Test {
+	type AccountStore = System;
+}
+
+parameter_types! {
+	// Current unix time in seconds seen by the pallet
+	pub static Now: u64 = 1_000;
+	pub const LateCancellationPenalty: Perbill = Perbill::from_percent(50);
+}
+
+pub struct MockTime;
+impl UnixTime for MockTime {
+	fn now() -> Duration {
+		Duration::from_secs(Now::get())
+	}
+}
+
+// Account 2, the doctor of the tests, is the only registered doctor
+pub struct MockDoctors;
+impl Contains<u64> for MockDoctors {
+	fn contains(who: &u64) -> bool {
+		*who == 2
+	}
 }
 
 impl pallet_medical_appointment::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type TimeProvider = MockTime;
+	type RuntimeAppointmentStatus = pallet_medical_appointment::AppointmentStatus;
+	type MaxReasonLength = ConstU32<32>;
+	type MaxAvailabilityWindows = ConstU32<4>;
+	type MaxUnavailablePeriods = ConstU32<2>;
+	type MaxBookingsPerDoctor = ConstU32<4>;
+	type ExpectedBlockTime = ConstU64<6_000>;
+	type ReminderLeadTime = ConstU64<1_800>;
+	type NoShowGracePeriod = ConstU64<900>;
+	type MaxTasksPerBlock = ConstU32<2>;
+	type Currency = Balances;
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type BookingDeposit = ConstU64<10>;
+	// A day
+	type LateCancellationPeriod = ConstU64<86_400>;
+	type LateCancellationPenalty = LateCancellationPenalty;
+	type Doctors = MockDoctors;
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: (1..=3).map(|account| (account, 1_000)).collect(),
+		..Default::default()
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext: sp_io::TestExternalities = t.into();
+	// Events are not deposited on the genesis block
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		Now::set(1_000);
+	});
+	ext
 }
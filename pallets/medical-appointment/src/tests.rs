@@ -1,2 +1,910 @@
-use crate::{mock::*, Error, Event, Something};
-use frame_support::{assert_noop, assert_ok};
\ No newline at end of file
+use crate::{
+	mock::*, Agenda, Appointment, AppointmentStatus, AppointmentTask, AvailabilityWindow, Booking,
+	Error, Escrow, Event, HoldReason, ScheduledTask, UnavailablePeriod, SECONDS_PER_DAY,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{
+		fungible::{Inspect, InspectHold},
+		Hooks,
+	},
+	BoundedVec,
+};
+use sp_runtime::TokenError;
+
+const PATIENT: u64 = 1;
+const DOCTOR: u64 = 2;
+const STRANGER: u64 = 3;
+
+// The mock starts on Thursday 1970-01-01 at 00:16:40 UTC
+const THURSDAY: u8 = 3;
+const HALF_HOUR: u64 = 1_800;
+// The second slot of DOCTOR's Thursday window, 01:30
+const LATER: u64 = 5_400;
+
+// Have DOCTOR take half hour appointments on Thursdays from 01:00 to 05:00
+fn publish_availability() {
+	if MedicalAppointment::doctor_availability(DOCTOR).is_none() {
+		assert_ok!(MedicalAppointment::set_availability(
+			RuntimeOrigin::signed(DOCTOR),
+			windows(&[(THURSDAY, 3_600, 18_000)]),
+			HALF_HOUR as u32,
+		));
+	}
+}
+
+fn windows(windows: &[(u8, u32, u32)]) -> BoundedVec<AvailabilityWindow, frame_support::traits::ConstU32<4>> {
+	BoundedVec::truncate_from(
+		windows
+			.iter()
+			.map(|&(weekday, start, end)| AvailabilityWindow { weekday, start, end })
+			.collect(),
+	)
+}
+
+fn book_at(scheduled_time: u64) -> frame_support::dispatch::DispatchResult {
+	MedicalAppointment::create_medical_appointment(
+		RuntimeOrigin::signed(PATIENT),
+		PATIENT,
+		DOCTOR,
+		scheduled_time,
+	)
+}
+
+// Book appointment between PATIENT and DOCTOR at `LATER` and return its ID
+fn book() -> u32 {
+	publish_availability();
+	let appointment_id = MedicalAppointment::next_appointment_id();
+	assert_ok!(book_at(LATER));
+	appointment_id
+}
+
+// Book an appointment and have the doctor confirm it
+fn confirmed() -> u32 {
+	let appointment_id = book();
+	assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+	appointment_id
+}
+
+fn reason(text: &[u8]) -> BoundedVec<u8, frame_support::traits::ConstU32<32>> {
+	BoundedVec::truncate_from(text.to_vec())
+}
+
+fn status(appointment_id: u32) -> AppointmentStatus {
+	MedicalAppointment::appointments(appointment_id).unwrap().status
+}
+
+// Produce blocks up to `n`, six seconds apart as the mock expects
+fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		let block = System::block_number() + 1;
+		System::set_block_number(block);
+		Now::set(Now::get() + 6);
+		MedicalAppointment::on_initialize(block);
+	}
+}
+
+fn reminders() -> Vec<u64> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			RuntimeEvent::MedicalAppointment(Event::AppointmentReminder { scheduled_time, .. }) =>
+				Some(scheduled_time),
+			_ => None,
+		})
+		.collect()
+}
+
+// Blocks the reminder and the no-show check of an appointment at `LATER` booked on block 1 run at:
+// half an hour before and fifteen minutes after it
+const REMINDER_BLOCK: u64 = 435;
+const NO_SHOW_BLOCK: u64 = 885;
+
+#[test]
+fn create_medical_appointment_works() {
+	new_test_ext().execute_with(|| {
+		publish_availability();
+		assert_ok!(MedicalAppointment::create_medical_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			PATIENT,
+			DOCTOR,
+			LATER,
+		));
+
+		assert_eq!(
+			MedicalAppointment::appointments(0),
+			Some(Appointment {
+				appointment_id: 0,
+				patient_id: PATIENT,
+				doctor_id: DOCTOR,
+				scheduled_time: LATER,
+				status: AppointmentStatus::Scheduled,
+				created_at: 1_000,
+				updated_at: 1_000,
+			})
+		);
+		assert_eq!(MedicalAppointment::next_appointment_id(), 1);
+		assert_eq!(MedicalAppointment::appointments_of_patient(&PATIENT), vec![0]);
+		assert_eq!(MedicalAppointment::appointments_of_doctor(&DOCTOR), vec![0]);
+		assert!(MedicalAppointment::appointments_of_patient(&DOCTOR).is_empty());
+		assert_eq!(
+			MedicalAppointment::doctor_bookings(DOCTOR).into_inner(),
+			vec![Booking { appointment_id: 0, start: LATER, end: LATER + HALF_HOUR }]
+		);
+		System::assert_last_event(
+			Event::AppointmentBooked {
+				appointment_id: 0,
+				patient_id: PATIENT,
+				doctor_id: DOCTOR,
+				scheduled_time: LATER,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn create_medical_appointment_validates_input() {
+	new_test_ext().execute_with(|| {
+		publish_availability();
+		assert_noop!(
+			MedicalAppointment::create_medical_appointment(
+				RuntimeOrigin::signed(STRANGER),
+				PATIENT,
+				DOCTOR,
+				LATER,
+			),
+			Error::<Test>::NotParticipant
+		);
+		assert_noop!(
+			MedicalAppointment::create_medical_appointment(
+				RuntimeOrigin::signed(PATIENT),
+				PATIENT,
+				PATIENT,
+				LATER,
+			),
+			Error::<Test>::PatientIsDoctor
+		);
+		assert_noop!(
+			MedicalAppointment::create_medical_appointment(
+				RuntimeOrigin::signed(PATIENT),
+				PATIENT,
+				DOCTOR,
+				1_000,
+			),
+			Error::<Test>::InvalidAppointmentTime
+		);
+
+		// Publishing availability does not let an account book for others
+		assert_ok!(MedicalAppointment::set_availability(
+			RuntimeOrigin::signed(STRANGER),
+			windows(&[(THURSDAY, 3_600, 18_000)]),
+			HALF_HOUR as u32,
+		));
+		assert_noop!(
+			MedicalAppointment::create_medical_appointment(
+				RuntimeOrigin::signed(STRANGER),
+				PATIENT,
+				STRANGER,
+				LATER,
+			),
+			Error::<Test>::NotRegisteredDoctor
+		);
+		// The patient can still book with them
+		assert_ok!(MedicalAppointment::create_medical_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			PATIENT,
+			STRANGER,
+			LATER,
+		));
+	});
+}
+
+#[test]
+fn appointment_lifecycle_works() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = book();
+
+		assert_noop!(
+			MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(PATIENT), appointment_id),
+			Error::<Test>::NotDoctor
+		);
+		Now::set(2_000);
+		assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+		assert_eq!(status(appointment_id), AppointmentStatus::Confirmed);
+		assert_eq!(MedicalAppointment::appointments(appointment_id).unwrap().updated_at, 2_000);
+		System::assert_last_event(
+			Event::AppointmentUpdated { appointment_id, status: AppointmentStatus::Confirmed }.into(),
+		);
+
+		assert_noop!(
+			MedicalAppointment::check_in(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::NotPatient
+		);
+		assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id));
+		assert_eq!(status(appointment_id), AppointmentStatus::CheckedIn);
+
+		assert_noop!(
+			MedicalAppointment::complete_appointment(RuntimeOrigin::signed(PATIENT), appointment_id),
+			Error::<Test>::NotDoctor
+		);
+		assert_ok!(MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+		assert_eq!(status(appointment_id), AppointmentStatus::Completed);
+		System::assert_last_event(
+			Event::AppointmentUpdated { appointment_id, status: AppointmentStatus::Completed }.into(),
+		);
+
+		// A completed appointment is closed
+		assert_noop!(
+			MedicalAppointment::cancel_appointment(
+				RuntimeOrigin::signed(PATIENT),
+				appointment_id,
+				reason(b"too late"),
+			),
+			Error::<Test>::AppointmentAlreadyCompleted
+		);
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id, LATER),
+			Error::<Test>::AppointmentAlreadyCompleted
+		);
+	});
+}
+
+#[test]
+fn invalid_transitions_are_rejected() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = book();
+
+		// Not confirmed yet
+		assert_noop!(
+			MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+		assert_noop!(
+			MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+		assert_noop!(
+			MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+		// Not checked in yet
+		assert_noop!(
+			MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id));
+		// A checked in appointment can no longer be moved or cancelled
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(PATIENT), appointment_id, LATER),
+			Error::<Test>::InvalidStatusTransition
+		);
+		assert_noop!(
+			MedicalAppointment::cancel_appointment(
+				RuntimeOrigin::signed(PATIENT),
+				appointment_id,
+				reason(b"changed my mind"),
+			),
+			Error::<Test>::InvalidStatusTransition
+		);
+		// Nor marked as a no-show once its time has passed
+		Now::set(LATER);
+		assert_noop!(
+			MedicalAppointment::mark_no_show(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		assert_noop!(
+			MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), 42),
+			Error::<Test>::AppointmentNotFound
+		);
+	});
+}
+
+#[test]
+fn status_transitions_follow_the_lifecycle() {
+	use AppointmentStatus::*;
+
+	let all = [Scheduled, Confirmed, CheckedIn, Completed, Cancelled, NoShow];
+	let allowed = [
+		(Scheduled, Confirmed),
+		(Scheduled, Cancelled),
+		(Scheduled, NoShow),
+		(Confirmed, CheckedIn),
+		(Confirmed, NoShow),
+		(Confirmed, Cancelled),
+		(CheckedIn, Completed),
+	];
+	for from in all {
+		for to in all {
+			assert_eq!(from.can_transition_to(to), allowed.contains(&(from, to)), "{:?} -> {:?}", from, to);
+		}
+	}
+}
+
+#[test]
+fn reschedule_appointment_works() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = confirmed();
+
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(STRANGER), appointment_id, 7_200),
+			Error::<Test>::NotParticipant
+		);
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(PATIENT), appointment_id, 999),
+			Error::<Test>::InvalidAppointmentTime
+		);
+
+		// The doctor has to confirm the patient's new time
+		assert_ok!(MedicalAppointment::reschedule_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			appointment_id,
+			7_200,
+		));
+		let appointment = MedicalAppointment::appointments(appointment_id).unwrap();
+		assert_eq!(appointment.scheduled_time, 7_200);
+		assert_eq!(appointment.status, AppointmentStatus::Scheduled);
+		System::assert_last_event(
+			Event::AppointmentRescheduled {
+				appointment_id,
+				scheduled_time: 7_200,
+				status: AppointmentStatus::Scheduled,
+			}
+			.into(),
+		);
+
+		// The doctor's own change stays confirmed
+		assert_ok!(MedicalAppointment::reschedule_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			appointment_id,
+			9_000,
+		));
+		let appointment = MedicalAppointment::appointments(appointment_id).unwrap();
+		assert_eq!(appointment.scheduled_time, 9_000);
+		assert_eq!(appointment.status, AppointmentStatus::Confirmed);
+	});
+}
+
+#[test]
+fn cancel_appointment_works() {
+	new_test_ext().execute_with(|| {
+		let by_patient = book();
+		assert_ok!(book_at(LATER + HALF_HOUR));
+		let by_doctor = by_patient + 1;
+		assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), by_doctor));
+
+		assert_noop!(
+			MedicalAppointment::cancel_appointment(
+				RuntimeOrigin::signed(STRANGER),
+				by_patient,
+				reason(b"not mine"),
+			),
+			Error::<Test>::NotParticipant
+		);
+
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			by_patient,
+			reason(b"feeling better"),
+		));
+		assert_eq!(status(by_patient), AppointmentStatus::Cancelled);
+		System::assert_last_event(
+			Event::AppointmentCancelled {
+				appointment_id: by_patient,
+				cancelled_by: PATIENT,
+				reason: reason(b"feeling better"),
+			}
+			.into(),
+		);
+
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			by_doctor,
+			reason(b"on leave"),
+		));
+		assert_eq!(status(by_doctor), AppointmentStatus::Cancelled);
+
+		// Cancelled appointments cannot be brought back
+		assert_noop!(
+			MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), by_patient),
+			Error::<Test>::InvalidStatusTransition
+		);
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(DOCTOR), by_doctor, LATER),
+			Error::<Test>::InvalidStatusTransition
+		);
+
+		// Cancelled appointments stay in the indexes
+		assert_eq!(MedicalAppointment::appointments_of_patient(&PATIENT).len(), 2);
+	});
+}
+
+#[test]
+fn mark_no_show_works() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = confirmed();
+
+		assert_noop!(
+			MedicalAppointment::mark_no_show(RuntimeOrigin::signed(DOCTOR), appointment_id),
+			Error::<Test>::AppointmentNotDue
+		);
+
+		Now::set(LATER);
+		assert_noop!(
+			MedicalAppointment::mark_no_show(RuntimeOrigin::signed(PATIENT), appointment_id),
+			Error::<Test>::NotDoctor
+		);
+		assert_ok!(MedicalAppointment::mark_no_show(RuntimeOrigin::signed(DOCTOR), appointment_id));
+		assert_eq!(status(appointment_id), AppointmentStatus::NoShow);
+		System::assert_last_event(
+			Event::AppointmentUpdated { appointment_id, status: AppointmentStatus::NoShow }.into(),
+		);
+
+		assert_noop!(
+			MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id),
+			Error::<Test>::InvalidStatusTransition
+		);
+	});
+}
+
+#[test]
+fn set_availability_validates_windows() {
+	new_test_ext().execute_with(|| {
+		for (windows, slot_length) in [
+			(windows(&[(THURSDAY, 3_600, 18_000)]), 0),
+			(windows(&[(7, 3_600, 18_000)]), 1_800),
+			(windows(&[(THURSDAY, 18_000, 3_600)]), 1_800),
+			(windows(&[(THURSDAY, 3_600, 86_401)]), 1_800),
+			(windows(&[(THURSDAY, 3_600, 4_000)]), 1_800),
+		] {
+			assert_noop!(
+				MedicalAppointment::set_availability(RuntimeOrigin::signed(DOCTOR), windows, slot_length),
+				Error::<Test>::InvalidAvailability
+			);
+		}
+
+		publish_availability();
+		System::assert_last_event(Event::AvailabilitySet { doctor_id: DOCTOR, slot_length: 1_800 }.into());
+	});
+}
+
+#[test]
+fn bookings_must_fall_on_a_free_slot() {
+	new_test_ext().execute_with(|| {
+		// No calendar published yet
+		assert_noop!(book_at(LATER), Error::<Test>::DoctorUnavailable);
+		publish_availability();
+
+		// Before and after the window, across its end and off the half hour grid
+		for time in [3_000, 18_000, 18_000 - 900, LATER + 60] {
+			assert_noop!(book_at(time), Error::<Test>::DoctorUnavailable);
+		}
+		// Same time of day, but on Friday
+		assert_noop!(book_at(LATER + SECONDS_PER_DAY), Error::<Test>::DoctorUnavailable);
+		// Next Thursday is fine
+		assert_ok!(book_at(LATER + 7 * SECONDS_PER_DAY));
+
+		assert_ok!(book_at(LATER));
+		assert_noop!(book_at(LATER), Error::<Test>::SlotTaken);
+		assert_noop!(
+			MedicalAppointment::create_medical_appointment(RuntimeOrigin::signed(3), 3, DOCTOR, LATER),
+			Error::<Test>::SlotTaken
+		);
+		// Adjacent slots are free
+		assert_ok!(book_at(LATER - HALF_HOUR));
+		assert_ok!(book_at(LATER + HALF_HOUR));
+		// MaxBookingsPerDoctor is 4
+		assert_noop!(book_at(LATER + 2 * HALF_HOUR), Error::<Test>::TooManyBookings);
+	});
+}
+
+#[test]
+fn closed_appointments_free_their_slot() {
+	new_test_ext().execute_with(|| {
+		let cancelled = book();
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			cancelled,
+			reason(b"feeling better"),
+		));
+		assert!(MedicalAppointment::doctor_bookings(DOCTOR).is_empty());
+		let rebooked = book();
+
+		// Rescheduling moves the slot
+		assert_ok!(MedicalAppointment::reschedule_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			rebooked,
+			LATER + HALF_HOUR,
+		));
+		assert_eq!(
+			MedicalAppointment::doctor_bookings(DOCTOR).into_inner(),
+			vec![Booking { appointment_id: rebooked, start: LATER + HALF_HOUR, end: LATER + 2 * HALF_HOUR }]
+		);
+		assert_ok!(book_at(LATER));
+		assert_noop!(
+			MedicalAppointment::reschedule_appointment(RuntimeOrigin::signed(PATIENT), rebooked, LATER),
+			Error::<Test>::SlotTaken
+		);
+
+		// A checked in appointment keeps its slot until it is completed
+		assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), rebooked));
+		assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), rebooked));
+		assert_noop!(book_at(LATER + HALF_HOUR), Error::<Test>::SlotTaken);
+		assert_ok!(MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), rebooked));
+		assert_ok!(book_at(LATER + HALF_HOUR));
+	});
+}
+
+#[test]
+fn unavailable_periods_block_bookings() {
+	new_test_ext().execute_with(|| {
+		publish_availability();
+
+		assert_noop!(
+			MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 5_000, 5_000),
+			Error::<Test>::InvalidPeriod
+		);
+		assert_noop!(
+			MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 0, 900),
+			Error::<Test>::InvalidPeriod
+		);
+
+		// Away from 02:00 on
+		assert_ok!(MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 7_200, 86_400));
+		System::assert_last_event(
+			Event::UnavailabilityAdded { doctor_id: DOCTOR, start: 7_200, end: 86_400 }.into(),
+		);
+		assert_ok!(book_at(LATER));
+		assert_noop!(book_at(7_200), Error::<Test>::DoctorUnavailable);
+		// Overlaps the end of the slot
+		assert_ok!(MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 4_000, 4_001));
+		assert_noop!(book_at(3_600), Error::<Test>::DoctorUnavailable);
+		// MaxUnavailablePeriods is 2
+		assert_noop!(
+			MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 10_000, 10_001),
+			Error::<Test>::TooManyUnavailablePeriods
+		);
+
+		assert_noop!(
+			MedicalAppointment::remove_unavailability(RuntimeOrigin::signed(DOCTOR), 1),
+			Error::<Test>::PeriodNotFound
+		);
+		assert_ok!(MedicalAppointment::remove_unavailability(RuntimeOrigin::signed(DOCTOR), 7_200));
+		System::assert_last_event(Event::UnavailabilityRemoved { doctor_id: DOCTOR, start: 7_200 }.into());
+		assert_eq!(
+			MedicalAppointment::doctor_unavailability(DOCTOR).into_inner(),
+			vec![UnavailablePeriod { start: 4_000, end: 4_001 }]
+		);
+		assert_ok!(book_at(7_200));
+
+		// Periods that are over are dropped when adding new ones
+		Now::set(5_000);
+		assert_ok!(MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 10_000, 10_001));
+		assert_ok!(MedicalAppointment::add_unavailability(RuntimeOrigin::signed(DOCTOR), 12_000, 12_001));
+		assert_eq!(MedicalAppointment::doctor_unavailability(DOCTOR).len(), 2);
+	});
+}
+
+#[test]
+fn reminders_are_emitted_before_appointments() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = book();
+		assert_eq!(
+			MedicalAppointment::agenda(REMINDER_BLOCK).into_inner(),
+			vec![ScheduledTask { appointment_id, task: AppointmentTask::Remind, scheduled_time: LATER }]
+		);
+
+		run_to_block(REMINDER_BLOCK - 1);
+		assert!(reminders().is_empty());
+
+		run_to_block(REMINDER_BLOCK);
+		System::assert_last_event(
+			Event::AppointmentReminder {
+				appointment_id,
+				patient_id: PATIENT,
+				doctor_id: DOCTOR,
+				scheduled_time: LATER,
+			}
+			.into(),
+		);
+		assert!(!Agenda::<Test>::contains_key(REMINDER_BLOCK));
+		assert_eq!(MedicalAppointment::queued_tasks(appointment_id, AppointmentTask::Remind), None);
+		assert_eq!(
+			MedicalAppointment::queued_tasks(appointment_id, AppointmentTask::NoShowCheck),
+			Some(NO_SHOW_BLOCK)
+		);
+	});
+}
+
+#[test]
+fn unattended_appointments_become_no_shows() {
+	new_test_ext().execute_with(|| {
+		let missed = book();
+		let attended = by_checked_in_patient(LATER + HALF_HOUR);
+
+		run_to_block(NO_SHOW_BLOCK - 1);
+		assert_eq!(status(missed), AppointmentStatus::Scheduled);
+
+		run_to_block(NO_SHOW_BLOCK);
+		assert_eq!(status(missed), AppointmentStatus::NoShow);
+		System::assert_has_event(
+			Event::AppointmentUpdated { appointment_id: missed, status: AppointmentStatus::NoShow }.into(),
+		);
+		assert!(!MedicalAppointment::doctor_bookings(DOCTOR).iter().any(|b| b.appointment_id == missed));
+
+		// Well past the second appointment's no-show check
+		run_to_block(NO_SHOW_BLOCK + 600);
+		assert_eq!(status(attended), AppointmentStatus::CheckedIn);
+	});
+}
+
+// Book a confirmed appointment at `time` the patient checked in for
+fn by_checked_in_patient(time: u64) -> u32 {
+	let appointment_id = MedicalAppointment::next_appointment_id();
+	assert_ok!(book_at(time));
+	assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+	assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id));
+	appointment_id
+}
+
+#[test]
+fn no_show_check_waits_for_the_grace_period() {
+	new_test_ext().execute_with(|| {
+		let appointment_id = book();
+
+		// Blocks came faster than expected: the grace period ends at 6_300
+		System::set_block_number(NO_SHOW_BLOCK);
+		Now::set(6_000);
+		MedicalAppointment::on_initialize(NO_SHOW_BLOCK);
+
+		assert_eq!(status(appointment_id), AppointmentStatus::Scheduled);
+		assert_eq!(
+			MedicalAppointment::agenda(NO_SHOW_BLOCK + 50).into_inner(),
+			vec![ScheduledTask { appointment_id, task: AppointmentTask::NoShowCheck, scheduled_time: LATER }]
+		);
+
+		run_to_block(NO_SHOW_BLOCK + 50);
+		assert_eq!(status(appointment_id), AppointmentStatus::NoShow);
+	});
+}
+
+#[test]
+fn tasks_of_rescheduled_and_cancelled_appointments_are_dropped() {
+	new_test_ext().execute_with(|| {
+		let moved = book();
+		assert_ok!(MedicalAppointment::reschedule_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			moved,
+			LATER + 2 * HALF_HOUR,
+		));
+		// The tasks for the previous time are taken out of the agenda
+		assert!(!Agenda::<Test>::contains_key(REMINDER_BLOCK));
+		assert!(!Agenda::<Test>::contains_key(NO_SHOW_BLOCK));
+		let remind_at = MedicalAppointment::queued_tasks(moved, AppointmentTask::Remind).unwrap();
+		assert_eq!(
+			MedicalAppointment::agenda(remind_at).into_inner(),
+			vec![ScheduledTask {
+				appointment_id: moved,
+				task: AppointmentTask::Remind,
+				scheduled_time: LATER + 2 * HALF_HOUR,
+			}]
+		);
+		let cancelled = MedicalAppointment::next_appointment_id();
+		assert_ok!(book_at(LATER + HALF_HOUR));
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			cancelled,
+			reason(b"feeling better"),
+		));
+
+		run_to_block(1_100);
+		// Only the reminder for the new time
+		assert_eq!(reminders(), vec![LATER + 2 * HALF_HOUR]);
+		assert_eq!(status(moved), AppointmentStatus::Confirmed);
+		assert_eq!(status(cancelled), AppointmentStatus::Cancelled);
+
+		run_to_block(1_500);
+		assert_eq!(status(moved), AppointmentStatus::NoShow);
+		assert_eq!(status(cancelled), AppointmentStatus::Cancelled);
+	});
+}
+
+#[test]
+fn tasks_spill_over_into_later_blocks() {
+	new_test_ext().execute_with(|| {
+		publish_availability();
+		let other = ScheduledTask { appointment_id: 99, task: AppointmentTask::Remind, scheduled_time: 0 };
+		let full = BoundedVec::truncate_from(vec![other.clone(), other]);
+
+		// MaxTasksPerBlock is 2
+		Agenda::<Test>::insert(REMINDER_BLOCK, full.clone());
+		let appointment_id = book();
+		assert_eq!(MedicalAppointment::agenda(REMINDER_BLOCK + 1)[0].appointment_id, appointment_id);
+
+		// Up to 16 blocks later, the reminder of a 01:00 appointment is due on block 135
+		for block in 135..135 + 16 {
+			Agenda::<Test>::insert(block, full.clone());
+		}
+		assert_noop!(book_at(LATER - HALF_HOUR), Error::<Test>::AgendaFull);
+	});
+}
+
+#[test]
+fn closed_appointments_leave_the_agenda() {
+	new_test_ext().execute_with(|| {
+		publish_availability();
+		// A week ahead, so that cancelling refunds the deposit in full
+		let next_week = LATER + 7 * SECONDS_PER_DAY;
+
+		// Two tasks per booking, more than the two per block over 16 blocks the agenda can hold
+		for _ in 0..17 {
+			let appointment_id = MedicalAppointment::next_appointment_id();
+			assert_ok!(book_at(next_week));
+			assert_ok!(MedicalAppointment::cancel_appointment(
+				RuntimeOrigin::signed(PATIENT),
+				appointment_id,
+				reason(b"changed my mind"),
+			));
+		}
+		assert_eq!(Agenda::<Test>::iter().count(), 0);
+		assert_eq!(held(PATIENT), 0);
+		assert_eq!(Balances::balance(&PATIENT), 1_000);
+		assert_ok!(book_at(next_week));
+
+		let completed = MedicalAppointment::next_appointment_id() - 1;
+		assert_ok!(MedicalAppointment::confirm_appointment(RuntimeOrigin::signed(DOCTOR), completed));
+		assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), completed));
+		assert_ok!(MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), completed));
+		assert!(MedicalAppointment::queued_tasks(completed, AppointmentTask::Remind).is_none());
+		assert!(MedicalAppointment::queued_tasks(completed, AppointmentTask::NoShowCheck).is_none());
+		assert_eq!(Agenda::<Test>::iter().count(), 0);
+	});
+}
+
+fn held(who: u64) -> u64 {
+	Balances::balance_on_hold(&HoldReason::AppointmentDeposit.into(), &who)
+}
+
+// DOCTOR charges 50 on top of the mock's booking deposit of 10
+fn charge_fee() {
+	assert_ok!(MedicalAppointment::set_consultation_fee(RuntimeOrigin::signed(DOCTOR), 50));
+	System::assert_last_event(Event::ConsultationFeeSet { doctor_id: DOCTOR, fee: 50 }.into());
+}
+
+#[test]
+fn booking_holds_deposit_and_fee() {
+	new_test_ext().execute_with(|| {
+		charge_fee();
+		let appointment_id = book();
+
+		assert_eq!(held(PATIENT), 60);
+		assert_eq!(Balances::free_balance(PATIENT), 940);
+		assert_eq!(
+			MedicalAppointment::escrows(appointment_id),
+			Some(Escrow { payer: PATIENT, deposit: 10, fee: 50 })
+		);
+		System::assert_has_event(
+			Event::DepositHeld { appointment_id, patient_id: PATIENT, deposit: 10, fee: 50 }.into(),
+		);
+
+		// A doctor booking for the patient does not take their funds
+		assert_ok!(MedicalAppointment::create_medical_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			PATIENT,
+			DOCTOR,
+			LATER + HALF_HOUR,
+		));
+		assert_eq!(held(PATIENT), 60);
+		assert_eq!(MedicalAppointment::escrows(appointment_id + 1), None);
+
+		// Not enough funds for the hold
+		assert_ok!(MedicalAppointment::set_consultation_fee(RuntimeOrigin::signed(DOCTOR), 2_000));
+		assert_noop!(book_at(LATER + 2 * HALF_HOUR), TokenError::FundsUnavailable);
+	});
+}
+
+#[test]
+fn attendance_releases_deposit_and_completion_pays_doctor() {
+	new_test_ext().execute_with(|| {
+		charge_fee();
+		let appointment_id = confirmed();
+
+		assert_ok!(MedicalAppointment::check_in(RuntimeOrigin::signed(PATIENT), appointment_id));
+		assert_eq!(held(PATIENT), 50);
+		assert_eq!(Balances::free_balance(PATIENT), 950);
+		System::assert_has_event(Event::DepositReleased { appointment_id, amount: 10 }.into());
+
+		assert_ok!(MedicalAppointment::complete_appointment(RuntimeOrigin::signed(DOCTOR), appointment_id));
+		assert_eq!(held(PATIENT), 0);
+		assert_eq!(Balances::total_balance(&PATIENT), 950);
+		assert_eq!(Balances::free_balance(DOCTOR), 1_050);
+		assert_eq!(MedicalAppointment::escrows(appointment_id), None);
+		System::assert_has_event(Event::FeePaid { appointment_id, doctor_id: DOCTOR, amount: 50 }.into());
+	});
+}
+
+#[test]
+fn late_cancellation_by_patient_forfeits_part_of_deposit() {
+	new_test_ext().execute_with(|| {
+		charge_fee();
+		let late = book();
+		let by_doctor = MedicalAppointment::next_appointment_id();
+		assert_ok!(book_at(LATER + HALF_HOUR));
+		let early = MedicalAppointment::next_appointment_id();
+		assert_ok!(book_at(LATER + 7 * SECONDS_PER_DAY));
+		assert_eq!(held(PATIENT), 180);
+
+		// Less than a day before the appointment, half of the deposit goes to the doctor
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			late,
+			reason(b"feeling better"),
+		));
+		System::assert_has_event(Event::DepositSlashed { appointment_id: late, amount: 5 }.into());
+		System::assert_has_event(Event::DepositReleased { appointment_id: late, amount: 55 }.into());
+		assert_eq!(Balances::free_balance(DOCTOR), 1_005);
+
+		// The doctor cancelling late costs the patient nothing
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(DOCTOR),
+			by_doctor,
+			reason(b"on leave"),
+		));
+		// Neither does cancelling a week ahead
+		assert_ok!(MedicalAppointment::cancel_appointment(
+			RuntimeOrigin::signed(PATIENT),
+			early,
+			reason(b"moving away"),
+		));
+
+		assert_eq!(held(PATIENT), 0);
+		assert_eq!(Balances::free_balance(PATIENT), 995);
+		assert_eq!(Balances::free_balance(DOCTOR), 1_005);
+	});
+}
+
+#[test]
+fn no_show_forfeits_deposit() {
+	new_test_ext().execute_with(|| {
+		charge_fee();
+		let appointment_id = book();
+
+		run_to_block(NO_SHOW_BLOCK);
+		assert_eq!(status(appointment_id), AppointmentStatus::NoShow);
+		System::assert_has_event(Event::DepositSlashed { appointment_id, amount: 10 }.into());
+		assert_eq!(held(PATIENT), 0);
+		assert_eq!(Balances::free_balance(PATIENT), 990);
+		assert_eq!(Balances::free_balance(DOCTOR), 1_010);
+		assert_eq!(MedicalAppointment::escrows(appointment_id), None);
+	});
+}
+
+#[test]
+fn other_pallets_act_on_behalf_of_the_doctor() {
+	new_test_ext().execute_with(|| {
+		charge_fee();
+		let appointment_id = book();
+
+		for who in [PATIENT, STRANGER] {
+			assert_noop!(
+				MedicalAppointment::do_reschedule(&who, appointment_id, LATER + HALF_HOUR),
+				Error::<Test>::NotDoctor
+			);
+			assert_noop!(
+				MedicalAppointment::do_cancel(&who, appointment_id, reason(b"")),
+				Error::<Test>::NotDoctor
+			);
+		}
+
+		assert_ok!(MedicalAppointment::do_reschedule(&DOCTOR, appointment_id, LATER + HALF_HOUR));
+		assert_eq!(status(appointment_id), AppointmentStatus::Confirmed);
+		// Cancelled by the doctor, so the patient gets everything back even this late
+		assert_ok!(MedicalAppointment::do_cancel(&DOCTOR, appointment_id, reason(b"")));
+		assert_eq!(status(appointment_id), AppointmentStatus::Cancelled);
+		assert_eq!(held(PATIENT), 0);
+	});
+}
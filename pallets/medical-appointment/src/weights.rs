@@ -1,5 +1,5 @@
 
-//! Autogenerated weights for pallet_template
+//! Autogenerated weights for pallet_medical_appointment
 //!
 //! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
 //! DATE: 2023-04-06, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
@@ -34,13 +34,108 @@ use core::marker::PhantomData;
 
 /// Weight functions needed for pallet_medical_appointment.
 pub trait WeightInfo {
+	fn create_medical_appointment() -> Weight;
+	fn confirm_appointment() -> Weight;
+	fn reschedule_appointment() -> Weight;
+	fn cancel_appointment() -> Weight;
+	fn check_in() -> Weight;
+	fn complete_appointment() -> Weight;
+	fn mark_no_show() -> Weight;
+	fn set_availability() -> Weight;
+	fn add_unavailability() -> Weight;
+	fn remove_unavailability() -> Weight;
+	fn on_initialize(s: u32) -> Weight;
+	fn set_consultation_fee() -> Weight;
 }
 
 /// Weights for pallet_medical_appointment using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn create_medical_appointment() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(9)) // counter + doctor availability, unavailability and bookings + agenda + fee + patient account and holds
+			.saturating_add(T::DbWeight::get().writes(12)) // appointment + counter + indexes + doctor bookings + agenda + queued tasks + escrow + patient account and holds
+	}
+
+	fn confirm_appointment() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn reschedule_appointment() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(10)) // appointment + doctor availability, unavailability and bookings + queued tasks + old and new agenda
+			.saturating_add(T::DbWeight::get().writes(10)) // appointment + doctor bookings + old and new agenda + old and new queued tasks
+	}
+
+	fn cancel_appointment() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+			.saturating_add(T::DbWeight::get().writes(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+	}
+
+	fn check_in() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(4)) // appointment + escrow + patient account and holds
+			.saturating_add(T::DbWeight::get().writes(4)) // appointment + escrow + patient account and holds
+	}
+
+	fn complete_appointment() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+			.saturating_add(T::DbWeight::get().writes(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+	}
+
+	fn mark_no_show() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+			.saturating_add(T::DbWeight::get().writes(6)) // appointment + doctor bookings + escrow + patient account and holds + doctor account
+	}
+
+	fn set_availability() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn add_unavailability() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn remove_unavailability() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn on_initialize(s: u32) -> Weight {
+		Weight::from_parts(5_000, 0)
+			.saturating_add(Weight::from_parts(10_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(1)) // agenda
+			.saturating_add(T::DbWeight::get().writes(1)) // agenda
+			.saturating_add(T::DbWeight::get().reads_writes((6 * s).into(), (7 * s).into())) // appointment + doctor bookings + escrow + accounts and holds + queued task
+	}
+
+	fn set_consultation_fee() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
+	fn create_medical_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn confirm_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn reschedule_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn cancel_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn check_in() -> Weight { Weight::from_parts(10_000, 0) }
+	fn complete_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn mark_no_show() -> Weight { Weight::from_parts(10_000, 0) }
+	fn set_availability() -> Weight { Weight::from_parts(10_000, 0) }
+	fn add_unavailability() -> Weight { Weight::from_parts(10_000, 0) }
+	fn remove_unavailability() -> Weight { Weight::from_parts(10_000, 0) }
+	fn on_initialize(_s: u32) -> Weight { Weight::from_parts(10_000, 0) }
+	fn set_consultation_fee() -> Weight { Weight::from_parts(10_000, 0) }
 }
@@ -0,0 +1,318 @@
+//! Client side encryption of patient PII for `pallet-medical-record`.
+//!
+//! When the runtime requires encrypted PII, the name, date of birth, address, phone and emergency
+//! contact of a patient are sealed before they are submitted, so the chain only ever sees
+//! ciphertext. Every patient has one random [`DataKey`]:
+//!
+//! 1. Each account that reads patient details derives an [`EncryptionKeypair`], usually from its
+//!    sr25519 account key, and publishes the public half with `register_encryption_key`.
+//! 2. Whoever registers the patient generates a data key, seals the fields with
+//!    [`DataKey::seal`] and submits `create_patient`.
+//! 3. The data key is wrapped for every reader with [`DataKey::wrap_for`] and stored with
+//!    `set_data_keys`. A reader recovers it with [`DataKey::unwrap`] and opens the fields with
+//!    [`DataKey::open`].
+//!
+//! Fields are sealed with XChaCha20-Poly1305. The tag binds a field to its name and key version,
+//! so a sealed phone number cannot be replayed as an address. Data keys are wrapped with
+//! XChaCha20-Poly1305 under a key derived with HKDF-SHA256 from an x25519 exchange between a
+//! fresh ephemeral key and the reader's key, bound to the patient and key version.
+//!
+//! Patients are looked up by a blinded [`name_index`] rather than by name: an HMAC-SHA256 of the
+//! normalised name under a secret the facility shares with its staff. Staff holding the secret
+//! compute the same index for `create_patient`, `update_patient` and `search_patient_by_name`,
+//! while chain observers cannot link an index to a name.
+
+use chacha20poly1305::{
+	aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+	XChaCha20Poly1305, XNonce,
+};
+use codec::Encode;
+use core::fmt;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use pallet_medical_record::{
+	envelope::{DATA_KEY_LENGTH, ENVELOPE_VERSION},
+	NameIndex,
+};
+use sha2::Sha256;
+use sp_core::{sr25519, Pair};
+use unicode_normalization::UnicodeNormalization;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+pub use pallet_medical_record::{PiiEnvelope, WrappedDataKey};
+
+const SEAL_CONTEXT: &[u8] = b"healer-network/pii/v1/";
+const WRAP_CONTEXT: &[u8] = b"healer-network/pii/wrap/v1";
+const SR25519_CONTEXT: &[u8] = b"healer-network/pii/x25519/v1";
+const NAME_INDEX_CONTEXT: &[u8] = b"healer-network/pii/name-index/v1";
+
+/// A patient field that is encrypted when the runtime requires encrypted PII.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+	PatientName,
+	DateOfBirth,
+	Address,
+	Phone,
+	EmergencyContact,
+}
+
+impl Field {
+	/// The field name, as used by the pallet's change history.
+	pub fn name(self) -> &'static [u8] {
+		match self {
+			Field::PatientName => b"patient_name",
+			Field::DateOfBirth => b"date_of_birth",
+			Field::Address => b"address",
+			Field::Phone => b"phone",
+			Field::EmergencyContact => b"emergency_contact",
+		}
+	}
+}
+
+/// Why a field or wrapped key could not be opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The bytes are not an envelope the pallet would accept.
+	InvalidEnvelope,
+	/// The recipient key is a low order point, so no secret can be agreed with it.
+	InvalidPublicKey,
+	/// The key is wrong, or the ciphertext, field, patient or key version does not match.
+	Decryption,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::InvalidEnvelope => write!(f, "not a valid PII envelope"),
+			Error::InvalidPublicKey => write!(f, "invalid x25519 public key"),
+			Error::Decryption => write!(f, "decryption failed"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// The x25519 key pair patient data keys are wrapped for an account with.
+pub struct EncryptionKeypair {
+	secret: StaticSecret,
+	public: PublicKey,
+}
+
+impl EncryptionKeypair {
+	/// Use a raw x25519 secret key.
+	pub fn from_secret(secret: [u8; 32]) -> Self {
+		let secret = StaticSecret::from(secret);
+		let public = PublicKey::from(&secret);
+		Self { secret, public }
+	}
+
+	/// Derive the encryption key of an sr25519 account, so it can be recovered from the account's
+	/// seed alone.
+	pub fn from_sr25519(pair: &sr25519::Pair) -> Self {
+		let account_secret = Zeroizing::new(pair.to_raw_vec());
+		let mut secret = Zeroizing::new([0u8; 32]);
+		Hkdf::<Sha256>::new(None, &account_secret)
+			.expand(SR25519_CONTEXT, &mut secret[..])
+			.expect("32 bytes is a valid HKDF-SHA256 output length; qed");
+		Self::from_secret(*secret)
+	}
+
+	/// The public key to register with `register_encryption_key`.
+	pub fn public_key(&self) -> [u8; 32] {
+		self.public.to_bytes()
+	}
+}
+
+/// A patient data key.
+pub struct DataKey(Zeroizing<[u8; DATA_KEY_LENGTH]>);
+
+impl DataKey {
+	/// Generate a new random data key.
+	pub fn generate() -> Self {
+		Self(Zeroizing::new(XChaCha20Poly1305::generate_key(&mut OsRng).into()))
+	}
+
+	/// Use existing key material.
+	pub fn from_bytes(bytes: [u8; DATA_KEY_LENGTH]) -> Self {
+		Self(Zeroizing::new(bytes))
+	}
+
+	/// The raw key material.
+	pub fn as_bytes(&self) -> &[u8; DATA_KEY_LENGTH] {
+		&self.0
+	}
+
+	/// Seal `plaintext` as the given field, returning the bytes to submit to the pallet.
+	pub fn seal(&self, key_version: u32, field: Field, plaintext: &[u8]) -> Vec<u8> {
+		let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+		let aad = field_aad(field, key_version);
+		let ciphertext = self
+			.cipher()
+			.encrypt(&nonce, Payload { msg: plaintext, aad: &aad })
+			.expect("XChaCha20-Poly1305 encrypts any field length; qed");
+
+		PiiEnvelope { version: ENVELOPE_VERSION, key_version, nonce: nonce.into(), ciphertext }.encode()
+	}
+
+	/// Open a field read from the chain.
+	pub fn open(&self, field: Field, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+		let envelope = PiiEnvelope::from_field(sealed).ok_or(Error::InvalidEnvelope)?;
+		let aad = field_aad(field, envelope.key_version);
+		self.cipher()
+			.decrypt(XNonce::from_slice(&envelope.nonce), Payload { msg: &envelope.ciphertext, aad: &aad })
+			.map_err(|_| Error::Decryption)
+	}
+
+	/// Wrap this key for the account that registered `recipient` as its encryption key.
+	pub fn wrap_for(
+		&self,
+		patient_id: u32,
+		key_version: u32,
+		recipient: &[u8; 32],
+	) -> Result<WrappedDataKey, Error> {
+		let recipient = PublicKey::from(*recipient);
+		let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+		let ephemeral_public = PublicKey::from(&ephemeral_secret);
+		let shared_secret = ephemeral_secret.diffie_hellman(&recipient);
+		if !shared_secret.was_contributory() {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		let cipher = wrapping_cipher(shared_secret.as_bytes(), &ephemeral_public, &recipient);
+		let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+		let aad = wrap_aad(patient_id, key_version);
+		let ciphertext = cipher
+			.encrypt(&nonce, Payload { msg: &self.0[..], aad: &aad })
+			.expect("XChaCha20-Poly1305 encrypts a data key; qed");
+
+		Ok(WrappedDataKey {
+			ephemeral_public: ephemeral_public.to_bytes(),
+			nonce: nonce.into(),
+			ciphertext: ciphertext.try_into().expect("ciphertext is the key followed by its tag; qed"),
+		})
+	}
+
+	/// Recover a data key wrapped for `keypair`.
+	pub fn unwrap(
+		wrapped: &WrappedDataKey,
+		patient_id: u32,
+		key_version: u32,
+		keypair: &EncryptionKeypair,
+	) -> Result<Self, Error> {
+		let ephemeral_public = PublicKey::from(wrapped.ephemeral_public);
+		let shared_secret = keypair.secret.diffie_hellman(&ephemeral_public);
+		if !shared_secret.was_contributory() {
+			return Err(Error::InvalidPublicKey);
+		}
+
+		let cipher = wrapping_cipher(shared_secret.as_bytes(), &ephemeral_public, &keypair.public);
+		let aad = wrap_aad(patient_id, key_version);
+		let key = Zeroizing::new(
+			cipher
+				.decrypt(XNonce::from_slice(&wrapped.nonce), Payload { msg: &wrapped.ciphertext, aad: &aad })
+				.map_err(|_| Error::Decryption)?,
+		);
+
+		Ok(Self::from_bytes(key.as_slice().try_into().map_err(|_| Error::Decryption)?))
+	}
+
+	fn cipher(&self) -> XChaCha20Poly1305 {
+		XChaCha20Poly1305::new((&*self.0).into())
+	}
+}
+
+/// Blinded index of a patient name under the facility secret.
+///
+/// The name is normalised first (NFKC, lowercase, whitespace collapsed to single spaces), so
+/// `"  John  DOE"` and `"john doe"` share an index. Rotating the secret means re-indexing every
+/// patient with `update_patient`.
+pub fn name_index(facility_secret: &[u8], name: &str) -> NameIndex {
+	let normalised = name.nfkc().collect::<String>().to_lowercase();
+	let normalised = normalised.split_whitespace().collect::<Vec<_>>().join(" ");
+	let mut mac =
+		<Hmac<Sha256> as Mac>::new_from_slice(facility_secret).expect("HMAC accepts keys of any length; qed");
+	mac.update(NAME_INDEX_CONTEXT);
+	mac.update(normalised.as_bytes());
+	mac.finalize().into_bytes().into()
+}
+
+fn field_aad(field: Field, key_version: u32) -> Vec<u8> {
+	[SEAL_CONTEXT, field.name(), &key_version.to_le_bytes()].concat()
+}
+
+fn wrap_aad(patient_id: u32, key_version: u32) -> Vec<u8> {
+	[&patient_id.to_le_bytes()[..], &key_version.to_le_bytes()].concat()
+}
+
+fn wrapping_cipher(shared_secret: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> XChaCha20Poly1305 {
+	let salt = [ephemeral.as_bytes().as_slice(), recipient.as_bytes()].concat();
+	let mut key = Zeroizing::new([0u8; 32]);
+	Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+		.expand(WRAP_CONTEXT, &mut key[..])
+		.expect("32 bytes is a valid HKDF-SHA256 output length; qed");
+	XChaCha20Poly1305::new((&*key).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sealed_fields_open_with_the_data_key() {
+		let key = DataKey::generate();
+		let sealed = key.seal(0, Field::Phone, b"555-1234");
+
+		let envelope = PiiEnvelope::from_field(&sealed).expect("the pallet accepts sealed fields");
+		assert_eq!(envelope.key_version, 0);
+		assert!(!sealed.windows(8).any(|window| window == b"555-1234"));
+		assert_eq!(key.open(Field::Phone, &sealed), Ok(b"555-1234".to_vec()));
+
+		// Another key, another field or a rewritten header cannot open it
+		assert_eq!(DataKey::generate().open(Field::Phone, &sealed), Err(Error::Decryption));
+		assert_eq!(key.open(Field::Address, &sealed), Err(Error::Decryption));
+		let mut relabelled = envelope.clone();
+		relabelled.key_version = 1;
+		assert_eq!(key.open(Field::Phone, &relabelled.encode()), Err(Error::Decryption));
+		assert_eq!(key.open(Field::Phone, b"555-1234"), Err(Error::InvalidEnvelope));
+	}
+
+	#[test]
+	fn wrapped_keys_open_for_their_recipient_only() {
+		let key = DataKey::generate();
+		let doctor = EncryptionKeypair::from_secret([1; 32]);
+		let nurse = EncryptionKeypair::from_secret([2; 32]);
+
+		let wrapped = key.wrap_for(7, 0, &doctor.public_key()).unwrap();
+		let unwrapped = DataKey::unwrap(&wrapped, 7, 0, &doctor).unwrap();
+		assert_eq!(unwrapped.as_bytes(), key.as_bytes());
+
+		assert!(matches!(DataKey::unwrap(&wrapped, 7, 0, &nurse), Err(Error::Decryption)));
+		assert!(matches!(DataKey::unwrap(&wrapped, 8, 0, &doctor), Err(Error::Decryption)));
+		assert!(matches!(DataKey::unwrap(&wrapped, 7, 1, &doctor), Err(Error::Decryption)));
+		assert!(matches!(key.wrap_for(7, 0, &[0; 32]), Err(Error::InvalidPublicKey)));
+	}
+
+	#[test]
+	fn name_index_normalises_and_depends_on_the_secret() {
+		let index = name_index(b"facility secret", "John Doe");
+		assert_eq!(index, name_index(b"facility secret", "  JOHN\tdoe "));
+		assert_eq!(index, name_index(b"facility secret", "\u{FF2A}ohn Doe"));
+		assert_ne!(index, name_index(b"facility secret", "John Doe Jr"));
+		assert_ne!(index, name_index(b"another secret", "John Doe"));
+	}
+
+	#[test]
+	fn sr25519_accounts_derive_a_stable_encryption_key() {
+		let (pair, _) = sr25519::Pair::generate();
+		let keypair = EncryptionKeypair::from_sr25519(&pair);
+		assert_eq!(keypair.public_key(), EncryptionKeypair::from_sr25519(&pair).public_key());
+
+		let (other, _) = sr25519::Pair::generate();
+		assert_ne!(keypair.public_key(), EncryptionKeypair::from_sr25519(&other).public_key());
+
+		let key = DataKey::generate();
+		let wrapped = key.wrap_for(0, 0, &keypair.public_key()).unwrap();
+		assert_eq!(DataKey::unwrap(&wrapped, 0, 0, &keypair).unwrap().as_bytes(), key.as_bytes());
+	}
+}
@@ -0,0 +1,484 @@
+//! RPC interface for the medical-record pallet.
+//!
+//! Serves the `medicalRecord_*` methods by calling into
+//! [`MedicalRecordRuntimeApi`] at the requested block (or the best block if none is given).
+//!
+//! [`MedicalDocuments`] additionally keeps documents registered with `store_document` in the
+//! node's offchain database, as handed over by their uploader, and serves them to readers who
+//! sign the request and are allowed to read them.
+
+use std::{
+	collections::BTreeSet,
+	marker::PhantomData,
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+use codec::{Codec, Encode};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{ErrorObject, ErrorObjectOwned},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::{HeaderBackend, Info};
+use sp_core::{
+	hashing::{blake2_256, sha2_256},
+	offchain::{OffchainStorage, STORAGE_PREFIX},
+	Bytes, H256,
+};
+use sp_runtime::traits::{Block as BlockT, IdentifyAccount, NumberFor, Saturating, Verify};
+
+pub use pallet_medical_record_runtime_api::{
+	document_key, document_read_payload, document_write_payload, AuditError,
+	MedicalDocumentsApi as MedicalDocumentsRuntimeApi, MedicalRecordApi as MedicalRecordRuntimeApi,
+	Page, RecordType,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Number of blocks a signed document request stays valid for. Each read request is served once.
+pub const DOCUMENT_REQUEST_VALIDITY: u32 = 50;
+
+#[rpc(client, server)]
+pub trait MedicalRecordApi<BlockHash, AccountId, BlockNumber, PatientInfo, ClinicalTest, ChangeHistory> {
+	/// A page of active patients, starting after `cursor`.
+	#[method(name = "medicalRecord_getAllPatients")]
+	fn get_all_patients(
+		&self,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Page<PatientInfo>>;
+
+	/// All clinical tests of a patient.
+	#[method(name = "medicalRecord_getPatientClinicalTests")]
+	fn get_patient_clinical_tests(
+		&self,
+		patient_id: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<ClinicalTest>>;
+
+	/// Clinical tests, visits and records of a patient in chronological order.
+	#[method(name = "medicalRecord_getPatientTimeline")]
+	fn get_patient_timeline(
+		&self,
+		patient_id: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(BlockNumber, Vec<u8>)>>;
+
+	/// The change history of a single record.
+	#[method(name = "medicalRecord_getRecordHistory")]
+	fn get_record_history(
+		&self,
+		record_type: RecordType,
+		record_id: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<ChangeHistory>>;
+
+	/// Changes made by an account among the `limit` changes after `cursor`.
+	#[method(name = "medicalRecord_getChangesByUser")]
+	fn get_changes_by_user(
+		&self,
+		user: AccountId,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Page<ChangeHistory>>;
+
+	/// Changes made between two blocks, inclusive, among the `limit` changes after `cursor`.
+	#[method(name = "medicalRecord_getChangesInRange")]
+	fn get_changes_in_range(
+		&self,
+		start_block: BlockNumber,
+		end_block: BlockNumber,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Page<ChangeHistory>>;
+
+	/// The `limit` changes made before `cursor`, or the latest ones, newest first.
+	#[method(name = "medicalRecord_getLatestChanges")]
+	fn get_latest_changes(
+		&self,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Page<ChangeHistory>>;
+
+	/// Verify the hash chain of the audit trail from change `from` up to change `to`, or up to
+	/// the audit head if `to` is omitted, and return the number of changes checked.
+	#[method(name = "medicalRecord_verifyAuditTrail")]
+	fn verify_audit_trail(
+		&self,
+		from: u32,
+		to: Option<u32>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Result<u32, AuditError>>;
+}
+
+#[rpc(client, server)]
+pub trait MedicalDocumentsApi<AccountId, BlockNumber, Signature> {
+	/// Hand the node the content of a document registered with `store_document`, and return its
+	/// content hash.
+	///
+	/// `signature` is the uploader's signature over `document_write_payload(content_hash,
+	/// signed_at)`, where `content_hash` is the SHA-256 hash of `content` and `signed_at` is a
+	/// recent block number. The content is kept in the node's offchain database if the uploader
+	/// registered a document of that hash and size.
+	#[method(name = "medicalRecord_putDocument")]
+	fn put_document(
+		&self,
+		content: Bytes,
+		uploader: AccountId,
+		signed_at: BlockNumber,
+		signature: Signature,
+	) -> RpcResult<H256>;
+
+	/// Fetch a stored document by content hash.
+	///
+	/// `signature` is the reader's signature over `document_read_payload(content_hash,
+	/// signed_at)`, where `signed_at` is a recent block number. A signed request is served at
+	/// most once, so readers sign a new one for every fetch. The document is served from the
+	/// node's offchain database, so it must have been handed to this node.
+	#[method(name = "medicalRecord_getDocument")]
+	fn get_document(
+		&self,
+		content_hash: H256,
+		reader: AccountId,
+		signed_at: BlockNumber,
+		signature: Signature,
+	) -> RpcResult<Bytes>;
+}
+
+/// Provides RPC methods to query medical records.
+pub struct MedicalRecord<C, Block> {
+	/// Shared reference to the client.
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> MedicalRecord<C, Block> {
+	/// Creates a new instance of the MedicalRecord Rpc helper.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Keeps documents handed over by their uploader in the offchain database and serves them to
+/// authorised readers.
+pub struct MedicalDocuments<C, Block: BlockT, S> {
+	/// Shared reference to the client.
+	client: Arc<C>,
+	/// Offchain database the documents are kept in.
+	storage: Mutex<S>,
+	/// Read requests served so far, by the block they were signed at and a hash of the reader
+	/// and document, dropped once they expire.
+	served_requests: Mutex<BTreeSet<(NumberFor<Block>, [u8; 32])>>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block: BlockT, S> MedicalDocuments<C, Block, S> {
+	/// Creates a new instance of the MedicalDocuments Rpc helper.
+	pub fn new(client: Arc<C>, storage: S) -> Self {
+		Self {
+			client,
+			storage: Mutex::new(storage),
+			served_requests: Default::default(),
+			_marker: Default::default(),
+		}
+	}
+}
+
+// A panic while holding a lock leaves the data consistent, so carry on with it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The request signature does not match the reader or uploader.
+	BadSignature,
+	/// The request was signed too long ago, or at a future block.
+	StaleRequest,
+	/// The reader may not read the document, or the uploader did not register it.
+	Unauthorized,
+	/// The document is not in this node's offchain database.
+	DocumentUnavailable,
+	/// The request has been served already.
+	RequestReused,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::BadSignature => 2,
+			Error::StaleRequest => 3,
+			Error::Unauthorized => 4,
+			Error::DocumentUnavailable => 5,
+			Error::RequestReused => 6,
+		}
+	}
+}
+
+fn runtime_error(error: impl ToString, desc: &'static str) -> ErrorObjectOwned {
+	ErrorObject::owned(Error::RuntimeError.into(), desc, Some(error.to_string()))
+}
+
+fn request_error(error: Error, desc: &'static str) -> ErrorObjectOwned {
+	ErrorObject::owned(error.into(), desc, None::<()>)
+}
+
+impl<C, Block, AccountId, BlockNumber, PatientInfo, ClinicalTest, ChangeHistory>
+	MedicalRecordApiServer<
+		<Block as BlockT>::Hash,
+		AccountId,
+		BlockNumber,
+		PatientInfo,
+		ClinicalTest,
+		ChangeHistory,
+	> for MedicalRecord<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MedicalRecordRuntimeApi<Block, AccountId, BlockNumber, PatientInfo, ClinicalTest, ChangeHistory>,
+	AccountId: Codec + DeserializeOwned + Send + Sync + 'static,
+	BlockNumber: Codec + Serialize + DeserializeOwned + Send + Sync + 'static,
+	PatientInfo: Codec + Serialize + Send + Sync + 'static,
+	ClinicalTest: Codec + Serialize + Send + Sync + 'static,
+	ChangeHistory: Codec + Serialize + Send + Sync + 'static,
+{
+	fn get_all_patients(
+		&self,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Page<PatientInfo>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_all_patients(at_hash, cursor, limit)
+			.map_err(|e| runtime_error(e, "Unable to query patients."))
+	}
+
+	fn get_patient_clinical_tests(
+		&self,
+		patient_id: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<ClinicalTest>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_patient_clinical_tests(at_hash, patient_id)
+			.map_err(|e| runtime_error(e, "Unable to query clinical tests."))
+	}
+
+	fn get_patient_timeline(
+		&self,
+		patient_id: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<(BlockNumber, Vec<u8>)>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_patient_timeline(at_hash, patient_id)
+			.map_err(|e| runtime_error(e, "Unable to query patient timeline."))
+	}
+
+	fn get_record_history(
+		&self,
+		record_type: RecordType,
+		record_id: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<ChangeHistory>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_record_history(at_hash, record_type, record_id)
+			.map_err(|e| runtime_error(e, "Unable to query record history."))
+	}
+
+	fn get_changes_by_user(
+		&self,
+		user: AccountId,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Page<ChangeHistory>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_changes_by_user(at_hash, user, cursor, limit)
+			.map_err(|e| runtime_error(e, "Unable to query changes by user."))
+	}
+
+	fn get_changes_in_range(
+		&self,
+		start_block: BlockNumber,
+		end_block: BlockNumber,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Page<ChangeHistory>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_changes_in_range(at_hash, start_block, end_block, cursor, limit)
+			.map_err(|e| runtime_error(e, "Unable to query changes in range."))
+	}
+
+	fn get_latest_changes(
+		&self,
+		cursor: Option<u32>,
+		limit: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Page<ChangeHistory>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		self.client
+			.runtime_api()
+			.get_latest_changes(at_hash, cursor, limit)
+			.map_err(|e| runtime_error(e, "Unable to query latest changes."))
+	}
+
+	fn verify_audit_trail(
+		&self,
+		from: u32,
+		to: Option<u32>,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Result<u32, AuditError>> {
+		let at_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		let api = self.client.runtime_api();
+		// Runtimes before version 2 of the API do not keep a verifiable audit trail
+		let supported = api
+			.has_api_with::<dyn MedicalRecordRuntimeApi<
+				Block,
+				AccountId,
+				BlockNumber,
+				PatientInfo,
+				ClinicalTest,
+				ChangeHistory,
+			>, _>(at_hash, |version| version >= 2)
+			.map_err(|e| runtime_error(e, "Unable to verify the audit trail."))?;
+		if !supported {
+			return Err(runtime_error(
+				"runtime API version 2 required",
+				"Unable to verify the audit trail.",
+			));
+		}
+		api.verify_audit_trail(at_hash, from, to)
+			.map_err(|e| runtime_error(e, "Unable to verify the audit trail."))
+	}
+}
+
+impl<C, Block, S> MedicalDocuments<C, Block, S>
+where
+	Block: BlockT,
+	C: HeaderBackend<Block>,
+{
+	// Ensure a request is signed by `signer` at a block at most `DOCUMENT_REQUEST_VALIDITY`
+	// blocks behind the best block, returning the chain info it was checked against
+	fn check_request<AccountId, Signature>(
+		&self,
+		payload: &[u8],
+		signer: &AccountId,
+		signed_at: NumberFor<Block>,
+		signature: &Signature,
+	) -> RpcResult<Info<Block>>
+	where
+		Signature: Verify,
+		Signature::Signer: IdentifyAccount<AccountId = AccountId>,
+	{
+		if !signature.verify(payload, signer) {
+			return Err(request_error(Error::BadSignature, "Invalid request signature."));
+		}
+
+		let info = self.client.info();
+		if signed_at > info.best_number ||
+			info.best_number - signed_at > DOCUMENT_REQUEST_VALIDITY.into()
+		{
+			return Err(request_error(Error::StaleRequest, "Request signed at a stale block."));
+		}
+		Ok(info)
+	}
+}
+
+impl<C, Block, S, AccountId, Signature>
+	MedicalDocumentsApiServer<AccountId, NumberFor<Block>, Signature> for MedicalDocuments<C, Block, S>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MedicalDocumentsRuntimeApi<Block, AccountId>,
+	S: OffchainStorage + 'static,
+	AccountId: Codec + DeserializeOwned + Send + Sync + 'static,
+	Signature: Verify + DeserializeOwned + Send + Sync + 'static,
+	Signature::Signer: IdentifyAccount<AccountId = AccountId>,
+{
+	fn put_document(
+		&self,
+		content: Bytes,
+		uploader: AccountId,
+		signed_at: NumberFor<Block>,
+		signature: Signature,
+	) -> RpcResult<H256> {
+		let content_hash = sha2_256(&content);
+		let payload = document_write_payload(&content_hash, signed_at);
+		let info = self.check_request(&payload, &uploader, signed_at, &signature)?;
+
+		// Only the content the uploader registered is kept, so nodes store nothing else
+		let allowed = match u32::try_from(content.len()) {
+			Ok(size) => self
+				.client
+				.runtime_api()
+				.can_write_document(info.best_hash, uploader, content_hash, size)
+				.map_err(|e| runtime_error(e, "Unable to check document access."))?,
+			Err(_) => false,
+		};
+		if !allowed {
+			return Err(request_error(Error::Unauthorized, "Not allowed to store the document."));
+		}
+
+		lock(&self.storage).set(STORAGE_PREFIX, &document_key(&content_hash), &content);
+		Ok(H256(content_hash))
+	}
+
+	fn get_document(
+		&self,
+		content_hash: H256,
+		reader: AccountId,
+		signed_at: NumberFor<Block>,
+		signature: Signature,
+	) -> RpcResult<Bytes> {
+		let content_hash = content_hash.0;
+		let payload = document_read_payload(&content_hash, signed_at);
+		let info = self.check_request(&payload, &reader, signed_at, &signature)?;
+
+		// Expired requests fail the check above, so only the ones still valid are remembered
+		let request = (signed_at, blake2_256(&(&reader, content_hash).encode()));
+		{
+			let mut served = lock(&self.served_requests);
+			let oldest = info.best_number.saturating_sub(DOCUMENT_REQUEST_VALIDITY.into());
+			*served = served.split_off(&(oldest, [0; 32]));
+			if !served.insert(request) {
+				return Err(request_error(Error::RequestReused, "Request served already."));
+			}
+		}
+
+		let allowed = self
+			.client
+			.runtime_api()
+			.can_read_document(info.best_hash, reader, content_hash)
+			.map_err(|e| runtime_error(e, "Unable to check document access."))?;
+		if !allowed {
+			return Err(request_error(Error::Unauthorized, "Not allowed to read the document."));
+		}
+
+		lock(&self.storage)
+			.get(STORAGE_PREFIX, &document_key(&content_hash))
+			.map(Bytes)
+			.ok_or_else(|| request_error(Error::DocumentUnavailable, "Document not available on this node."))
+	}
+}
@@ -0,0 +1,235 @@
+use super::*;
+use sp_api::{ApiError, ApiRef};
+use sp_blockchain::{BlockStatus, Info};
+use sp_core::{
+	hashing::sha2_256,
+	offchain::{storage::InMemOffchainStorage, OffchainStorage},
+	sr25519, Pair,
+};
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, IdentifyAccount},
+	AccountId32, MultiSignature, MultiSigner, OpaqueExtrinsic,
+};
+
+type Header = generic::Header<u32, BlakeTwo256>;
+type Block = generic::Block<Header, OpaqueExtrinsic>;
+
+const BEST_NUMBER: u32 = 100;
+const DOCUMENT: &[u8] = b"scan";
+
+fn content_hash() -> [u8; 32] {
+	sha2_256(DOCUMENT)
+}
+
+#[derive(Clone, Default)]
+struct TestApi {
+	// Accounts allowed to read `DOCUMENT`
+	readers: Vec<AccountId32>,
+	// Account that registered `DOCUMENT`
+	uploader: Option<AccountId32>,
+}
+
+sp_api::mock_impl_runtime_apis! {
+	impl MedicalRecordRuntimeApi<Block, AccountId32, u32, (), (), ()> for TestApi {
+		#[advanced]
+		fn get_all_patients(
+			&self,
+			_at: <Block as BlockT>::Hash,
+			_cursor: Option<u32>,
+			_limit: u32,
+		) -> Result<Page<()>, ApiError> {
+			Err(ApiError::Application("state unavailable".into()))
+		}
+
+		fn verify_audit_trail(_from: u32, _to: Option<u32>) -> Result<u32, AuditError> {
+			Err(AuditError::Missing(3))
+		}
+	}
+
+	impl MedicalDocumentsRuntimeApi<Block, AccountId32> for TestApi {
+		fn can_read_document(&self, reader: AccountId32, content_hash: [u8; 32]) -> bool {
+			content_hash == self::content_hash() && self.readers.contains(&reader)
+		}
+
+		fn can_write_document(&self, writer: AccountId32, content_hash: [u8; 32], size: u32) -> bool {
+			content_hash == self::content_hash() &&
+				size == DOCUMENT.len() as u32 &&
+				self.uploader == Some(writer)
+		}
+	}
+}
+
+struct TestClient {
+	api: TestApi,
+}
+
+impl ProvideRuntimeApi<Block> for TestClient {
+	type Api = TestApi;
+
+	fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+		self.api.clone().into()
+	}
+}
+
+impl HeaderBackend<Block> for TestClient {
+	fn header(&self, _hash: H256) -> sp_blockchain::Result<Option<Header>> {
+		Ok(None)
+	}
+
+	fn info(&self) -> Info<Block> {
+		Info {
+			best_hash: H256::repeat_byte(1),
+			best_number: BEST_NUMBER,
+			genesis_hash: H256::zero(),
+			finalized_hash: H256::zero(),
+			finalized_number: 0,
+			finalized_state: None,
+			number_leaves: 1,
+			block_gap: None,
+		}
+	}
+
+	fn status(&self, _hash: H256) -> sp_blockchain::Result<BlockStatus> {
+		Ok(BlockStatus::InChain)
+	}
+
+	fn number(&self, _hash: H256) -> sp_blockchain::Result<Option<u32>> {
+		Ok(None)
+	}
+
+	fn hash(&self, _number: u32) -> sp_blockchain::Result<Option<H256>> {
+		Ok(None)
+	}
+}
+
+fn account(pair: &sr25519::Pair) -> AccountId32 {
+	MultiSigner::from(pair.public()).into_account()
+}
+
+fn sign(pair: &sr25519::Pair, signed_at: u32) -> MultiSignature {
+	pair.sign(&document_read_payload(&content_hash(), signed_at)[..]).into()
+}
+
+fn sign_upload(pair: &sr25519::Pair, content: &[u8], signed_at: u32) -> MultiSignature {
+	pair.sign(&document_write_payload(&sha2_256(content), signed_at)[..]).into()
+}
+
+// Documents RPC with `DOCUMENT` registered and readable by `reader`, and stored on the node if
+// `stored`
+fn documents(reader: &sr25519::Pair, stored: bool) -> MedicalDocuments<TestClient, Block, InMemOffchainStorage> {
+	let mut storage = InMemOffchainStorage::default();
+	if stored {
+		storage.set(STORAGE_PREFIX, &document_key(&content_hash()), DOCUMENT);
+	}
+	let api = TestApi { readers: vec![account(reader)], uploader: Some(account(reader)) };
+	MedicalDocuments::new(Arc::new(TestClient { api }), storage)
+}
+
+#[test]
+fn runtime_failures_are_reported_as_runtime_errors() {
+	let rpc = MedicalRecord::<_, Block>::new(Arc::new(TestClient { api: TestApi::default() }));
+
+	let error = MedicalRecordApiServer::<_, AccountId32, u32, (), (), ()>::get_all_patients(&rpc, None, 10, None)
+		.unwrap_err();
+	assert_eq!(error.code(), 1);
+	assert_eq!(error.message(), "Unable to query patients.");
+	assert!(error.data().unwrap().get().contains("state unavailable"));
+}
+
+#[test]
+fn audit_errors_are_returned_as_results() {
+	let rpc = MedicalRecord::<_, Block>::new(Arc::new(TestClient { api: TestApi::default() }));
+
+	let result = MedicalRecordApiServer::<_, AccountId32, u32, (), (), ()>::verify_audit_trail(&rpc, 0, None, None);
+	assert_eq!(result.unwrap(), Err(AuditError::Missing(3)));
+}
+
+#[test]
+fn documents_are_served_to_authorised_readers() {
+	let reader = sr25519::Pair::from_string("//Alice", None).unwrap();
+	let rpc = documents(&reader, true);
+
+	let signed_at = BEST_NUMBER - 1;
+	let signature = sign(&reader, signed_at);
+	let document = rpc.get_document(H256(content_hash()), account(&reader), signed_at, signature.clone());
+	assert_eq!(document.unwrap(), Bytes(DOCUMENT.to_vec()));
+
+	// A request is served once; the reader signs a new one to fetch the document again
+	let error = rpc
+		.get_document(H256(content_hash()), account(&reader), signed_at, signature)
+		.unwrap_err();
+	assert_eq!(error.code(), 6);
+	let document = rpc.get_document(H256(content_hash()), account(&reader), BEST_NUMBER, sign(&reader, BEST_NUMBER));
+	assert_eq!(document.unwrap(), Bytes(DOCUMENT.to_vec()));
+}
+
+#[test]
+fn registered_documents_are_stored_from_their_uploader() {
+	let uploader = sr25519::Pair::from_string("//Alice", None).unwrap();
+	let other = sr25519::Pair::from_string("//Bob", None).unwrap();
+	let rpc = documents(&uploader, false);
+	let upload = |signer: &sr25519::Pair, uploader: &sr25519::Pair, content: &[u8], signed_at: u32| {
+		rpc.put_document(Bytes(content.to_vec()), account(uploader), signed_at, sign_upload(signer, content, signed_at))
+	};
+
+	// Signed by another account than the uploader, or too long ago
+	assert_eq!(upload(&other, &uploader, DOCUMENT, BEST_NUMBER).unwrap_err().code(), 2);
+	assert_eq!(upload(&uploader, &uploader, DOCUMENT, BEST_NUMBER - DOCUMENT_REQUEST_VALIDITY - 1).unwrap_err().code(), 3);
+	// Not the registered uploader, or not the registered content
+	assert_eq!(upload(&other, &other, DOCUMENT, BEST_NUMBER).unwrap_err().code(), 4);
+	assert_eq!(upload(&uploader, &uploader, b"scam", BEST_NUMBER).unwrap_err().code(), 4);
+	// A read signature does not upload anything
+	let error = rpc
+		.put_document(Bytes(DOCUMENT.to_vec()), account(&uploader), BEST_NUMBER, sign(&uploader, BEST_NUMBER))
+		.unwrap_err();
+	assert_eq!(error.code(), 2);
+	assert_eq!(
+		rpc.get_document(H256(content_hash()), account(&uploader), BEST_NUMBER, sign(&uploader, BEST_NUMBER))
+			.unwrap_err()
+			.code(),
+		5
+	);
+
+	assert_eq!(upload(&uploader, &uploader, DOCUMENT, BEST_NUMBER).unwrap(), H256(content_hash()));
+	let document = rpc.get_document(H256(content_hash()), account(&uploader), BEST_NUMBER - 1, sign(&uploader, BEST_NUMBER - 1));
+	assert_eq!(document.unwrap(), Bytes(DOCUMENT.to_vec()));
+}
+
+#[test]
+fn document_request_errors_have_distinct_codes() {
+	let reader = sr25519::Pair::from_string("//Alice", None).unwrap();
+	let other = sr25519::Pair::from_string("//Bob", None).unwrap();
+	let rpc = documents(&reader, true);
+	let request = |signer: &sr25519::Pair, reader: &sr25519::Pair, signed_at: u32| {
+		rpc.get_document(H256(content_hash()), account(reader), signed_at, sign(signer, signed_at))
+			.unwrap_err()
+			.code()
+	};
+
+	// Signed by another account than the reader
+	assert_eq!(request(&other, &reader, BEST_NUMBER), 2);
+	// Signed too long ago, or at a future block
+	assert_eq!(request(&reader, &reader, BEST_NUMBER - DOCUMENT_REQUEST_VALIDITY - 1), 3);
+	assert_eq!(request(&reader, &reader, BEST_NUMBER + 1), 3);
+	// Signed by an account that may not read the document
+	assert_eq!(request(&other, &other, BEST_NUMBER), 4);
+
+	// Allowed, but not stored on this node
+	let rpc = documents(&reader, false);
+	let error = rpc
+		.get_document(H256(content_hash()), account(&reader), BEST_NUMBER, sign(&reader, BEST_NUMBER))
+		.unwrap_err();
+	assert_eq!(error.code(), 5);
+	assert_eq!(error.message(), "Document not available on this node.");
+}
+
+#[test]
+fn error_codes_are_stable() {
+	assert_eq!(i32::from(Error::RuntimeError), 1);
+	assert_eq!(i32::from(Error::BadSignature), 2);
+	assert_eq!(i32::from(Error::StaleRequest), 3);
+	assert_eq!(i32::from(Error::Unauthorized), 4);
+	assert_eq!(i32::from(Error::DocumentUnavailable), 5);
+	assert_eq!(i32::from(Error::RequestReused), 6);
+}
@@ -0,0 +1,73 @@
+//! Runtime API definition for the medical-record pallet.
+//!
+//! Exposes the read helpers of `pallet-medical-record` so clients can query records without
+//! submitting extrinsics.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use codec::Codec;
+
+pub use pallet_medical_record::{
+	data_pointer::{document_key, document_read_payload, document_write_payload},
+	AuditError, ContentHash, Page, RecordType,
+};
+
+sp_api::decl_runtime_apis! {
+	/// Version 2 added `verify_audit_trail`.
+	#[api_version(2)]
+	pub trait MedicalRecordApi<AccountId, BlockNumber, PatientInfo, ClinicalTest, ChangeHistory>
+	where
+		AccountId: Codec,
+		BlockNumber: Codec,
+		PatientInfo: Codec,
+		ClinicalTest: Codec,
+		ChangeHistory: Codec,
+	{
+		/// A page of active patients, starting after `cursor`.
+		fn get_all_patients(cursor: Option<u32>, limit: u32) -> Page<PatientInfo>;
+
+		/// All clinical tests of a patient.
+		fn get_patient_clinical_tests(patient_id: u32) -> Vec<ClinicalTest>;
+
+		/// Clinical tests, visits and records of a patient in chronological order.
+		fn get_patient_timeline(patient_id: u32) -> Vec<(BlockNumber, Vec<u8>)>;
+
+		/// The change history of a single record.
+		fn get_record_history(record_type: RecordType, record_id: u32) -> Vec<ChangeHistory>;
+
+		/// Changes made by an account among the `limit` changes after `cursor`.
+		fn get_changes_by_user(user: AccountId, cursor: Option<u32>, limit: u32) -> Page<ChangeHistory>;
+
+		/// Changes made between two blocks, inclusive, among the `limit` changes after `cursor`.
+		fn get_changes_in_range(
+			start_block: BlockNumber,
+			end_block: BlockNumber,
+			cursor: Option<u32>,
+			limit: u32,
+		) -> Page<ChangeHistory>;
+
+		/// The `limit` changes made before `cursor`, or the latest ones, newest first.
+		fn get_latest_changes(cursor: Option<u32>, limit: u32) -> Page<ChangeHistory>;
+
+		/// Verify the hash chain of the audit trail from change `from` up to change `to`, or up to
+		/// the audit head if `to` is `None`, and return the number of changes checked.
+		#[api_version(2)]
+		fn verify_audit_trail(from: u32, to: Option<u32>) -> Result<u32, AuditError>;
+	}
+
+	/// Access checks for documents registered with `store_document` and kept off-chain.
+	pub trait MedicalDocumentsApi<AccountId>
+	where
+		AccountId: Codec,
+	{
+		/// Whether `reader` may fetch the stored document with the given content hash.
+		fn can_read_document(reader: AccountId, content_hash: ContentHash) -> bool;
+
+		/// Whether `writer` may hand a node the content of the registered document with the given
+		/// content hash and size.
+		fn can_write_document(writer: AccountId, content_hash: ContentHash, size: u32) -> bool;
+	}
+}
@@ -5,8 +5,74 @@ use super::*;
 #[allow(unused)]
 use crate::Pallet as MedicalRecord;
 use frame_benchmarking::v2::*;
+use frame_support::traits::{Get, Hooks};
 use frame_system::RawOrigin;
 
+fn grant_full_consent<T: Config>(patient_id: u32, grantee: &T::AccountId, record_type: RecordType) {
+	Consents::<T>::insert(
+		patient_id,
+		(grantee.clone(), record_type),
+		ConsentGrant::<T> {
+			scope: ConsentScope { read: true, append: true, amend: true },
+			expires_at: None,
+			granted_by: grantee.clone(),
+			granted_at: frame_system::Pallet::<T>::block_number(),
+		},
+	);
+}
+
+// Stand-in for the keyed hash clients compute over a normalised name
+fn name_index(name: &[u8]) -> NameIndex {
+	use frame_support::sp_runtime::traits::{BlakeTwo256, Hash};
+	BlakeTwo256::hash(name).0
+}
+
+// A PII field in the form the runtime accepts: sealed in an envelope with the first data key
+// when PII has to be encrypted
+fn pii<T: Config>(value: &[u8]) -> Vec<u8> {
+	if !T::EncryptedPii::get() {
+		return value.to_vec();
+	}
+	PiiEnvelope {
+		version: envelope::ENVELOPE_VERSION,
+		key_version: 0,
+		nonce: [0; 24],
+		ciphertext: [value, &[0; envelope::TAG_LENGTH]].concat(),
+	}
+	.encode()
+}
+
+// Have `doctor` create progression 0 of a linked patient and open their calendar, returning a
+// time its follow-up can be booked at
+fn progression_without_follow_up<T: Config>(doctor: &T::AccountId) -> u64 {
+	AccountRoles::<T>::insert(doctor, Role::Doctor, ());
+	MedicalRecord::<T>::create_patient(
+		RawOrigin::Signed(doctor.clone()).into(),
+		pii::<T>(b"John Doe"),
+		pii::<T>(b"1990-01-01"),
+		b"Male".to_vec(),
+		pii::<T>(b"123 Main St"),
+		pii::<T>(b"555-1234"),
+		pii::<T>(b"Jane Doe - 555-5678"),
+		name_index(b"John Doe"),
+	)
+	.unwrap();
+	grant_full_consent::<T>(0, doctor, RecordType::DiseaseProgression);
+	PatientAccounts::<T>::insert(0, account::<T::AccountId>("patient", 0, 0));
+	MedicalRecord::<T>::create_disease_progression(
+		RawOrigin::Signed(doctor.clone()).into(),
+		0,
+		b"2023-01-15".to_vec(),
+		b"Fever, headache".to_vec(),
+		b"Common cold".to_vec(),
+		b"Rest and fluids".to_vec(),
+		b"Paracetamol 500mg".to_vec(),
+		None,
+	)
+	.unwrap();
+	T::Appointments::prepare_slot(doctor)
+}
+
 #[benchmarks]
 mod benchmarks {
 	use super::*;
@@ -14,34 +80,99 @@ mod benchmarks {
 	#[benchmark]
 	fn create_patient() {
 		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
 		#[extrinsic_call]
 		_(
 			RawOrigin::Signed(caller),
-			b"John Doe".to_vec(),
-			b"1990-01-01".to_vec(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
 			b"Male".to_vec(),
-			b"123 Main St".to_vec(),
-			b"555-1234".to_vec(),
-			b"Jane Doe - 555-5678".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
 		);
 
 		assert_eq!(MedicalRecord::<T>::next_patient_id(), 1);
 	}
 
+	#[benchmark]
+	fn set_data_keys(n: Linear<1, { T::MaxDataKeyHolders::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
+		let _ = MedicalRecord::<T>::create_patient(
+			RawOrigin::Signed(caller.clone()).into(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
+			b"Male".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
+		);
+		let wrapped_key = WrappedDataKey {
+			ephemeral_public: [1; 32],
+			nonce: [2; 24],
+			ciphertext: [3; envelope::DATA_KEY_LENGTH + envelope::TAG_LENGTH],
+		};
+		let wrapped_keys: Vec<_> = (0..n)
+			.map(|i| {
+				let holder: T::AccountId = account("holder", i, 0);
+				EncryptionKeys::<T>::insert(&holder, [4; 32]);
+				(holder, wrapped_key.clone())
+			})
+			.collect();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), 0, 0, wrapped_keys);
+
+		assert_eq!(MedicalRecord::<T>::patient_data_keys(0).map(|keyring| keyring.wrapped_keys.len()), Some(n as usize));
+	}
+
+	#[benchmark]
+	fn store_document() {
+		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
+		let _ = MedicalRecord::<T>::create_patient(
+			RawOrigin::Signed(caller.clone()).into(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
+			b"Male".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
+		);
+		// Worst case checks every role and every record type before finding the consent
+		AccountRoles::<T>::remove(&caller, Role::Doctor);
+		AccountRoles::<T>::insert(&caller, Role::LabTechnician, ());
+		grant_full_consent::<T>(0, &caller, RecordType::MedicalRecord);
+		let content_hash = [7u8; 32];
+		let size = T::MaxDocumentSize::get();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), 0, content_hash, size);
+
+		assert_eq!(MedicalRecord::<T>::documents(content_hash).map(|document| document.size), Some(size));
+	}
+
 	#[benchmark]
 	fn create_clinical_test() {
 		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
 		
 		// First create a patient
 		let _ = MedicalRecord::<T>::create_patient(
 			RawOrigin::Signed(caller.clone()).into(),
-			b"John Doe".to_vec(),
-			b"1990-01-01".to_vec(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
 			b"Male".to_vec(),
-			b"123 Main St".to_vec(),
-			b"555-1234".to_vec(),
-			b"Jane Doe - 555-5678".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
 		);
+		grant_full_consent::<T>(0, &caller, RecordType::ClinicalTest);
 
 		#[extrinsic_call]
 		_(
@@ -59,17 +190,23 @@ mod benchmarks {
 	#[benchmark]
 	fn create_disease_progression() {
 		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
 		
 		// First create a patient
 		let _ = MedicalRecord::<T>::create_patient(
 			RawOrigin::Signed(caller.clone()).into(),
-			b"John Doe".to_vec(),
-			b"1990-01-01".to_vec(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
 			b"Male".to_vec(),
-			b"123 Main St".to_vec(),
-			b"555-1234".to_vec(),
-			b"Jane Doe - 555-5678".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
 		);
+		grant_full_consent::<T>(0, &caller, RecordType::DiseaseProgression);
+		// Worst case books the follow-up appointment
+		PatientAccounts::<T>::insert(0, account::<T::AccountId>("patient", 0, 0));
+		let follow_up = T::Appointments::prepare_slot(&caller);
 
 		#[extrinsic_call]
 		_(
@@ -80,10 +217,116 @@ mod benchmarks {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			b"2023-01-22".to_vec(),
+			Some(follow_up),
 		);
 
 		assert_eq!(MedicalRecord::<T>::next_progression_id(), 1);
+		let progression = DiseaseProgressions::<T>::get(0).unwrap();
+		assert!(MedicalRecord::<T>::next_appointment_of(&progression).is_some());
+	}
+
+	#[benchmark]
+	fn set_next_appointment() {
+		let caller: T::AccountId = whitelisted_caller();
+		let follow_up = progression_without_follow_up::<T>(&caller);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), 0u32, follow_up);
+
+		let progression = DiseaseProgressions::<T>::get(0).unwrap();
+		assert!(MedicalRecord::<T>::next_appointment_of(&progression).is_some());
+	}
+
+	#[benchmark]
+	fn clear_appointment() {
+		let caller: T::AccountId = whitelisted_caller();
+		let follow_up = progression_without_follow_up::<T>(&caller);
+		MedicalRecord::<T>::set_next_appointment(RawOrigin::Signed(caller.clone()).into(), 0, follow_up)
+			.unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), 0u32);
+
+		assert_eq!(DiseaseProgressions::<T>::get(0).unwrap().next_appointment, None);
+	}
+
+	#[benchmark]
+	fn reindex_patient_names(n: Linear<1, { T::MaxReindexBatchSize::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&caller, Role::Doctor, ());
+		for i in 0..n {
+			MedicalRecord::<T>::create_patient(
+				RawOrigin::Signed(caller.clone()).into(),
+				pii::<T>(b"John Doe"),
+				pii::<T>(b"1990-01-01"),
+				b"Male".to_vec(),
+				pii::<T>(b"123 Main St"),
+				pii::<T>(b"555-1234"),
+				pii::<T>(b"Jane Doe - 555-5678"),
+				name_index(&i.to_le_bytes()),
+			)
+			.unwrap();
+		}
+		AccountRoles::<T>::insert(&caller, Role::Admin, ());
+		let entries: Vec<_> = (0..n).map(|i| (i, name_index(&(n + i).to_le_bytes()))).collect();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), frame_support::BoundedVec::truncate_from(entries));
+
+		assert_eq!(MedicalRecord::<T>::get_patient_id_by_name(&name_index(&n.to_le_bytes())), Some(0));
+	}
+
+	// Worst case: every step deletes a disease progression whose follow-up is booked and paid for,
+	// so that it is cancelled and its funds returned, and records the deletion in the audit trail
+	#[benchmark]
+	fn on_initialize(s: Linear<0, { T::MaxRemovalStepsPerBlock::get() }>) {
+		let admin: T::AccountId = whitelisted_caller();
+		AccountRoles::<T>::insert(&admin, Role::Doctor, ());
+		AccountRoles::<T>::insert(&admin, Role::Admin, ());
+		MedicalRecord::<T>::create_patient(
+			RawOrigin::Signed(admin.clone()).into(),
+			pii::<T>(b"John Doe"),
+			pii::<T>(b"1990-01-01"),
+			b"Male".to_vec(),
+			pii::<T>(b"123 Main St"),
+			pii::<T>(b"555-1234"),
+			pii::<T>(b"Jane Doe - 555-5678"),
+			name_index(b"John Doe"),
+		)
+		.unwrap();
+		PatientAccounts::<T>::insert(0, account::<T::AccountId>("patient", 0, 0));
+		// One doctor per follow-up, each with their own calendar
+		for i in 0..s {
+			let doctor: T::AccountId = account("doctor", i, 0);
+			AccountRoles::<T>::insert(&doctor, Role::Doctor, ());
+			grant_full_consent::<T>(0, &doctor, RecordType::DiseaseProgression);
+			let follow_up = T::Appointments::prepare_slot(&doctor);
+			MedicalRecord::<T>::create_disease_progression(
+				RawOrigin::Signed(doctor).into(),
+				0,
+				b"2023-01-15".to_vec(),
+				b"Fever, headache".to_vec(),
+				b"Common cold".to_vec(),
+				b"Rest and fluids".to_vec(),
+				b"Paracetamol 500mg".to_vec(),
+				Some(follow_up),
+			)
+			.unwrap();
+			let appointment_id = DiseaseProgressions::<T>::get(i).unwrap().next_appointment.unwrap();
+			T::Appointments::prepare_escrow(appointment_id);
+		}
+		// Leave only the progressions to delete
+		let _ = Consents::<T>::clear_prefix(0, u32::MAX, None);
+		PatientDoctors::<T>::remove(0);
+		MedicalRecord::<T>::delete_patient(RawOrigin::Signed(admin).into(), 0).unwrap();
+		let block = frame_system::Pallet::<T>::block_number() + 1u32.into();
+
+		#[block]
+		{
+			MedicalRecord::<T>::on_initialize(block);
+		}
+
+		assert!(PatientDiseaseProgressions::<T>::get(0).is_empty());
 	}
 
 	impl_benchmark_test_suite!(MedicalRecord, crate::mock::new_test_ext(), crate::mock::Test);
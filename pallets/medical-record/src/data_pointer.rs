@@ -0,0 +1,158 @@
+//! Content-addressed pointers to documents kept off-chain.
+//!
+//! Lab PDFs, imaging reports and other documents never go into runtime state. A record refers to
+//! them with a [`DataPointer`]: where the document lives, the SHA-256 hash of its content and its
+//! size, so whoever fetches it can check they got the right bytes. Documents registered with
+//! `store_document` are handed to nodes over RPC by their uploader, kept in the node's offchain
+//! database under [`document_key`], and served to authorised readers over RPC.
+
+use codec::{Decode, DecodeWithMemTracking, Encode, MaxEncodedLen};
+use frame_support::{
+	traits::Get, BoundedVec, CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
+use scale_info::{prelude::vec::Vec, TypeInfo};
+use serde::Serialize;
+
+/// Multicodec of a raw binary CID, whose multihash is the hash of the content itself.
+pub const RAW_CODEC: u64 = 0x55;
+
+/// Multihash code of SHA2-256.
+pub const SHA2_256_CODE: u64 = 0x12;
+
+/// Prefix of the offchain database keys stored documents are indexed under.
+pub const DOCUMENT_KEY_PREFIX: &[u8] = b"medical-record/document/";
+
+/// Context signed by readers requesting a document over RPC, see [`document_read_payload`].
+pub const DOCUMENT_READ_CONTEXT: &[u8] = b"healer-network/document-read/v1";
+
+/// Context signed by uploaders handing a document to a node over RPC, see
+/// [`document_write_payload`].
+pub const DOCUMENT_WRITE_CONTEXT: &[u8] = b"healer-network/document-write/v1";
+
+/// SHA-256 hash of a document.
+pub type ContentHash = [u8; 32];
+
+/// Where an off-chain document lives.
+#[derive(
+	Encode,
+	Decode,
+	DecodeWithMemTracking,
+	MaxEncodedLen,
+	TypeInfo,
+	CloneNoBound,
+	PartialEqNoBound,
+	EqNoBound,
+	RuntimeDebugNoBound,
+	Serialize,
+)]
+#[scale_info(skip_type_params(S))]
+#[codec(mel_bound(S: Get<u32>))]
+#[serde(bound = "")]
+pub enum DataLocation<S: Get<u32>> {
+	/// An IPFS object, as a binary CIDv1 with a SHA2-256 multihash.
+	Ipfs(BoundedVec<u8, S>),
+	/// A document registered with `store_document`, addressed by its content hash.
+	Blob,
+	/// An `https://` URL.
+	Url(BoundedVec<u8, S>),
+}
+
+/// A document kept off-chain, attached to a record.
+#[derive(
+	Encode,
+	Decode,
+	DecodeWithMemTracking,
+	MaxEncodedLen,
+	TypeInfo,
+	CloneNoBound,
+	PartialEqNoBound,
+	EqNoBound,
+	RuntimeDebugNoBound,
+	Serialize,
+)]
+#[scale_info(skip_type_params(S))]
+#[codec(mel_bound(S: Get<u32>))]
+#[serde(bound = "")]
+pub struct DataPointer<S: Get<u32>> {
+	pub location: DataLocation<S>,
+	/// SHA-256 hash of the document.
+	pub content_hash: ContentHash,
+	/// Size of the document in bytes.
+	pub size: u64,
+}
+
+impl<S: Get<u32>> DataPointer<S> {
+	/// Check the pointer is well-formed: the document is not empty, an IPFS location is a CIDv1
+	/// with a SHA2-256 multihash matching the content hash if the CID is raw, and a URL is a
+	/// printable `https://` URL with a host.
+	pub fn is_valid(&self) -> bool {
+		if self.size == 0 {
+			return false;
+		}
+		match &self.location {
+			DataLocation::Ipfs(cid) => match parse_cid_v1(cid) {
+				Some((RAW_CODEC, digest)) => digest == self.content_hash,
+				Some(_) => true,
+				None => false,
+			},
+			DataLocation::Blob => true,
+			DataLocation::Url(url) => is_valid_url(url),
+		}
+	}
+}
+
+/// Split a binary CIDv1 into its content codec and SHA2-256 digest.
+///
+/// Returns `None` for other CID versions, other hash functions and trailing bytes.
+pub fn parse_cid_v1(cid: &[u8]) -> Option<(u64, [u8; 32])> {
+	let mut input = cid;
+	if read_varint(&mut input)? != 1 {
+		return None;
+	}
+	let codec = read_varint(&mut input)?;
+	if read_varint(&mut input)? != SHA2_256_CODE || read_varint(&mut input)? != 32 {
+		return None;
+	}
+	Some((codec, input.try_into().ok()?))
+}
+
+/// Offchain database key a stored document is indexed under.
+pub fn document_key(content_hash: &ContentHash) -> Vec<u8> {
+	[DOCUMENT_KEY_PREFIX, &content_hash[..]].concat()
+}
+
+/// Message a reader signs to fetch a document over RPC, valid from block `signed_at`.
+pub fn document_read_payload<BlockNumber: Encode>(
+	content_hash: &ContentHash,
+	signed_at: BlockNumber,
+) -> Vec<u8> {
+	(DOCUMENT_READ_CONTEXT, content_hash, signed_at).encode()
+}
+
+/// Message an uploader signs to hand a document to a node over RPC, valid from block `signed_at`.
+pub fn document_write_payload<BlockNumber: Encode>(
+	content_hash: &ContentHash,
+	signed_at: BlockNumber,
+) -> Vec<u8> {
+	(DOCUMENT_WRITE_CONTEXT, content_hash, signed_at).encode()
+}
+
+// Unsigned LEB128 varint as used by multiformats, at most 9 bytes long
+fn read_varint(input: &mut &[u8]) -> Option<u64> {
+	let mut value = 0u64;
+	for shift in (0..63).step_by(7) {
+		let (&byte, rest) = input.split_first()?;
+		*input = rest;
+		value |= u64::from(byte & 0x7f) << shift;
+		if byte & 0x80 == 0 {
+			return Some(value);
+		}
+	}
+	None
+}
+
+fn is_valid_url(url: &[u8]) -> bool {
+	let Some(rest) = url.strip_prefix(b"https://") else { return false };
+	let host = rest.split(|&byte| byte == b'/').next().unwrap_or_default();
+	!host.is_empty() && rest.iter().all(|byte| byte.is_ascii_graphic())
+}
@@ -0,0 +1,59 @@
+//! Encrypted envelopes for patient PII.
+//!
+//! With `Config::EncryptedPii` enabled the name, date of birth, address, phone and emergency
+//! contact of a patient hold a SCALE encoded [`PiiEnvelope`] instead of plaintext. Each patient
+//! has one symmetric data key, which is wrapped for every account allowed to read the patient's
+//! details and stored as a [`WrappedDataKey`]. Encryption and decryption happen client side, see
+//! the `pallet-medical-record-pii` crate; the pallet only checks that fields are well-formed
+//! envelopes sealed with the patient's current data key.
+
+use codec::{Decode, DecodeAll, DecodeWithMemTracking, Encode, MaxEncodedLen};
+use frame_support::sp_runtime::RuntimeDebug;
+use scale_info::{prelude::vec::Vec, TypeInfo};
+
+/// Envelope format written by the client helpers: XChaCha20-Poly1305 under the patient's data key.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Length of the Poly1305 tag at the end of every ciphertext.
+pub const TAG_LENGTH: usize = 16;
+
+/// Length of a patient data key.
+pub const DATA_KEY_LENGTH: usize = 32;
+
+/// Bytes an envelope adds to its plaintext: version, key version, nonce, the length prefix of
+/// the ciphertext (up to two bytes for fields shorter than 16 KiB) and the tag.
+pub const ENVELOPE_OVERHEAD: u32 = 1 + 4 + 24 + 2 + TAG_LENGTH as u32;
+
+/// A PII field sealed with a patient's data key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PiiEnvelope {
+	/// Always [`ENVELOPE_VERSION`].
+	pub version: u8,
+	/// Version of the patient data key the field is sealed with.
+	pub key_version: u32,
+	pub nonce: [u8; 24],
+	/// The sealed field followed by its tag.
+	pub ciphertext: Vec<u8>,
+}
+
+impl PiiEnvelope {
+	/// Decode a field as an envelope, rejecting trailing bytes, unknown versions and ciphertexts
+	/// too short to carry a tag.
+	pub fn from_field(field: &[u8]) -> Option<Self> {
+		let envelope = Self::decode_all(&mut &field[..]).ok()?;
+		(envelope.version == ENVELOPE_VERSION && envelope.ciphertext.len() >= TAG_LENGTH)
+			.then_some(envelope)
+	}
+}
+
+/// A patient data key wrapped for one account.
+///
+/// The key is sealed with XChaCha20-Poly1305 under a key derived from an x25519 exchange between
+/// `ephemeral_public` and the encryption key the account registered.
+#[derive(Encode, Decode, DecodeWithMemTracking, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct WrappedDataKey {
+	pub ephemeral_public: [u8; 32],
+	pub nonce: [u8; 24],
+	/// The sealed data key followed by its tag.
+	pub ciphertext: [u8; DATA_KEY_LENGTH + TAG_LENGTH],
+}
@@ -19,30 +19,81 @@ mod tests;
 // for each dispatchable and generates this pallet's weight.rs file. Learn more about benchmarking here: https://docs.substrate.io/test/benchmark/
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
+pub mod data_pointer;
+pub mod envelope;
+pub mod migrations;
 pub mod weights;
 pub use weights::*;
+pub use data_pointer::{ContentHash, DataLocation, DataPointer};
+pub use envelope::{PiiEnvelope, WrappedDataKey};
 
 use scale_info::prelude::vec::*;
-use scale_info::prelude::vec;
 
 use scale_info::prelude::format;
 
+use codec::{Decode, Encode};
+use frame_support::{dispatch::DispatchResult, sp_runtime::{DispatchError, RuntimeDebug}};
+use scale_info::TypeInfo;
+use serde::{Deserialize, Serialize};
+
+/// An upcoming appointment a disease progression links to.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, Serialize, Deserialize)]
+pub struct ScheduledAppointment<AccountId> {
+	pub appointment_id: u32,
+	pub doctor_id: AccountId,
+	/// Unix time of the appointment, in seconds.
+	pub scheduled_time: u64,
+}
+
+/// The appointment book follow-up visits are booked in.
+///
+/// Implemented by the runtime on top of the medical appointment pallet, so a disease progression
+/// points at a real appointment instead of a free-text date. This pallet checks who may change a
+/// progression; the scheduler books, moves and cancels on its behalf.
+pub trait AppointmentScheduler<AccountId> {
+	/// Book an appointment of `patient` with `doctor` at `scheduled_time`, returning its id.
+	fn schedule(patient: &AccountId, doctor: &AccountId, scheduled_time: u64) -> Result<u32, DispatchError>;
+	/// Move an upcoming appointment to `scheduled_time` on behalf of `who`, who has to be its
+	/// doctor.
+	fn reschedule(who: &AccountId, appointment_id: u32, scheduled_time: u64) -> DispatchResult;
+	/// Cancel an upcoming appointment on behalf of `who`, who has to be its doctor.
+	fn cancel(who: &AccountId, appointment_id: u32) -> DispatchResult;
+	/// The appointment, if it is still upcoming: neither cancelled nor already seen.
+	fn upcoming(appointment_id: u32) -> Option<ScheduledAppointment<AccountId>>;
+	/// Open the calendar of `doctor`, returning a time an appointment can be booked at.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_slot(doctor: &AccountId) -> u64;
+	/// Hold funds for an upcoming appointment as if its patient had paid for it, so cancelling it
+	/// has them returned.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_escrow(appointment_id: u32);
+}
+
 // All pallet logic is defined in its own module and must be annotated by the `pallet` attribute.
-#[frame_support::pallet(dev_mode)]
+#[allow(clippy::too_many_arguments)]
+#[frame_support::pallet]
 pub mod pallet {
 	// Import various useful types required by all FRAME pallets.
 	use super::*;
 	use frame_support::{
 		pallet_prelude::*,
-		sp_runtime::traits::{Hash, Member},
+		sp_runtime::traits::Hash,
+		traits::DefensiveTruncateFrom,
+		DefaultNoBound,
 	};
+	use pallet_poh::ProofAnchor as _;
+	#[cfg(any(feature = "try-runtime", test))]
+	use frame_support::sp_runtime::TryRuntimeError;
 
 	use frame_system::pallet_prelude::*;
 
 	// The `Pallet` struct serves as a placeholder to implement traits, methods and dispatchables
 	// (`Call`s) in this pallet.
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(8);
+
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// The pallet's configuration trait.
@@ -56,25 +107,348 @@ pub mod pallet {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		/// A type representing the weights required by the dispatchables of this pallet.
 		type WeightInfo: WeightInfo;
+		/// Origin allowed to grant and revoke roles in the registry.
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Origin allowed to approve or reject requests to erase a patient's data.
+		type DataProtectionOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Proof chain every clinical test, disease progression and medical record version is
+		/// anchored into.
+		type ProofAnchor: pallet_poh::ProofAnchor<Self::AccountId, Self::Hash>;
+		/// Appointment book the follow-up visits of disease progressions are booked in.
+		type Appointments: AppointmentScheduler<Self::AccountId>;
+
+		/// Maximum length of a patient name.
+		#[pallet::constant]
+		type MaxNameLength: Get<u32>;
+		/// Maximum length of a date field (date of birth, test date, visit date).
+		#[pallet::constant]
+		type MaxDateLength: Get<u32>;
+		/// Maximum length of the gender field.
+		#[pallet::constant]
+		type MaxGenderLength: Get<u32>;
+		/// Maximum length of a patient address.
+		#[pallet::constant]
+		type MaxAddressLength: Get<u32>;
+		/// Maximum length of a phone number.
+		#[pallet::constant]
+		type MaxPhoneLength: Get<u32>;
+		/// Maximum length of the emergency contact field.
+		#[pallet::constant]
+		type MaxEmergencyContactLength: Get<u32>;
+		/// Maximum length of a clinical test type.
+		#[pallet::constant]
+		type MaxTestTypeLength: Get<u32>;
+		/// Maximum length of a clinical test result.
+		#[pallet::constant]
+		type MaxTestResultLength: Get<u32>;
+		/// Maximum length of clinical test notes.
+		#[pallet::constant]
+		type MaxNotesLength: Get<u32>;
+		/// Maximum length of symptoms, diagnosis, treatment and prescription fields.
+		#[pallet::constant]
+		type MaxClinicalTextLength: Get<u32>;
+		/// Maximum length of the CID or URL of an off-chain data pointer.
+		#[pallet::constant]
+		type MaxDataPointerLength: Get<u32>;
+		/// Maximum number of documents attached to a single clinical test or disease progression.
+		#[pallet::constant]
+		type MaxAttachmentsPerRecord: Get<u32>;
+		/// Maximum size of a document registered with `store_document`, in bytes.
+		#[pallet::constant]
+		type MaxDocumentSize: Get<u32>;
+		/// Maximum length of a field name in the change history.
+		#[pallet::constant]
+		type MaxFieldNameLength: Get<u32>;
+		/// Maximum length of an old or new value in the change history.
+		#[pallet::constant]
+		type MaxChangeValueLength: Get<u32>;
+		/// Maximum number of patients registered under the same name.
+		#[pallet::constant]
+		type MaxPatientsPerName: Get<u32>;
+		/// Maximum number of items returned by a single page of a paginated query.
+		#[pallet::constant]
+		type MaxPageSize: Get<u32>;
+		/// Maximum number of patients re-indexed by a single `reindex_patient_names` call.
+		#[pallet::constant]
+		type MaxReindexBatchSize: Get<u32>;
+		/// Maximum number of clinical tests, progressions or medical records per patient.
+		#[pallet::constant]
+		type MaxRecordsPerPatient: Get<u32>;
+		/// Maximum number of patients linked to a single doctor.
+		#[pallet::constant]
+		type MaxPatientsPerDoctor: Get<u32>;
+		/// Maximum number of doctors linked to a single patient.
+		#[pallet::constant]
+		type MaxDoctorsPerPatient: Get<u32>;
+		/// Maximum number of anchored versions kept for a single record.
+		#[pallet::constant]
+		type MaxProofsPerRecord: Get<u32>;
+		/// Maximum number of changes checked by a single audit trail verification.
+		#[pallet::constant]
+		type MaxAuditRange: Get<u32>;
+		/// Whether patient PII fields must be encrypted envelopes rather than plaintext.
+		#[pallet::constant]
+		type EncryptedPii: Get<bool>;
+		/// Maximum number of accounts a patient data key is wrapped for.
+		#[pallet::constant]
+		type MaxDataKeyHolders: Get<u32>;
+		/// Maximum number of approved erasures waiting to be carried out.
+		#[pallet::constant]
+		type MaxPendingErasures: Get<u32>;
+		/// Maximum number of deleted patients whose data is waiting to be deleted.
+		#[pallet::constant]
+		type MaxPendingDeletions: Get<u32>;
+		/// Maximum number of erasure and cascading deletion steps carried out at the start of a
+		/// block.
+		#[pallet::constant]
+		type MaxRemovalStepsPerBlock: Get<u32>;
+	}
+
+	// Roles that can be held by an account in the hospital registry
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+		DecodeWithMemTracking,
+		Serialize,
+		Deserialize,
+	)]
+	pub enum Role {
+		Admin,
+		Doctor,
+		Nurse,
+		LabTechnician,
+		Patient,
+	}
+
+	// Kind of access a consent grant can allow
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo, DecodeWithMemTracking)]
+	pub enum AccessKind {
+		/// Read existing entries.
+		Read,
+		/// Add new entries.
+		Append,
+		/// Modify or remove existing entries.
+		Amend,
+	}
+
+	// Scope of a consent grant - which kinds of access are allowed
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		Copy,
+		Default,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+		DecodeWithMemTracking,
+	)]
+	pub struct ConsentScope {
+		pub read: bool,
+		pub append: bool,
+		pub amend: bool,
+	}
+
+	impl ConsentScope {
+		pub fn allows(&self, access: AccessKind) -> bool {
+			match access {
+				AccessKind::Read => self.read,
+				AccessKind::Append => self.append,
+				AccessKind::Amend => self.amend,
+			}
+		}
+
+		pub fn is_empty(&self) -> bool {
+			!self.read && !self.append && !self.amend
+		}
+	}
+
+	// Pointer to an off-chain document, with CIDs and URLs bounded by `MaxDataPointerLength`
+	pub type DataPointerOf<T> = DataPointer<<T as Config>::MaxDataPointerLength>;
+
+	// Documents attached to a clinical test or disease progression
+	pub type AttachmentsOf<T> = BoundedVec<DataPointerOf<T>, <T as Config>::MaxAttachmentsPerRecord>;
+
+	// Keyed hash of a normalised patient name, computed client side with a secret held by the
+	// facility's staff, so the index can be searched without revealing names to chain observers
+	pub type NameIndex = [u8; 32];
+
+	// Consent given by a patient (or their guardian) to a specific account
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct ConsentGrant<T: Config> {
+		pub scope: ConsentScope,
+		pub expires_at: Option<BlockNumberFor<T>>,
+		pub granted_by: T::AccountId,
+		pub granted_at: BlockNumberFor<T>,
+	}
+
+	// The current data key of a patient, wrapped for every account allowed to read its PII
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct DataKeyring<T: Config> {
+		pub key_version: u32,
+		pub wrapped_keys: BoundedVec<(T::AccountId, WrappedDataKey), T::MaxDataKeyHolders>,
+	}
+
+	// A document registered with `store_document`; its content lives in the offchain database
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct DocumentInfo<T: Config> {
+		pub patient_id: u32,
+		pub size: u32,
+		pub uploaded_by: T::AccountId,
+		pub uploaded_at: BlockNumberFor<T>,
+	}
+
+	// Where an erasure request stands
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+	pub enum ErasureStage {
+		/// Waiting for approval by the data-protection origin.
+		Requested,
+		/// Erasing the patient's records, documents, consent grants and care links.
+		Records,
+		/// Redacting the audit entries of every record the patient ever had, of the patient
+		/// themselves and of their consent grants.
+		Audit,
+	}
+
+	// A patient's request to erase their data, carried out over several blocks once approved
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct ErasureRequest<T: Config> {
+		pub requested_by: T::AccountId,
+		pub requested_at: BlockNumberFor<T>,
+		pub approved_at: Option<BlockNumberFor<T>>,
+		pub stage: ErasureStage,
+		/// Record whose audit entries are being redacted, with the last change redacted so far.
+		pub redacting: Option<(RecordType, u32, Option<u32>)>,
+		/// Number of records, documents, grants, links and audit entries erased so far.
+		pub erased_items: u32,
+		/// Running hash over the hashes of everything erased so far, in erasure order.
+		pub digest: T::Hash,
+	}
+
+	// What is left of a patient after their data has been erased
+	#[derive(
+		Encode,
+		Decode,
+		MaxEncodedLen,
+		TypeInfo,
+		CloneNoBound,
+		PartialEqNoBound,
+		RuntimeDebugNoBound,
+	)]
+	#[scale_info(skip_type_params(T))]
+	pub struct ErasureTombstone<T: Config> {
+		pub requested_by: T::AccountId,
+		pub requested_at: BlockNumberFor<T>,
+		pub approved_at: BlockNumberFor<T>,
+		pub erased_at: BlockNumberFor<T>,
+		pub erased_items: u32,
+		/// Hash over the hashes of everything erased, see [`ErasureRequest::digest`].
+		pub digest: T::Hash,
+	}
+
+	// Something removed from a patient's data by a cascading deletion or an erasure
+	enum RemovedItem<T: Config> {
+		// A clinical test, disease progression or medical record, with the hash of its content
+		Record(RecordType, u32, Option<T::Hash>),
+		// A consent grant
+		Consent((T::AccountId, RecordType), ConsentGrant<T>),
+		// A document or care link, by hash
+		Other(T::Hash),
+	}
+
+	impl<T: Config> RemovedItem<T> {
+		fn hash(&self) -> Option<T::Hash> {
+			match self {
+				Self::Record(_, _, hash) => *hash,
+				Self::Consent(key, grant) => Some(T::Hashing::hash_of(&(key, grant))),
+				Self::Other(hash) => Some(*hash),
+			}
+		}
+	}
+
+	// A page of query results with the cursor to pass in to fetch the next page
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, Serialize, Deserialize)]
+	pub struct Page<Item> {
+		pub items: Vec<Item>,
+		pub next_cursor: Option<u32>,
+	}
+
+	// Everything stored for a patient, as returned by the `patient_complete_history` view function
+	#[derive(Encode, Decode, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct PatientHistory<T: Config> {
+		pub patient: PatientInfo<T>,
+		pub clinical_tests: Vec<ClinicalTest<T>>,
+		pub disease_progressions: Vec<DiseaseProgression<T>>,
+		pub medical_records: Vec<MedicalRecord<T>>,
 	}
 
 	// Patient Information Structure (TT_Bệnh nhân)
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		Serialize,
 	)]
 	#[scale_info(skip_type_params(T))]
+	#[serde(bound(serialize = "T::AccountId: Serialize, BlockNumberFor<T>: Serialize"))]
 	pub struct PatientInfo<T: Config> {
 		pub patient_id: u32,
-		pub patient_name: Vec<u8>,
-		pub date_of_birth: Vec<u8>,
-		pub gender: Vec<u8>,
-		pub address: Vec<u8>,
-		pub phone: Vec<u8>,
-		pub emergency_contact: Vec<u8>,
+		pub patient_name: BoundedVec<u8, T::MaxNameLength>,
+		pub date_of_birth: BoundedVec<u8, T::MaxDateLength>,
+		pub gender: BoundedVec<u8, T::MaxGenderLength>,
+		pub address: BoundedVec<u8, T::MaxAddressLength>,
+		pub phone: BoundedVec<u8, T::MaxPhoneLength>,
+		pub emergency_contact: BoundedVec<u8, T::MaxEmergencyContactLength>,
 		pub created_at: BlockNumberFor<T>,
 		pub created_by: T::AccountId,
 		pub last_modified_at: BlockNumberFor<T>,
@@ -85,19 +459,25 @@ pub mod pallet {
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		Serialize,
 	)]
 	#[scale_info(skip_type_params(T))]
+	#[serde(bound(serialize = "T::AccountId: Serialize, BlockNumberFor<T>: Serialize"))]
 	pub struct ClinicalTest<T: Config> {
 		pub test_id: u32,
 		pub patient_id: u32,
 		pub doctor_id: T::AccountId,
-		pub test_type: Vec<u8>,
-		pub test_date: Vec<u8>,
-		pub result: Vec<u8>,
-		pub notes: Vec<u8>,
+		pub test_type: BoundedVec<u8, T::MaxTestTypeLength>,
+		pub test_date: BoundedVec<u8, T::MaxDateLength>,
+		pub result: BoundedVec<u8, T::MaxTestResultLength>,
+		pub notes: BoundedVec<u8, T::MaxNotesLength>,
+		/// Lab reports and other documents kept off-chain.
+		pub attachments: AttachmentsOf<T>,
 		pub created_at: BlockNumberFor<T>,
 		pub created_by: T::AccountId,
 		pub last_modified_at: BlockNumberFor<T>,
@@ -108,21 +488,26 @@ pub mod pallet {
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
 	)]
 	#[scale_info(skip_type_params(T))]
 	pub struct DiseaseProgression<T: Config> {
 		pub progression_id: u32,
 		pub patient_id: u32,
 		pub doctor_id: T::AccountId,
-		pub visit_date: Vec<u8>,
-		pub symptoms: Vec<u8>,
-		pub diagnosis: Vec<u8>,
-		pub treatment: Vec<u8>,
-		pub prescription: Vec<u8>,
-		pub next_appointment: Vec<u8>,
+		pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+		pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+		pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+		pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+		pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+		/// Follow-up appointment booked in [`Config::Appointments`].
+		pub next_appointment: Option<u32>,
+		/// Imaging reports and other documents kept off-chain.
+		pub attachments: AttachmentsOf<T>,
 		pub created_at: BlockNumberFor<T>,
 		pub created_by: T::AccountId,
 		pub last_modified_at: BlockNumberFor<T>,
@@ -133,9 +518,11 @@ pub mod pallet {
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
 	)]
 	#[scale_info(skip_type_params(T))]
 	pub struct MedicalRecord<T: Config> {
@@ -143,9 +530,9 @@ pub mod pallet {
 		pub(crate) patient_id: u32,
 		pub(crate) doctor_id: T::AccountId,
 		pub(crate) record_hash: T::Hash,
-		pub(crate) data_pointer: Option<Vec<u8>>,
-		pub(crate) diagnosis: Vec<u8>,
-		pub(crate) treatment: Vec<u8>,
+		pub(crate) data_pointer: Option<DataPointerOf<T>>,
+		pub(crate) diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+		pub(crate) treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
 		pub(crate) created_at: BlockNumberFor<T>,
 		pub(crate) created_by: T::AccountId,
 		pub(crate) last_modified_at: BlockNumberFor<T>,
@@ -163,14 +550,25 @@ pub mod pallet {
 		OptionQuery
 	>;
 
-	// Storage for mapping patient names to patient IDs for search functionality
+	// Blinded name index for search: patients registered under each keyed hash of a name
+	#[pallet::storage]
+	#[pallet::getter(fn patients_by_name)]
+	pub type PatientsByName<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		NameIndex, // blinded name as key
+		BoundedVec<u32, T::MaxPatientsPerName>, // array of patient_ids as value
+		OptionQuery
+	>;
+
+	// Blinded name each patient is indexed under
 	#[pallet::storage]
-	#[pallet::getter(fn patient_name_to_id)]
-	pub type PatientNameToId<T: Config> = StorageMap<
+	#[pallet::getter(fn name_index_of)]
+	pub type PatientNameIndex<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
-		Vec<u8>, // patient_name as key
-		Vec<u32>, // array of patient_ids as value
+		u32, // patient_id
+		NameIndex,
 		OptionQuery
 	>;
 
@@ -192,7 +590,7 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		u32, // patient_id
-		Vec<u32>, // test_ids
+		BoundedVec<u32, T::MaxRecordsPerPatient>, // test_ids
 		ValueQuery
 	>;
 
@@ -214,7 +612,7 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		u32, // patient_id
-		Vec<u32>, // progression_ids
+		BoundedVec<u32, T::MaxRecordsPerPatient>, // progression_ids
 		ValueQuery
 	>;
 
@@ -225,7 +623,7 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		T::AccountId, // doctor_id
-		Vec<u32>, // patient_ids
+		BoundedVec<u32, T::MaxPatientsPerDoctor>, // patient_ids
 		ValueQuery
 	>;
 
@@ -236,35 +634,43 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		u32, // patient_id
-		Vec<T::AccountId>, // doctor_ids
+		BoundedVec<T::AccountId, T::MaxDoctorsPerPatient>, // doctor_ids
 		ValueQuery
 	>;
 
-	// Storage for active patients (not deleted)
+	// Storage for active patients (not deleted), one entry per patient
 	#[pallet::storage]
-	#[pallet::getter(fn active_patients)]
-	pub type ActivePatients<T: Config> = StorageValue<_, Vec<u32>, ValueQuery>;
+	pub type ActivePatients<T: Config> = CountedStorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		(),
+		OptionQuery
+	>;
 
-	// Storage for patients by gender for demographic queries
+	// Storage for patients by gender for demographic queries, one entry per patient so that a
+	// gender can hold any number of them
 	#[pallet::storage]
-	#[pallet::getter(fn patients_by_gender)]
-	pub type PatientsByGender<T: Config> = StorageMap<
+	pub type PatientsByGender<T: Config> = StorageDoubleMap<
 		_,
 		Blake2_128Concat,
-		Vec<u8>, // gender
-		Vec<u32>, // patient_ids
-		ValueQuery
+		BoundedVec<u8, T::MaxGenderLength>, // gender
+		Blake2_128Concat,
+		u32, // patient_id
+		(),
+		OptionQuery
 	>;
 
-	// Storage for patients by age range (birth year)
+	// Storage for patients by age range (birth year), one entry per patient
 	#[pallet::storage]
-	#[pallet::getter(fn patients_by_birth_year)]
-	pub type PatientsByBirthYear<T: Config> = StorageMap<
+	pub type PatientsByBirthYear<T: Config> = StorageDoubleMap<
 		_,
 		Blake2_128Concat,
 		u32, // birth_year
-		Vec<u32>, // patient_ids
-		ValueQuery
+		Blake2_128Concat,
+		u32, // patient_id
+		(),
+		OptionQuery
 	>;
 
 	// Counter storages
@@ -302,7 +708,7 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		u32, // patient_id
-		Vec<u32>, // record_ids
+		BoundedVec<u32, T::MaxRecordsPerPatient>, // record_ids
 		ValueQuery
 	>;
 
@@ -335,43 +741,263 @@ pub mod pallet {
 	#[pallet::getter(fn next_change_id)]
 	pub type NextChangeId<T: Config> = StorageValue<_, u32, ValueQuery>;
 
-	// Change History Structure for audit trail
+	// Hash of the latest change, the head of the hash-chained audit trail
+	#[pallet::storage]
+	#[pallet::getter(fn audit_head)]
+	pub type AuditHead<T: Config> = StorageValue<_, T::Hash, ValueQuery>;
+
+	// x25519 public key each account has data keys wrapped with
+	#[pallet::storage]
+	#[pallet::getter(fn encryption_key)]
+	pub type EncryptionKeys<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		[u8; 32],
+		OptionQuery
+	>;
+
+	// Wrapped copies of each patient's data key
+	#[pallet::storage]
+	#[pallet::getter(fn patient_data_keys)]
+	pub type PatientDataKeys<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		DataKeyring<T>,
+		OptionQuery
+	>;
+
+	// Documents registered with `store_document`, by content hash
+	#[pallet::storage]
+	#[pallet::getter(fn documents)]
+	pub type Documents<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		ContentHash,
+		DocumentInfo<T>,
+		OptionQuery
+	>;
+
+	// Documents uploaded for each patient
+	#[pallet::storage]
+	#[pallet::getter(fn patient_documents)]
+	pub type PatientDocuments<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		Blake2_128Concat,
+		ContentHash,
+		(),
+		OptionQuery
+	>;
+
+	// Every record ever created for a patient, kept after the record is deleted so an erasure can
+	// still redact its audit entries
+	#[pallet::storage]
+	#[pallet::getter(fn patient_record_index)]
+	pub type PatientRecordIndex<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		Blake2_128Concat,
+		(RecordType, u32), // (record_type, record_id)
+		(),
+		OptionQuery
+	>;
+
+	// Deleted patients whose records are still being deleted, with the admin who deleted them
+	#[pallet::storage]
+	#[pallet::getter(fn deletion_queue)]
+	pub type DeletionQueue<T: Config> =
+		StorageValue<_, BoundedVec<(u32, T::AccountId), T::MaxPendingDeletions>, ValueQuery>;
+
+	// Requests to erase a patient's data, until they have been carried out or rejected
+	#[pallet::storage]
+	#[pallet::getter(fn erasure_request)]
+	pub type ErasureRequests<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		ErasureRequest<T>,
+		OptionQuery
+	>;
+
+	// Approved erasures in the order they are carried out
+	#[pallet::storage]
+	#[pallet::getter(fn erasure_queue)]
+	pub type ErasureQueue<T: Config> = StorageValue<_, BoundedVec<u32, T::MaxPendingErasures>, ValueQuery>;
+
+	// Tombstones of patients whose data has been erased
+	#[pallet::storage]
+	#[pallet::getter(fn erasure_tombstone)]
+	pub type ErasureTombstones<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		ErasureTombstone<T>,
+		OptionQuery
+	>;
+
+	// Storage for the role registry - which roles each account holds
+	#[pallet::storage]
+	#[pallet::getter(fn account_roles)]
+	pub type AccountRoles<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		Role,
+		(),
+		OptionQuery
+	>;
+
+	// Storage for the account that belongs to each patient
+	#[pallet::storage]
+	#[pallet::getter(fn patient_account)]
+	pub type PatientAccounts<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		T::AccountId,
+		OptionQuery
+	>;
+
+	// Storage for the guardian allowed to act on behalf of a patient
+	#[pallet::storage]
+	#[pallet::getter(fn patient_guardian)]
+	pub type PatientGuardians<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		T::AccountId,
+		OptionQuery
+	>;
+
+	// Storage for consent grants given by patients
+	#[pallet::storage]
+	#[pallet::getter(fn consents)]
+	pub type Consents<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		u32, // patient_id
+		Blake2_128Concat,
+		(T::AccountId, RecordType), // (grantee, record_type)
+		ConsentGrant<T>,
+		OptionQuery
+	>;
+
+	// Storage for the proof hashes anchored for each version of a record, oldest first
+	#[pallet::storage]
+	#[pallet::getter(fn record_proofs)]
+	pub type RecordProofs<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RecordType,
+		Blake2_128Concat,
+		u32, // record_id
+		BoundedVec<T::Hash, T::MaxProofsPerRecord>,
+		ValueQuery
+	>;
+
+	#[pallet::genesis_config]
+	#[derive(DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// Roles granted to accounts at genesis, e.g. the first hospital admin.
+		pub initial_roles: Vec<(T::AccountId, Role)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for (who, role) in &self.initial_roles {
+				AccountRoles::<T>::insert(who, role, ());
+			}
+		}
+	}
+
+	// Change History Structure for audit trail. Every change is chained to the one before it, so
+	// removing or rewriting an entry breaks the chain up to `AuditHead`.
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		Serialize,
 	)]
 	#[scale_info(skip_type_params(T))]
+	#[serde(bound(serialize = "T::AccountId: Serialize, BlockNumberFor<T>: Serialize, T::Hash: Serialize"))]
 	pub struct ChangeHistory<T: Config> {
 		pub change_id: u32,
 		pub record_type: RecordType,
 		pub record_id: u32,
-		pub field_name: Vec<u8>,
-		pub old_value: Option<Vec<u8>>,
-		pub new_value: Vec<u8>,
+		pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+		pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+		pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
 		pub changed_by: T::AccountId,
 		pub changed_at: BlockNumberFor<T>,
 		pub operation: OperationType,
+		/// Hash of the previous change, the default hash for the first one.
+		pub prev_change_hash: T::Hash,
+		/// Hash of this change, see [`Pallet::compute_change_hash`].
+		pub change_hash: T::Hash,
+		/// Whether the old and new values have been replaced by their hashes on erasure.
+		pub redacted: bool,
+	}
+
+	// Why a segment of the audit trail failed to verify
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, Serialize, Deserialize)]
+	pub enum AuditError {
+		/// No change is stored under this id
+		Missing(u32),
+		/// The stored change does not rehash to its own change hash
+		HashMismatch(u32),
+		/// The change does not link to the hash of the change before it
+		BrokenLink(u32),
+		/// The latest change is not the audit head
+		HeadMismatch,
+		/// The range is empty or ends after the latest change
+		InvalidRange,
+		/// The range is longer than `MaxAuditRange`
+		RangeTooLong,
 	}
 
 	// Enum for different record types
-	#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, MaxEncodedLen, TypeInfo, DecodeWithMemTracking)]
+	#[derive(
+		Encode,
+		Decode,
+		Clone,
+		PartialEq,
+		Eq,
+		Debug,
+		MaxEncodedLen,
+		TypeInfo,
+		DecodeWithMemTracking,
+		Serialize,
+		Deserialize,
+	)]
 	pub enum RecordType {
 		Patient,
 		ClinicalTest,
 		DiseaseProgression,
 		MedicalRecord,
+		Consent,
 	}
 
 	// Enum for different operation types
 	#[derive(
 		Encode,
 		Decode,
+		MaxEncodedLen,
 		TypeInfo,
 		CloneNoBound,
 		PartialEqNoBound,
+		RuntimeDebugNoBound,
+		Serialize,
+		Deserialize,
 	)]
 	pub enum OperationType {
 		Create,
@@ -379,24 +1005,108 @@ pub mod pallet {
 		Delete,
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			// Approved erasures go first, cascading deletions after them
+			let mut steps = 0;
+			while steps < T::MaxRemovalStepsPerBlock::get() {
+				if let Some(&patient_id) = ErasureQueue::<T>::get().first() {
+					Self::erasure_step(patient_id);
+				} else if let Some((patient_id, deleted_by)) = DeletionQueue::<T>::get().first().cloned() {
+					Self::deletion_step(patient_id, deleted_by);
+				} else {
+					break;
+				}
+				steps += 1;
+			}
+			T::WeightInfo::on_initialize(steps)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), TryRuntimeError> {
+			Self::do_try_state()
+		}
+
+		fn integrity_test() {
+			// Every audited field value is copied into the change history, so it has to fit there
+			let max_field_length = [
+				T::MaxNameLength::get(),
+				T::MaxDateLength::get(),
+				T::MaxGenderLength::get(),
+				T::MaxAddressLength::get(),
+				T::MaxPhoneLength::get(),
+				T::MaxEmergencyContactLength::get(),
+				T::MaxTestTypeLength::get(),
+				T::MaxTestResultLength::get(),
+				T::MaxNotesLength::get(),
+				T::MaxClinicalTextLength::get(),
+				DataPointerOf::<T>::max_encoded_len() as u32,
+			]
+			.into_iter()
+			.max()
+			.unwrap_or_default();
+			let consent_length =
+				<(T::AccountId, RecordType, ConsentScope, Option<BlockNumberFor<T>>)>::max_encoded_len() as u32;
+
+			assert!(
+				T::MaxChangeValueLength::get() >= max_field_length.max(consent_length),
+				"MaxChangeValueLength must fit every audited field",
+			);
+			assert!(
+				T::MaxFieldNameLength::get() as usize >= b"emergency_contact".len(),
+				"MaxFieldNameLength must fit every audited field name",
+			);
+			assert!(
+				T::MaxChangeValueLength::get() as usize >= T::Hash::max_encoded_len(),
+				"MaxChangeValueLength must fit the hash of a redacted value",
+			);
+			assert!(T::MaxRemovalStepsPerBlock::get() > 0, "MaxRemovalStepsPerBlock must not be zero");
+
+			if T::EncryptedPii::get() {
+				let min_pii_length = [
+					T::MaxNameLength::get(),
+					T::MaxDateLength::get(),
+					T::MaxAddressLength::get(),
+					T::MaxPhoneLength::get(),
+					T::MaxEmergencyContactLength::get(),
+				]
+				.into_iter()
+				.min()
+				.unwrap_or_default();
+				assert!(
+					min_pii_length > envelope::ENVELOPE_OVERHEAD,
+					"every PII field must fit an encrypted envelope",
+				);
+			}
+		}
+	}
+
 	/// Events that functions in this pallet can emit.
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// A new patient has been created.
+		/// A new patient has been created. Events stay on-chain for good, so the name is left out.
 		PatientCreated {
 			patient_id: u32,
-			patient_name: Vec<u8>,
 		},
 		/// Patient information has been updated.
 		PatientUpdated {
 			patient_id: u32,
 			updated_by: T::AccountId,
 		},
-		/// Patient has been deleted.
+		/// Patients have been indexed under new blinded names.
+		PatientNamesReindexed {
+			count: u32,
+		},
+		/// Patient has been deleted; their records follow over the next blocks.
 		PatientDeleted {
 			patient_id: u32,
 		},
+		/// Every record, document, consent grant and care link of a deleted patient is gone.
+		PatientDeletionCompleted {
+			patient_id: u32,
+		},
 		/// A new clinical test has been created.
 		ClinicalTestCreated {
 			test_id: u32,
@@ -433,15 +1143,19 @@ pub mod pallet {
 			doctor_id: T::AccountId,
 			patient_id: u32, // Changed from T::AccountId to u32
 		},
+		/// A medical record has been deleted along with its patient.
+		MedicalRecordDeleted {
+			record_id: u32,
+		},
 		/// Patient found by name search.
 		PatientFoundByName {
 			patient_id: u32,
-			patient_name: Vec<u8>,
+			name_index: NameIndex,
 		},
 		/// Multiple patients found by name search.
 		MultiplePatientsFoundByName {
 			patient_ids: Vec<u32>,
-			patient_name: Vec<u8>,
+			name_index: NameIndex,
 		},
 		/// Patients found by demographic search.
 		PatientsFoundByDemographics {
@@ -453,19 +1167,111 @@ pub mod pallet {
 			patient_id: u32,
 			records_count: u32,
 		},
+		/// A version of a record has been anchored into the proof chain.
+		RecordAnchored {
+			record_type: RecordType,
+			record_id: u32,
+			proof_hash: T::Hash,
+		},
 		/// A change has been recorded in the audit trail.
 		ChangeRecorded {
 			change_id: u32,
 			record_type: RecordType,
 			record_id: u32,
 			changed_by: T::AccountId,
+			change_hash: T::Hash,
 		},
-	}
-
-	/// Errors that can be returned by this pallet.
-	#[pallet::error]
-	pub enum Error<T> {
-		/// The value retrieved was `None` as no value was previously set.
+		/// A role has been granted to an account.
+		RoleGranted {
+			who: T::AccountId,
+			role: Role,
+		},
+		/// A role has been revoked from an account.
+		RoleRevoked {
+			who: T::AccountId,
+			role: Role,
+		},
+		/// An account has been linked to a patient.
+		PatientAccountLinked {
+			patient_id: u32,
+			account: T::AccountId,
+		},
+		/// The guardian of a patient has been set or cleared.
+		GuardianSet {
+			patient_id: u32,
+			guardian: Option<T::AccountId>,
+		},
+		/// A patient has granted consent to an account.
+		ConsentGranted {
+			patient_id: u32,
+			grantee: T::AccountId,
+			record_type: RecordType,
+			scope: ConsentScope,
+			expires_at: Option<BlockNumberFor<T>>,
+		},
+		/// A patient has revoked consent from an account.
+		ConsentRevoked {
+			patient_id: u32,
+			grantee: T::AccountId,
+			record_type: RecordType,
+		},
+		/// An account has registered the key data keys are wrapped for it with.
+		EncryptionKeyRegistered {
+			who: T::AccountId,
+		},
+		/// Wrapped copies of a patient's data key have been stored.
+		DataKeysSet {
+			patient_id: u32,
+			key_version: u32,
+		},
+		/// An account's copy of a patient's data key has been removed.
+		DataKeyRemoved {
+			patient_id: u32,
+			account: T::AccountId,
+		},
+		/// A document has been registered, for its uploader to hand to nodes over RPC.
+		DocumentStored {
+			content_hash: ContentHash,
+			patient_id: u32,
+			size: u32,
+		},
+		/// A document has been attached to a record.
+		AttachmentAdded {
+			record_type: RecordType,
+			record_id: u32,
+			content_hash: ContentHash,
+		},
+		/// A document has been detached from a record.
+		AttachmentRemoved {
+			record_type: RecordType,
+			record_id: u32,
+			content_hash: ContentHash,
+		},
+		/// A patient has asked for their data to be erased.
+		ErasureRequested {
+			patient_id: u32,
+			requested_by: T::AccountId,
+		},
+		/// An erasure request has been approved and queued.
+		ErasureApproved {
+			patient_id: u32,
+		},
+		/// An erasure request has been rejected.
+		ErasureRejected {
+			patient_id: u32,
+		},
+		/// All data of a patient has been erased, leaving a tombstone with the given digest.
+		PatientErased {
+			patient_id: u32,
+			erased_items: u32,
+			digest: T::Hash,
+		},
+	}
+
+	/// Errors that can be returned by this pallet.
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The value retrieved was `None` as no value was previously set.
 		NoneValue,
 		/// There was an attempt to increment the value in storage over `u32::MAX`.
 		StorageOverflow,
@@ -489,12 +1295,95 @@ pub mod pallet {
 		PatientNameAlreadyExists,
 		/// Patient not found by name.
 		PatientNotFoundByName,
+		/// The caller does not hold a role allowed to perform this operation.
+		NotAuthorized,
+		/// The account already holds this role.
+		RoleAlreadyGranted,
+		/// The account does not hold this role.
+		RoleNotGranted,
+		/// No active consent grant covers this operation.
+		NoConsent,
+		/// Only the patient or their guardian can manage consent.
+		NotPatientOrGuardian,
+		/// The consent scope does not allow any access.
+		InvalidConsentScope,
+		/// The consent expiry is not in the future.
+		InvalidConsentExpiry,
+		/// The consent grant does not exist.
+		ConsentNotFound,
+		/// Consent can only be granted to a doctor, nurse or lab technician.
+		InvalidGrantee,
+		/// A patient field exceeds its maximum length.
+		PatientDataTooLong,
+		/// A clinical test field exceeds its maximum length.
+		ClinicalTestDataTooLong,
+		/// A disease progression field exceeds its maximum length.
+		DiseaseProgressionDataTooLong,
+		/// A medical record field exceeds its maximum length.
+		MedicalRecordDataTooLong,
+		/// A value recorded in the change history exceeds its maximum length.
+		ChangeValueTooLong,
+		/// Too many patients are registered under the same name.
+		TooManyPatientsWithName,
+		/// A new patient name has to come with its blinded name index.
+		NameIndexRequired,
+		/// The patient has reached the maximum number of records of this type.
+		TooManyRecordsForPatient,
+		/// The doctor is linked to the maximum number of patients.
+		TooManyPatientsForDoctor,
+		/// The patient is linked to the maximum number of doctors.
+		TooManyDoctorsForPatient,
+		/// The record has reached the maximum number of anchored versions.
+		TooManyProofsForRecord,
+		/// The patient has no linked account to book an appointment for.
+		PatientAccountNotLinked,
+		/// A PII field is not a valid encrypted envelope.
+		InvalidEnvelope,
+		/// A PII field is not sealed with the patient's current data key.
+		DataKeyVersionMismatch,
+		/// The key version is neither the current one nor the next.
+		InvalidDataKeyVersion,
+		/// The account has not registered an encryption key.
+		EncryptionKeyNotRegistered,
+		/// The patient's data key is wrapped for the maximum number of accounts.
+		TooManyDataKeyHolders,
+		/// The patient's data key is not wrapped for the account.
+		DataKeyNotFound,
+		/// A data pointer is malformed or does not match the document it points to.
+		InvalidDataPointer,
+		/// No document is stored under this content hash.
+		DocumentNotFound,
+		/// A document with the same content has already been stored.
+		DocumentAlreadyStored,
+		/// The document is empty.
+		EmptyDocument,
+		/// The document exceeds `MaxDocumentSize`.
+		DocumentTooLarge,
+		/// Only clinical tests and disease progressions take attachments.
+		AttachmentsNotSupported,
+		/// The record has reached the maximum number of attachments.
+		TooManyAttachments,
+		/// The document is already attached to the record.
+		AttachmentAlreadyExists,
+		/// The document is not attached to the record.
+		AttachmentNotFound,
+		/// The patient has already asked for their data to be erased.
+		ErasureAlreadyRequested,
+		/// The patient has not asked for their data to be erased.
+		ErasureNotRequested,
+		/// The erasure has already been approved.
+		ErasureAlreadyApproved,
+		/// Too many approved erasures are waiting to be carried out.
+		TooManyPendingErasures,
+		/// Too many deleted patients are waiting for their records to be deleted.
+		TooManyPendingDeletions,
 	}
 
 	/// The pallet's dispatchable functions ([`Call`]s).
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		// Patient CRUD operations
+		#[pallet::call_index(0)]
 		#[pallet::weight(T::WeightInfo::create_patient())]
 		pub fn create_patient(
 			origin: OriginFor<T>,
@@ -504,78 +1393,72 @@ pub mod pallet {
 			address: Vec<u8>,
 			phone: Vec<u8>,
 			emergency_contact: Vec<u8>,
+			name_index: NameIndex,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor, Role::Nurse])?;
 
 			ensure!(!patient_name.is_empty(), Error::<T>::InvalidPatientData);
 
+			let bounded_name: BoundedVec<u8, T::MaxNameLength> =
+				patient_name.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+			let bounded_gender: BoundedVec<u8, T::MaxGenderLength> =
+				gender.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+
 			let patient_id = Self::next_patient_id();
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
 
+			for field in [&patient_name, &date_of_birth, &address, &phone, &emergency_contact] {
+				Self::ensure_pii(patient_id, field)?;
+			}
+
 			let patient = PatientInfo::<T> {
 				patient_id,
-				patient_name: patient_name.clone(),
-				date_of_birth: date_of_birth.clone(),
-				gender: gender.clone(),
-				address: address.clone(),
-				phone: phone.clone(),
-				emergency_contact: emergency_contact.clone(),
+				patient_name: bounded_name.clone(),
+				date_of_birth: date_of_birth.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?,
+				gender: bounded_gender.clone(),
+				address: address.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?,
+				phone: phone.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?,
+				emergency_contact: emergency_contact.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?,
 				created_at: block_number,
 				created_by: who.clone(),
 				last_modified_at: block_number,
 				last_modified_by: who.clone(),
 			};
 
+			// Add to the active and demographic indexes
+			Self::list_patient(&patient);
+
 			// Insert patient record
 			Patients::<T>::insert(patient_id, patient);
 			
-			// Add patient_id to the name mapping array
-			PatientNameToId::<T>::mutate(&patient_name, |ids_opt| {
-				match ids_opt {
-					Some(ids) => ids.push(patient_id),
-					None => *ids_opt = Some(vec![patient_id]),
-				}
-			});
-
-			// Add to active patients list
-			ActivePatients::<T>::mutate(|patients| patients.push(patient_id));
-
-			// Add to gender mapping
-			PatientsByGender::<T>::mutate(&gender, |patients| patients.push(patient_id));
-
-			// Extract birth year and add to age mapping
-			if let Ok(birth_year_str) = core::str::from_utf8(&date_of_birth) {
-				if let Ok(birth_year) = birth_year_str[0..4].parse::<u32>() {
-					PatientsByBirthYear::<T>::mutate(birth_year, |patients| patients.push(patient_id));
-				}
-			}
+			// Add patient_id to the blinded name index
+			Self::index_name(patient_id, name_index)?;
 			
 			NextPatientId::<T>::put(patient_id + 1);
 
 			// Record creation in audit trail - using internal helper
-			let _ = Self::do_record_change(
+			Self::do_record_change(
 				RecordType::Patient,
 				patient_id,
 				b"patient_name".to_vec(),
 				None,
-				patient_name.clone(),
+				patient_name,
 				who.clone(),
 				OperationType::Create,
-			);
-			let _ = Self::do_record_change(RecordType::Patient, patient_id, b"date_of_birth".to_vec(), None, date_of_birth, who.clone(), OperationType::Create);
-			let _ = Self::do_record_change(RecordType::Patient, patient_id, b"gender".to_vec(), None, gender, who.clone(), OperationType::Create);
-			let _ = Self::do_record_change(RecordType::Patient, patient_id, b"address".to_vec(), None, address, who.clone(), OperationType::Create);
-			let _ = Self::do_record_change(RecordType::Patient, patient_id, b"phone".to_vec(), None, phone, who.clone(), OperationType::Create);
-			let _ = Self::do_record_change(RecordType::Patient, patient_id, b"emergency_contact".to_vec(), None, emergency_contact, who, OperationType::Create);
+			)?;
+			Self::do_record_change(RecordType::Patient, patient_id, b"date_of_birth".to_vec(), None, date_of_birth, who.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::Patient, patient_id, b"gender".to_vec(), None, gender, who.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::Patient, patient_id, b"address".to_vec(), None, address, who.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::Patient, patient_id, b"phone".to_vec(), None, phone, who.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::Patient, patient_id, b"emergency_contact".to_vec(), None, emergency_contact, who, OperationType::Create)?;
 
-			Self::deposit_event(Event::PatientCreated {
-				patient_id,
-				patient_name,
-			});
+			Self::deposit_event(Event::PatientCreated { patient_id });
 
 			Ok(())
 		}
 
+		#[pallet::call_index(1)]
 		#[pallet::weight(T::WeightInfo::update_patient())]
 		pub fn update_patient(
 			origin: OriginFor<T>,
@@ -586,70 +1469,79 @@ pub mod pallet {
 			address: Option<Vec<u8>>,
 			phone: Option<Vec<u8>>,
 			emergency_contact: Option<Vec<u8>>,
+			name_index: Option<NameIndex>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor, Role::Nurse])?;
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
 
 			Patients::<T>::try_mutate(patient_id, |patient_opt| -> DispatchResult {
 				let patient = patient_opt.as_mut().ok_or(Error::<T>::PatientNotFound)?;
+				let listed = patient.clone();
+
+				// A new name has to be indexed again; the index alone may be replaced to re-index a
+				// patient, e.g. after the facility secret is rotated
+				ensure!(patient_name.is_none() || name_index.is_some(), Error::<T>::NameIndexRequired);
+				if let Some(new_index) = name_index {
+					Self::unindex_name(patient_id);
+					Self::index_name(patient_id, new_index)?;
+				}
+
+				for field in [&patient_name, &date_of_birth, &address, &phone, &emergency_contact].into_iter().flatten() {
+					Self::ensure_pii(patient_id, field)?;
+				}
 
-				// If updating name, handle the name mapping
 				if let Some(new_name) = patient_name {
 					ensure!(!new_name.is_empty(), Error::<T>::InvalidPatientData);
 					
 					let old_name = patient.patient_name.clone();
+					let bounded_name: BoundedVec<u8, T::MaxNameLength> =
+						new_name.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
 					
 					// Record the change
 					Self::do_record_change(
 						RecordType::Patient,
 						patient_id,
 						b"patient_name".to_vec(),
-						Some(old_name.clone()),
-						new_name.clone(),
+						Some(old_name.to_vec()),
+						new_name,
 						who.clone(),
 						OperationType::Update,
 					)?;
-
-					// Remove patient_id from old name mapping
-					PatientNameToId::<T>::mutate(&old_name, |ids_opt| {
-						if let Some(ids) = ids_opt {
-							ids.retain(|&id| id != patient_id);
-							if ids.is_empty() {
-								*ids_opt = None;
-							}
-						}
-					});
-					
-					// Add patient_id to new name mapping
-					PatientNameToId::<T>::mutate(&new_name, |ids_opt| {
-						match ids_opt {
-							Some(ids) => ids.push(patient_id),
-							None => *ids_opt = Some(vec![patient_id]),
-						}
-					});
 					
 					// Update patient name
-					patient.patient_name = new_name;
+					patient.patient_name = bounded_name;
 				}
 				if let Some(dob) = date_of_birth {
-					Self::do_record_change(RecordType::Patient, patient_id, b"date_of_birth".to_vec(), Some(patient.date_of_birth.clone()), dob.clone(), who.clone(), OperationType::Update)?;
-					patient.date_of_birth = dob;
+					let bounded = dob.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+					Self::do_record_change(RecordType::Patient, patient_id, b"date_of_birth".to_vec(), Some(patient.date_of_birth.to_vec()), dob, who.clone(), OperationType::Update)?;
+					patient.date_of_birth = bounded;
 				}
 				if let Some(g) = gender {
-					Self::do_record_change(RecordType::Patient, patient_id, b"gender".to_vec(), Some(patient.gender.clone()), g.clone(), who.clone(), OperationType::Update)?;
-					patient.gender = g;
+					let bounded = g.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+					Self::do_record_change(RecordType::Patient, patient_id, b"gender".to_vec(), Some(patient.gender.to_vec()), g, who.clone(), OperationType::Update)?;
+					patient.gender = bounded;
 				}
 				if let Some(addr) = address {
-					Self::do_record_change(RecordType::Patient, patient_id, b"address".to_vec(), Some(patient.address.clone()), addr.clone(), who.clone(), OperationType::Update)?;
-					patient.address = addr;
+					let bounded = addr.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+					Self::do_record_change(RecordType::Patient, patient_id, b"address".to_vec(), Some(patient.address.to_vec()), addr, who.clone(), OperationType::Update)?;
+					patient.address = bounded;
 				}
 				if let Some(p) = phone {
-					Self::do_record_change(RecordType::Patient, patient_id, b"phone".to_vec(), Some(patient.phone.clone()), p.clone(), who.clone(), OperationType::Update)?;
-					patient.phone = p;
+					let bounded = p.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+					Self::do_record_change(RecordType::Patient, patient_id, b"phone".to_vec(), Some(patient.phone.to_vec()), p, who.clone(), OperationType::Update)?;
+					patient.phone = bounded;
 				}
 				if let Some(ec) = emergency_contact {
-					Self::do_record_change(RecordType::Patient, patient_id, b"emergency_contact".to_vec(), Some(patient.emergency_contact.clone()), ec.clone(), who.clone(), OperationType::Update)?;
-					patient.emergency_contact = ec;
+					let bounded = ec.clone().try_into().map_err(|_| Error::<T>::PatientDataTooLong)?;
+					Self::do_record_change(RecordType::Patient, patient_id, b"emergency_contact".to_vec(), Some(patient.emergency_contact.to_vec()), ec, who.clone(), OperationType::Update)?;
+					patient.emergency_contact = bounded;
+				}
+
+				// Move the patient to the demographic indexes of their new gender and birth year
+				if patient.gender != listed.gender || patient.date_of_birth != listed.date_of_birth {
+					Self::unlist_patient(&listed);
+					Self::list_patient(patient);
 				}
 
 				// Update modification tracking
@@ -664,12 +1556,18 @@ pub mod pallet {
 			})
 		}
 
+		/// Delete a patient. The patient is unlisted right away; their clinical tests, disease
+		/// progressions, medical records, documents, consent grants and care links are deleted
+		/// over the following blocks. The audit trail and the patient's account and guardian links
+		/// are kept, so the patient can still ask for their data to be erased.
+		#[pallet::call_index(2)]
 		#[pallet::weight(T::WeightInfo::delete_patient())]
 		pub fn delete_patient(
 			origin: OriginFor<T>,
 			patient_id: u32,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin])?;
 
 			let patient = Patients::<T>::get(patient_id).ok_or(Error::<T>::PatientNotFound)?;
 
@@ -680,58 +1578,50 @@ pub mod pallet {
 				b"deleted".to_vec(),
 				Some(b"active".to_vec()),
 				b"deleted".to_vec(),
-				who,
+				who.clone(),
 				OperationType::Delete,
 			)?;
 
-			// Remove from all mappings
-			PatientNameToId::<T>::mutate(&patient.patient_name, |ids_opt| {
-				if let Some(ids) = ids_opt {
-					ids.retain(|&id| id != patient_id);
-					if ids.is_empty() {
-						*ids_opt = None;
-					}
-				}
-			});
+			// Everything recorded for the patient is deleted in `on_initialize`
+			DeletionQueue::<T>::try_append((patient_id, who))
+				.map_err(|_| Error::<T>::TooManyPendingDeletions)?;
 
-			ActivePatients::<T>::mutate(|patients| patients.retain(|&id| id != patient_id));
-			PatientsByGender::<T>::mutate(&patient.gender, |patients| patients.retain(|&id| id != patient_id));
+			// Remove from all mappings
+			Self::unindex_name(patient_id);
+			Self::unlist_patient(&patient);
 
-			// Remove from birth year mapping
-			if let Ok(birth_year_str) = core::str::from_utf8(&patient.date_of_birth) {
-				if let Ok(birth_year) = birth_year_str[0..4].parse::<u32>() {
-					PatientsByBirthYear::<T>::mutate(birth_year, |patients| patients.retain(|&id| id != patient_id));
-				}
-			}
-			
-			// Remove patient record
+			// Remove patient record and the wrapped copies of its data key
 			Patients::<T>::remove(patient_id);
+			PatientDataKeys::<T>::remove(patient_id);
 
 			Self::deposit_event(Event::PatientDeleted { patient_id });
 
 			Ok(())
 		}
 
-		// New function to search patient by name
-		#[pallet::weight(10_000)]
+		// Deprecated: use the `find_patients_by_name` view function instead. The runtime call
+		// filter rejects this call; it is kept so existing call indices stay stable.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::search_patient_by_name())]
 		pub fn search_patient_by_name(
 			origin: OriginFor<T>,
-			patient_name: Vec<u8>,
+			name_index: NameIndex,
 		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor, Role::Nurse])?;
 
-			let patient_ids = PatientNameToId::<T>::get(&patient_name)
+			let patient_ids = PatientsByName::<T>::get(name_index)
 				.ok_or(Error::<T>::PatientNotFoundByName)?;
 
 			if patient_ids.len() == 1 {
 				Self::deposit_event(Event::PatientFoundByName {
 					patient_id: patient_ids[0],
-					patient_name,
+					name_index,
 				});
 			} else {
 				Self::deposit_event(Event::MultiplePatientsFoundByName {
-					patient_ids,
-					patient_name,
+					patient_ids: patient_ids.into_inner(),
+					name_index,
 				});
 			}
 
@@ -739,7 +1629,8 @@ pub mod pallet {
 		}
 
 		// Clinical Test CRUD operations
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::create_clinical_test())]
 		pub fn create_clinical_test(
 			origin: OriginFor<T>,
 			patient_id: u32,
@@ -749,8 +1640,10 @@ pub mod pallet {
 			notes: Vec<u8>,
 		) -> DispatchResult {
 			let doctor_id = ensure_signed(origin)?;
+			Self::ensure_role(&doctor_id, &[Role::Doctor, Role::LabTechnician])?;
 
 			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			Self::ensure_consent(patient_id, &doctor_id, RecordType::ClinicalTest, AccessKind::Append)?;
 			ensure!(!test_type.is_empty(), Error::<T>::InvalidClinicalTestData);
 
 			let test_id = Self::next_test_id();
@@ -760,35 +1653,32 @@ pub mod pallet {
 				test_id,
 				patient_id,
 				doctor_id: doctor_id.clone(),
-				test_type,
-				test_date,
-				result,
-				notes,
+				test_type: test_type.clone().try_into().map_err(|_| Error::<T>::ClinicalTestDataTooLong)?,
+				test_date: test_date.clone().try_into().map_err(|_| Error::<T>::ClinicalTestDataTooLong)?,
+				result: result.clone().try_into().map_err(|_| Error::<T>::ClinicalTestDataTooLong)?,
+				notes: notes.clone().try_into().map_err(|_| Error::<T>::ClinicalTestDataTooLong)?,
+				attachments: Default::default(),
 				created_at: block_number,
 				created_by: doctor_id.clone(),
 				last_modified_at: block_number,
 				last_modified_by: doctor_id.clone(),
 			};
 
+			// Record creation in audit trail
+			Self::do_record_change(RecordType::ClinicalTest, test_id, b"test_type".to_vec(), None, test_type, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::ClinicalTest, test_id, b"test_date".to_vec(), None, test_date, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::ClinicalTest, test_id, b"result".to_vec(), None, result, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::ClinicalTest, test_id, b"notes".to_vec(), None, notes, doctor_id.clone(), OperationType::Create)?;
+
+			// Add to patient's test list and record index
+			PatientClinicalTests::<T>::try_mutate(patient_id, |tests| tests.try_push(test_id))
+				.map_err(|_| Error::<T>::TooManyRecordsForPatient)?;
+			PatientRecordIndex::<T>::insert(patient_id, (RecordType::ClinicalTest, test_id), ());
+
+			Self::anchor_record(&doctor_id, RecordType::ClinicalTest, test_id, T::Hashing::hash_of(&clinical_test))?;
+
 			ClinicalTests::<T>::insert(test_id, clinical_test);
 			
-			// Add to patient's test list
-			PatientClinicalTests::<T>::mutate(patient_id, |tests| tests.push(test_id));
-			
-			// Add to doctor's patient list if not already there
-			DoctorPatients::<T>::mutate(&doctor_id, |patients| {
-				if !patients.contains(&patient_id) {
-					patients.push(patient_id);
-				}
-			});
-			
-			// Add doctor to patient's doctor list if not already there
-			PatientDoctors::<T>::mutate(patient_id, |doctors| {
-				if !doctors.contains(&doctor_id) {
-					doctors.push(doctor_id.clone());
-				}
-			});
-			
 			NextTestId::<T>::put(test_id + 1);
 
 			Self::deposit_event(Event::ClinicalTestCreated {
@@ -800,7 +1690,8 @@ pub mod pallet {
 			Ok(())
 		}
 
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(5)]
+		#[pallet::weight(T::WeightInfo::update_clinical_test())]
 		pub fn update_clinical_test(
 			origin: OriginFor<T>,
 			test_id: u32,
@@ -810,28 +1701,32 @@ pub mod pallet {
 			notes: Option<Vec<u8>>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Doctor, Role::LabTechnician])?;
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
 
 			ClinicalTests::<T>::try_mutate(test_id, |test_opt| -> DispatchResult {
 				let test = test_opt.as_mut().ok_or(Error::<T>::ClinicalTestNotFound)?;
+				Self::ensure_consent(test.patient_id, &who, RecordType::ClinicalTest, AccessKind::Amend)?;
 
 				if let Some(tt) = test_type {
-					test.test_type = tt;
+					Self::do_update_field(RecordType::ClinicalTest, test_id, b"test_type", &mut test.test_type, tt, &who, Error::<T>::ClinicalTestDataTooLong)?;
 				}
 				if let Some(td) = test_date {
-					test.test_date = td;
+					Self::do_update_field(RecordType::ClinicalTest, test_id, b"test_date", &mut test.test_date, td, &who, Error::<T>::ClinicalTestDataTooLong)?;
 				}
 				if let Some(r) = result {
-					test.result = r;
+					Self::do_update_field(RecordType::ClinicalTest, test_id, b"result", &mut test.result, r, &who, Error::<T>::ClinicalTestDataTooLong)?;
 				}
 				if let Some(n) = notes {
-					test.notes = n;
+					Self::do_update_field(RecordType::ClinicalTest, test_id, b"notes", &mut test.notes, n, &who, Error::<T>::ClinicalTestDataTooLong)?;
 				}
 
 				// Update modification tracking
 				test.last_modified_at = block_number;
 				test.last_modified_by = who.clone();
 
+				Self::anchor_record(&who, RecordType::ClinicalTest, test_id, T::Hashing::hash_of(test))?;
+
 				Self::deposit_event(Event::ClinicalTestUpdated { 
 					test_id,
 					updated_by: who,
@@ -840,14 +1735,30 @@ pub mod pallet {
 			})
 		}
 
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::delete_clinical_test())]
 		pub fn delete_clinical_test(
 			origin: OriginFor<T>,
 			test_id: u32,
 		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor])?;
 
 			let test = ClinicalTests::<T>::get(test_id).ok_or(Error::<T>::ClinicalTestNotFound)?;
+			if !Self::has_role(&who, Role::Admin) {
+				Self::ensure_consent(test.patient_id, &who, RecordType::ClinicalTest, AccessKind::Amend)?;
+			}
+
+			// Record deletion in audit trail
+			Self::do_record_change(
+				RecordType::ClinicalTest,
+				test_id,
+				b"deleted".to_vec(),
+				Some(b"active".to_vec()),
+				b"deleted".to_vec(),
+				who,
+				OperationType::Delete,
+			)?;
 			
 			// Remove from patient's test list
 			PatientClinicalTests::<T>::mutate(test.patient_id, |tests| {
@@ -862,7 +1773,8 @@ pub mod pallet {
 		}
 
 		// Disease Progression CRUD operations
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::create_disease_progression())]
 		pub fn create_disease_progression(
 			origin: OriginFor<T>,
 			patient_id: u32,
@@ -871,53 +1783,71 @@ pub mod pallet {
 			diagnosis: Vec<u8>,
 			treatment: Vec<u8>,
 			prescription: Vec<u8>,
-			next_appointment: Vec<u8>,
+			next_appointment: Option<u64>,
 		) -> DispatchResult {
 			let doctor_id = ensure_signed(origin)?;
+			Self::ensure_role(&doctor_id, &[Role::Doctor])?;
 
 			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			Self::ensure_consent(patient_id, &doctor_id, RecordType::DiseaseProgression, AccessKind::Append)?;
 			ensure!(!visit_date.is_empty(), Error::<T>::InvalidDiseaseProgressionData);
 
 			let progression_id = Self::next_progression_id();
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
 
-			let progression = DiseaseProgression::<T> {
+			let mut progression = DiseaseProgression::<T> {
 				progression_id,
 				patient_id,
 				doctor_id: doctor_id.clone(),
-				visit_date,
-				symptoms,
-				diagnosis,
-				treatment,
-				prescription,
-				next_appointment,
+				visit_date: visit_date.clone().try_into().map_err(|_| Error::<T>::DiseaseProgressionDataTooLong)?,
+				symptoms: symptoms.clone().try_into().map_err(|_| Error::<T>::DiseaseProgressionDataTooLong)?,
+				diagnosis: diagnosis.clone().try_into().map_err(|_| Error::<T>::DiseaseProgressionDataTooLong)?,
+				treatment: treatment.clone().try_into().map_err(|_| Error::<T>::DiseaseProgressionDataTooLong)?,
+				prescription: prescription.clone().try_into().map_err(|_| Error::<T>::DiseaseProgressionDataTooLong)?,
+				next_appointment: None,
+				attachments: Default::default(),
 				created_at: block_number,
 				created_by: doctor_id.clone(),
 				last_modified_at: block_number,
 				last_modified_by: doctor_id.clone(),
 			};
+			if let Some(scheduled_time) = next_appointment {
+				Self::book_follow_up(&mut progression, &doctor_id, scheduled_time)?;
+			}
+
+			// Record creation in audit trail
+			let id = progression_id;
+			Self::do_record_change(RecordType::DiseaseProgression, id, b"visit_date".to_vec(), None, visit_date, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::DiseaseProgression, id, b"symptoms".to_vec(), None, symptoms, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::DiseaseProgression, id, b"diagnosis".to_vec(), None, diagnosis, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::DiseaseProgression, id, b"treatment".to_vec(), None, treatment, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::DiseaseProgression, id, b"prescription".to_vec(), None, prescription, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(
+				RecordType::DiseaseProgression,
+				id,
+				b"next_appointment".to_vec(),
+				None,
+				progression.next_appointment.encode(),
+				doctor_id.clone(),
+				OperationType::Create,
+			)?;
+
+			// Add to patient's progression list and record index
+			PatientDiseaseProgressions::<T>::try_mutate(patient_id, |progressions| {
+				progressions.try_push(progression_id)
+			})
+			.map_err(|_| Error::<T>::TooManyRecordsForPatient)?;
+			PatientRecordIndex::<T>::insert(patient_id, (RecordType::DiseaseProgression, progression_id), ());
+
+			Self::anchor_record(
+				&doctor_id,
+				RecordType::DiseaseProgression,
+				progression_id,
+				T::Hashing::hash_of(&progression),
+			)?;
 
 			DiseaseProgressions::<T>::insert(progression_id, progression);
 			
-			// Add to patient's progression list
-			PatientDiseaseProgressions::<T>::mutate(patient_id, |progressions| {
-				progressions.push(progression_id);
-			});
-			
-			// Add to doctor's patient list if not already there
-			DoctorPatients::<T>::mutate(&doctor_id, |patients| {
-				if !patients.contains(&patient_id) {
-					patients.push(patient_id);
-				}
-			});
-			
-			// Add doctor to patient's doctor list if not already there
-			PatientDoctors::<T>::mutate(patient_id, |doctors| {
-				if !doctors.contains(&doctor_id) {
-					doctors.push(doctor_id.clone());
-				}
-			});
-			
 			NextProgressionId::<T>::put(progression_id + 1);
 
 			Self::deposit_event(Event::DiseaseProgressionCreated {
@@ -929,7 +1859,8 @@ pub mod pallet {
 			Ok(())
 		}
 
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(8)]
+		#[pallet::weight(T::WeightInfo::update_disease_progression())]
 		pub fn update_disease_progression(
 			origin: OriginFor<T>,
 			progression_id: u32,
@@ -938,37 +1869,49 @@ pub mod pallet {
 			diagnosis: Option<Vec<u8>>,
 			treatment: Option<Vec<u8>>,
 			prescription: Option<Vec<u8>>,
-			next_appointment: Option<Vec<u8>>,
+			next_appointment: Option<u64>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Doctor])?;
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
 
 			DiseaseProgressions::<T>::try_mutate(progression_id, |progression_opt| -> DispatchResult {
 				let progression = progression_opt.as_mut().ok_or(Error::<T>::DiseaseProgressionNotFound)?;
+				Self::ensure_consent(progression.patient_id, &who, RecordType::DiseaseProgression, AccessKind::Amend)?;
 
+				let id = progression_id;
 				if let Some(vd) = visit_date {
-					progression.visit_date = vd;
+					Self::do_update_field(RecordType::DiseaseProgression, id, b"visit_date", &mut progression.visit_date, vd, &who, Error::<T>::DiseaseProgressionDataTooLong)?;
 				}
 				if let Some(s) = symptoms {
-					progression.symptoms = s;
+					Self::do_update_field(RecordType::DiseaseProgression, id, b"symptoms", &mut progression.symptoms, s, &who, Error::<T>::DiseaseProgressionDataTooLong)?;
 				}
 				if let Some(d) = diagnosis {
-					progression.diagnosis = d;
+					Self::do_update_field(RecordType::DiseaseProgression, id, b"diagnosis", &mut progression.diagnosis, d, &who, Error::<T>::DiseaseProgressionDataTooLong)?;
 				}
 				if let Some(t) = treatment {
-					progression.treatment = t;
+					Self::do_update_field(RecordType::DiseaseProgression, id, b"treatment", &mut progression.treatment, t, &who, Error::<T>::DiseaseProgressionDataTooLong)?;
 				}
 				if let Some(p) = prescription {
-					progression.prescription = p;
+					Self::do_update_field(RecordType::DiseaseProgression, id, b"prescription", &mut progression.prescription, p, &who, Error::<T>::DiseaseProgressionDataTooLong)?;
 				}
-				if let Some(na) = next_appointment {
-					progression.next_appointment = na;
+				if let Some(scheduled_time) = next_appointment {
+					let old_appointment = progression.next_appointment;
+					Self::book_follow_up(progression, &who, scheduled_time)?;
+					Self::record_appointment_change(progression, old_appointment, &who)?;
 				}
 
 				// Update modification tracking
 				progression.last_modified_at = block_number;
 				progression.last_modified_by = who.clone();
 
+				Self::anchor_record(
+					&who,
+					RecordType::DiseaseProgression,
+					progression_id,
+					T::Hashing::hash_of(progression),
+				)?;
+
 				Self::deposit_event(Event::DiseaseProgressionUpdated { 
 					progression_id,
 					updated_by: who,
@@ -977,15 +1920,31 @@ pub mod pallet {
 			})
 		}
 
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::WeightInfo::delete_disease_progression())]
 		pub fn delete_disease_progression(
 			origin: OriginFor<T>,
 			progression_id: u32,
 		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor])?;
 
 			let progression = DiseaseProgressions::<T>::get(progression_id)
 				.ok_or(Error::<T>::DiseaseProgressionNotFound)?;
+			if !Self::has_role(&who, Role::Admin) {
+				Self::ensure_consent(progression.patient_id, &who, RecordType::DiseaseProgression, AccessKind::Amend)?;
+			}
+
+			// Record deletion in audit trail
+			Self::do_record_change(
+				RecordType::DiseaseProgression,
+				progression_id,
+				b"deleted".to_vec(),
+				Some(b"active".to_vec()),
+				b"deleted".to_vec(),
+				who,
+				OperationType::Delete,
+			)?;
 			
 			// Remove from patient's progression list
 			PatientDiseaseProgressions::<T>::mutate(progression.patient_id, |progressions| {
@@ -993,24 +1952,31 @@ pub mod pallet {
 			});
 
 			DiseaseProgressions::<T>::remove(progression_id);
+			Self::cancel_follow_up(&progression);
 
 			Self::deposit_event(Event::DiseaseProgressionDeleted { progression_id });
 
 			Ok(())
 		}
 
-		#[pallet::weight(10_000)]
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::create_medical_record())]
 		pub fn create_medical_record(
 			origin: OriginFor<T>,
 			patient_id: u32,
 			diagnosis: Vec<u8>,
 			treatment: Vec<u8>,
-			data_pointer: Option<Vec<u8>>,
+			data_pointer: Option<DataPointerOf<T>>,
 		) -> DispatchResult {
 			let doctor_id = ensure_signed(origin)?;
+			Self::ensure_role(&doctor_id, &[Role::Doctor])?;
 
 			// Ensure patient exists
 			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			Self::ensure_consent(patient_id, &doctor_id, RecordType::MedicalRecord, AccessKind::Append)?;
+			if let Some(pointer) = &data_pointer {
+				Self::ensure_data_pointer(patient_id, pointer)?;
+			}
 
 			let record_id = Self::next_record_id();
 			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
@@ -1022,22 +1988,31 @@ pub mod pallet {
 				record_hash: T::Hashing::hash_of(
 					&(patient_id, doctor_id.clone(), diagnosis.clone(), treatment.clone(), data_pointer.clone())
 				),
-				data_pointer,
-				diagnosis,
-				treatment,
+				data_pointer: data_pointer.clone(),
+				diagnosis: diagnosis.clone().try_into().map_err(|_| Error::<T>::MedicalRecordDataTooLong)?,
+				treatment: treatment.clone().try_into().map_err(|_| Error::<T>::MedicalRecordDataTooLong)?,
 				created_at: block_number,
 				created_by: doctor_id.clone(),
 				last_modified_at: block_number,
 				last_modified_by: doctor_id.clone(),
 			};
 
+			// Record creation in audit trail
+			Self::do_record_change(RecordType::MedicalRecord, record_id, b"diagnosis".to_vec(), None, diagnosis, doctor_id.clone(), OperationType::Create)?;
+			Self::do_record_change(RecordType::MedicalRecord, record_id, b"treatment".to_vec(), None, treatment, doctor_id.clone(), OperationType::Create)?;
+			if let Some(pointer) = data_pointer {
+				Self::do_record_change(RecordType::MedicalRecord, record_id, b"data_pointer".to_vec(), None, pointer.encode(), doctor_id.clone(), OperationType::Create)?;
+			}
+
+			// Add to patient's medical records list and record index
+			PatientMedicalRecords::<T>::try_mutate(patient_id, |records| records.try_push(record_id))
+				.map_err(|_| Error::<T>::TooManyRecordsForPatient)?;
+			PatientRecordIndex::<T>::insert(patient_id, (RecordType::MedicalRecord, record_id), ());
+
+			Self::anchor_record(&doctor_id, RecordType::MedicalRecord, record_id, record.record_hash)?;
+
 			MedicalRecords::<T>::insert(record_id, record);
 			
-			// Add to patient's medical records list
-			PatientMedicalRecords::<T>::mutate(patient_id, |records| {
-				records.push(record_id);
-			});
-			
 			NextRecordId::<T>::put(record_id + 1);
 
 			Self::deposit_event(Event::MedicalRecordCreated {
@@ -1049,15 +2024,21 @@ pub mod pallet {
 			Ok(())
 		}
 
-		// New comprehensive query functions
-		#[pallet::weight(10_000)]
+		// Deprecated: use the `patient_complete_history` view function instead. The runtime call
+		// filter rejects this call; it is kept so existing call indices stay stable.
+		#[pallet::call_index(11)]
+		#[pallet::weight(T::WeightInfo::get_patient_complete_history())]
 		pub fn get_patient_complete_history(
 			origin: OriginFor<T>,
 			patient_id: u32,
 		) -> DispatchResult {
-			let _who = ensure_signed(origin)?;
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin, Role::Doctor, Role::Nurse])?;
 
 			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			if !Self::has_role(&who, Role::Admin) {
+				Self::ensure_consent(patient_id, &who, RecordType::Patient, AccessKind::Read)?;
+			}
 
 			let clinical_tests = PatientClinicalTests::<T>::get(patient_id);
 			let progressions = PatientDiseaseProgressions::<T>::get(patient_id);
@@ -1075,130 +2056,1553 @@ pub mod pallet {
 			Ok(())
 		}
 
-		// #[pallet::weight(10_000)]
-		// pub fn search_patients_by_demographics(
-		// 	origin: OriginFor<T>,
-		// 	gender: Option<Vec<u8>>,
-		// 	birth_year: Option<u32>,
-		// ) -> DispatchResult {
-		// 	let _who = ensure_signed(origin)?;
-
-		// 	let mut result_patients: Vec<u32> = Vec::new();
-		// 	let mut search_criteria = Vec::new();
+		// Role registry management
+		#[pallet::call_index(14)]
+		#[pallet::weight(T::WeightInfo::grant_role())]
+		pub fn grant_role(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			role: Role,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
 
-		// 	if let Some(g) = gender {
-		// 		result_patients = PatientsByGender::<T>::get(&g);
-		// 		search_criteria.extend_from_slice(b"gender:");
-		// 		search_criteria.extend_from_slice(&g);
-		// 	}
+			ensure!(!Self::has_role(&who, role), Error::<T>::RoleAlreadyGranted);
 
-		// 	if let Some(year) = birth_year {
-		// 		let year_patients = PatientsByBirthYear::<T>::get(year);
-		// 		if result_patients.is_empty() {
-		// 			result_patients = year_patients;
-		// 		} else {
-		// 			// Intersection of both criteria
-		// 			result_patients.retain(|id| year_patients.contains(id));
-		// 		}
-		// 		if !search_criteria.is_empty() {
-		// 			search_criteria.extend_from_slice(b",");
-		// 		}
-		// 		search_criteria.extend_from_slice(b"birth_year:");
-		// 		let year_str = year.to_string();
-		// 		search_criteria.extend_from_slice(&year_str.as_bytes());
-		// 	}
+			AccountRoles::<T>::insert(&who, role, ());
 
-		// 	Self::deposit_event(Event::PatientsFoundByDemographics {
-		// 		patient_ids: result_patients,
-		// 		search_criteria,
-		// 	});
+			Self::deposit_event(Event::RoleGranted { who, role });
 
-		// 	Ok(())
-		// }
-	}
+			Ok(())
+		}
 
-	impl<T: Config> Pallet<T> {
-		// Internal helper function for recording changes
-		fn do_record_change(
-			record_type: RecordType,
-			record_id: u32,
-			field_name: Vec<u8>,
-			old_value: Option<Vec<u8>>,
-			new_value: Vec<u8>,
-			changed_by: T::AccountId,
-			operation: OperationType,
+		#[pallet::call_index(15)]
+		#[pallet::weight(T::WeightInfo::revoke_role())]
+		pub fn revoke_role(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			role: Role,
 		) -> DispatchResult {
-			let change_id = Self::next_change_id();
-			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			T::AdminOrigin::ensure_origin(origin)?;
 
-			let change = ChangeHistory::<T> {
-				change_id,
-				record_type: record_type.clone(),
-				record_id,
-				field_name,
-				old_value,
-				new_value,
-				changed_by: changed_by.clone(),
-				changed_at: block_number,
-				operation,
-			};
+			ensure!(Self::has_role(&who, role), Error::<T>::RoleNotGranted);
 
-			// Store the change
-			ChangeHistories::<T>::insert(change_id, change);
-			// Map record to its changes
-			RecordChanges::<T>::insert((record_type.clone(), record_id), change_id, ());
-			NextChangeId::<T>::put(change_id + 1);
+			AccountRoles::<T>::remove(&who, role);
 
-			Self::deposit_event(Event::ChangeRecorded {
-				change_id,
-				record_type,
-				record_id,
-				changed_by,
-			});
+			Self::deposit_event(Event::RoleRevoked { who, role });
 
 			Ok(())
 		}
 
-		// Query function to get all active patients with pagination
-		pub fn get_all_patients(offset: u32, limit: u32) -> Vec<PatientInfo<T>> {
-			let active_patients = ActivePatients::<T>::get();
-			let start = offset as usize;
-			let end = ((offset + limit) as usize).min(active_patients.len());
-			
-			active_patients[start..end]
-				.iter()
-				.filter_map(|&patient_id| Patients::<T>::get(patient_id))
-				.collect()
-		}
+		// Consent management
+		#[pallet::call_index(16)]
+		#[pallet::weight(T::WeightInfo::link_patient_account())]
+		pub fn link_patient_account(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin])?;
 
-		// Query function to get all clinical tests for a patient
-		pub fn get_patient_clinical_tests(patient_id: u32) -> Vec<ClinicalTest<T>> {
-			PatientClinicalTests::<T>::get(patient_id)
-				.iter()
-				.filter_map(|&test_id| ClinicalTests::<T>::get(test_id))
-				.collect()
-		}
+			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
 
-		// Query function to get all disease progressions for a patient
-		pub fn get_patient_disease_progressions(patient_id: u32) -> Vec<DiseaseProgression<T>> {
-			PatientDiseaseProgressions::<T>::get(patient_id)
-				.iter()
-				.filter_map(|&progression_id| DiseaseProgressions::<T>::get(progression_id))
-				.collect()
+			PatientAccounts::<T>::insert(patient_id, account.clone());
+
+			Self::deposit_event(Event::PatientAccountLinked { patient_id, account });
+
+			Ok(())
 		}
 
-		// Query function to get all medical records for a patient
-		pub fn get_patient_medical_records(patient_id: u32) -> Vec<MedicalRecord<T>> {
-			PatientMedicalRecords::<T>::get(patient_id)
+		#[pallet::call_index(17)]
+		#[pallet::weight(T::WeightInfo::set_guardian())]
+		pub fn set_guardian(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			guardian: Option<T::AccountId>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin])?;
+
+			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+
+			match &guardian {
+				Some(account) => PatientGuardians::<T>::insert(patient_id, account),
+				None => PatientGuardians::<T>::remove(patient_id),
+			}
+
+			Self::deposit_event(Event::GuardianSet { patient_id, guardian });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(18)]
+		#[pallet::weight(T::WeightInfo::grant_consent())]
+		pub fn grant_consent(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			grantee: T::AccountId,
+			record_type: RecordType,
+			scope: ConsentScope,
+			expires_at: Option<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			ensure!(Self::is_patient_or_guardian(patient_id, &who), Error::<T>::NotPatientOrGuardian);
+			ensure!(record_type != RecordType::Consent, Error::<T>::InvalidConsentScope);
+			ensure!(!scope.is_empty(), Error::<T>::InvalidConsentScope);
+			ensure!(
+				[Role::Doctor, Role::Nurse, Role::LabTechnician]
+					.iter()
+					.any(|role| Self::has_role(&grantee, *role)),
+				Error::<T>::InvalidGrantee
+			);
+
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			if let Some(expiry) = expires_at {
+				ensure!(expiry > block_number, Error::<T>::InvalidConsentExpiry);
+			}
+
+			let key = (grantee.clone(), record_type.clone());
+			let old_grant = Consents::<T>::get(patient_id, &key);
+			let grant = ConsentGrant::<T> {
+				scope,
+				expires_at,
+				granted_by: who.clone(),
+				granted_at: block_number,
+			};
+
+			Self::do_record_change(
+				RecordType::Consent,
+				patient_id,
+				b"consent".to_vec(),
+				old_grant.as_ref().map(|old| (&grantee, &record_type, old.scope, old.expires_at).encode()),
+				(&grantee, &record_type, scope, expires_at).encode(),
+				who,
+				if old_grant.is_some() { OperationType::Update } else { OperationType::Create },
+			)?;
+
+			Consents::<T>::insert(patient_id, &key, grant);
+
+			// The care relationship only exists while consent has been given
+			DoctorPatients::<T>::try_mutate(&grantee, |patients| -> DispatchResult {
+				if !patients.contains(&patient_id) {
+					patients.try_push(patient_id).map_err(|_| Error::<T>::TooManyPatientsForDoctor)?;
+				}
+				Ok(())
+			})?;
+			PatientDoctors::<T>::try_mutate(patient_id, |doctors| -> DispatchResult {
+				if !doctors.contains(&grantee) {
+					doctors.try_push(grantee.clone()).map_err(|_| Error::<T>::TooManyDoctorsForPatient)?;
+				}
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ConsentGranted {
+				patient_id,
+				grantee,
+				record_type,
+				scope,
+				expires_at,
+			});
+
+			Ok(())
+		}
+
+		#[pallet::call_index(19)]
+		#[pallet::weight(T::WeightInfo::revoke_consent())]
+		pub fn revoke_consent(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			grantee: T::AccountId,
+			record_type: RecordType,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(Self::is_patient_or_guardian(patient_id, &who), Error::<T>::NotPatientOrGuardian);
+
+			let key = (grantee.clone(), record_type.clone());
+			let old_grant = Consents::<T>::get(patient_id, &key).ok_or(Error::<T>::ConsentNotFound)?;
+
+			Self::do_record_change(
+				RecordType::Consent,
+				patient_id,
+				b"consent".to_vec(),
+				Some((&grantee, &record_type, old_grant.scope, old_grant.expires_at).encode()),
+				b"revoked".to_vec(),
+				who,
+				OperationType::Delete,
+			)?;
+
+			Consents::<T>::remove(patient_id, &key);
+
+			// Drop the care relationship once no grant is left for this account
+			let has_other_grant = Consents::<T>::iter_prefix(patient_id)
+				.any(|((account, _), _)| account == grantee);
+			if !has_other_grant {
+				DoctorPatients::<T>::mutate(&grantee, |patients| patients.retain(|&id| id != patient_id));
+				PatientDoctors::<T>::mutate(patient_id, |doctors| doctors.retain(|doctor| doctor != &grantee));
+			}
+
+			Self::deposit_event(Event::ConsentRevoked {
+				patient_id,
+				grantee,
+				record_type,
+			});
+
+			Ok(())
+		}
+
+		/// Register the x25519 public key patient data keys are wrapped for the caller with.
+		#[pallet::call_index(20)]
+		#[pallet::weight(T::WeightInfo::register_encryption_key())]
+		pub fn register_encryption_key(
+			origin: OriginFor<T>,
+			public_key: [u8; 32],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			EncryptionKeys::<T>::insert(&who, public_key);
+
+			Self::deposit_event(Event::EncryptionKeyRegistered { who });
+			Ok(())
+		}
+
+		/// Store the patient's data key wrapped for each account in `wrapped_keys`, replacing any
+		/// copy an account already holds. Passing the next key version rotates the data key: the
+		/// copies of the previous version are dropped, and the PII fields have to be sealed again
+		/// with the new key.
+		#[pallet::call_index(21)]
+		#[pallet::weight(T::WeightInfo::set_data_keys(wrapped_keys.len() as u32))]
+		pub fn set_data_keys(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			key_version: u32,
+			wrapped_keys: Vec<(T::AccountId, WrappedDataKey)>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_key_manager(patient_id, &who)?;
+
+			let mut keyring = match PatientDataKeys::<T>::get(patient_id) {
+				Some(keyring) if keyring.key_version == key_version => keyring,
+				Some(keyring) if keyring.key_version.checked_add(1) == Some(key_version) =>
+					DataKeyring { key_version, wrapped_keys: BoundedVec::new() },
+				None if key_version == 0 => DataKeyring { key_version, wrapped_keys: BoundedVec::new() },
+				_ => return Err(Error::<T>::InvalidDataKeyVersion.into()),
+			};
+
+			for (account, wrapped_key) in wrapped_keys {
+				ensure!(EncryptionKeys::<T>::contains_key(&account), Error::<T>::EncryptionKeyNotRegistered);
+				match keyring.wrapped_keys.iter_mut().find(|(holder, _)| holder == &account) {
+					Some((_, existing)) => *existing = wrapped_key,
+					None => keyring
+						.wrapped_keys
+						.try_push((account, wrapped_key))
+						.map_err(|_| Error::<T>::TooManyDataKeyHolders)?,
+				}
+			}
+
+			PatientDataKeys::<T>::insert(patient_id, keyring);
+
+			Self::deposit_event(Event::DataKeysSet { patient_id, key_version });
+			Ok(())
+		}
+
+		/// Remove an account's copy of the patient's data key. The account can still read what it
+		/// decrypted before; rotate the data key to keep it from reading later updates.
+		#[pallet::call_index(22)]
+		#[pallet::weight(T::WeightInfo::remove_data_key())]
+		pub fn remove_data_key(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_key_manager(patient_id, &who)?;
+
+			PatientDataKeys::<T>::try_mutate(patient_id, |keyring_opt| -> DispatchResult {
+				let keyring = keyring_opt.as_mut().ok_or(Error::<T>::DataKeyNotFound)?;
+				let position = keyring
+					.wrapped_keys
+					.iter()
+					.position(|(holder, _)| holder == &account)
+					.ok_or(Error::<T>::DataKeyNotFound)?;
+				keyring.wrapped_keys.remove(position);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::DataKeyRemoved { patient_id, account });
+			Ok(())
+		}
+
+		/// Register a document for a patient by the SHA-256 hash and size of its content. The
+		/// content never goes on-chain: the uploader then sends it to a node over RPC, which checks
+		/// it against the registered hash and size before keeping it in its offchain database.
+		#[pallet::call_index(23)]
+		#[pallet::weight(T::WeightInfo::store_document())]
+		pub fn store_document(
+			origin: OriginFor<T>,
+			patient_id: u32,
+			content_hash: ContentHash,
+			size: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Doctor, Role::Nurse, Role::LabTechnician])?;
+
+			ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+			ensure!(Self::has_clinical_consent(patient_id, &who, AccessKind::Append), Error::<T>::NoConsent);
+			ensure!(size > 0, Error::<T>::EmptyDocument);
+			ensure!(size <= T::MaxDocumentSize::get(), Error::<T>::DocumentTooLarge);
+			ensure!(!Documents::<T>::contains_key(content_hash), Error::<T>::DocumentAlreadyStored);
+
+			Documents::<T>::insert(
+				content_hash,
+				DocumentInfo::<T> {
+					patient_id,
+					size,
+					uploaded_by: who,
+					uploaded_at: <frame_system::Pallet<T>>::block_number(),
+				},
+			);
+			PatientDocuments::<T>::insert(patient_id, content_hash, ());
+
+			Self::deposit_event(Event::DocumentStored { content_hash, patient_id, size });
+			Ok(())
+		}
+
+		/// Attach an off-chain document to a clinical test or disease progression.
+		#[pallet::call_index(24)]
+		#[pallet::weight(T::WeightInfo::add_attachment())]
+		pub fn add_attachment(
+			origin: OriginFor<T>,
+			record_type: RecordType,
+			record_id: u32,
+			pointer: DataPointerOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let content_hash = pointer.content_hash;
+
+			Self::do_mutate_attachments(&who, record_type.clone(), record_id, |patient_id, attachments| {
+				Self::ensure_data_pointer(patient_id, &pointer)?;
+				ensure!(
+					!attachments.iter().any(|attachment| attachment.content_hash == content_hash),
+					Error::<T>::AttachmentAlreadyExists
+				);
+				Self::do_record_change(
+					record_type.clone(),
+					record_id,
+					b"attachments".to_vec(),
+					None,
+					pointer.encode(),
+					who.clone(),
+					OperationType::Update,
+				)?;
+				attachments.try_push(pointer).map_err(|_| Error::<T>::TooManyAttachments)?;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::AttachmentAdded { record_type, record_id, content_hash });
+			Ok(())
+		}
+
+		/// Detach a document from a clinical test or disease progression.
+		#[pallet::call_index(25)]
+		#[pallet::weight(T::WeightInfo::remove_attachment())]
+		pub fn remove_attachment(
+			origin: OriginFor<T>,
+			record_type: RecordType,
+			record_id: u32,
+			content_hash: ContentHash,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::do_mutate_attachments(&who, record_type.clone(), record_id, |_, attachments| {
+				let index = attachments
+					.iter()
+					.position(|attachment| attachment.content_hash == content_hash)
+					.ok_or(Error::<T>::AttachmentNotFound)?;
+				let removed = attachments.remove(index);
+				Self::do_record_change(
+					record_type.clone(),
+					record_id,
+					b"attachments".to_vec(),
+					Some(removed.encode()),
+					b"removed".to_vec(),
+					who.clone(),
+					OperationType::Update,
+				)
+			})?;
+
+			Self::deposit_event(Event::AttachmentRemoved { record_type, record_id, content_hash });
+			Ok(())
+		}
+
+		/// Ask for all data of a patient to be erased. Only the patient or their guardian can ask,
+		/// and nothing is erased until the data-protection origin approves the request.
+		#[pallet::call_index(26)]
+		#[pallet::weight(T::WeightInfo::request_erasure())]
+		pub fn request_erasure(origin: OriginFor<T>, patient_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(Self::is_patient_or_guardian(patient_id, &who), Error::<T>::NotPatientOrGuardian);
+			ensure!(!ErasureRequests::<T>::contains_key(patient_id), Error::<T>::ErasureAlreadyRequested);
+
+			ErasureRequests::<T>::insert(
+				patient_id,
+				ErasureRequest::<T> {
+					requested_by: who.clone(),
+					requested_at: <frame_system::Pallet<T>>::block_number(),
+					approved_at: None,
+					stage: ErasureStage::Requested,
+					redacting: None,
+					erased_items: 0,
+					digest: Default::default(),
+				},
+			);
+
+			Self::deposit_event(Event::ErasureRequested { patient_id, requested_by: who });
+			Ok(())
+		}
+
+		/// Approve an erasure request. The patient's data keys are destroyed and the patient is
+		/// unlisted right away; everything else is erased over the following blocks.
+		#[pallet::call_index(27)]
+		#[pallet::weight(T::WeightInfo::approve_erasure())]
+		pub fn approve_erasure(origin: OriginFor<T>, patient_id: u32) -> DispatchResult {
+			T::DataProtectionOrigin::ensure_origin(origin)?;
+
+			ErasureRequests::<T>::try_mutate(patient_id, |request_opt| -> DispatchResult {
+				let request = request_opt.as_mut().ok_or(Error::<T>::ErasureNotRequested)?;
+				ensure!(request.stage == ErasureStage::Requested, Error::<T>::ErasureAlreadyApproved);
+
+				ErasureQueue::<T>::try_append(patient_id).map_err(|_| Error::<T>::TooManyPendingErasures)?;
+				request.approved_at = Some(<frame_system::Pallet<T>>::block_number());
+				request.stage = ErasureStage::Records;
+
+				// Without its data keys the patient's encrypted fields can no longer be read, and
+				// without the patient nothing new can be recorded for them
+				if let Some(keyring) = PatientDataKeys::<T>::take(patient_id) {
+					Self::note_erased(request, T::Hashing::hash_of(&keyring));
+				}
+				if let Some(patient) = Patients::<T>::take(patient_id) {
+					Self::unlist_patient(&patient);
+					Self::note_erased(request, T::Hashing::hash_of(&patient));
+				}
+				Self::unindex_name(patient_id);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::ErasureApproved { patient_id });
+			Ok(())
+		}
+
+		/// Reject an erasure request that has not been approved yet.
+		#[pallet::call_index(28)]
+		#[pallet::weight(T::WeightInfo::reject_erasure())]
+		pub fn reject_erasure(origin: OriginFor<T>, patient_id: u32) -> DispatchResult {
+			T::DataProtectionOrigin::ensure_origin(origin)?;
+
+			let request = ErasureRequests::<T>::get(patient_id).ok_or(Error::<T>::ErasureNotRequested)?;
+			ensure!(request.stage == ErasureStage::Requested, Error::<T>::ErasureAlreadyApproved);
+			ErasureRequests::<T>::remove(patient_id);
+
+			Self::deposit_event(Event::ErasureRejected { patient_id });
+			Ok(())
+		}
+
+		/// Book the follow-up appointment of a progression with the calling doctor, or move it to
+		/// `scheduled_time` if one is already upcoming. Only the doctor it is booked with can move
+		/// it.
+		#[pallet::call_index(29)]
+		#[pallet::weight(T::WeightInfo::set_next_appointment())]
+		pub fn set_next_appointment(
+			origin: OriginFor<T>,
+			progression_id: u32,
+			scheduled_time: u64,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Doctor])?;
+
+			Self::do_set_next_appointment(who, progression_id, Some(scheduled_time))
+		}
+
+		/// Cancel the upcoming follow-up appointment of a progression and unlink it. Only the doctor
+		/// it is booked with can cancel it.
+		#[pallet::call_index(30)]
+		#[pallet::weight(T::WeightInfo::clear_appointment())]
+		pub fn clear_appointment(
+			origin: OriginFor<T>,
+			progression_id: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Doctor])?;
+
+			Self::do_set_next_appointment(who, progression_id, None)
+		}
+
+		/// Index each patient of `entries` under its new blinded name, replacing the previous
+		/// index. Lets an admin re-index the facility's patients in batches, e.g. after the
+		/// plaintext name index was dropped or the facility secret was rotated.
+		#[pallet::call_index(31)]
+		#[pallet::weight(T::WeightInfo::reindex_patient_names(entries.len() as u32))]
+		pub fn reindex_patient_names(
+			origin: OriginFor<T>,
+			entries: BoundedVec<(u32, NameIndex), T::MaxReindexBatchSize>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_role(&who, &[Role::Admin])?;
+
+			let count = entries.len() as u32;
+			for (patient_id, name_index) in entries {
+				ensure!(Patients::<T>::contains_key(patient_id), Error::<T>::PatientNotFound);
+				Self::unindex_name(patient_id);
+				Self::index_name(patient_id, name_index)?;
+			}
+
+			Self::deposit_event(Event::PatientNamesReindexed { count });
+			Ok(())
+		}
+
+		// #[pallet::weight(10_000)]
+		// pub fn search_patients_by_demographics(
+		// 	origin: OriginFor<T>,
+		// 	gender: Option<Vec<u8>>,
+		// 	birth_year: Option<u32>,
+		// ) -> DispatchResult {
+		// 	let _who = ensure_signed(origin)?;
+
+		// 	let mut result_patients: Vec<u32> = Vec::new();
+		// 	let mut search_criteria = Vec::new();
+
+		// 	if let Some(g) = gender {
+		// 		result_patients = PatientsByGender::<T>::get(&g);
+		// 		search_criteria.extend_from_slice(b"gender:");
+		// 		search_criteria.extend_from_slice(&g);
+		// 	}
+
+		// 	if let Some(year) = birth_year {
+		// 		let year_patients = PatientsByBirthYear::<T>::get(year);
+		// 		if result_patients.is_empty() {
+		// 			result_patients = year_patients;
+		// 		} else {
+		// 			// Intersection of both criteria
+		// 			result_patients.retain(|id| year_patients.contains(id));
+		// 		}
+		// 		if !search_criteria.is_empty() {
+		// 			search_criteria.extend_from_slice(b",");
+		// 		}
+		// 		search_criteria.extend_from_slice(b"birth_year:");
+		// 		let year_str = year.to_string();
+		// 		search_criteria.extend_from_slice(&year_str.as_bytes());
+		// 	}
+
+		// 	Self::deposit_event(Event::PatientsFoundByDemographics {
+		// 		patient_ids: result_patients,
+		// 		search_criteria,
+		// 	});
+
+		// 	Ok(())
+		// }
+	}
+
+	#[pallet::view_functions_experimental]
+	impl<T: Config> Pallet<T> {
+		/// Ids of the patients indexed under the blinded name `name_index`, empty if there are
+		/// none.
+		pub fn find_patients_by_name(name_index: NameIndex) -> Vec<u32> {
+			PatientsByName::<T>::get(name_index)
+				.map(|ids| ids.into_inner())
+				.unwrap_or_default()
+		}
+
+		/// A patient together with their clinical tests, disease progressions and medical
+		/// records, or `None` if the patient does not exist.
+		///
+		/// This returns unrestricted state: view functions have no caller, so no role or consent
+		/// is checked, unlike the deprecated `get_patient_complete_history` call. Anyone who can
+		/// query the node can read the same data from storage directly. Runtime code that serves
+		/// the history on behalf of an account has to check that account's consent itself.
+		pub fn patient_complete_history(patient_id: u32) -> Option<PatientHistory<T>> {
+			let patient = Patients::<T>::get(patient_id)?;
+			Some(PatientHistory {
+				patient,
+				clinical_tests: Self::get_patient_clinical_tests(patient_id),
+				disease_progressions: Self::get_patient_disease_progressions(patient_id),
+				medical_records: Self::get_patient_medical_records(patient_id),
+			})
+		}
+
+		/// Proof hashes anchored for every version of a record, oldest first. Each one can be
+		/// looked up in the proof chain to check when that version was written.
+		pub fn proofs_for_record(record_type: RecordType, record_id: u32) -> Vec<T::Hash> {
+			RecordProofs::<T>::get(record_type, record_id).into_inner()
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		// Check whether an account holds a given role
+		pub fn has_role(who: &T::AccountId, role: Role) -> bool {
+			AccountRoles::<T>::contains_key(who, role)
+		}
+
+		// Check whether an account is the patient or the patient's guardian
+		pub fn is_patient_or_guardian(patient_id: u32, who: &T::AccountId) -> bool {
+			PatientAccounts::<T>::get(patient_id).as_ref() == Some(who) ||
+				PatientGuardians::<T>::get(patient_id).as_ref() == Some(who)
+		}
+
+		// Check whether an account holds an active consent grant for the given access
+		pub fn has_consent(
+			patient_id: u32,
+			who: &T::AccountId,
+			record_type: RecordType,
+			access: AccessKind,
+		) -> bool {
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			Consents::<T>::get(patient_id, (who, record_type)).is_some_and(|grant| {
+				grant.scope.allows(access) && grant.expires_at.is_none_or(|expiry| block_number < expiry)
+			})
+		}
+
+		// Check whether an account holds any unexpired consent grant from a patient
+		pub fn has_active_grant(patient_id: u32, who: &T::AccountId) -> bool {
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			Consents::<T>::iter_prefix(patient_id).any(|((account, _), grant)| {
+				&account == who && grant.expires_at.is_none_or(|expiry| block_number < expiry)
+			})
+		}
+
+		// Ensure an account holds an active consent grant for the given access
+		fn ensure_consent(
+			patient_id: u32,
+			who: &T::AccountId,
+			record_type: RecordType,
+			access: AccessKind,
+		) -> DispatchResult {
+			ensure!(Self::has_consent(patient_id, who, record_type, access), Error::<T>::NoConsent);
+			Ok(())
+		}
+
+		// Whether an account holds consent for the given access to any clinical record of a patient
+		fn has_clinical_consent(patient_id: u32, who: &T::AccountId, access: AccessKind) -> bool {
+			[RecordType::ClinicalTest, RecordType::DiseaseProgression, RecordType::MedicalRecord]
+				.into_iter()
+				.any(|record_type| Self::has_consent(patient_id, who, record_type, access))
+		}
+
+		/// Whether an account may fetch a stored document: the uploader, the patient or their
+		/// guardian, or anyone with read consent on the patient's clinical records.
+		pub fn can_read_document(reader: &T::AccountId, content_hash: &ContentHash) -> bool {
+			Documents::<T>::get(content_hash).is_some_and(|document| {
+				&document.uploaded_by == reader ||
+					Self::is_patient_or_guardian(document.patient_id, reader) ||
+					Self::has_clinical_consent(document.patient_id, reader, AccessKind::Read)
+			})
+		}
+
+		/// Whether an account may hand a node the content of a registered document: only its
+		/// uploader, with content of the registered size.
+		pub fn can_write_document(writer: &T::AccountId, content_hash: &ContentHash, size: u32) -> bool {
+			Documents::<T>::get(content_hash)
+				.is_some_and(|document| &document.uploaded_by == writer && document.size == size)
+		}
+
+		// Ensure a data pointer is well-formed, and that a stored document it points to belongs
+		// to the patient and has the stated size
+		fn ensure_data_pointer(patient_id: u32, pointer: &DataPointerOf<T>) -> DispatchResult {
+			ensure!(pointer.is_valid(), Error::<T>::InvalidDataPointer);
+			if matches!(pointer.location, DataLocation::Blob) {
+				let document = Documents::<T>::get(pointer.content_hash).ok_or(Error::<T>::DocumentNotFound)?;
+				ensure!(
+					document.patient_id == patient_id && u64::from(document.size) == pointer.size,
+					Error::<T>::InvalidDataPointer
+				);
+			}
+			Ok(())
+		}
+
+		// Change the attachments of a clinical test or disease progression on behalf of an account
+		// allowed to amend it, then update its modification tracking and anchor the new version
+		fn do_mutate_attachments(
+			who: &T::AccountId,
+			record_type: RecordType,
+			record_id: u32,
+			mutate: impl FnOnce(u32, &mut AttachmentsOf<T>) -> DispatchResult,
+		) -> DispatchResult {
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+			match record_type {
+				RecordType::ClinicalTest => {
+					Self::ensure_role(who, &[Role::Doctor, Role::LabTechnician])?;
+					ClinicalTests::<T>::try_mutate(record_id, |test_opt| -> DispatchResult {
+						let test = test_opt.as_mut().ok_or(Error::<T>::ClinicalTestNotFound)?;
+						Self::ensure_consent(test.patient_id, who, RecordType::ClinicalTest, AccessKind::Amend)?;
+						mutate(test.patient_id, &mut test.attachments)?;
+						test.last_modified_at = block_number;
+						test.last_modified_by = who.clone();
+						Self::anchor_record(who, RecordType::ClinicalTest, record_id, T::Hashing::hash_of(test))
+					})
+				},
+				RecordType::DiseaseProgression => {
+					Self::ensure_role(who, &[Role::Doctor])?;
+					DiseaseProgressions::<T>::try_mutate(record_id, |progression_opt| -> DispatchResult {
+						let progression = progression_opt.as_mut().ok_or(Error::<T>::DiseaseProgressionNotFound)?;
+						Self::ensure_consent(progression.patient_id, who, RecordType::DiseaseProgression, AccessKind::Amend)?;
+						mutate(progression.patient_id, &mut progression.attachments)?;
+						progression.last_modified_at = block_number;
+						progression.last_modified_by = who.clone();
+						Self::anchor_record(who, RecordType::DiseaseProgression, record_id, T::Hashing::hash_of(progression))
+					})
+				},
+				_ => Err(Error::<T>::AttachmentsNotSupported.into()),
+			}
+		}
+
+		// Ensure an account holds at least one of the allowed roles
+		fn ensure_role(who: &T::AccountId, allowed: &[Role]) -> DispatchResult {
+			ensure!(
+				allowed.iter().any(|role| Self::has_role(who, *role)),
+				Error::<T>::NotAuthorized
+			);
+			Ok(())
+		}
+
+		// Ensure an account may share a patient's data key: the patient or their guardian, an
+		// admin, or the account that registered the patient and generated the key
+		fn ensure_key_manager(patient_id: u32, who: &T::AccountId) -> DispatchResult {
+			let patient = Patients::<T>::get(patient_id).ok_or(Error::<T>::PatientNotFound)?;
+			ensure!(
+				Self::is_patient_or_guardian(patient_id, who) ||
+					Self::has_role(who, Role::Admin) ||
+					&patient.created_by == who,
+				Error::<T>::NotAuthorized
+			);
+			Ok(())
+		}
+
+		// Version of the data key a patient's PII fields have to be sealed with
+		pub fn current_key_version(patient_id: u32) -> u32 {
+			PatientDataKeys::<T>::get(patient_id).map_or(0, |keyring| keyring.key_version)
+		}
+
+		// Ensure a PII field is an envelope sealed with the patient's current data key when PII
+		// has to be encrypted
+		fn ensure_pii(patient_id: u32, field: &[u8]) -> DispatchResult {
+			if !T::EncryptedPii::get() {
+				return Ok(());
+			}
+			let envelope = PiiEnvelope::from_field(field).ok_or(Error::<T>::InvalidEnvelope)?;
+			ensure!(
+				envelope.key_version == Self::current_key_version(patient_id),
+				Error::<T>::DataKeyVersionMismatch
+			);
+			Ok(())
+		}
+
+		// Birth year of a plaintext `YYYY-MM-DD` date of birth. Encrypted dates are not indexed.
+		pub(crate) fn birth_year_of(date_of_birth: &[u8]) -> Option<u32> {
+			if T::EncryptedPii::get() {
+				return None;
+			}
+			core::str::from_utf8(date_of_birth.get(0..4)?).ok()?.parse().ok()
+		}
+
+		// Internal helper for booking, moving or cancelling the next appointment of a progression
+		fn do_set_next_appointment(
+			who: T::AccountId,
+			progression_id: u32,
+			next_appointment: Option<u64>,
+		) -> DispatchResult {
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+
+			DiseaseProgressions::<T>::try_mutate(progression_id, |progression_opt| -> DispatchResult {
+				let progression = progression_opt.as_mut().ok_or(Error::<T>::DiseaseProgressionNotFound)?;
+				Self::ensure_consent(progression.patient_id, &who, RecordType::DiseaseProgression, AccessKind::Amend)?;
+
+				let old_appointment = progression.next_appointment;
+				match next_appointment {
+					Some(scheduled_time) => Self::book_follow_up(progression, &who, scheduled_time)?,
+					None => {
+						if let Some(appointment_id) = progression.next_appointment.take() {
+							if T::Appointments::upcoming(appointment_id).is_some() {
+								T::Appointments::cancel(&who, appointment_id)?;
+							}
+						}
+					},
+				}
+				Self::record_appointment_change(progression, old_appointment, &who)?;
+				progression.last_modified_at = block_number;
+				progression.last_modified_by = who.clone();
+
+				Self::anchor_record(
+					&who,
+					RecordType::DiseaseProgression,
+					progression_id,
+					T::Hashing::hash_of(progression),
+				)?;
+
+				Self::deposit_event(Event::DiseaseProgressionUpdated {
+					progression_id,
+					updated_by: who,
+				});
+				Ok(())
+			})
+		}
+
+		// Book the follow-up of a progression with `doctor` at `scheduled_time`. An appointment the
+		// progression already links to is moved instead while it is upcoming, which only its own
+		// doctor can do.
+		fn book_follow_up(
+			progression: &mut DiseaseProgression<T>,
+			doctor: &T::AccountId,
+			scheduled_time: u64,
+		) -> DispatchResult {
+			if let Some(appointment_id) = progression.next_appointment {
+				if T::Appointments::upcoming(appointment_id).is_some() {
+					return T::Appointments::reschedule(doctor, appointment_id, scheduled_time);
+				}
+			}
+
+			let patient = PatientAccounts::<T>::get(progression.patient_id)
+				.ok_or(Error::<T>::PatientAccountNotLinked)?;
+			progression.next_appointment = Some(T::Appointments::schedule(&patient, doctor, scheduled_time)?);
+			Ok(())
+		}
+
+		// Record a change of the appointment a progression links to. Moving the linked appointment
+		// keeps its id, so only booking a new one or unlinking it shows up in the history.
+		fn record_appointment_change(
+			progression: &DiseaseProgression<T>,
+			old_appointment: Option<u32>,
+			who: &T::AccountId,
+		) -> DispatchResult {
+			if progression.next_appointment == old_appointment {
+				return Ok(());
+			}
+			Self::do_record_change(
+				RecordType::DiseaseProgression,
+				progression.progression_id,
+				b"next_appointment".to_vec(),
+				Some(old_appointment.encode()),
+				progression.next_appointment.encode(),
+				who.clone(),
+				OperationType::Update,
+			)
+		}
+
+		// Replace a bounded field of a record and record the change in the audit trail
+		fn do_update_field<S: Get<u32>>(
+			record_type: RecordType,
+			record_id: u32,
+			field_name: &[u8],
+			field: &mut BoundedVec<u8, S>,
+			new_value: Vec<u8>,
+			changed_by: &T::AccountId,
+			too_long: Error<T>,
+		) -> DispatchResult {
+			let bounded: BoundedVec<u8, S> = new_value.clone().try_into().map_err(|_| too_long)?;
+			Self::do_record_change(
+				record_type,
+				record_id,
+				field_name.to_vec(),
+				Some(field.to_vec()),
+				new_value,
+				changed_by.clone(),
+				OperationType::Update,
+			)?;
+			*field = bounded;
+			Ok(())
+		}
+
+		// Anchor the current version of a record into the proof chain. The anchored data hash
+		// commits to the record, its version and its content, so the same content saved twice
+		// still gets its own proof.
+		fn anchor_record(
+			who: &T::AccountId,
+			record_type: RecordType,
+			record_id: u32,
+			content_hash: T::Hash,
+		) -> DispatchResult {
+			let version = RecordProofs::<T>::decode_len(&record_type, record_id).unwrap_or(0) as u32;
+			let data_hash = T::Hashing::hash_of(&(record_type.clone(), record_id, version, content_hash));
+			let proof_hash = T::ProofAnchor::anchor(who, data_hash)?;
+
+			RecordProofs::<T>::try_mutate(&record_type, record_id, |proofs| proofs.try_push(proof_hash))
+				.map_err(|_| Error::<T>::TooManyProofsForRecord)?;
+
+			Self::deposit_event(Event::RecordAnchored { record_type, record_id, proof_hash });
+			Ok(())
+		}
+
+		// Internal helper function for recording changes
+		fn do_record_change(
+			record_type: RecordType,
+			record_id: u32,
+			field_name: Vec<u8>,
+			old_value: Option<Vec<u8>>,
+			new_value: Vec<u8>,
+			changed_by: T::AccountId,
+			operation: OperationType,
+		) -> DispatchResult {
+			Self::append_change(
+				record_type,
+				record_id,
+				field_name.try_into().map_err(|_| Error::<T>::ChangeValueTooLong)?,
+				old_value
+					.map(|value| value.try_into())
+					.transpose()
+					.map_err(|_| Error::<T>::ChangeValueTooLong)?,
+				new_value.try_into().map_err(|_| Error::<T>::ChangeValueTooLong)?,
+				changed_by,
+				operation,
+			);
+			Ok(())
+		}
+
+		// Record a change whose values are already bounded, so it cannot fail
+		fn append_change(
+			record_type: RecordType,
+			record_id: u32,
+			field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			changed_by: T::AccountId,
+			operation: OperationType,
+		) {
+			let change_id = Self::next_change_id();
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+
+			let mut change = ChangeHistory::<T> {
+				change_id,
+				record_type: record_type.clone(),
+				record_id,
+				field_name,
+				old_value,
+				new_value,
+				changed_by: changed_by.clone(),
+				changed_at: block_number,
+				operation,
+				prev_change_hash: AuditHead::<T>::get(),
+				change_hash: Default::default(),
+				redacted: false,
+			};
+			let change_hash = Self::compute_change_hash(&change);
+			change.change_hash = change_hash;
+
+			// Store the change
+			ChangeHistories::<T>::insert(change_id, change);
+			// Map record to its changes
+			RecordChanges::<T>::insert((record_type.clone(), record_id), change_id, ());
+			NextChangeId::<T>::put(change_id + 1);
+			AuditHead::<T>::put(change_hash);
+
+			Self::deposit_event(Event::ChangeRecorded {
+				change_id,
+				record_type,
+				record_id,
+				changed_by,
+				change_hash,
+			});
+		}
+
+		/// Hash of a change, committing to the hash of the change before it. Values enter through
+		/// their own hashes, so the chain still verifies once a value is redacted to its hash.
+		pub fn compute_change_hash(change: &ChangeHistory<T>) -> T::Hash {
+			let value_hash = |value: &[u8]| match change.redacted {
+				true => T::Hash::decode(&mut &value[..]).unwrap_or_default(),
+				false => T::Hashing::hash(value),
+			};
+			T::Hashing::hash_of(&(
+				change.prev_change_hash,
+				change.change_id,
+				&change.record_type,
+				change.record_id,
+				&change.field_name,
+				change.old_value.as_ref().map(|value| value_hash(value)),
+				value_hash(&change.new_value),
+				&change.changed_by,
+				change.changed_at,
+				&change.operation,
+			))
+		}
+
+		/// Verify the audit trail from change `from` up to change `to`, or up to the latest change
+		/// and the audit head if `to` is `None`, and return the number of changes checked.
+		pub fn verify_audit_trail(from: u32, to: Option<u32>) -> Result<u32, AuditError> {
+			let latest = Self::next_change_id().checked_sub(1);
+			let Some(to) = to.or(latest) else {
+				// Nothing recorded yet
+				return match AuditHead::<T>::get() == T::Hash::default() {
+					true => Ok(0),
+					false => Err(AuditError::HeadMismatch),
+				};
+			};
+			ensure!(from <= to && latest.is_some_and(|latest| to <= latest), AuditError::InvalidRange);
+			ensure!(to - from < T::MaxAuditRange::get(), AuditError::RangeTooLong);
+
+			let mut prev_change_hash = match from.checked_sub(1) {
+				Some(before) => ChangeHistories::<T>::get(before).ok_or(AuditError::Missing(before))?.change_hash,
+				None => T::Hash::default(),
+			};
+			for change_id in from..=to {
+				let change = ChangeHistories::<T>::get(change_id).ok_or(AuditError::Missing(change_id))?;
+				ensure!(
+					change.change_id == change_id && Self::compute_change_hash(&change) == change.change_hash,
+					AuditError::HashMismatch(change_id)
+				);
+				ensure!(change.prev_change_hash == prev_change_hash, AuditError::BrokenLink(change_id));
+				prev_change_hash = change.change_hash;
+			}
+
+			if Some(to) == latest {
+				ensure!(AuditHead::<T>::get() == prev_change_hash, AuditError::HeadMismatch);
+			}
+			Ok(to - from + 1)
+		}
+
+		// Add a patient to the active and demographic indexes
+		fn list_patient(patient: &PatientInfo<T>) {
+			let patient_id = patient.patient_id;
+			ActivePatients::<T>::insert(patient_id, ());
+			PatientsByGender::<T>::insert(&patient.gender, patient_id, ());
+			if let Some(birth_year) = Self::birth_year_of(&patient.date_of_birth) {
+				PatientsByBirthYear::<T>::insert(birth_year, patient_id, ());
+			}
+		}
+
+		// Remove a patient from the active and demographic indexes
+		fn unlist_patient(patient: &PatientInfo<T>) {
+			let patient_id = patient.patient_id;
+			ActivePatients::<T>::remove(patient_id);
+			PatientsByGender::<T>::remove(&patient.gender, patient_id);
+			if let Some(birth_year) = Self::birth_year_of(&patient.date_of_birth) {
+				PatientsByBirthYear::<T>::remove(birth_year, patient_id);
+			}
+		}
+
+		// Fold the hash of an erased item into the digest of an erasure
+		fn note_erased(request: &mut ErasureRequest<T>, item_hash: T::Hash) {
+			request.erased_items = request.erased_items.saturating_add(1);
+			request.digest = T::Hashing::hash_of(&(request.digest, item_hash));
+		}
+
+		// Carry out one step of the first approved erasure: erase one record, document, consent
+		// grant or care link of the patient, or redact one audit entry, or write the tombstone
+		fn erasure_step(patient_id: u32) {
+			let Some(mut request) = ErasureRequests::<T>::get(patient_id) else {
+				ErasureQueue::<T>::mutate(|queue| queue.retain(|&id| id != patient_id));
+				return;
+			};
+
+			if let Some((record_type, record_id, after)) = request.redacting.clone() {
+				request.redacting = Self::redact_next_change(&mut request, &record_type, record_id, after)
+					.map(|change_id| (record_type, record_id, Some(change_id)));
+			} else if request.stage == ErasureStage::Records {
+				match Self::remove_next_item(patient_id) {
+					Some(item) => {
+						if let Some(item_hash) = item.hash() {
+							Self::note_erased(&mut request, item_hash);
+						}
+					},
+					None => {
+						// The patient's own entries and those of their consent grants are redacted
+						// along with those of their records
+						PatientRecordIndex::<T>::insert(patient_id, (RecordType::Patient, patient_id), ());
+						PatientRecordIndex::<T>::insert(patient_id, (RecordType::Consent, patient_id), ());
+						request.stage = ErasureStage::Audit;
+					},
+				}
+			} else if let Some(key) = PatientRecordIndex::<T>::iter_key_prefix(patient_id).next() {
+				PatientRecordIndex::<T>::remove(patient_id, &key);
+				request.redacting = Some((key.0, key.1, None));
+			} else {
+				return Self::finish_erasure(patient_id, request);
+			}
+			ErasureRequests::<T>::insert(patient_id, request);
+		}
+
+		// Redact the next audit entry of a record after change `after`, replacing its values by
+		// their hashes, and return its id, or `None` once every entry has been redacted
+		fn redact_next_change(
+			request: &mut ErasureRequest<T>,
+			record_type: &RecordType,
+			record_id: u32,
+			after: Option<u32>,
+		) -> Option<u32> {
+			let key = (record_type.clone(), record_id);
+			let change_id = match after {
+				Some(after) => RecordChanges::<T>::iter_key_prefix_from(&key, RecordChanges::<T>::hashed_key_for(&key, after))
+					.next()?,
+				None => RecordChanges::<T>::iter_key_prefix(&key).next()?,
+			};
+
+			ChangeHistories::<T>::mutate(change_id, |change_opt| {
+				let Some(change) = change_opt.as_mut().filter(|change| !change.redacted) else { return };
+				let redact = |value: &[u8]| BoundedVec::truncate_from(T::Hashing::hash(value).encode());
+				change.old_value = change.old_value.as_ref().map(|value| redact(value));
+				change.new_value = redact(&change.new_value);
+				change.redacted = true;
+				Self::note_erased(request, change.change_hash);
+			});
+			Some(change_id)
+		}
+
+		// Carry out one step of the first queued deletion: delete one record, document, consent
+		// grant or care link of the patient on behalf of `deleted_by`, or wrap up once none is left
+		fn deletion_step(patient_id: u32, deleted_by: T::AccountId) {
+			let Some(item) = Self::remove_next_item(patient_id) else {
+				DeletionQueue::<T>::mutate(|queue| queue.retain(|(id, _)| *id != patient_id));
+				Self::remove_patient_lists(patient_id);
+				Self::deposit_event(Event::PatientDeletionCompleted { patient_id });
+				return;
+			};
+
+			// Recorded as if deleted by hand. The values fit their bounds, as checked by the
+			// integrity test, so the deletion cannot be stalled by its own audit entry.
+			match item {
+				RemovedItem::Record(record_type, record_id, _) => {
+					Self::append_change(
+						record_type.clone(),
+						record_id,
+						BoundedVec::defensive_truncate_from(b"deleted".to_vec()),
+						Some(BoundedVec::defensive_truncate_from(b"active".to_vec())),
+						BoundedVec::defensive_truncate_from(b"deleted".to_vec()),
+						deleted_by,
+						OperationType::Delete,
+					);
+					Self::deposit_event(match record_type {
+						RecordType::ClinicalTest => Event::ClinicalTestDeleted { test_id: record_id },
+						RecordType::DiseaseProgression => Event::DiseaseProgressionDeleted { progression_id: record_id },
+						_ => Event::MedicalRecordDeleted { record_id },
+					});
+				},
+				RemovedItem::Consent((grantee, record_type), grant) => {
+					Self::append_change(
+						RecordType::Consent,
+						patient_id,
+						BoundedVec::defensive_truncate_from(b"consent".to_vec()),
+						Some(BoundedVec::defensive_truncate_from(
+							(&grantee, &record_type, grant.scope, grant.expires_at).encode(),
+						)),
+						BoundedVec::defensive_truncate_from(b"revoked".to_vec()),
+						deleted_by,
+						OperationType::Delete,
+					);
+					Self::deposit_event(Event::ConsentRevoked { patient_id, grantee, record_type });
+				},
+				RemovedItem::Other(_) => {},
+			}
+		}
+
+		// Remove the next clinical test, disease progression, medical record, document, consent
+		// grant or care link of a patient, keeping every secondary index in step, or return `None`
+		// once nothing is left. The audit trail and the patient's record index are left alone.
+		fn remove_next_item(patient_id: u32) -> Option<RemovedItem<T>> {
+			let item = if let Some(test_id) = PatientClinicalTests::<T>::mutate(patient_id, |tests| tests.pop()) {
+				let test = ClinicalTests::<T>::take(test_id);
+				RemovedItem::Record(RecordType::ClinicalTest, test_id, test.map(|test| T::Hashing::hash_of(&test)))
+			} else if let Some(progression_id) =
+				PatientDiseaseProgressions::<T>::mutate(patient_id, |progressions| progressions.pop())
+			{
+				let progression = DiseaseProgressions::<T>::take(progression_id);
+				if let Some(progression) = &progression {
+					Self::cancel_follow_up(progression);
+				}
+				RemovedItem::Record(
+					RecordType::DiseaseProgression,
+					progression_id,
+					progression.map(|progression| T::Hashing::hash_of(&progression)),
+				)
+			} else if let Some(record_id) = PatientMedicalRecords::<T>::mutate(patient_id, |records| records.pop()) {
+				let record = MedicalRecords::<T>::take(record_id);
+				RemovedItem::Record(RecordType::MedicalRecord, record_id, record.map(|record| T::Hashing::hash_of(&record)))
+			} else if let Some(content_hash) = PatientDocuments::<T>::iter_key_prefix(patient_id).next() {
+				PatientDocuments::<T>::remove(patient_id, content_hash);
+				Documents::<T>::remove(content_hash);
+				// Nodes with offchain indexing enabled drop their copy of the content as well
+				sp_io::offchain_index::clear(&data_pointer::document_key(&content_hash));
+				RemovedItem::Other(T::Hashing::hash_of(&content_hash))
+			} else if let Some(key) = Consents::<T>::iter_key_prefix(patient_id).next() {
+				let grant = Consents::<T>::take(patient_id, &key)?;
+				RemovedItem::Consent(key, grant)
+			} else if let Some(doctor) = PatientDoctors::<T>::mutate(patient_id, |doctors| doctors.pop()) {
+				DoctorPatients::<T>::mutate(&doctor, |patients| patients.retain(|&id| id != patient_id));
+				RemovedItem::Other(T::Hashing::hash_of(&doctor))
+			} else {
+				return None;
+			};
+			Some(item)
+		}
+
+		// Cancel the upcoming follow-up of a progression that is going away. Nobody is left to see
+		// it, so it is cancelled in the name of the doctor it is booked with, and a failure to
+		// cancel it must not stall a deletion or an erasure.
+		fn cancel_follow_up(progression: &DiseaseProgression<T>) {
+			if let Some(appointment) = progression.next_appointment.and_then(T::Appointments::upcoming) {
+				let _ = T::Appointments::cancel(&appointment.doctor_id, appointment.appointment_id);
+			}
+		}
+
+		// Drop the emptied record lists and care links of a patient
+		fn remove_patient_lists(patient_id: u32) {
+			PatientClinicalTests::<T>::remove(patient_id);
+			PatientDiseaseProgressions::<T>::remove(patient_id);
+			PatientMedicalRecords::<T>::remove(patient_id);
+			PatientDoctors::<T>::remove(patient_id);
+		}
+
+		// Drop what is left of an erased patient, leaving a tombstone chained into the audit trail
+		fn finish_erasure(patient_id: u32, request: ErasureRequest<T>) {
+			let block_number: BlockNumberFor<T> = <frame_system::Pallet<T>>::block_number();
+
+			ErasureRequests::<T>::remove(patient_id);
+			ErasureQueue::<T>::mutate(|queue| queue.retain(|&id| id != patient_id));
+			Self::remove_patient_lists(patient_id);
+			PatientAccounts::<T>::remove(patient_id);
+			PatientGuardians::<T>::remove(patient_id);
+
+			// Both values fit their bounds, as checked by the integrity test
+			Self::append_change(
+				RecordType::Patient,
+				patient_id,
+				BoundedVec::defensive_truncate_from(b"erased".to_vec()),
+				None,
+				BoundedVec::defensive_truncate_from(request.digest.encode()),
+				request.requested_by.clone(),
+				OperationType::Delete,
+			);
+			ErasureTombstones::<T>::insert(
+				patient_id,
+				ErasureTombstone::<T> {
+					requested_by: request.requested_by,
+					requested_at: request.requested_at,
+					approved_at: request.approved_at.unwrap_or(block_number),
+					erased_at: block_number,
+					erased_items: request.erased_items,
+					digest: request.digest,
+				},
+			);
+
+			Self::deposit_event(Event::PatientErased {
+				patient_id,
+				erased_items: request.erased_items,
+				digest: request.digest,
+			});
+		}
+
+		// Check that every secondary index agrees with what it indexes, and that nothing but their
+		// audit trail, record index and account links is left of deleted patients once their
+		// deletion or erasure has been carried out
+		#[cfg(any(feature = "try-runtime", test))]
+		pub fn do_try_state() -> Result<(), TryRuntimeError> {
+			let pending: Vec<u32> = ErasureQueue::<T>::get()
+				.into_iter()
+				.chain(DeletionQueue::<T>::get().into_iter().map(|(patient_id, _)| patient_id))
+				.collect();
+			let is_known = |patient_id: u32| Patients::<T>::contains_key(patient_id) || pending.contains(&patient_id);
+
+			// Patients and their active, demographic and name indexes
+			ensure!(
+				ActivePatients::<T>::count() as usize == Patients::<T>::iter_keys().count(),
+				"active patient count is off"
+			);
+			for (patient_id, patient) in Patients::<T>::iter() {
+				ensure!(patient.patient_id == patient_id, "patient stored under another id");
+				ensure!(patient_id < NextPatientId::<T>::get(), "patient id not allocated yet");
+				ensure!(!pending.contains(&patient_id), "patient still listed while being removed");
+				ensure!(ActivePatients::<T>::contains_key(patient_id), "patient missing from the active index");
+				ensure!(
+					PatientsByGender::<T>::contains_key(&patient.gender, patient_id),
+					"patient missing from the gender index"
+				);
+				if let Some(birth_year) = Self::birth_year_of(&patient.date_of_birth) {
+					ensure!(
+						PatientsByBirthYear::<T>::contains_key(birth_year, patient_id),
+						"patient missing from the birth year index"
+					);
+				}
+			}
+			for patient_id in ActivePatients::<T>::iter_keys() {
+				ensure!(Patients::<T>::contains_key(patient_id), "active index lists a removed patient");
+			}
+			for (gender, id) in PatientsByGender::<T>::iter_keys() {
+				ensure!(
+					Patients::<T>::get(id).is_some_and(|patient| patient.gender == gender),
+					"gender index lists a removed or moved patient"
+				);
+			}
+			for (birth_year, id) in PatientsByBirthYear::<T>::iter_keys() {
+				ensure!(
+					Patients::<T>::get(id)
+						.is_some_and(|patient| Self::birth_year_of(&patient.date_of_birth) == Some(birth_year)),
+					"birth year index lists a removed or moved patient"
+				);
+			}
+			for (patient_id, name_index) in PatientNameIndex::<T>::iter() {
+				ensure!(Patients::<T>::contains_key(patient_id), "name index lists a removed patient");
+				ensure!(
+					PatientsByName::<T>::get(name_index).is_some_and(|ids| ids.contains(&patient_id)),
+					"patient missing from the name index"
+				);
+			}
+			for (name_index, patient_ids) in PatientsByName::<T>::iter() {
+				ensure!(!patient_ids.is_empty(), "empty name index entry");
+				ensure!(
+					patient_ids.iter().all(|&id| PatientNameIndex::<T>::get(id) == Some(name_index)),
+					"name index entry without its reverse mapping"
+				);
+			}
+
+			// Records belong to a patient that exists or is being removed, and are listed for them
+			for (test_id, test) in ClinicalTests::<T>::iter() {
+				ensure!(test.test_id == test_id && test_id < NextTestId::<T>::get(), "clinical test stored under another id");
+				ensure!(is_known(test.patient_id), "clinical test of a deleted patient");
+				ensure!(
+					PatientClinicalTests::<T>::get(test.patient_id).contains(&test_id),
+					"clinical test missing from its patient's list"
+				);
+				ensure!(
+					PatientRecordIndex::<T>::contains_key(test.patient_id, (RecordType::ClinicalTest, test_id)),
+					"clinical test missing from its patient's record index"
+				);
+			}
+			for (patient_id, test_ids) in PatientClinicalTests::<T>::iter() {
+				ensure!(
+					test_ids.iter().all(|&id| ClinicalTests::<T>::get(id).is_some_and(|test| test.patient_id == patient_id)),
+					"patient lists a deleted clinical test or one of another patient"
+				);
+			}
+			for (progression_id, progression) in DiseaseProgressions::<T>::iter() {
+				ensure!(
+					progression.progression_id == progression_id && progression_id < NextProgressionId::<T>::get(),
+					"disease progression stored under another id"
+				);
+				ensure!(is_known(progression.patient_id), "disease progression of a deleted patient");
+				ensure!(
+					PatientDiseaseProgressions::<T>::get(progression.patient_id).contains(&progression_id),
+					"disease progression missing from its patient's list"
+				);
+				ensure!(
+					PatientRecordIndex::<T>::contains_key(
+						progression.patient_id,
+						(RecordType::DiseaseProgression, progression_id)
+					),
+					"disease progression missing from its patient's record index"
+				);
+			}
+			for (patient_id, progression_ids) in PatientDiseaseProgressions::<T>::iter() {
+				ensure!(
+					progression_ids.iter().all(|&id| {
+						DiseaseProgressions::<T>::get(id).is_some_and(|progression| progression.patient_id == patient_id)
+					}),
+					"patient lists a deleted disease progression or one of another patient"
+				);
+			}
+			for (record_id, record) in MedicalRecords::<T>::iter() {
+				ensure!(
+					record.record_id == record_id && record_id < NextRecordId::<T>::get(),
+					"medical record stored under another id"
+				);
+				ensure!(is_known(record.patient_id), "medical record of a deleted patient");
+				ensure!(
+					PatientMedicalRecords::<T>::get(record.patient_id).contains(&record_id),
+					"medical record missing from its patient's list"
+				);
+				ensure!(
+					PatientRecordIndex::<T>::contains_key(record.patient_id, (RecordType::MedicalRecord, record_id)),
+					"medical record missing from its patient's record index"
+				);
+			}
+			for (patient_id, record_ids) in PatientMedicalRecords::<T>::iter() {
+				ensure!(
+					record_ids.iter().all(|&id| MedicalRecords::<T>::get(id).is_some_and(|record| record.patient_id == patient_id)),
+					"patient lists a deleted medical record or one of another patient"
+				);
+			}
+
+			// Documents are indexed by the patient they were uploaded for
+			for (content_hash, document) in Documents::<T>::iter() {
+				ensure!(is_known(document.patient_id), "document of a deleted patient");
+				ensure!(
+					PatientDocuments::<T>::contains_key(document.patient_id, content_hash),
+					"document missing from its patient's index"
+				);
+			}
+			for (patient_id, content_hash) in PatientDocuments::<T>::iter_keys() {
+				ensure!(
+					Documents::<T>::get(content_hash).is_some_and(|document| document.patient_id == patient_id),
+					"patient lists a removed document or one of another patient"
+				);
+			}
+
+			// Care links go both ways and follow consent grants; a removal drops the grants first
+			for (patient_id, (grantee, _)) in Consents::<T>::iter_keys() {
+				ensure!(is_known(patient_id), "consent grant of a deleted patient");
+				ensure!(
+					PatientDoctors::<T>::get(patient_id).contains(&grantee),
+					"consent grant without a care link"
+				);
+			}
+			for (patient_id, doctors) in PatientDoctors::<T>::iter() {
+				ensure!(doctors.is_empty() || is_known(patient_id), "care links of a deleted patient");
+				for doctor in doctors {
+					ensure!(
+						DoctorPatients::<T>::get(&doctor).contains(&patient_id),
+						"care link missing from the doctor's list"
+					);
+					ensure!(
+						pending.contains(&patient_id) ||
+							Consents::<T>::iter_key_prefix(patient_id).any(|(grantee, _)| grantee == doctor),
+						"care link without a consent grant"
+					);
+				}
+			}
+			for (doctor, patient_ids) in DoctorPatients::<T>::iter() {
+				ensure!(
+					patient_ids.iter().all(|&id| PatientDoctors::<T>::get(id).contains(&doctor)),
+					"doctor lists a patient without the reverse care link"
+				);
+			}
+
+			// Data keys go with the patient; account links and the record index stay until erasure
+			for patient_id in PatientDataKeys::<T>::iter_keys() {
+				ensure!(Patients::<T>::contains_key(patient_id), "data keys of a removed patient");
+			}
+			for patient_id in PatientAccounts::<T>::iter_keys().chain(PatientGuardians::<T>::iter_keys()) {
+				ensure!(patient_id < NextPatientId::<T>::get(), "account linked to an unallocated patient id");
+			}
+			for (patient_id, _) in PatientRecordIndex::<T>::iter_keys() {
+				ensure!(patient_id < NextPatientId::<T>::get(), "record index of an unallocated patient id");
+			}
+
+			// Approved erasures, and only those, are queued
+			for (patient_id, request) in ErasureRequests::<T>::iter() {
+				ensure!(
+					(request.stage != ErasureStage::Requested) == ErasureQueue::<T>::get().contains(&patient_id),
+					"approved erasure not queued or queued erasure not approved"
+				);
+			}
+			for patient_id in ErasureQueue::<T>::get() {
+				ensure!(ErasureRequests::<T>::contains_key(patient_id), "queued erasure without a request");
+			}
+
+			Ok(())
+		}
+
+		// Add a patient to the blinded name index
+		fn index_name(patient_id: u32, name_index: NameIndex) -> DispatchResult {
+			PatientsByName::<T>::try_mutate(name_index, |ids_opt| -> DispatchResult {
+				ids_opt
+					.get_or_insert_with(BoundedVec::new)
+					.try_push(patient_id)
+					.map_err(|_| Error::<T>::TooManyPatientsWithName)?;
+				Ok(())
+			})?;
+			PatientNameIndex::<T>::insert(patient_id, name_index);
+			Ok(())
+		}
+
+		// Remove a patient from the blinded name index
+		fn unindex_name(patient_id: u32) {
+			let Some(name_index) = PatientNameIndex::<T>::take(patient_id) else { return };
+			PatientsByName::<T>::mutate(name_index, |ids_opt| {
+				if let Some(ids) = ids_opt {
+					ids.retain(|&id| id != patient_id);
+					if ids.is_empty() {
+						*ids_opt = None;
+					}
+				}
+			});
+		}
+
+		// Query function to get the patient id indexed under a blinded name
+		pub fn get_patient_id_by_name(name_index: &NameIndex) -> Option<u32> {
+			PatientsByName::<T>::get(name_index).and_then(|ids| ids.first().copied())
+		}
+
+		// Check whether a blinded name is already indexed
+		pub fn patient_name_exists(name_index: &NameIndex) -> bool {
+			PatientsByName::<T>::get(name_index).is_some_and(|ids| !ids.is_empty())
+		}
+
+		// Query function to get the ids of the patients of a gender
+		pub fn patients_by_gender(gender: BoundedVec<u8, T::MaxGenderLength>) -> Vec<u32> {
+			PatientsByGender::<T>::iter_key_prefix(gender).collect()
+		}
+
+		// Query function to get the ids of the patients born in a year
+		pub fn patients_by_birth_year(birth_year: u32) -> Vec<u32> {
+			PatientsByBirthYear::<T>::iter_key_prefix(birth_year).collect()
+		}
+
+		// Number of active patients
+		pub fn active_patient_count() -> u32 {
+			ActivePatients::<T>::count()
+		}
+
+		// Read up to `limit` active patient ids after `cursor` (capped at `MaxPageSize`).
+		// Patients are returned in storage order, and the returned cursor is `None` once the
+		// index has been fully walked.
+		fn active_patient_ids(cursor: Option<u32>, limit: u32) -> (Vec<u32>, Option<u32>) {
+			let limit = limit.min(T::MaxPageSize::get()) as usize;
+			let mut iter = match cursor {
+				Some(patient_id) => ActivePatients::<T>::iter_from(ActivePatients::<T>::hashed_key_for(patient_id)),
+				None => ActivePatients::<T>::iter(),
+			};
+
+			let patient_ids: Vec<u32> = iter.by_ref().take(limit).map(|(patient_id, _)| patient_id).collect();
+			let next_cursor = match iter.next() {
+				Some(_) => patient_ids.last().copied(),
+				None => None,
+			};
+			(patient_ids, next_cursor)
+		}
+
+		// Query function to get a page of active patients
+		pub fn get_all_patients(cursor: Option<u32>, limit: u32) -> Page<PatientInfo<T>> {
+			let (patient_ids, next_cursor) = Self::active_patient_ids(cursor, limit);
+			Page {
+				items: patient_ids.into_iter().filter_map(Patients::<T>::get).collect(),
+				next_cursor,
+			}
+		}
+
+		// Query function to get all clinical tests for a patient
+		pub fn get_patient_clinical_tests(patient_id: u32) -> Vec<ClinicalTest<T>> {
+			PatientClinicalTests::<T>::get(patient_id)
+				.iter()
+				.filter_map(|&test_id| ClinicalTests::<T>::get(test_id))
+				.collect()
+		}
+
+		// Query function to get all disease progressions for a patient
+		pub fn get_patient_disease_progressions(patient_id: u32) -> Vec<DiseaseProgression<T>> {
+			PatientDiseaseProgressions::<T>::get(patient_id)
+				.iter()
+				.filter_map(|&progression_id| DiseaseProgressions::<T>::get(progression_id))
+				.collect()
+		}
+
+		// The upcoming follow-up appointment of a progression
+		pub fn next_appointment_of(
+			progression: &DiseaseProgression<T>,
+		) -> Option<ScheduledAppointment<T::AccountId>> {
+			progression.next_appointment.and_then(T::Appointments::upcoming)
+		}
+
+		// Query function to get all medical records for a patient
+		pub fn get_patient_medical_records(patient_id: u32) -> Vec<MedicalRecord<T>> {
+			PatientMedicalRecords::<T>::get(patient_id)
 				.iter()
 				.filter_map(|&record_id| MedicalRecords::<T>::get(record_id))
 				.collect()
 		}
 
-		// Query function to get all patients of a doctor
+		// Query function to get all patients of a doctor. Care links stay in storage after a
+		// grant expires, so only patients the doctor still holds an unexpired grant for are listed
 		pub fn get_doctor_patients(doctor_id: &T::AccountId) -> Vec<PatientInfo<T>> {
 			DoctorPatients::<T>::get(doctor_id)
 				.iter()
+				.filter(|&&patient_id| Self::has_active_grant(patient_id, doctor_id))
 				.filter_map(|&patient_id| Patients::<T>::get(patient_id))
 				.collect()
 		}
@@ -1227,84 +3631,104 @@ pub mod pallet {
 			timeline
 		}
 
-		// Query function for emergency contact lookup
-		pub fn get_emergency_contacts() -> Vec<(u32, Vec<u8>, Vec<u8>)> {
-			ActivePatients::<T>::get()
-				.iter()
-				.filter_map(|&patient_id| {
+		// Query function for emergency contact lookup, one page of active patients at a time
+		pub fn get_emergency_contacts(cursor: Option<u32>, limit: u32) -> Page<(u32, Vec<u8>, Vec<u8>)> {
+			let (patient_ids, next_cursor) = Self::active_patient_ids(cursor, limit);
+			let items = patient_ids
+				.into_iter()
+				.filter_map(|patient_id| {
 					Patients::<T>::get(patient_id).map(|patient| {
-						(patient_id, patient.patient_name, patient.emergency_contact)
+						(patient_id, patient.patient_name.into_inner(), patient.emergency_contact.into_inner())
 					})
 				})
-				.collect()
+				.collect();
+			Page { items, next_cursor }
 		}
 
-		// Query function for patients with upcoming appointments
-		pub fn get_patients_with_appointments() -> Vec<(u32, Vec<u8>, Vec<u8>)> {
-			ActivePatients::<T>::get()
-				.iter()
-				.filter_map(|&patient_id| {
-					let progressions = Self::get_patient_disease_progressions(patient_id);
-					let upcoming = progressions
+		// Query function for patients with upcoming appointments, one page of active patients at
+		// a time, each with their earliest follow-up. A page can hold fewer items than `limit`
+		// since patients without an appointment are skipped; keep following `next_cursor` until
+		// it is `None`.
+		pub fn get_patients_with_appointments(
+			cursor: Option<u32>,
+			limit: u32,
+		) -> Page<(u32, Vec<u8>, ScheduledAppointment<T::AccountId>)> {
+			let (patient_ids, next_cursor) = Self::active_patient_ids(cursor, limit);
+			let items = patient_ids
+				.into_iter()
+				.filter_map(|patient_id| {
+					let upcoming = Self::get_patient_disease_progressions(patient_id)
 						.iter()
-						.filter(|p| !p.next_appointment.is_empty())
-						.last();
-					
-					if let Some(progression) = upcoming {
-						Patients::<T>::get(patient_id).map(|patient| {
-							(patient_id, patient.patient_name, progression.next_appointment.clone())
-						})
-					} else {
-						None
-					}
+						.filter_map(Self::next_appointment_of)
+						.min_by_key(|appointment| appointment.scheduled_time)?;
+
+					Patients::<T>::get(patient_id)
+						.map(|patient| (patient_id, patient.patient_name.into_inner(), upcoming))
 				})
-				.collect()
+				.collect();
+			Page { items, next_cursor }
 		}
 
-		// Function to get all changes for a specific record
+		// Function to get all changes for a specific record, oldest first
 		pub fn get_record_history(record_type: RecordType, record_id: u32) -> Vec<ChangeHistory<T>> {
-			RecordChanges::<T>::iter_prefix((record_type, record_id))
+			let mut history: Vec<_> = RecordChanges::<T>::iter_prefix((record_type, record_id))
 				.filter_map(|(change_id, _)| ChangeHistories::<T>::get(change_id))
-				.collect()
+				.collect();
+			history.sort_by_key(|change| change.change_id);
+			history
 		}
 
-		// Function to get changes by a specific user
-		pub fn get_changes_by_user(user: &T::AccountId) -> Vec<ChangeHistory<T>> {
-			ChangeHistories::<T>::iter()
-				.filter_map(|(_, change)| {
-					if &change.changed_by == user {
-						Some(change)
-					} else {
-						None
-					}
-				})
-				.collect()
+		// Read up to `limit` change ids after `cursor` (capped at `MaxPageSize`), oldest first.
+		// The returned cursor is `None` once the latest change has been read.
+		fn change_ids(cursor: Option<u32>, limit: u32) -> (core::ops::Range<u32>, Option<u32>) {
+			let next_change_id = Self::next_change_id();
+			let start = cursor.map_or(0, |change_id| change_id.saturating_add(1)).min(next_change_id);
+			let end = start.saturating_add(limit.min(T::MaxPageSize::get())).min(next_change_id);
+			let next_cursor = if end < next_change_id { end.checked_sub(1) } else { None };
+			(start..end, next_cursor)
+		}
+
+		// Function to get changes by a specific user, one page of the change history at a time.
+		// A page can hold fewer items than `limit` since changes by other accounts are skipped;
+		// keep following `next_cursor` until it is `None`.
+		pub fn get_changes_by_user(
+			user: &T::AccountId,
+			cursor: Option<u32>,
+			limit: u32,
+		) -> Page<ChangeHistory<T>> {
+			let (change_ids, next_cursor) = Self::change_ids(cursor, limit);
+			let items = change_ids
+				.filter_map(ChangeHistories::<T>::get)
+				.filter(|change| &change.changed_by == user)
+				.collect();
+			Page { items, next_cursor }
 		}
 
-		// Function to get changes within a time range
+		// Function to get changes within a time range, one page of the change history at a time.
+		// A page can hold fewer items than `limit` since changes outside the range are skipped;
+		// keep following `next_cursor` until it is `None`.
 		pub fn get_changes_in_range(
 			start_block: BlockNumberFor<T>,
 			end_block: BlockNumberFor<T>,
-		) -> Vec<ChangeHistory<T>> {
-			ChangeHistories::<T>::iter()
-				.filter_map(|(_, change)| {
-					if change.changed_at >= start_block && change.changed_at <= end_block {
-						Some(change)
-					} else {
-						None
-					}
-				})
-				.collect()
+			cursor: Option<u32>,
+			limit: u32,
+		) -> Page<ChangeHistory<T>> {
+			let (change_ids, next_cursor) = Self::change_ids(cursor, limit);
+			let items = change_ids
+				.filter_map(ChangeHistories::<T>::get)
+				.filter(|change| change.changed_at >= start_block && change.changed_at <= end_block)
+				.collect();
+			Page { items, next_cursor }
 		}
 
-		// Function to get latest changes (last N changes)
-		pub fn get_latest_changes(limit: u32) -> Vec<ChangeHistory<T>> {
-			let current_change_id = Self::next_change_id();
-			let start_id = if current_change_id > limit { current_change_id - limit } else { 0 };
-			
-			(start_id..current_change_id)
-				.filter_map(|id| ChangeHistories::<T>::get(id))
-				.collect()
+		// Function to get latest changes, newest first, one page at a time. Pass in the returned
+		// cursor to get the changes made before the page.
+		pub fn get_latest_changes(cursor: Option<u32>, limit: u32) -> Page<ChangeHistory<T>> {
+			let end = cursor.unwrap_or_else(Self::next_change_id).min(Self::next_change_id());
+			let start = end.saturating_sub(limit.min(T::MaxPageSize::get()));
+			let items = (start..end).rev().filter_map(ChangeHistories::<T>::get).collect();
+			let next_cursor = if start > 0 && start < end { Some(start) } else { None };
+			Page { items, next_cursor }
 		}
 	}
 }
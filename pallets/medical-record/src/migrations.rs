@@ -0,0 +1,2090 @@
+//! Storage migrations for pallet-medical-record.
+//!
+//! Every migration is a [`SteppedMigration`], run by the runtime's multi-block migrator over as
+//! many blocks as it needs, one after the other in storage version order. A migration only
+//! changes storage while the on-chain storage version is the one it migrates from, and sets the
+//! version it migrates to once it is done. It converts between its own copies of the layouts
+//! involved, so it keeps working as the pallet's types evolve.
+
+use super::*;
+use codec::FullCodec;
+use frame_support::{
+	migrations::{MigrationId, SteppedMigration, SteppedMigrationError},
+	pallet_prelude::*,
+	storage::{IterableStorageMap, StoragePrefixedMap},
+	traits::{GetStorageVersion, StorageVersion},
+	weights::WeightMeter,
+	Blake2_128, StorageHasher,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+
+const LOG_TARGET: &str = "runtime::medical-record";
+
+/// The pallet part of the identifiers of the migrations of this pallet.
+pub const PALLET_MIGRATIONS_ID: &[u8; 21] = b"pallet-medical-record";
+
+// Identifier of the migration from storage version `from` to `to`
+fn migration_id(from: u8, to: u8) -> MigrationId<21> {
+	MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: from, version_to: to }
+}
+
+// Start a step of the migration from storage version `from`. The meter has to pay for reading
+// and updating the storage version, and for at least one entry of `weight`. Returns whether the
+// migration still has to run.
+fn begin_step<T: Config>(
+	from: u16,
+	meter: &mut WeightMeter,
+	weight: Weight,
+) -> Result<bool, SteppedMigrationError> {
+	let version = T::DbWeight::get().reads_writes(1, 1);
+	let required = version.saturating_add(weight);
+	if !meter.can_consume(required) {
+		return Err(SteppedMigrationError::InsufficientWeight { required });
+	}
+	meter.consume(version);
+
+	if Pallet::<T>::on_chain_storage_version() != from {
+		log::info!(target: LOG_TARGET, "storage version is not {}, skipping the migration", from);
+		return Ok(false);
+	}
+	Ok(true)
+}
+
+/// How far a walk over the entries of a storage map got.
+enum Walked<K> {
+	/// Every entry has been handled.
+	Done,
+	/// The meter ran out; the walk goes on after the given key.
+	Paused(Option<K>),
+}
+
+// Hand `entries` to `f` one by one, as long as the meter can pay `weight` for each
+fn walk_entries<K, V, C>(
+	mut entries: impl Iterator<Item = (K, V)>,
+	mut last: Option<C>,
+	meter: &mut WeightMeter,
+	weight: Weight,
+	cursor_of: impl Fn(&K) -> C,
+	mut f: impl FnMut(K, V),
+) -> Walked<C> {
+	loop {
+		if meter.try_consume(weight).is_err() {
+			return Walked::Paused(last);
+		}
+		let Some((key, value)) = entries.next() else { return Walked::Done };
+		last = Some(cursor_of(&key));
+		f(key, value);
+	}
+}
+
+// Hand the entries of `Map` after the key `last` to `f`, as long as the meter can pay `weight`
+// for each
+fn walk<Map, K, V>(
+	last: Option<K>,
+	meter: &mut WeightMeter,
+	weight: Weight,
+	f: impl FnMut(K, V),
+) -> Walked<K>
+where
+	Map: IterableStorageMap<K, V> + frame_support::storage::StorageMap<K, V>,
+	K: FullCodec + Clone,
+	V: FullCodec,
+{
+	let entries = match &last {
+		Some(key) => Map::iter_from(Map::hashed_key_for(key)),
+		None => Map::iter(),
+	};
+	walk_entries(entries, last, meter, weight, K::clone, f)
+}
+
+// Same as `walk`, for legacy maps keyed by text of any length, which may not fit in a cursor.
+// The cursor is the hash of the last key instead, and the walk goes on with the keys hashed
+// after it.
+fn walk_text<Map, V>(
+	last: Option<[u8; 16]>,
+	meter: &mut WeightMeter,
+	weight: Weight,
+	f: impl FnMut(Vec<u8>, V),
+) -> Walked<[u8; 16]>
+where
+	Map: IterableStorageMap<Vec<u8>, V> + StoragePrefixedMap<V>,
+	V: FullCodec,
+{
+	let mut start = Map::final_prefix().to_vec();
+	if let Some(hash) = last {
+		let Some(next) = u128::from_be_bytes(hash).checked_add(1) else { return Walked::Done };
+		start.extend(next.to_be_bytes());
+	}
+	walk_entries(
+		Map::iter_from(start),
+		last,
+		meter,
+		weight,
+		|key| key.using_encoded(Blake2_128::hash),
+		f,
+	)
+}
+
+/// Migration from unbounded `Vec<u8>` storage (v0) to `BoundedVec` storage (v1).
+///
+/// `Vec<u8>` and `BoundedVec<u8, _>` share the same SCALE encoding, so entries that already fit
+/// their new bound are carried over unchanged. Oversize legacy entries are handled as follows:
+///
+/// - text fields of patients, clinical tests, disease progressions, medical records and change
+///   history entries are truncated to their bound;
+/// - the name index is rebuilt under the truncated keys;
+/// - index lists (per-patient record lists, doctor/patient links) keep their first entries up to
+///   the bound.
+///
+/// The demographic indexes go from one list per gender or birth year to one entry per patient,
+/// so they hold any number of patients. The legacy lists are dropped first and the indexes are
+/// rebuilt from the migrated patients, under their truncated genders.
+///
+/// Every truncation is logged with the affected key. `ActivePatients` is left as is and moved to
+/// its own map by [`v2`].
+pub mod v1 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v0 {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct PatientInfo<T: Config> {
+			pub patient_id: u32,
+			pub patient_name: Vec<u8>,
+			pub date_of_birth: Vec<u8>,
+			pub gender: Vec<u8>,
+			pub address: Vec<u8>,
+			pub phone: Vec<u8>,
+			pub emergency_contact: Vec<u8>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ClinicalTest<T: Config> {
+			pub test_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub test_type: Vec<u8>,
+			pub test_date: Vec<u8>,
+			pub result: Vec<u8>,
+			pub notes: Vec<u8>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: Vec<u8>,
+			pub symptoms: Vec<u8>,
+			pub diagnosis: Vec<u8>,
+			pub treatment: Vec<u8>,
+			pub prescription: Vec<u8>,
+			pub next_appointment: Vec<u8>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct MedicalRecord<T: Config> {
+			pub record_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub record_hash: T::Hash,
+			pub data_pointer: Option<Vec<u8>>,
+			pub diagnosis: Vec<u8>,
+			pub treatment: Vec<u8>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: Vec<u8>,
+			pub old_value: Option<Vec<u8>>,
+			pub new_value: Vec<u8>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+		}
+
+		#[frame_support::storage_alias]
+		pub type Patients<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, PatientInfo<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type ClinicalTests<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ClinicalTest<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type MedicalRecords<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, MedicalRecord<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientNameToId<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, Vec<u8>, Vec<u32>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientsByGender<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, Vec<u8>, Vec<u32>, ValueQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientsByBirthYear<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, Vec<u32>, ValueQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientClinicalTests<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, Vec<u32>, ValueQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientDiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, Vec<u32>, ValueQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientMedicalRecords<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, Vec<u32>, ValueQuery>;
+
+		#[frame_support::storage_alias]
+		pub type DoctorPatients<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			Vec<u32>,
+			ValueQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientDoctors<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			u32,
+			Vec<<T as frame_system::Config>::AccountId>,
+			ValueQuery,
+		>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct PatientInfo<T: Config> {
+			pub patient_id: u32,
+			pub patient_name: BoundedVec<u8, T::MaxNameLength>,
+			pub date_of_birth: BoundedVec<u8, T::MaxDateLength>,
+			pub gender: BoundedVec<u8, T::MaxGenderLength>,
+			pub address: BoundedVec<u8, T::MaxAddressLength>,
+			pub phone: BoundedVec<u8, T::MaxPhoneLength>,
+			pub emergency_contact: BoundedVec<u8, T::MaxEmergencyContactLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ClinicalTest<T: Config> {
+			pub test_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub test_type: BoundedVec<u8, T::MaxTestTypeLength>,
+			pub test_date: BoundedVec<u8, T::MaxDateLength>,
+			pub result: BoundedVec<u8, T::MaxTestResultLength>,
+			pub notes: BoundedVec<u8, T::MaxNotesLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+			pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub next_appointment: BoundedVec<u8, T::MaxDateLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct MedicalRecord<T: Config> {
+			pub record_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub record_hash: T::Hash,
+			pub data_pointer: Option<BoundedVec<u8, T::MaxDataPointerLength>>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+		}
+
+		#[frame_support::storage_alias]
+		pub type Patients<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, PatientInfo<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type ClinicalTests<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ClinicalTest<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type MedicalRecords<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, MedicalRecord<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientNameToId<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			BoundedVec<u8, <T as Config>::MaxNameLength>,
+			BoundedVec<u32, <T as Config>::MaxPatientsPerName>,
+			OptionQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientsByGender<T: Config> = StorageDoubleMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			BoundedVec<u8, <T as Config>::MaxGenderLength>,
+			Blake2_128Concat,
+			u32,
+			(),
+			OptionQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientsByBirthYear<T: Config> =
+			StorageDoubleMap<Pallet<T>, Blake2_128Concat, u32, Blake2_128Concat, u32, (), OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type PatientClinicalTests<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			u32,
+			BoundedVec<u32, <T as Config>::MaxRecordsPerPatient>,
+			ValueQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientDiseaseProgressions<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			u32,
+			BoundedVec<u32, <T as Config>::MaxRecordsPerPatient>,
+			ValueQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientMedicalRecords<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			u32,
+			BoundedVec<u32, <T as Config>::MaxRecordsPerPatient>,
+			ValueQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type DoctorPatients<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			BoundedVec<u32, <T as Config>::MaxPatientsPerDoctor>,
+			ValueQuery,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type PatientDoctors<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			u32,
+			BoundedVec<<T as frame_system::Config>::AccountId, <T as Config>::MaxDoctorsPerPatient>,
+			ValueQuery,
+		>;
+	}
+
+	/// Progress of [`MigrateV0ToV1`]: the storage item being migrated, and the last key
+	/// migrated in it.
+	#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum Cursor<AccountId> {
+		/// The legacy gender lists are dropped from the start of the map, so no key is kept.
+		PatientsByGender,
+		/// The legacy birth year lists are dropped from the start of the map.
+		PatientsByBirthYear,
+		Patients(Option<u32>),
+		ClinicalTests(Option<u32>),
+		DiseaseProgressions(Option<u32>),
+		MedicalRecords(Option<u32>),
+		ChangeHistories(Option<u32>),
+		/// Keyed by legacy names of any length, so only the hash of the last name is kept.
+		PatientNameToId(Option<[u8; 16]>),
+		PatientClinicalTests(Option<u32>),
+		PatientDiseaseProgressions(Option<u32>),
+		PatientMedicalRecords(Option<u32>),
+		DoctorPatients(Option<AccountId>),
+		PatientDoctors(Option<u32>),
+	}
+
+	// Bound a legacy value, noting whether it had to be truncated
+	fn bound<S: Get<u32>>(value: Vec<u8>, truncated: &mut bool) -> BoundedVec<u8, S> {
+		*truncated |= value.len() > S::get() as usize;
+		BoundedVec::truncate_from(value)
+	}
+
+	// Bound a legacy index list, keeping its first entries
+	fn bound_ids<I, S: Get<u32>>(ids: Vec<I>, index: &str) -> BoundedVec<I, S> {
+		if ids.len() > S::get() as usize {
+			log::warn!(
+				target: LOG_TARGET,
+				"{} holds {} entries, keeping the first {}",
+				index,
+				ids.len(),
+				S::get(),
+			);
+		}
+		BoundedVec::truncate_from(ids)
+	}
+
+	// Drop the lists of a legacy index one by one, as long as the meter can pay `weight` for each.
+	// Only the keys are read, however long the lists are.
+	fn drop_legacy_lists<Old, K, I>(meter: &mut WeightMeter, weight: Weight) -> Walked<()>
+	where
+		Old: IterableStorageMap<K, Vec<I>> + frame_support::storage::StorageMap<K, Vec<I>>,
+		K: FullCodec,
+		I: FullCodec,
+	{
+		loop {
+			if meter.try_consume(weight).is_err() {
+				return Walked::Paused(None);
+			}
+			let Some(key) = Old::iter_keys().next() else { return Walked::Done };
+			Old::remove(key);
+		}
+	}
+
+	// Bound the oversize lists of a legacy index after the key `last`. Lists within their bound
+	// are already encoded as the bounded list they become, so they are left as they are.
+	fn bound_index<Old, New, K, I, S>(
+		last: Option<K>,
+		meter: &mut WeightMeter,
+		weight: Weight,
+		index: &str,
+	) -> Walked<K>
+	where
+		Old: IterableStorageMap<K, Vec<I>> + frame_support::storage::StorageMap<K, Vec<I>>,
+		New: frame_support::storage::StorageMap<K, BoundedVec<I, S>>,
+		K: FullCodec + Clone,
+		I: FullCodec,
+		S: Get<u32>,
+	{
+		walk::<Old, _, _>(last, meter, weight, |key, ids| {
+			if ids.len() > S::get() as usize {
+				New::insert(key, bound_ids::<_, S>(ids, index));
+			}
+		})
+	}
+
+	/// Migrates the state of this pallet from V0 to V1 over as many blocks as it takes.
+	///
+	/// Only runs when the on-chain storage version is 0, and sets it to 1 once done.
+	pub struct MigrateV0ToV1<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> MigrateV0ToV1<T> {
+		// Weight of migrating one entry: reading and writing it, and the index entry it is
+		// merged into when its key is truncated or, for a patient, its demographic index entries
+		fn entry_weight() -> Weight {
+			T::DbWeight::get().reads_writes(2, 3)
+		}
+	}
+
+	impl<T: crate::Config> SteppedMigration for MigrateV0ToV1<T> {
+		type Cursor = Cursor<T::AccountId>;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(0, 1)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = Self::entry_weight();
+			if !begin_step::<T>(0, meter, weight)? {
+				return Ok(None);
+			}
+
+			let mut cursor = cursor.unwrap_or(Cursor::PatientsByGender);
+			loop {
+				cursor = match cursor {
+					// The legacy lists share their prefix with the per-patient entries that replace
+					// them, so they are dropped before any of those is written
+					Cursor::PatientsByGender =>
+						match drop_legacy_lists::<v0::PatientsByGender<T>, _, _>(meter, weight) {
+							Walked::Paused(_) => return Ok(Some(Cursor::PatientsByGender)),
+							Walked::Done => Cursor::PatientsByBirthYear,
+						},
+					Cursor::PatientsByBirthYear =>
+						match drop_legacy_lists::<v0::PatientsByBirthYear<T>, _, _>(meter, weight) {
+							Walked::Paused(_) => return Ok(Some(Cursor::PatientsByBirthYear)),
+							Walked::Done => Cursor::Patients(None),
+						},
+					Cursor::Patients(last) => match walk::<v0::Patients<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|patient_id, old| {
+							let mut truncated = false;
+							let patient = new::PatientInfo::<T> {
+								patient_id: old.patient_id,
+								patient_name: bound(old.patient_name, &mut truncated),
+								date_of_birth: bound(old.date_of_birth, &mut truncated),
+								gender: bound(old.gender, &mut truncated),
+								address: bound(old.address, &mut truncated),
+								phone: bound(old.phone, &mut truncated),
+								emergency_contact: bound(old.emergency_contact, &mut truncated),
+								created_at: old.created_at,
+								created_by: old.created_by,
+								last_modified_at: old.last_modified_at,
+								last_modified_by: old.last_modified_by,
+							};
+							if truncated {
+								log::warn!(target: LOG_TARGET, "truncated oversize fields of patient {}", patient_id);
+							}
+							new::PatientsByGender::<T>::insert(&patient.gender, patient_id, ());
+							if let Some(birth_year) = Pallet::<T>::birth_year_of(&patient.date_of_birth) {
+								new::PatientsByBirthYear::<T>::insert(birth_year, patient_id, ());
+							}
+							new::Patients::<T>::insert(patient_id, patient);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::Patients(last))),
+						Walked::Done => Cursor::ClinicalTests(None),
+					},
+					Cursor::ClinicalTests(last) => match walk::<v0::ClinicalTests<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|test_id, old| {
+							let mut truncated = false;
+							let test = new::ClinicalTest::<T> {
+								test_id: old.test_id,
+								patient_id: old.patient_id,
+								doctor_id: old.doctor_id,
+								test_type: bound(old.test_type, &mut truncated),
+								test_date: bound(old.test_date, &mut truncated),
+								result: bound(old.result, &mut truncated),
+								notes: bound(old.notes, &mut truncated),
+								created_at: old.created_at,
+								created_by: old.created_by,
+								last_modified_at: old.last_modified_at,
+								last_modified_by: old.last_modified_by,
+							};
+							if truncated {
+								log::warn!(target: LOG_TARGET, "truncated oversize fields of clinical test {}", test_id);
+							}
+							new::ClinicalTests::<T>::insert(test_id, test);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::ClinicalTests(last))),
+						Walked::Done => Cursor::DiseaseProgressions(None),
+					},
+					Cursor::DiseaseProgressions(last) => {
+						match walk::<v0::DiseaseProgressions<T>, _, _>(
+							last,
+							meter,
+							weight,
+							|progression_id, old| {
+								let mut truncated = false;
+								let progression = new::DiseaseProgression::<T> {
+									progression_id: old.progression_id,
+									patient_id: old.patient_id,
+									doctor_id: old.doctor_id,
+									visit_date: bound(old.visit_date, &mut truncated),
+									symptoms: bound(old.symptoms, &mut truncated),
+									diagnosis: bound(old.diagnosis, &mut truncated),
+									treatment: bound(old.treatment, &mut truncated),
+									prescription: bound(old.prescription, &mut truncated),
+									next_appointment: bound(old.next_appointment, &mut truncated),
+									created_at: old.created_at,
+									created_by: old.created_by,
+									last_modified_at: old.last_modified_at,
+									last_modified_by: old.last_modified_by,
+								};
+								if truncated {
+									log::warn!(
+										target: LOG_TARGET,
+										"truncated oversize fields of disease progression {}",
+										progression_id,
+									);
+								}
+								new::DiseaseProgressions::<T>::insert(progression_id, progression);
+							},
+						) {
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::DiseaseProgressions(last)))
+							}
+							Walked::Done => Cursor::MedicalRecords(None),
+						}
+					}
+					Cursor::MedicalRecords(last) => match walk::<v0::MedicalRecords<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|record_id, old| {
+							let mut truncated = false;
+							let record = new::MedicalRecord::<T> {
+								record_id: old.record_id,
+								patient_id: old.patient_id,
+								doctor_id: old.doctor_id,
+								record_hash: old.record_hash,
+								data_pointer: old
+									.data_pointer
+									.map(|pointer| bound(pointer, &mut truncated)),
+								diagnosis: bound(old.diagnosis, &mut truncated),
+								treatment: bound(old.treatment, &mut truncated),
+								created_at: old.created_at,
+								created_by: old.created_by,
+								last_modified_at: old.last_modified_at,
+								last_modified_by: old.last_modified_by,
+							};
+							if truncated {
+								log::warn!(target: LOG_TARGET, "truncated oversize fields of medical record {}", record_id);
+							}
+							new::MedicalRecords::<T>::insert(record_id, record);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::MedicalRecords(last))),
+						Walked::Done => Cursor::ChangeHistories(None),
+					},
+					Cursor::ChangeHistories(last) => match walk::<v0::ChangeHistories<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|change_id, old| {
+							let mut truncated = false;
+							let change = new::ChangeHistory::<T> {
+								change_id: old.change_id,
+								record_type: old.record_type,
+								record_id: old.record_id,
+								field_name: bound(old.field_name, &mut truncated),
+								old_value: old.old_value.map(|value| bound(value, &mut truncated)),
+								new_value: bound(old.new_value, &mut truncated),
+								changed_by: old.changed_by,
+								changed_at: old.changed_at,
+								operation: old.operation,
+							};
+							if truncated {
+								log::warn!(target: LOG_TARGET, "truncated oversize values of change {}", change_id);
+							}
+							new::ChangeHistories::<T>::insert(change_id, change);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::ChangeHistories(last))),
+						Walked::Done => Cursor::PatientNameToId(None),
+					},
+					// Indexes keyed by text are rebuilt under their truncated keys
+					Cursor::PatientNameToId(last) => match walk_text::<v0::PatientNameToId<T>, _>(
+						last,
+						meter,
+						weight,
+						|name, ids| {
+							let max_ids = T::MaxPatientsPerName::get() as usize;
+							if name.len() <= T::MaxNameLength::get() as usize
+								&& ids.len() <= max_ids
+							{
+								return;
+							}
+							v0::PatientNameToId::<T>::remove(&name);
+							let key: BoundedVec<u8, T::MaxNameLength> =
+								BoundedVec::truncate_from(name);
+							let mut merged = new::PatientNameToId::<T>::get(&key)
+								.map(|ids| ids.into_inner())
+								.unwrap_or_default();
+							merged.extend(ids);
+							new::PatientNameToId::<T>::insert(
+								key,
+								bound_ids::<_, T::MaxPatientsPerName>(merged, "PatientNameToId"),
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::PatientNameToId(last))),
+						Walked::Done => Cursor::PatientClinicalTests(None),
+					},
+					Cursor::PatientClinicalTests(last) => {
+						match bound_index::<
+							v0::PatientClinicalTests<T>,
+							new::PatientClinicalTests<T>,
+							_,
+							_,
+							_,
+						>(last, meter, weight, "PatientClinicalTests")
+						{
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::PatientClinicalTests(last)))
+							}
+							Walked::Done => Cursor::PatientDiseaseProgressions(None),
+						}
+					}
+					Cursor::PatientDiseaseProgressions(last) => {
+						match bound_index::<
+							v0::PatientDiseaseProgressions<T>,
+							new::PatientDiseaseProgressions<T>,
+							_,
+							_,
+							_,
+						>(last, meter, weight, "PatientDiseaseProgressions")
+						{
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::PatientDiseaseProgressions(last)))
+							}
+							Walked::Done => Cursor::PatientMedicalRecords(None),
+						}
+					}
+					Cursor::PatientMedicalRecords(last) => {
+						match bound_index::<
+							v0::PatientMedicalRecords<T>,
+							new::PatientMedicalRecords<T>,
+							_,
+							_,
+							_,
+						>(last, meter, weight, "PatientMedicalRecords")
+						{
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::PatientMedicalRecords(last)))
+							}
+							Walked::Done => Cursor::DoctorPatients(None),
+						}
+					}
+					Cursor::DoctorPatients(last) => {
+						match bound_index::<v0::DoctorPatients<T>, new::DoctorPatients<T>, _, _, _>(
+							last,
+							meter,
+							weight,
+							"DoctorPatients",
+						) {
+							Walked::Paused(last) => return Ok(Some(Cursor::DoctorPatients(last))),
+							Walked::Done => Cursor::PatientDoctors(None),
+						}
+					}
+					Cursor::PatientDoctors(last) => {
+						match bound_index::<v0::PatientDoctors<T>, new::PatientDoctors<T>, _, _, _>(
+							last,
+							meter,
+							weight,
+							"PatientDoctors",
+						) {
+							Walked::Paused(last) => return Ok(Some(Cursor::PatientDoctors(last))),
+							Walked::Done => break,
+						}
+					}
+				};
+			}
+
+			log::info!(target: LOG_TARGET, "bounded all legacy storage");
+			StorageVersion::new(1).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				return Ok(Vec::new());
+			}
+			let counts = (
+				v0::Patients::<T>::iter_keys().count() as u32,
+				v0::ClinicalTests::<T>::iter_keys().count() as u32,
+				v0::DiseaseProgressions::<T>::iter_keys().count() as u32,
+				v0::MedicalRecords::<T>::iter_keys().count() as u32,
+				v0::ChangeHistories::<T>::iter_keys().count() as u32,
+			);
+			Ok(counts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let (patients, tests, progressions, records, changes): (u32, u32, u32, u32, u32) =
+				Decode::decode(&mut &state[..])
+					.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(new::Patients::<T>::iter_values().count() as u32 == patients, "patients lost");
+			ensure!(
+				new::ClinicalTests::<T>::iter_values().count() as u32 == tests,
+				"clinical tests lost"
+			);
+			ensure!(
+				new::DiseaseProgressions::<T>::iter_values().count() as u32 == progressions,
+				"disease progressions lost"
+			);
+			ensure!(
+				new::MedicalRecords::<T>::iter_values().count() as u32 == records,
+				"medical records lost"
+			);
+			ensure!(
+				new::ChangeHistories::<T>::iter_values().count() as u32 == changes,
+				"change history lost"
+			);
+			ensure!(
+				new::PatientsByGender::<T>::iter_keys().count() as u32 == patients,
+				"patients missing from the gender index"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migration from the `ActivePatients` list (v1) to a counted map keyed by patient id (v2).
+///
+/// The list used to be read and rewritten in full on every patient creation and deletion.
+/// Each id is moved to its own entry, so the migration costs one write per active patient.
+pub mod v2 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v1 {
+		use super::*;
+
+		#[frame_support::storage_alias]
+		pub type ActivePatients<T: Config> = StorageValue<Pallet<T>, Vec<u32>, ValueQuery>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[frame_support::storage_alias]
+		pub type ActivePatients<T: Config> =
+			CountedStorageMap<Pallet<T>, Blake2_128Concat, u32, (), OptionQuery>;
+	}
+
+	/// Migrates the state of this pallet from V1 to V2 over as many blocks as it takes. The
+	/// cursor is the position in the old list of the next id to move.
+	///
+	/// The old list is only removed once every id has been moved. It lives at the prefix of the
+	/// new map, outside of its entries, so both can be in storage at the same time.
+	///
+	/// Only runs when the on-chain storage version is 1, and sets it to 2 once done.
+	pub struct MigrateV1ToV2<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV1ToV2<T> {
+		type Cursor = u32;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(1, 2)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			// Moving an id checks for a duplicate, inserts it and updates the counter
+			let weight = T::DbWeight::get().reads_writes(1, 2);
+			let list_weight = T::DbWeight::get().reads(1);
+			if !begin_step::<T>(1, meter, list_weight.saturating_add(weight))? {
+				return Ok(None);
+			}
+
+			meter.consume(list_weight);
+			let active = v1::ActivePatients::<T>::get();
+			let mut position = cursor.unwrap_or_default();
+			while let Some(&patient_id) = active.get(position as usize) {
+				if meter.try_consume(weight).is_err() {
+					return Ok(Some(position));
+				}
+				if !new::ActivePatients::<T>::contains_key(patient_id) {
+					new::ActivePatients::<T>::insert(patient_id, ());
+				}
+				position += 1;
+			}
+
+			if meter.try_consume(T::DbWeight::get().writes(1)).is_err() {
+				return Ok(Some(position));
+			}
+			v1::ActivePatients::<T>::kill();
+
+			log::info!(
+				target: LOG_TARGET,
+				"moved {} active patients to the counted map",
+				new::ActivePatients::<T>::count(),
+			);
+			StorageVersion::new(2).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				return Ok(Vec::new());
+			}
+			let mut active = v1::ActivePatients::<T>::get();
+			active.sort_unstable();
+			active.dedup();
+			Ok(Some(active).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let active: Option<Vec<u32>> = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+			let active = active.unwrap_or_default();
+
+			ensure!(
+				new::ActivePatients::<T>::count() as usize == active.len(),
+				"active patient count mismatch"
+			);
+			ensure!(
+				active.iter().all(new::ActivePatients::<T>::contains_key),
+				"active patient missing"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migration from free-text next appointments (v2) to links to booked appointments (v3).
+///
+/// A free-text date cannot be matched to an appointment in [`Config::Appointments`], so every
+/// progression loses its next appointment and has to be booked again. Each dropped date is logged
+/// with the progression id.
+pub mod v3 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v2 {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+			pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub next_appointment: BoundedVec<u8, T::MaxDateLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+			pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub next_appointment: Option<u32>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+	}
+
+	/// Migrates the state of this pallet from V2 to V3 over as many blocks as it takes. The
+	/// cursor is the last disease progression migrated.
+	///
+	/// Only runs when the on-chain storage version is 2, and sets it to 3 once done.
+	pub struct MigrateV2ToV3<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV2ToV3<T> {
+		type Cursor = u32;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(2, 3)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			if !begin_step::<T>(2, meter, weight)? {
+				return Ok(None);
+			}
+
+			match walk::<v2::DiseaseProgressions<T>, _, _>(
+				cursor,
+				meter,
+				weight,
+				|progression_id, old| {
+					if !old.next_appointment.is_empty() {
+						log::warn!(
+							target: LOG_TARGET,
+							"dropped next appointment {:?} of disease progression {}",
+							core::str::from_utf8(&old.next_appointment).unwrap_or("<invalid utf8>"),
+							progression_id,
+						);
+					}
+					new::DiseaseProgressions::<T>::insert(
+						progression_id,
+						new::DiseaseProgression::<T> {
+							progression_id: old.progression_id,
+							patient_id: old.patient_id,
+							doctor_id: old.doctor_id,
+							visit_date: old.visit_date,
+							symptoms: old.symptoms,
+							diagnosis: old.diagnosis,
+							treatment: old.treatment,
+							prescription: old.prescription,
+							next_appointment: None,
+							created_at: old.created_at,
+							created_by: old.created_by,
+							last_modified_at: old.last_modified_at,
+							last_modified_by: old.last_modified_by,
+						},
+					);
+				},
+			) {
+				Walked::Paused(Some(last)) => return Ok(Some(last)),
+				Walked::Paused(None) => {
+					return Err(SteppedMigrationError::InsufficientWeight { required: weight })
+				}
+				Walked::Done => {}
+			}
+
+			log::info!(target: LOG_TARGET, "moved disease progressions to appointment links");
+			StorageVersion::new(3).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 2 {
+				return Ok(Vec::new());
+			}
+			Ok((v2::DiseaseProgressions::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let progressions: u32 = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(
+				new::DiseaseProgressions::<T>::iter_values().count() as u32 == progressions,
+				"disease progressions lost"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migration from unlinked change history entries (v3) to a hash-chained audit trail (v4).
+///
+/// Existing changes are chained in id order and `AuditHead` is set to the hash of the latest one.
+/// Ids without an entry are logged and skipped, so [`Pallet::verify_audit_trail`] keeps
+/// reporting them as missing.
+pub mod v4 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v3 {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+			pub prev_change_hash: T::Hash,
+			pub change_hash: T::Hash,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+	}
+
+	/// Migrates the state of this pallet from V3 to V4 over as many blocks as it takes. The
+	/// cursor is the next change to chain and the hash of the last change chained.
+	///
+	/// Only runs when the on-chain storage version is 3, and sets it to 4 once done.
+	pub struct MigrateV3ToV4<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV3ToV4<T> {
+		type Cursor = (u32, T::Hash);
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(3, 4)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			if !begin_step::<T>(
+				3,
+				meter,
+				weight.saturating_add(T::DbWeight::get().reads_writes(1, 1)),
+			)? {
+				return Ok(None);
+			}
+
+			meter.consume(T::DbWeight::get().reads(1));
+			let next_change_id = NextChangeId::<T>::get();
+			let (mut change_id, mut head) = cursor.unwrap_or_default();
+			while change_id < next_change_id {
+				if meter.try_consume(weight).is_err() {
+					return Ok(Some((change_id, head)));
+				}
+				let Some(old) = v3::ChangeHistories::<T>::get(change_id) else {
+					log::warn!(target: LOG_TARGET, "change {} is missing from the audit trail", change_id);
+					change_id += 1;
+					continue;
+				};
+				let mut change = ChangeHistory::<T> {
+					change_id: old.change_id,
+					record_type: old.record_type,
+					record_id: old.record_id,
+					field_name: old.field_name,
+					old_value: old.old_value,
+					new_value: old.new_value,
+					changed_by: old.changed_by,
+					changed_at: old.changed_at,
+					operation: old.operation,
+					prev_change_hash: head,
+					change_hash: Default::default(),
+					redacted: false,
+				};
+				head = Pallet::<T>::compute_change_hash(&change);
+				change.change_hash = head;
+				new::ChangeHistories::<T>::insert(
+					change_id,
+					new::ChangeHistory::<T> {
+						change_id: change.change_id,
+						record_type: change.record_type,
+						record_id: change.record_id,
+						field_name: change.field_name,
+						old_value: change.old_value,
+						new_value: change.new_value,
+						changed_by: change.changed_by,
+						changed_at: change.changed_at,
+						operation: change.operation,
+						prev_change_hash: change.prev_change_hash,
+						change_hash: change.change_hash,
+					},
+				);
+				change_id += 1;
+			}
+
+			meter.consume(T::DbWeight::get().writes(1));
+			AuditHead::<T>::put(head);
+
+			log::info!(target: LOG_TARGET, "chained {} changes into the audit trail", next_change_id);
+			StorageVersion::new(4).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 3 {
+				return Ok(Vec::new());
+			}
+			Ok((v3::ChangeHistories::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let changes: u32 = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(
+				new::ChangeHistories::<T>::iter_values().count() as u32 == changes,
+				"change history lost"
+			);
+			let latest = new::ChangeHistories::<T>::iter_values()
+				.max_by_key(|change| change.change_id)
+				.map(|change| change.change_hash)
+				.unwrap_or_default();
+			ensure!(AuditHead::<T>::get() == latest, "audit head does not match the latest change");
+			Ok(())
+		}
+	}
+}
+
+/// Migrates storage from version 4 to 5, dropping the plaintext name index.
+///
+/// `PatientNameToId` was keyed by raw patient names. It is replaced by `PatientsByName`, keyed by
+/// a blinded name computed with a facility secret the chain never sees, so the new index cannot
+/// be built here. Patients stay searchable once staff re-index them with `update_patient`, or in
+/// batches with `reindex_patient_names`, passing only the blinded name.
+pub mod v5 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v4 {
+		use super::*;
+
+		#[frame_support::storage_alias]
+		pub type PatientNameToId<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			BoundedVec<u8, <T as Config>::MaxNameLength>,
+			BoundedVec<u32, <T as Config>::MaxPatientsPerName>,
+			OptionQuery,
+		>;
+	}
+
+	/// Migrates the state of this pallet from V4 to V5, dropping as many names per block as the
+	/// meter allows. The cursor counts the names dropped so far.
+	///
+	/// Only runs when the on-chain storage version is 4, and sets it to 5 once done.
+	pub struct MigrateV4ToV5<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV4ToV5<T> {
+		type Cursor = u32;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(4, 5)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			if !begin_step::<T>(4, meter, weight)? {
+				return Ok(None);
+			}
+
+			let mut dropped = cursor.unwrap_or_default();
+			loop {
+				if meter.try_consume(weight).is_err() {
+					return Ok(Some(dropped));
+				}
+				if v4::PatientNameToId::<T>::drain().next().is_none() {
+					break;
+				}
+				dropped = dropped.saturating_add(1);
+			}
+
+			log::info!(
+				target: LOG_TARGET,
+				"dropped {} plaintext names; {} patients have to be re-indexed under a blinded name",
+				dropped,
+				ActivePatients::<T>::count(),
+			);
+			StorageVersion::new(5).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			ensure!(v4::PatientNameToId::<T>::iter_keys().next().is_none(), "plaintext names left");
+			Ok(())
+		}
+	}
+}
+
+/// Migrates storage from version 5 to 6, introducing typed off-chain data pointers.
+///
+/// Clinical tests and disease progressions gain an empty list of attachments. The data pointer
+/// of a medical record used to be free text that nothing validated; it carries no content hash
+/// or size, so it cannot be turned into a [`DataPointer`]. Such pointers are dropped and logged
+/// with their record id so the documents can be attached again.
+pub mod v6 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v5 {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ClinicalTest<T: Config> {
+			pub test_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub test_type: BoundedVec<u8, T::MaxTestTypeLength>,
+			pub test_date: BoundedVec<u8, T::MaxDateLength>,
+			pub result: BoundedVec<u8, T::MaxTestResultLength>,
+			pub notes: BoundedVec<u8, T::MaxNotesLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+			pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub next_appointment: Option<u32>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct MedicalRecord<T: Config> {
+			pub record_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub record_hash: T::Hash,
+			pub data_pointer: Option<BoundedVec<u8, T::MaxDataPointerLength>>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ClinicalTests<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ClinicalTest<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type MedicalRecords<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, MedicalRecord<T>, OptionQuery>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ClinicalTest<T: Config> {
+			pub test_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub test_type: BoundedVec<u8, T::MaxTestTypeLength>,
+			pub test_date: BoundedVec<u8, T::MaxDateLength>,
+			pub result: BoundedVec<u8, T::MaxTestResultLength>,
+			pub notes: BoundedVec<u8, T::MaxNotesLength>,
+			pub attachments: AttachmentsOf<T>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct DiseaseProgression<T: Config> {
+			pub progression_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub visit_date: BoundedVec<u8, T::MaxDateLength>,
+			pub symptoms: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub prescription: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub next_appointment: Option<u32>,
+			pub attachments: AttachmentsOf<T>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct MedicalRecord<T: Config> {
+			pub record_id: u32,
+			pub patient_id: u32,
+			pub doctor_id: T::AccountId,
+			pub record_hash: T::Hash,
+			pub data_pointer: Option<DataPointerOf<T>>,
+			pub diagnosis: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub treatment: BoundedVec<u8, T::MaxClinicalTextLength>,
+			pub created_at: BlockNumberFor<T>,
+			pub created_by: T::AccountId,
+			pub last_modified_at: BlockNumberFor<T>,
+			pub last_modified_by: T::AccountId,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ClinicalTests<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ClinicalTest<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type DiseaseProgressions<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, DiseaseProgression<T>, OptionQuery>;
+
+		#[frame_support::storage_alias]
+		pub type MedicalRecords<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, MedicalRecord<T>, OptionQuery>;
+	}
+
+	/// Progress of [`MigrateV5ToV6`]: the storage item being migrated, and the last record
+	/// migrated in it.
+	#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum Cursor {
+		ClinicalTests(Option<u32>),
+		DiseaseProgressions(Option<u32>),
+		MedicalRecords(Option<u32>),
+	}
+
+	/// Migrates the state of this pallet from V5 to V6 over as many blocks as it takes.
+	///
+	/// Only runs when the on-chain storage version is 5, and sets it to 6 once done.
+	pub struct MigrateV5ToV6<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV5ToV6<T> {
+		type Cursor = Cursor;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(5, 6)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			if !begin_step::<T>(5, meter, weight)? {
+				return Ok(None);
+			}
+
+			let mut cursor = cursor.unwrap_or(Cursor::ClinicalTests(None));
+			loop {
+				cursor = match cursor {
+					Cursor::ClinicalTests(last) => match walk::<v5::ClinicalTests<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|test_id, old| {
+							new::ClinicalTests::<T>::insert(
+								test_id,
+								new::ClinicalTest::<T> {
+									test_id: old.test_id,
+									patient_id: old.patient_id,
+									doctor_id: old.doctor_id,
+									test_type: old.test_type,
+									test_date: old.test_date,
+									result: old.result,
+									notes: old.notes,
+									attachments: Default::default(),
+									created_at: old.created_at,
+									created_by: old.created_by,
+									last_modified_at: old.last_modified_at,
+									last_modified_by: old.last_modified_by,
+								},
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::ClinicalTests(last))),
+						Walked::Done => Cursor::DiseaseProgressions(None),
+					},
+					Cursor::DiseaseProgressions(last) => {
+						match walk::<v5::DiseaseProgressions<T>, _, _>(
+							last,
+							meter,
+							weight,
+							|progression_id, old| {
+								new::DiseaseProgressions::<T>::insert(
+									progression_id,
+									new::DiseaseProgression::<T> {
+										progression_id: old.progression_id,
+										patient_id: old.patient_id,
+										doctor_id: old.doctor_id,
+										visit_date: old.visit_date,
+										symptoms: old.symptoms,
+										diagnosis: old.diagnosis,
+										treatment: old.treatment,
+										prescription: old.prescription,
+										next_appointment: old.next_appointment,
+										attachments: Default::default(),
+										created_at: old.created_at,
+										created_by: old.created_by,
+										last_modified_at: old.last_modified_at,
+										last_modified_by: old.last_modified_by,
+									},
+								);
+							},
+						) {
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::DiseaseProgressions(last)))
+							}
+							Walked::Done => Cursor::MedicalRecords(None),
+						}
+					}
+					Cursor::MedicalRecords(last) => match walk::<v5::MedicalRecords<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|record_id, old| {
+							if let Some(pointer) = old.data_pointer {
+								log::warn!(
+									target: LOG_TARGET,
+									"dropped untyped data pointer {:?} of medical record {}",
+									core::str::from_utf8(&pointer).unwrap_or("<invalid utf8>"),
+									record_id,
+								);
+							}
+							new::MedicalRecords::<T>::insert(
+								record_id,
+								new::MedicalRecord::<T> {
+									record_id: old.record_id,
+									patient_id: old.patient_id,
+									doctor_id: old.doctor_id,
+									record_hash: old.record_hash,
+									data_pointer: None,
+									diagnosis: old.diagnosis,
+									treatment: old.treatment,
+									created_at: old.created_at,
+									created_by: old.created_by,
+									last_modified_at: old.last_modified_at,
+									last_modified_by: old.last_modified_by,
+								},
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::MedicalRecords(last))),
+						Walked::Done => break,
+					},
+				};
+			}
+
+			log::info!(target: LOG_TARGET, "migrated records to typed data pointers");
+			StorageVersion::new(6).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 5 {
+				return Ok(Vec::new());
+			}
+			Ok((
+				v5::ClinicalTests::<T>::iter_keys().count() as u32,
+				v5::DiseaseProgressions::<T>::iter_keys().count() as u32,
+				v5::MedicalRecords::<T>::iter_keys().count() as u32,
+			)
+				.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let (tests, progressions, records): (u32, u32, u32) =
+				Decode::decode(&mut &state[..])
+					.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(
+				new::ClinicalTests::<T>::iter_values().count() as u32 == tests,
+				"clinical tests lost"
+			);
+			ensure!(
+				new::DiseaseProgressions::<T>::iter_values().count() as u32 == progressions,
+				"disease progressions lost"
+			);
+			ensure!(
+				new::MedicalRecords::<T>::iter_values().count() as u32 == records,
+				"medical records lost"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migrates storage from version 6 to 7, preparing the audit trail for erasure.
+///
+/// Change history entries gain a `redacted` flag, set once their values are replaced by their
+/// hashes on erasure, and the documents uploaded so far are indexed by patient so an erasure can
+/// find them.
+pub mod v7 {
+	use super::*;
+
+	/// Storage layout before the migration.
+	pub mod v6 {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+			pub prev_change_hash: T::Hash,
+			pub change_hash: T::Hash,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+	}
+
+	/// Storage layout after the migration.
+	pub mod new {
+		use super::*;
+
+		#[derive(Encode, Decode, TypeInfo, CloneNoBound, PartialEqNoBound, RuntimeDebugNoBound)]
+		#[scale_info(skip_type_params(T))]
+		pub struct ChangeHistory<T: Config> {
+			pub change_id: u32,
+			pub record_type: RecordType,
+			pub record_id: u32,
+			pub field_name: BoundedVec<u8, T::MaxFieldNameLength>,
+			pub old_value: Option<BoundedVec<u8, T::MaxChangeValueLength>>,
+			pub new_value: BoundedVec<u8, T::MaxChangeValueLength>,
+			pub changed_by: T::AccountId,
+			pub changed_at: BlockNumberFor<T>,
+			pub operation: OperationType,
+			pub prev_change_hash: T::Hash,
+			pub change_hash: T::Hash,
+			pub redacted: bool,
+		}
+
+		#[frame_support::storage_alias]
+		pub type ChangeHistories<T: Config> =
+			StorageMap<Pallet<T>, Blake2_128Concat, u32, ChangeHistory<T>, OptionQuery>;
+	}
+
+	/// Progress of [`MigrateV6ToV7`]: the storage item being migrated, and the last key
+	/// migrated in it.
+	#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum Cursor {
+		ChangeHistories(Option<u32>),
+		Documents(Option<ContentHash>),
+	}
+
+	/// Migrates the state of this pallet from V6 to V7 over as many blocks as it takes.
+	///
+	/// Only runs when the on-chain storage version is 6, and sets it to 7 once done.
+	pub struct MigrateV6ToV7<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> SteppedMigration for MigrateV6ToV7<T> {
+		type Cursor = Cursor;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(6, 7)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			if !begin_step::<T>(6, meter, weight)? {
+				return Ok(None);
+			}
+
+			let mut cursor = cursor.unwrap_or(Cursor::ChangeHistories(None));
+			loop {
+				cursor = match cursor {
+					Cursor::ChangeHistories(last) => match walk::<v6::ChangeHistories<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|change_id, old| {
+							new::ChangeHistories::<T>::insert(
+								change_id,
+								new::ChangeHistory::<T> {
+									change_id: old.change_id,
+									record_type: old.record_type,
+									record_id: old.record_id,
+									field_name: old.field_name,
+									old_value: old.old_value,
+									new_value: old.new_value,
+									changed_by: old.changed_by,
+									changed_at: old.changed_at,
+									operation: old.operation,
+									prev_change_hash: old.prev_change_hash,
+									change_hash: old.change_hash,
+									redacted: false,
+								},
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::ChangeHistories(last))),
+						Walked::Done => Cursor::Documents(None),
+					},
+					Cursor::Documents(last) => match walk::<Documents<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|content_hash, document| {
+							PatientDocuments::<T>::insert(document.patient_id, content_hash, ());
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::Documents(last))),
+						Walked::Done => break,
+					},
+				};
+			}
+
+			log::info!(target: LOG_TARGET, "added redaction flags and indexed documents by patient");
+			StorageVersion::new(7).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 6 {
+				return Ok(Vec::new());
+			}
+			Ok((
+				v6::ChangeHistories::<T>::iter_keys().count() as u32,
+				Documents::<T>::iter_keys().count() as u32,
+			)
+				.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let (changes, documents): (u32, u32) = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(
+				new::ChangeHistories::<T>::iter_values().count() as u32 == changes,
+				"change history lost"
+			);
+			ensure!(
+				PatientDocuments::<T>::iter_keys().count() as u32 == documents,
+				"documents not indexed by patient"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Migrates storage from version 7 to 8, making deletion referentially safe.
+///
+/// Every clinical test, disease progression and medical record is added to its patient's record
+/// index, so an erasure also redacts the audit entries of records deleted after the upgrade;
+/// records deleted before it are not known by patient any more. Patients deleted before deletions
+/// cascaded may have left records, documents, consent grants and care links behind. Their
+/// deletion is queued again on behalf of the admin who deleted them, as found in the audit trail,
+/// and carried out once the migration is done. Patients whose deletion is not in the audit trail,
+/// or that no longer fit the queue, are logged and left as they are.
+pub mod v8 {
+	use super::*;
+
+	/// Progress of [`MigrateV7ToV8`]: the records being indexed and the last one indexed, then
+	/// the next patient id to look for leftovers of a deleted patient.
+	#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub enum Cursor {
+		ClinicalTests(Option<u32>),
+		DiseaseProgressions(Option<u32>),
+		MedicalRecords(Option<u32>),
+		DeletedPatients(u32),
+	}
+
+	/// Migrates the state of this pallet from V7 to V8 over as many blocks as it takes.
+	///
+	/// Only runs when the on-chain storage version is 7, and sets it to 8 once done.
+	pub struct MigrateV7ToV8<T: crate::Config>(core::marker::PhantomData<T>);
+
+	impl<T: crate::Config> MigrateV7ToV8<T> {
+		// The admin whose `delete_patient` call is recorded in the patient's audit entries
+		fn deleted_by(patient_id: u32) -> Option<T::AccountId> {
+			RecordChanges::<T>::iter_key_prefix((RecordType::Patient, patient_id))
+				.filter_map(ChangeHistories::<T>::get)
+				.filter(|change| change.field_name.as_slice() == b"deleted")
+				.last()
+				.map(|change| change.changed_by)
+		}
+
+		// Whether a patient that no longer exists left anything behind
+		fn has_leftovers(patient_id: u32) -> bool {
+			PatientRecordIndex::<T>::iter_key_prefix(patient_id).next().is_some()
+				|| PatientClinicalTests::<T>::contains_key(patient_id)
+				|| PatientDiseaseProgressions::<T>::contains_key(patient_id)
+				|| PatientMedicalRecords::<T>::contains_key(patient_id)
+				|| PatientDoctors::<T>::contains_key(patient_id)
+				|| Consents::<T>::iter_key_prefix(patient_id).next().is_some()
+				|| PatientDocuments::<T>::iter_key_prefix(patient_id).next().is_some()
+		}
+
+		// Queue the deletion of a patient deleted before deletions cascaded, if it left anything
+		// behind
+		fn requeue_deletion(patient_id: u32) {
+			if Patients::<T>::contains_key(patient_id)
+				|| ErasureQueue::<T>::get().contains(&patient_id)
+				|| !Self::has_leftovers(patient_id)
+			{
+				return;
+			}
+			let Some(deleted_by) = Self::deleted_by(patient_id) else {
+				log::warn!(target: LOG_TARGET, "no deletion of patient {} in the audit trail", patient_id);
+				return;
+			};
+			if DeletionQueue::<T>::try_append((patient_id, deleted_by)).is_err() {
+				log::warn!(target: LOG_TARGET, "deletion queue full, patient {} left as is", patient_id);
+				return;
+			}
+			log::info!(target: LOG_TARGET, "queued the deletion of deleted patient {}", patient_id);
+		}
+	}
+
+	impl<T: crate::Config> SteppedMigration for MigrateV7ToV8<T> {
+		type Cursor = Cursor;
+		type Identifier = MigrationId<21>;
+
+		fn id() -> Self::Identifier {
+			migration_id(7, 8)
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			let weight = T::DbWeight::get().reads_writes(1, 1);
+			// Looking up a patient reads their entry, the queues and the indexes left behind
+			let patient_weight = T::DbWeight::get().reads_writes(12, 1);
+			if !begin_step::<T>(7, meter, patient_weight)? {
+				return Ok(None);
+			}
+
+			let mut cursor = cursor.unwrap_or(Cursor::ClinicalTests(None));
+			loop {
+				cursor = match cursor {
+					Cursor::ClinicalTests(last) => match walk::<ClinicalTests<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|test_id, test| {
+							PatientRecordIndex::<T>::insert(
+								test.patient_id,
+								(RecordType::ClinicalTest, test_id),
+								(),
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::ClinicalTests(last))),
+						Walked::Done => Cursor::DiseaseProgressions(None),
+					},
+					Cursor::DiseaseProgressions(last) => {
+						match walk::<DiseaseProgressions<T>, _, _>(
+							last,
+							meter,
+							weight,
+							|progression_id, progression| {
+								PatientRecordIndex::<T>::insert(
+									progression.patient_id,
+									(RecordType::DiseaseProgression, progression_id),
+									(),
+								);
+							},
+						) {
+							Walked::Paused(last) => {
+								return Ok(Some(Cursor::DiseaseProgressions(last)))
+							}
+							Walked::Done => Cursor::MedicalRecords(None),
+						}
+					}
+					Cursor::MedicalRecords(last) => match walk::<MedicalRecords<T>, _, _>(
+						last,
+						meter,
+						weight,
+						|record_id, record| {
+							PatientRecordIndex::<T>::insert(
+								record.patient_id,
+								(RecordType::MedicalRecord, record_id),
+								(),
+							);
+						},
+					) {
+						Walked::Paused(last) => return Ok(Some(Cursor::MedicalRecords(last))),
+						Walked::Done => Cursor::DeletedPatients(0),
+					},
+					// Anything a deleted patient may have left behind
+					Cursor::DeletedPatients(mut patient_id) => {
+						meter.consume(T::DbWeight::get().reads(1));
+						let next_patient_id = NextPatientId::<T>::get();
+						while patient_id < next_patient_id {
+							if meter.try_consume(patient_weight).is_err() {
+								return Ok(Some(Cursor::DeletedPatients(patient_id)));
+							}
+							Self::requeue_deletion(patient_id);
+							patient_id += 1;
+						}
+						break;
+					}
+				};
+			}
+
+			log::info!(target: LOG_TARGET, "indexed records by patient and queued the deletion of deleted patients");
+			StorageVersion::new(8).put::<Pallet<T>>();
+			Ok(None)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, frame_support::sp_runtime::TryRuntimeError> {
+			if Pallet::<T>::on_chain_storage_version() != 7 {
+				return Ok(Vec::new());
+			}
+			let records = ClinicalTests::<T>::iter_keys().count()
+				+ DiseaseProgressions::<T>::iter_keys().count()
+				+ MedicalRecords::<T>::iter_keys().count();
+			Ok((records as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), frame_support::sp_runtime::TryRuntimeError> {
+			if state.is_empty() {
+				return Ok(());
+			}
+			let records: u32 = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+
+			ensure!(
+				PatientRecordIndex::<T>::iter_keys().count() as u32 == records,
+				"records not indexed by patient"
+			);
+			Ok(())
+		}
+	}
+}
@@ -1,6 +1,10 @@
 use crate as pallet_medical_record;
-use frame_support::derive_impl;
-use sp_runtime::BuildStorage;
+use crate::{Role, ScheduledAppointment};
+use frame_support::{derive_impl, ensure, parameter_types, traits::{ConstU32, Time}};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{traits::BlakeTwo256, BuildStorage, DispatchError, DispatchResult};
+use std::collections::BTreeMap;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -28,19 +32,175 @@ mod runtime {
 
 	#[runtime::pallet_index(1)]
 	pub type MedicalRecord = pallet_medical_record::Pallet<Test>;
+
+	#[runtime::pallet_index(2)]
+	pub type PoH = pallet_poh::Pallet<Test>;
 }
 
 #[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
 impl frame_system::Config for Test {
 	type Block = Block;
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
+}
+
+// Mock time implementation
+pub struct MockTime;
+
+impl Time for MockTime {
+	type Moment = u64;
+
+	fn now() -> Self::Moment {
+		0
+	}
+}
+
+impl pallet_poh::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Hasher = BlakeTwo256;
+	type Time = MockTime;
+	type Hash = H256;
+	type WeightInfo = ();
+	type MaxBatchSize = ConstU32<16>;
+	type MaxRangeLength = ConstU32<64>;
+}
+
+// Unix time of the mock appointment book, appointments have to be booked after it
+pub const NOW: u64 = 1_000;
+
+// An appointment booked through `MockAppointments`
+#[derive(Clone, PartialEq, Debug)]
+pub struct MockAppointment {
+	pub patient: u64,
+	pub doctor: u64,
+	pub scheduled_time: u64,
+	pub cancelled: bool,
+}
+
+parameter_types! {
+	pub static BookedAppointments: BTreeMap<u32, MockAppointment> = BTreeMap::new();
+	// Plaintext PII unless a test switches on encrypted envelopes
+	pub static EncryptedPii: bool = false;
+}
+
+// Appointment book that keeps appointments in memory
+pub struct MockAppointments;
+
+impl crate::AppointmentScheduler<u64> for MockAppointments {
+	fn schedule(patient: &u64, doctor: &u64, scheduled_time: u64) -> Result<u32, DispatchError> {
+		if scheduled_time <= NOW {
+			return Err(DispatchError::Other("appointment in the past"));
+		}
+		let appointment = MockAppointment { patient: *patient, doctor: *doctor, scheduled_time, cancelled: false };
+		BookedAppointments::mutate(|booked| {
+			let appointment_id = booked.len() as u32;
+			booked.insert(appointment_id, appointment);
+			Ok(appointment_id)
+		})
+	}
+
+	fn reschedule(who: &u64, appointment_id: u32, scheduled_time: u64) -> DispatchResult {
+		if scheduled_time <= NOW {
+			return Err(DispatchError::Other("appointment in the past"));
+		}
+		BookedAppointments::mutate(|booked| {
+			let appointment = booked.get_mut(&appointment_id).ok_or(DispatchError::Other("no appointment"))?;
+			ensure!(appointment.doctor == *who, DispatchError::Other("not the doctor"));
+			appointment.scheduled_time = scheduled_time;
+			Ok(())
+		})
+	}
+
+	fn cancel(who: &u64, appointment_id: u32) -> DispatchResult {
+		BookedAppointments::mutate(|booked| {
+			let appointment = booked.get_mut(&appointment_id).ok_or(DispatchError::Other("no appointment"))?;
+			ensure!(appointment.doctor == *who, DispatchError::Other("not the doctor"));
+			appointment.cancelled = true;
+			Ok(())
+		})
+	}
+
+	fn upcoming(appointment_id: u32) -> Option<ScheduledAppointment<u64>> {
+		BookedAppointments::get()
+			.get(&appointment_id)
+			.filter(|appointment| !appointment.cancelled)
+			.map(|appointment| ScheduledAppointment {
+				appointment_id,
+				doctor_id: appointment.doctor,
+				scheduled_time: appointment.scheduled_time,
+			})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_slot(_doctor: &u64) -> u64 {
+		NOW + 1
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_escrow(_appointment_id: u32) {}
 }
 
 impl pallet_medical_record::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type AdminOrigin = EnsureRoot<u64>;
+	type DataProtectionOrigin = EnsureRoot<u64>;
+	type ProofAnchor = PoH;
+	type Appointments = MockAppointments;
+	type MaxNameLength = ConstU32<64>;
+	type MaxDateLength = ConstU32<64>;
+	type MaxGenderLength = ConstU32<16>;
+	type MaxAddressLength = ConstU32<128>;
+	type MaxPhoneLength = ConstU32<64>;
+	type MaxEmergencyContactLength = ConstU32<128>;
+	type MaxTestTypeLength = ConstU32<64>;
+	type MaxTestResultLength = ConstU32<256>;
+	type MaxNotesLength = ConstU32<256>;
+	type MaxClinicalTextLength = ConstU32<256>;
+	type MaxDataPointerLength = ConstU32<128>;
+	type MaxAttachmentsPerRecord = ConstU32<4>;
+	type MaxDocumentSize = ConstU32<1024>;
+	type MaxFieldNameLength = ConstU32<32>;
+	type MaxChangeValueLength = ConstU32<256>;
+	type MaxPatientsPerName = ConstU32<4>;
+	type MaxPageSize = ConstU32<10>;
+	type MaxReindexBatchSize = ConstU32<4>;
+	type MaxRecordsPerPatient = ConstU32<20>;
+	type MaxPatientsPerDoctor = ConstU32<100>;
+	type MaxDoctorsPerPatient = ConstU32<8>;
+	type MaxProofsPerRecord = ConstU32<8>;
+	type MaxAuditRange = ConstU32<16>;
+	type EncryptedPii = EncryptedPii;
+	type MaxDataKeyHolders = ConstU32<4>;
+	type MaxPendingErasures = ConstU32<2>;
+	type MaxPendingDeletions = ConstU32<2>;
+	type MaxRemovalStepsPerBlock = ConstU32<4>;
 }
 
+// Account that holds no role in the registry.
+pub const UNAUTHORIZED: u64 = 99;
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	frame_system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut t = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_medical_record::GenesisConfig::<Test> {
+		initial_roles: vec![
+			(1, Role::Admin),
+			(2, Role::Admin),
+			(1, Role::Doctor),
+			(2, Role::Doctor),
+			(3, Role::Doctor),
+			(4, Role::Doctor),
+			(5, Role::Doctor),
+		],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	BookedAppointments::take();
+	EncryptedPii::take();
+
+	let mut ext: sp_io::TestExternalities = t.into();
+	// Go past genesis block so events get deposited
+	ext.execute_with(|| System::set_block_number(1));
+	ext
 }
@@ -1,5 +1,61 @@
-use crate::{mock::*, Error, Event, PatientInfo, ClinicalTest, DiseaseProgression, MedicalRecord, Patients, ClinicalTests, DiseaseProgressions, MedicalRecords};
-use frame_support::{assert_noop, assert_ok};
+use crate::{mock::*, AccessKind, ConsentScope, Error, Event, NameIndex, Patients, ClinicalTests, DiseaseProgressions, MedicalRecords, OperationType, RecordType, Role, ScheduledAppointment};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	migrations::SteppedMigration,
+	weights::{Weight, WeightMeter},
+};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+
+// Time of the follow-up appointment booked in tests, a day after `NOW`
+const FOLLOW_UP: u64 = NOW + 86_400;
+
+// Account linked to a patient in tests
+fn patient_account(patient_id: u32) -> u64 {
+	100 + patient_id as u64
+}
+
+// Stand-in for the keyed hash clients compute over a normalised name; the pallet treats the
+// index as opaque
+fn name_index(name: &[u8]) -> NameIndex {
+	BlakeTwo256::hash(name).0
+}
+
+// A document hosted at an `https://` URL
+fn url_pointer(url: &[u8]) -> crate::DataPointerOf<Test> {
+	crate::DataPointer {
+		location: crate::DataLocation::Url(url.to_vec().try_into().unwrap()),
+		content_hash: sp_io::hashing::sha2_256(url),
+		size: 1024,
+	}
+}
+
+// Link the patient's account and have it grant full consent on every record type
+fn give_consent(patient_id: u32, grantee: u64) {
+	if MedicalRecord::patient_account(patient_id).is_none() {
+		assert_ok!(MedicalRecord::link_patient_account(
+			RuntimeOrigin::signed(1),
+			patient_id,
+			patient_account(patient_id),
+		));
+	}
+	for record_type in [
+		RecordType::Patient,
+		RecordType::ClinicalTest,
+		RecordType::DiseaseProgression,
+		RecordType::MedicalRecord,
+	] {
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(patient_id)),
+			patient_id,
+			grantee,
+			record_type,
+			ConsentScope { read: true, append: true, amend: true },
+			None,
+		));
+	}
+}
 
 #[test]
 fn create_patient_works() {
@@ -13,6 +69,7 @@ fn create_patient_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Check that the patient was created
@@ -21,10 +78,7 @@ fn create_patient_works() {
 		assert_eq!(patient.gender, b"Male".to_vec());
 
 		// Check that the event was emitted
-		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::PatientCreated {
-			patient_id: 0,
-			patient_name: b"John Doe".to_vec(),
-		}));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::PatientCreated { patient_id: 0 }));
 	});
 }
 
@@ -40,6 +94,7 @@ fn update_patient_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Update the patient
@@ -52,6 +107,7 @@ fn update_patient_works() {
 			Some(b"456 Oak Ave".to_vec()),
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		// Check that the patient was updated
@@ -73,6 +129,7 @@ fn delete_patient_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Delete the patient
@@ -95,9 +152,11 @@ fn create_clinical_test_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a clinical test
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_clinical_test(
 			RuntimeOrigin::signed(2),
 			0,
@@ -127,9 +186,11 @@ fn create_disease_progression_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a disease progression record
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_disease_progression(
 			RuntimeOrigin::signed(2),
 			0,
@@ -138,7 +199,7 @@ fn create_disease_progression_works() {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			b"2023-01-22".to_vec(),
+			Some(FOLLOW_UP),
 		));
 
 		// Check that the disease progression was created
@@ -161,6 +222,7 @@ fn create_patient_with_empty_name_fails() {
 				b"123 Main St".to_vec(),
 				b"555-1234".to_vec(),
 				b"Jane Doe - 555-5678".to_vec(),
+				name_index(b""),
 			),
 			Error::<Test>::InvalidPatientData
 		);
@@ -180,6 +242,7 @@ fn update_nonexistent_patient_fails() {
 				None,
 				None,
 				None,
+				Some(name_index(b"John Smith")),
 			),
 			Error::<Test>::PatientNotFound
 		);
@@ -215,9 +278,11 @@ fn create_disease_progression_without_appointment_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
-		// Create a disease progression record WITHOUT next appointment (empty vec)
+		// Create a disease progression record WITHOUT next appointment
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_disease_progression(
 			RuntimeOrigin::signed(2),
 			0,
@@ -226,16 +291,17 @@ fn create_disease_progression_without_appointment_works() {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			Vec::new(), // No next appointment
+			None,
 		));
 
 		// Check that the disease progression was created with no appointment
 		let progression = DiseaseProgressions::<Test>::get(0).unwrap();
 		assert_eq!(progression.patient_id, 0);
-		assert_eq!(progression.next_appointment, Vec::<u8>::new());
+		assert_eq!(progression.next_appointment, None);
+		assert!(BookedAppointments::get().is_empty());
 		
 		// Check helper function
-		assert!(!MedicalRecord::has_next_appointment(0));
+		assert!(!has_follow_up(0));
 	});
 }
 
@@ -251,9 +317,11 @@ fn set_next_appointment_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a disease progression record without next appointment
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_disease_progression(
 			RuntimeOrigin::signed(2),
 			0,
@@ -262,20 +330,31 @@ fn set_next_appointment_works() {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			Vec::new(), // No appointment initially
+			None,
 		));
 
 		// Set next appointment
-		assert_ok!(MedicalRecord::set_next_appointment(
-			RuntimeOrigin::signed(2),
-			0,
-			b"2023-01-22 10:00".to_vec(),
-		));
+		assert_ok!(MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP));
 
-		// Check that the appointment was set
+		// Check that an appointment was booked for the patient's account with the doctor
 		let progression = DiseaseProgressions::<Test>::get(0).unwrap();
-		assert_eq!(progression.next_appointment, b"2023-01-22 10:00".to_vec());
-		assert!(MedicalRecord::has_next_appointment(0));
+		assert_eq!(progression.next_appointment, Some(0));
+		assert_eq!(
+			BookedAppointments::get().get(&0),
+			Some(&MockAppointment {
+				patient: patient_account(0),
+				doctor: 2,
+				scheduled_time: FOLLOW_UP,
+				cancelled: false,
+			})
+		);
+		assert!(has_follow_up(0));
+
+		// Setting it again moves the same appointment
+		assert_ok!(MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP + 3_600));
+		assert_eq!(DiseaseProgressions::<Test>::get(0).unwrap().next_appointment, Some(0));
+		assert_eq!(BookedAppointments::get().len(), 1);
+		assert_eq!(BookedAppointments::get()[&0].scheduled_time, FOLLOW_UP + 3_600);
 	});
 }
 
@@ -291,9 +370,11 @@ fn clear_appointment_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a disease progression record with next appointment
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_disease_progression(
 			RuntimeOrigin::signed(2),
 			0,
@@ -302,68 +383,140 @@ fn clear_appointment_works() {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			b"2023-01-22 10:00".to_vec(),
+			Some(FOLLOW_UP),
 		));
 
 		// Verify appointment is set
-		assert!(MedicalRecord::has_next_appointment(0));
+		assert!(has_follow_up(0));
 
 		// Clear the appointment
 		assert_ok!(MedicalRecord::clear_appointment(RuntimeOrigin::signed(2), 0));
 
-		// Check that the appointment was cleared
+		// Check that the appointment was cancelled and unlinked
 		let progression = DiseaseProgressions::<Test>::get(0).unwrap();
-		assert_eq!(progression.next_appointment, Vec::<u8>::new());
-		assert!(!MedicalRecord::has_next_appointment(0));
+		assert_eq!(progression.next_appointment, None);
+		assert!(BookedAppointments::get()[&0].cancelled);
+		assert!(!has_follow_up(0));
 	});
 }
 
+// Whether a progression has an upcoming follow-up appointment
+fn has_follow_up(progression_id: u32) -> bool {
+	DiseaseProgressions::<Test>::get(progression_id)
+		.and_then(|progression| MedicalRecord::next_appointment_of(&progression))
+		.is_some()
+}
+
+// Create a progression of patient 0 by doctor 2, optionally booking its follow-up
+fn create_progression(next_appointment: Option<u64>) -> sp_runtime::DispatchResult {
+	MedicalRecord::create_disease_progression(
+		RuntimeOrigin::signed(2),
+		0,
+		b"2023-01-15".to_vec(),
+		b"Fever".to_vec(),
+		b"Cold".to_vec(),
+		b"Rest".to_vec(),
+		b"Medicine".to_vec(),
+		next_appointment,
+	)
+}
+
 #[test]
-fn get_scheduled_appointments_filters_correctly() {
+fn booking_follow_up_needs_patient_account() {
 	new_test_ext().execute_with(|| {
-		// First create a patient
-		assert_ok!(MedicalRecord::create_patient(
-			RuntimeOrigin::signed(1),
-			b"John Doe".to_vec(),
-			b"1990-01-01".to_vec(),
-			b"Male".to_vec(),
-			b"123 Main St".to_vec(),
-			b"555-1234".to_vec(),
-			b"Jane Doe - 555-5678".to_vec(),
-		));
+		create_linked_patient();
+		give_consent(0, 2);
+		crate::PatientAccounts::<Test>::remove(0);
 
-		// Create progression WITH appointment
-		assert_ok!(MedicalRecord::create_disease_progression(
-			RuntimeOrigin::signed(2),
-			0,
-			b"2023-01-15".to_vec(),
-			b"Fever".to_vec(),
-			b"Cold".to_vec(),
-			b"Rest".to_vec(),
-			b"Medicine".to_vec(),
-			b"2023-01-22 10:00".to_vec(), // HAS appointment
-		));
+		assert_noop!(create_progression(Some(FOLLOW_UP)), Error::<Test>::PatientAccountNotLinked);
+
+		// A progression without a follow-up does not need one
+		assert_ok!(create_progression(None));
+		assert_noop!(
+			MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP),
+			Error::<Test>::PatientAccountNotLinked
+		);
+	});
+}
+
+#[test]
+fn failed_booking_rejects_progression() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_noop!(create_progression(Some(NOW)), sp_runtime::DispatchError::Other("appointment in the past"));
+		assert!(DiseaseProgressions::<Test>::get(0).is_none());
+		assert!(BookedAppointments::get().is_empty());
+	});
+}
+
+#[test]
+fn only_the_booked_doctor_moves_or_cancels_a_follow_up() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(create_progression(Some(FOLLOW_UP)));
+
+		// Doctor 3 may amend the progression, but the follow-up is booked with doctor 2
+		give_consent(0, 3);
+		let not_the_doctor = sp_runtime::DispatchError::Other("not the doctor");
+		assert_noop!(
+			MedicalRecord::set_next_appointment(RuntimeOrigin::signed(3), 0, FOLLOW_UP + 3_600),
+			not_the_doctor
+		);
+		assert_noop!(MedicalRecord::clear_appointment(RuntimeOrigin::signed(3), 0), not_the_doctor);
+		assert_eq!(BookedAppointments::get()[&0].scheduled_time, FOLLOW_UP);
+		assert!(has_follow_up(0));
+	});
+}
+
+#[test]
+fn cancelled_appointment_is_booked_again() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(create_progression(Some(FOLLOW_UP)));
+
+		// Cancelled in the appointment book, e.g. by the patient
+		BookedAppointments::mutate(|booked| booked.get_mut(&0).unwrap().cancelled = true);
+		assert!(!has_follow_up(0));
+		assert!(MedicalRecord::get_patients_with_appointments(None, 10).items.is_empty());
+
+		assert_ok!(MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP));
+		assert_eq!(DiseaseProgressions::<Test>::get(0).unwrap().next_appointment, Some(1));
+		assert!(has_follow_up(0));
+	});
+}
 
-		// Create progression WITHOUT appointment
+#[test]
+fn patients_with_appointments_show_earliest_follow_up() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		give_consent(0, 3);
+		assert_ok!(create_progression(Some(FOLLOW_UP + 86_400)));
 		assert_ok!(MedicalRecord::create_disease_progression(
-			RuntimeOrigin::signed(2),
+			RuntimeOrigin::signed(3),
 			0,
-			b"2023-01-22".to_vec(),
-			b"Better".to_vec(),
-			b"Recovering".to_vec(),
-			b"Continue".to_vec(),
-			b"Same medicine".to_vec(),
-			Vec::new(), // NO appointment
+			b"2023-01-16".to_vec(),
+			b"Cough".to_vec(),
+			b"Bronchitis".to_vec(),
+			b"Rest".to_vec(),
+			b"Syrup".to_vec(),
+			Some(FOLLOW_UP),
 		));
+		assert_ok!(create_progression(None));
 
-		// Get scheduled appointments (should only return the first one)
-		let scheduled = MedicalRecord::get_scheduled_appointments(0);
-		assert_eq!(scheduled.len(), 1);
-		assert_eq!(scheduled[0].next_appointment, b"2023-01-22 10:00".to_vec());
-
-		// Get all progressions (should return both)
-		let all = MedicalRecord::get_patient_progressions(0);
-		assert_eq!(all.len(), 2);
+		let page = MedicalRecord::get_patients_with_appointments(None, 10);
+		assert_eq!(
+			page.items,
+			vec![(
+				0,
+				b"John Doe".to_vec(),
+				ScheduledAppointment { appointment_id: 1, doctor_id: 3, scheduled_time: FOLLOW_UP },
+			)]
+		);
 	});
 }
 
@@ -379,15 +532,17 @@ fn create_legacy_medical_record_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a legacy medical record
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_medical_record(
 			RuntimeOrigin::signed(2),
 			0u32, // Patient ID as u32
 			b"Flu diagnosis".to_vec(),
 			b"Rest and medication".to_vec(),
-			123u32,
+			Some(url_pointer(b"https://lab.example/record-123")),
 		));
 
 		// Check that the medical record was created
@@ -414,7 +569,7 @@ fn create_legacy_medical_record_for_nonexistent_patient_fails() {
 				999u32, // Nonexistent patient ID
 				b"Flu diagnosis".to_vec(),
 				b"Rest and medication".to_vec(),
-				123u32,
+				Some(url_pointer(b"https://lab.example/record-123")),
 			),
 			Error::<Test>::PatientNotFound
 		);
@@ -433,18 +588,19 @@ fn search_patient_by_name_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Search for the patient by name
 		assert_ok!(MedicalRecord::search_patient_by_name(
 			RuntimeOrigin::signed(1),
-			b"John Doe".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Check that the search event was emitted
 		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::PatientFoundByName {
 			patient_id: 0,
-			patient_name: b"John Doe".to_vec(),
+			name_index: name_index(b"John Doe"),
 		}));
 	});
 }
@@ -455,7 +611,7 @@ fn search_nonexistent_patient_by_name_fails() {
 		assert_noop!(
 			MedicalRecord::search_patient_by_name(
 				RuntimeOrigin::signed(1),
-				b"Nonexistent Patient".to_vec(),
+				name_index(b"Nonexistent Patient"),
 			),
 			Error::<Test>::PatientNotFoundByName
 		);
@@ -463,32 +619,59 @@ fn search_nonexistent_patient_by_name_fails() {
 }
 
 #[test]
-fn get_patient_by_name_helper_works() {
+fn find_patients_by_name_view_function_works() {
 	new_test_ext().execute_with(|| {
-		// Create a patient
-		assert_ok!(MedicalRecord::create_patient(
-			RuntimeOrigin::signed(1),
-			b"Alice Smith".to_vec(),
-			b"1985-05-15".to_vec(),
-			b"Female".to_vec(),
-			b"456 Oak St".to_vec(),
-			b"555-9876".to_vec(),
-			b"Bob Smith - 555-5432".to_vec(),
-		));
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"John Doe")), Vec::<u32>::new());
+
+		create_patients(2);
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"Patient 1")), vec![1]);
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"Nonexistent Patient")), Vec::<u32>::new());
+	});
+}
+
+#[test]
+fn patient_complete_history_view_function_works() {
+	new_test_ext().execute_with(|| {
+		assert!(MedicalRecord::patient_complete_history(0).is_none());
 
-		// Use helper function to get patient by name
-		let patient = MedicalRecord::get_patient_by_name(b"Alice Smith").unwrap();
-		assert_eq!(patient.patient_id, 0);
-		assert_eq!(patient.patient_name, b"Alice Smith".to_vec());
-		assert_eq!(patient.gender, b"Female".to_vec());
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"All values within range".to_vec(),
+		));
+		assert_ok!(MedicalRecord::create_disease_progression(
+			RuntimeOrigin::signed(2),
+			0,
+			b"2023-01-15".to_vec(),
+			b"Fever".to_vec(),
+			b"Cold".to_vec(),
+			b"Rest".to_vec(),
+			b"Water".to_vec(),
+			None,
+		));
+		assert_ok!(MedicalRecord::create_medical_record(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Flu diagnosis".to_vec(),
+			b"Rest and medication".to_vec(),
+			None,
+		));
 
-		// Test with nonexistent name
-		assert_eq!(MedicalRecord::get_patient_by_name(b"Nonexistent"), None);
+		let history = MedicalRecord::patient_complete_history(0).unwrap();
+		assert_eq!(history.patient, Patients::<Test>::get(0).unwrap());
+		assert_eq!(history.clinical_tests, vec![ClinicalTests::<Test>::get(0).unwrap()]);
+		assert_eq!(history.disease_progressions, vec![DiseaseProgressions::<Test>::get(0).unwrap()]);
+		assert_eq!(history.medical_records, vec![MedicalRecords::<Test>::get(0).unwrap()]);
 	});
 }
 
 #[test]
-fn create_patient_with_duplicate_name_fails() {
+fn patients_can_share_a_name() {
 	new_test_ext().execute_with(|| {
 		// Create first patient
 		assert_ok!(MedicalRecord::create_patient(
@@ -499,21 +682,50 @@ fn create_patient_with_duplicate_name_fails() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
-		// Try to create another patient with same name
-		assert_noop!(
+		// A different patient with the same name is indexed under the same entry
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(2),
+			b"John Doe".to_vec(), // Same name
+			b"1985-05-15".to_vec(),
+			b"Male".to_vec(),
+			b"456 Oak St".to_vec(),
+			b"555-9876".to_vec(),
+			b"Mary Doe - 555-5432".to_vec(),
+			name_index(b"John Doe"),
+		));
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"John Doe")), vec![0, 1]);
+
+		assert_ok!(MedicalRecord::search_patient_by_name(RuntimeOrigin::signed(1), name_index(b"John Doe")));
+		System::assert_last_event(
+			Event::MultiplePatientsFoundByName { patient_ids: vec![0, 1], name_index: name_index(b"John Doe") }
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn name_index_entry_is_bounded() {
+	new_test_ext().execute_with(|| {
+		let create = || {
 			MedicalRecord::create_patient(
-				RuntimeOrigin::signed(2),
-				b"John Doe".to_vec(), // Same name
-				b"1985-05-15".to_vec(),
+				RuntimeOrigin::signed(1),
+				b"John Doe".to_vec(),
+				b"1990-01-01".to_vec(),
 				b"Male".to_vec(),
-				b"456 Oak St".to_vec(),
-				b"555-9876".to_vec(),
-				b"Mary Doe - 555-5432".to_vec(),
-			),
-			Error::<Test>::PatientNameAlreadyExists
-		);
+				b"123 Main St".to_vec(),
+				b"555-1234".to_vec(),
+				b"Jane Doe - 555-5678".to_vec(),
+				name_index(b"John Doe"),
+			)
+		};
+		for _ in 0..4 {
+			assert_ok!(create());
+		}
+
+		assert_noop!(create(), Error::<Test>::TooManyPatientsWithName);
 	});
 }
 
@@ -529,6 +741,7 @@ fn update_patient_name_updates_mapping() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Update patient name
@@ -541,22 +754,23 @@ fn update_patient_name_updates_mapping() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		// Old name should not exist
-		assert!(!MedicalRecord::patient_name_exists(b"John Doe"));
+		assert!(!MedicalRecord::patient_name_exists(&name_index(b"John Doe")));
 		// New name should exist
-		assert!(MedicalRecord::patient_name_exists(b"John Smith"));
+		assert!(MedicalRecord::patient_name_exists(&name_index(b"John Smith")));
 		
 		// Search by new name should work
-		assert_eq!(MedicalRecord::get_patient_id_by_name(b"John Smith"), Some(0));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"John Smith")), Some(0));
 		// Search by old name should fail
-		assert_eq!(MedicalRecord::get_patient_id_by_name(b"John Doe"), None);
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"John Doe")), None);
 	});
 }
 
 #[test]
-fn update_patient_name_to_existing_name_fails() {
+fn update_patient_name_to_existing_name_works() {
 	new_test_ext().execute_with(|| {
 		// Create first patient
 		assert_ok!(MedicalRecord::create_patient(
@@ -567,6 +781,7 @@ fn update_patient_name_to_existing_name_fails() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create second patient
@@ -578,22 +793,23 @@ fn update_patient_name_to_existing_name_fails() {
 			b"456 Oak St".to_vec(),
 			b"555-9876".to_vec(),
 			b"Bob Smith - 555-5432".to_vec(),
+			name_index(b"Alice Smith"),
 		));
 
-		// Try to update second patient's name to first patient's name
-		assert_noop!(
-			MedicalRecord::update_patient(
-				RuntimeOrigin::signed(1),
-				1,
-				Some(b"John Doe".to_vec()), // Already exists
-				None,
-				None,
-				None,
-				None,
-				None,
-			),
-			Error::<Test>::PatientNameAlreadyExists
-		);
+		// Rename the second patient to the first patient's name
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			1,
+			Some(b"John Doe".to_vec()), // Already exists
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(name_index(b"John Doe")),
+		));
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"John Doe")), vec![0, 1]);
+		assert!(!MedicalRecord::patient_name_exists(&name_index(b"Alice Smith")));
 	});
 }
 
@@ -609,17 +825,105 @@ fn delete_patient_removes_name_mapping() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Verify name mapping exists
-		assert!(MedicalRecord::patient_name_exists(b"John Doe"));
+		assert!(MedicalRecord::patient_name_exists(&name_index(b"John Doe")));
 
 		// Delete the patient
 		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 0));
 
 		// Verify name mapping is removed
-		assert!(!MedicalRecord::patient_name_exists(b"John Doe"));
-		assert_eq!(MedicalRecord::get_patient_id_by_name(b"John Doe"), None);
+		assert!(!MedicalRecord::patient_name_exists(&name_index(b"John Doe")));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"John Doe")), None);
+	});
+}
+
+#[test]
+fn name_lookup_is_keyed_by_blinded_index() {
+	new_test_ext().execute_with(|| {
+		create_patients(2);
+
+		// Only the blinded index is stored as a key, never the name itself
+		let keys: Vec<_> = crate::PatientsByName::<Test>::iter_keys().collect();
+		assert_eq!(keys.len(), 2);
+		assert!(keys.contains(&name_index(b"Patient 0")));
+		assert!(keys.iter().all(|key| !key.windows(9).any(|bytes| bytes == b"Patient 0")));
+		assert_eq!(MedicalRecord::name_index_of(1), Some(name_index(b"Patient 1")));
+	});
+}
+
+#[test]
+fn rename_requires_new_name_index() {
+	new_test_ext().execute_with(|| {
+		create_patients(1);
+
+		assert_noop!(
+			MedicalRecord::update_patient(
+				RuntimeOrigin::signed(1),
+				0,
+				Some(b"John Smith".to_vec()),
+				None,
+				None,
+				None,
+				None,
+				None,
+				None,
+			),
+			Error::<Test>::NameIndexRequired
+		);
+	});
+}
+
+#[test]
+fn patient_can_be_reindexed_without_renaming() {
+	new_test_ext().execute_with(|| {
+		create_patients(2);
+
+		// After the facility secret is rotated staff replace the index alone
+		let rotated = BlakeTwo256::hash(b"rotated secret Patient 0").0;
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(rotated),
+		));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&rotated), Some(0));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Patient 0")), None);
+		assert_eq!(MedicalRecord::name_index_of(0), Some(rotated));
+		assert_eq!(MedicalRecord::patients(0).unwrap().patient_name.to_vec(), b"Patient 0".to_vec());
+
+		// Re-indexing under the current index is a no-op, another patient's index is shared
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(rotated),
+		));
+		assert_eq!(MedicalRecord::find_patients_by_name(rotated), vec![0]);
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(name_index(b"Patient 1")),
+		));
+		assert_eq!(MedicalRecord::find_patients_by_name(name_index(b"Patient 1")), vec![1, 0]);
 	});
 }
 
@@ -635,6 +939,7 @@ fn modification_tracking_works_for_patient() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		let patient = Patients::<Test>::get(0).unwrap();
@@ -655,6 +960,7 @@ fn modification_tracking_works_for_patient() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		let updated_patient = Patients::<Test>::get(0).unwrap();
@@ -678,9 +984,11 @@ fn modification_tracking_works_for_clinical_test() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a clinical test
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_clinical_test(
 			RuntimeOrigin::signed(2),
 			0,
@@ -699,6 +1007,7 @@ fn modification_tracking_works_for_clinical_test() {
 		System::set_block_number(2);
 
 		// Update the test with different account
+		give_consent(0, 3);
 		assert_ok!(MedicalRecord::update_clinical_test(
 			RuntimeOrigin::signed(3),
 			0,
@@ -731,9 +1040,11 @@ fn modification_tracking_works_for_disease_progression() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Create a disease progression
+		give_consent(0, 2);
 		assert_ok!(MedicalRecord::create_disease_progression(
 			RuntimeOrigin::signed(2),
 			0,
@@ -742,7 +1053,7 @@ fn modification_tracking_works_for_disease_progression() {
 			b"Common cold".to_vec(),
 			b"Rest and fluids".to_vec(),
 			b"Paracetamol 500mg".to_vec(),
-			b"2023-01-22".to_vec(),
+			Some(FOLLOW_UP),
 		));
 
 		let progression = DiseaseProgressions::<Test>::get(0).unwrap();
@@ -753,6 +1064,7 @@ fn modification_tracking_works_for_disease_progression() {
 		System::set_block_number(2);
 
 		// Update the progression with different account
+		give_consent(0, 3);
 		assert_ok!(MedicalRecord::update_disease_progression(
 			RuntimeOrigin::signed(3),
 			0,
@@ -761,7 +1073,7 @@ fn modification_tracking_works_for_disease_progression() {
 			Some(b"Recovering from cold".to_vec()),
 			None,
 			None,
-			Some(b"2023-01-29".to_vec()),
+			None,
 		));
 
 		let updated_progression = DiseaseProgressions::<Test>::get(0).unwrap();
@@ -772,7 +1084,11 @@ fn modification_tracking_works_for_disease_progression() {
 		assert_eq!(updated_progression.last_modified_at, 2);
 		assert_eq!(updated_progression.symptoms, b"Feeling better".to_vec());
 		assert_eq!(updated_progression.diagnosis, b"Recovering from cold".to_vec());
-		assert_eq!(updated_progression.next_appointment, b"2023-01-29".to_vec());
+		// The appointment stays booked with the original doctor, only they can move it
+		assert_eq!(updated_progression.next_appointment, Some(0));
+		assert_eq!(BookedAppointments::get().len(), 1);
+		assert_eq!(BookedAppointments::get()[&0].doctor, 2);
+		assert_eq!(BookedAppointments::get()[&0].scheduled_time, FOLLOW_UP);
 	});
 }
 
@@ -788,6 +1104,7 @@ fn update_events_include_modifier_info() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Update the patient
@@ -800,6 +1117,7 @@ fn update_events_include_modifier_info() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		// Check that the update event includes modifier info
@@ -822,13 +1140,14 @@ fn modification_tracking_preserves_original_creator() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Multiple updates by different users
 		for i in 2..=5 {
 			System::set_block_number(i);
 			assert_ok!(MedicalRecord::update_patient(
-				RuntimeOrigin::signed(i as u64),
+				RuntimeOrigin::signed(i),
 				0,
 				None,
 				None,
@@ -836,11 +1155,12 @@ fn modification_tracking_preserves_original_creator() {
 				Some(format!("Address {}", i).into_bytes()),
 				None,
 				None,
+				None,
 			));
 
 			let patient = Patients::<Test>::get(0).unwrap();
 			assert_eq!(patient.created_by, 1); // Always original creator
-			assert_eq!(patient.last_modified_by, i as u64); // Latest modifier
+			assert_eq!(patient.last_modified_by, i); // Latest modifier
 			assert_eq!(patient.created_at, 1); // Original creation time
 			assert_eq!(patient.last_modified_at, i); // Latest modification time
 		}
@@ -859,6 +1179,7 @@ fn comprehensive_change_history_tracking_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Move to next block
@@ -874,6 +1195,7 @@ fn comprehensive_change_history_tracking_works() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		// Move to next block
@@ -889,6 +1211,7 @@ fn comprehensive_change_history_tracking_works() {
 			Some(b"456 Oak St".to_vec()),
 			None,
 			None,
+			None,
 		));
 
 		// Get complete history for this patient
@@ -908,21 +1231,35 @@ fn comprehensive_change_history_tracking_works() {
 		assert_eq!(update_changes.len(), 2);
 
 		// Find name change
-		let name_change = update_changes.iter().find(|h| h.field_name == b"patient_name").unwrap();
-		assert_eq!(name_change.old_value, Some(b"John Doe".to_vec()));
+		let name_change = update_changes.iter().find(|h| h.field_name.to_vec() == b"patient_name").unwrap();
+		assert_eq!(name_change.old_value.as_deref(), Some(&b"John Doe".to_vec()));
 		assert_eq!(name_change.new_value, b"John Smith".to_vec());
 		assert_eq!(name_change.changed_by, 2);
 		assert_eq!(name_change.changed_at, 2);
 
 		// Find address change
-		let address_change = update_changes.iter().find(|h| h.field_name == b"address").unwrap();
-		assert_eq!(address_change.old_value, Some(b"123 Main St".to_vec()));
+		let address_change = update_changes.iter().find(|h| h.field_name.to_vec() == b"address").unwrap();
+		assert_eq!(address_change.old_value.as_deref(), Some(&b"123 Main St".to_vec()));
 		assert_eq!(address_change.new_value, b"456 Oak St".to_vec());
 		assert_eq!(address_change.changed_by, 3);
 		assert_eq!(address_change.changed_at, 3);
 	});
 }
 
+// Collect the items of every page of a paginated query, following `next_cursor`
+fn walk_pages<Item>(query: impl Fn(Option<u32>) -> crate::Page<Item>) -> Vec<Item> {
+	let mut items = Vec::new();
+	let mut cursor = None;
+	loop {
+		let page = query(cursor);
+		items.extend(page.items);
+		match page.next_cursor {
+			Some(next) => cursor = Some(next),
+			None => return items,
+		}
+	}
+}
+
 #[test]
 fn get_changes_by_user_works() {
 	new_test_ext().execute_with(|| {
@@ -935,6 +1272,7 @@ fn get_changes_by_user_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		assert_ok!(MedicalRecord::create_patient(
@@ -945,6 +1283,7 @@ fn get_changes_by_user_works() {
 			b"456 Oak St".to_vec(),
 			b"555-9876".to_vec(),
 			b"Bob Smith - 555-5432".to_vec(),
+			name_index(b"Alice Smith"),
 		));
 
 		// Update first patient with user 2
@@ -957,16 +1296,22 @@ fn get_changes_by_user_works() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Updated")),
 		));
 
 		// Get changes by user 1 (should have 6 creation changes)
-		let user1_changes = MedicalRecord::get_changes_by_user(&1);
+		let user1_changes = walk_pages(|cursor| MedicalRecord::get_changes_by_user(&1, cursor, 5));
 		assert_eq!(user1_changes.len(), 6);
 		assert!(user1_changes.iter().all(|c| matches!(c.operation, crate::OperationType::Create)));
 
 		// Get changes by user 2 (should have 6 creation + 1 update = 7 changes)
-		let user2_changes = MedicalRecord::get_changes_by_user(&2);
+		let user2_changes = walk_pages(|cursor| MedicalRecord::get_changes_by_user(&2, cursor, 5));
 		assert_eq!(user2_changes.len(), 7);
+
+		// Each page reads at most `limit` changes, so it can hold fewer matching items
+		let first = MedicalRecord::get_changes_by_user(&2, None, 5);
+		assert!(first.items.is_empty());
+		assert_eq!(first.next_cursor, Some(4));
 		
 		let user2_creates = user2_changes.iter().filter(|c| matches!(c.operation, crate::OperationType::Create)).count();
 		let user2_updates = user2_changes.iter().filter(|c| matches!(c.operation, crate::OperationType::Update)).count();
@@ -987,6 +1332,7 @@ fn get_changes_in_time_range_works() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Block 2: Update patient
@@ -1000,6 +1346,7 @@ fn get_changes_in_time_range_works() {
 			None,
 			None,
 			None,
+			Some(name_index(b"John Smith")),
 		));
 
 		// Block 3: Another update
@@ -1013,16 +1360,17 @@ fn get_changes_in_time_range_works() {
 			Some(b"New Address".to_vec()),
 			None,
 			None,
+			None,
 		));
 
 		// Get changes from block 1 to 2
-		let range_changes = MedicalRecord::get_changes_in_range(1, 2);
+		let range_changes = walk_pages(|cursor| MedicalRecord::get_changes_in_range(1, 2, cursor, 10));
 		assert_eq!(range_changes.len(), 7); // 6 creates + 1 update
 
 		// Get changes only from block 3
-		let block3_changes = MedicalRecord::get_changes_in_range(3, 3);
+		let block3_changes = walk_pages(|cursor| MedicalRecord::get_changes_in_range(3, 3, cursor, 10));
 		assert_eq!(block3_changes.len(), 1); // 1 address update
-		assert_eq!(block3_changes[0].field_name, b"address");
+		assert_eq!(block3_changes[0].field_name.to_vec(), b"address");
 	});
 }
 
@@ -1039,6 +1387,7 @@ fn get_latest_changes_works() {
 				b"123 Main St".to_vec(),
 				b"555-1234".to_vec(),
 				b"Emergency Contact".to_vec(),
+				name_index(&format!("Patient {}", i).into_bytes()),
 			));
 		}
 
@@ -1046,17 +1395,24 @@ fn get_latest_changes_works() {
 		let total_changes = MedicalRecord::next_change_id();
 		assert_eq!(total_changes, 18);
 
-		// Get latest 5 changes
-		let latest_5 = MedicalRecord::get_latest_changes(5);
-		assert_eq!(latest_5.len(), 5);
-		
-		// Get latest 10 changes
-		let latest_10 = MedicalRecord::get_latest_changes(10);
-		assert_eq!(latest_10.len(), 10);
+		// Get latest 5 changes, newest first
+		let latest_5 = MedicalRecord::get_latest_changes(None, 5);
+		let ids: Vec<u32> = latest_5.items.iter().map(|change| change.change_id).collect();
+		assert_eq!(ids, vec![17, 16, 15, 14, 13]);
+		assert_eq!(latest_5.next_cursor, Some(13));
+
+		// The next page goes on with the changes made before
+		let next_5 = MedicalRecord::get_latest_changes(latest_5.next_cursor, 5);
+		assert_eq!(next_5.items.first().map(|change| change.change_id), Some(12));
+
+		// Pages are capped at MaxPageSize (10 in the mock)
+		let capped = MedicalRecord::get_latest_changes(None, 50);
+		assert_eq!(capped.items.len(), 10);
 
 		// Get all changes (more than exist)
-		let all_changes = MedicalRecord::get_latest_changes(50);
+		let all_changes = walk_pages(|cursor| MedicalRecord::get_latest_changes(cursor, 50));
 		assert_eq!(all_changes.len(), 18);
+		assert_eq!(all_changes.last().map(|change| change.change_id), Some(0));
 	});
 }
 
@@ -1072,6 +1428,7 @@ fn delete_patient_records_audit_trail() {
 			b"123 Main St".to_vec(),
 			b"555-1234".to_vec(),
 			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
 		));
 
 		// Delete the patient
@@ -1083,32 +1440,2631 @@ fn delete_patient_records_audit_trail() {
 		assert_eq!(delete_changes.len(), 1);
 		
 		let delete_change = delete_changes[0];
-		assert_eq!(delete_change.field_name, b"deleted");
-		assert_eq!(delete_change.old_value, Some(b"active".to_vec()));
+		assert_eq!(delete_change.field_name.to_vec(), b"deleted");
+		assert_eq!(delete_change.old_value.as_deref(), Some(&b"active".to_vec()));
 		assert_eq!(delete_change.new_value, b"deleted".to_vec());
 		assert_eq!(delete_change.changed_by, 2);
 	});
 }
 
+// Field, old value, new value, author and operation of a recorded change
+type ChangeSummary = (Vec<u8>, Option<Vec<u8>>, Vec<u8>, u64, OperationType);
+
+// Every change to a record, oldest first
+fn history_of(record_type: RecordType, record_id: u32) -> Vec<ChangeSummary> {
+	MedicalRecord::get_record_history(record_type, record_id)
+		.into_iter()
+		.map(|change| {
+			(
+				change.field_name.to_vec(),
+				change.old_value.map(|value| value.to_vec()),
+				change.new_value.to_vec(),
+				change.changed_by,
+				change.operation,
+			)
+		})
+		.collect()
+}
+
 #[test]
-fn change_events_are_emitted() {
+fn clinical_test_history_is_complete() {
 	new_test_ext().execute_with(|| {
-		// Create a patient
-		assert_ok!(MedicalRecord::create_patient(
-			RuntimeOrigin::signed(1),
-			b"John Doe".to_vec(),
-			b"1990-01-01".to_vec(),
-			b"Male".to_vec(),
-			b"123 Main St".to_vec(),
-			b"555-1234".to_vec(),
-			b"Jane Doe - 555-5678".to_vec(),
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Pending".to_vec(),
+			b"".to_vec(),
 		));
-
-		// Check that change events were emitted (6 for each field)
-		let events = System::events();
-		let change_events: Vec<_> = events.iter()
-			.filter(|e| matches!(e.event, RuntimeEvent::MedicalRecord(crate::Event::ChangeRecorded { .. })))
-			.collect();
+		assert_ok!(MedicalRecord::update_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			None,
+			None,
+			Some(b"Normal".to_vec()),
+			Some(b"All values within range".to_vec()),
+		));
+		assert_ok!(MedicalRecord::delete_clinical_test(RuntimeOrigin::signed(1), 0));
+
+		assert_eq!(
+			history_of(RecordType::ClinicalTest, 0),
+			vec![
+				(b"test_type".to_vec(), None, b"Blood Test".to_vec(), 2, OperationType::Create),
+				(b"test_date".to_vec(), None, b"2023-01-15".to_vec(), 2, OperationType::Create),
+				(b"result".to_vec(), None, b"Pending".to_vec(), 2, OperationType::Create),
+				(b"notes".to_vec(), None, b"".to_vec(), 2, OperationType::Create),
+				(b"result".to_vec(), Some(b"Pending".to_vec()), b"Normal".to_vec(), 2, OperationType::Update),
+				(b"notes".to_vec(), Some(b"".to_vec()), b"All values within range".to_vec(), 2, OperationType::Update),
+				// The admin who deleted the test is on record
+				(b"deleted".to_vec(), Some(b"active".to_vec()), b"deleted".to_vec(), 1, OperationType::Delete),
+			]
+		);
+	});
+}
+
+#[test]
+fn disease_progression_history_is_complete() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(create_progression(None));
+		assert_ok!(MedicalRecord::update_disease_progression(
+			RuntimeOrigin::signed(2),
+			0,
+			None,
+			Some(b"High fever".to_vec()),
+			Some(b"Flu".to_vec()),
+			None,
+			None,
+			Some(FOLLOW_UP),
+		));
+		// Moving the follow-up keeps the linked appointment, so it adds no change
+		assert_ok!(MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP + 3_600));
+		assert_ok!(MedicalRecord::clear_appointment(RuntimeOrigin::signed(2), 0));
+		assert_ok!(MedicalRecord::delete_disease_progression(RuntimeOrigin::signed(2), 0));
+
+		let none = Option::<u32>::None.encode();
+		let booked = Some(0u32).encode();
+		assert_eq!(
+			history_of(RecordType::DiseaseProgression, 0),
+			vec![
+				(b"visit_date".to_vec(), None, b"2023-01-15".to_vec(), 2, OperationType::Create),
+				(b"symptoms".to_vec(), None, b"Fever".to_vec(), 2, OperationType::Create),
+				(b"diagnosis".to_vec(), None, b"Cold".to_vec(), 2, OperationType::Create),
+				(b"treatment".to_vec(), None, b"Rest".to_vec(), 2, OperationType::Create),
+				(b"prescription".to_vec(), None, b"Medicine".to_vec(), 2, OperationType::Create),
+				(b"next_appointment".to_vec(), None, none.clone(), 2, OperationType::Create),
+				(b"symptoms".to_vec(), Some(b"Fever".to_vec()), b"High fever".to_vec(), 2, OperationType::Update),
+				(b"diagnosis".to_vec(), Some(b"Cold".to_vec()), b"Flu".to_vec(), 2, OperationType::Update),
+				(b"next_appointment".to_vec(), Some(none.clone()), booked.clone(), 2, OperationType::Update),
+				(b"next_appointment".to_vec(), Some(booked), none, 2, OperationType::Update),
+				(b"deleted".to_vec(), Some(b"active".to_vec()), b"deleted".to_vec(), 2, OperationType::Delete),
+			]
+		);
+	});
+}
+
+#[test]
+fn medical_record_history_is_complete() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(MedicalRecord::create_medical_record(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Flu diagnosis".to_vec(),
+			b"Rest and medication".to_vec(),
+			Some(url_pointer(b"https://lab.example/record-123")),
+		));
+		assert_ok!(MedicalRecord::create_medical_record(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Checkup".to_vec(),
+			b"None".to_vec(),
+			None,
+		));
+
+		assert_eq!(
+			history_of(RecordType::MedicalRecord, 0),
+			vec![
+				(b"diagnosis".to_vec(), None, b"Flu diagnosis".to_vec(), 2, OperationType::Create),
+				(b"treatment".to_vec(), None, b"Rest and medication".to_vec(), 2, OperationType::Create),
+				(b"data_pointer".to_vec(), None, url_pointer(b"https://lab.example/record-123").encode(), 2, OperationType::Create),
+			]
+		);
+		assert_eq!(
+			history_of(RecordType::MedicalRecord, 1),
+			vec![
+				(b"diagnosis".to_vec(), None, b"Checkup".to_vec(), 2, OperationType::Create),
+				(b"treatment".to_vec(), None, b"None".to_vec(), 2, OperationType::Create),
+			]
+		);
+	});
+}
+
+#[test]
+fn patient_history_is_complete() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			Some(b"555-0000".to_vec()),
+			None,
+			None,
+		));
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(2), 0));
+
+		assert_eq!(
+			history_of(RecordType::Patient, 0),
+			vec![
+				(b"patient_name".to_vec(), None, b"John Doe".to_vec(), 1, OperationType::Create),
+				(b"date_of_birth".to_vec(), None, b"1990-01-01".to_vec(), 1, OperationType::Create),
+				(b"gender".to_vec(), None, b"Male".to_vec(), 1, OperationType::Create),
+				(b"address".to_vec(), None, b"123 Main St".to_vec(), 1, OperationType::Create),
+				(b"phone".to_vec(), None, b"555-1234".to_vec(), 1, OperationType::Create),
+				(b"emergency_contact".to_vec(), None, b"Jane Doe - 555-5678".to_vec(), 1, OperationType::Create),
+				(b"phone".to_vec(), Some(b"555-1234".to_vec()), b"555-0000".to_vec(), 1, OperationType::Update),
+				(b"deleted".to_vec(), Some(b"active".to_vec()), b"deleted".to_vec(), 2, OperationType::Delete),
+			]
+		);
+	});
+}
+
+#[test]
+fn change_events_are_emitted() {
+	new_test_ext().execute_with(|| {
+		// Create a patient
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(1),
+			b"John Doe".to_vec(),
+			b"1990-01-01".to_vec(),
+			b"Male".to_vec(),
+			b"123 Main St".to_vec(),
+			b"555-1234".to_vec(),
+			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
+		));
+
+		// Check that change events were emitted (6 for each field)
+		let events = System::events();
+		let change_events: Vec<_> = events.iter()
+			.filter(|e| matches!(e.event, RuntimeEvent::MedicalRecord(crate::Event::ChangeRecorded { .. })))
+			.collect();
 		assert_eq!(change_events.len(), 6);
 	});
-}
\ No newline at end of file
+}
+#[test]
+fn grant_and_revoke_role_works() {
+	new_test_ext().execute_with(|| {
+		assert!(!MedicalRecord::has_role(&10, Role::Nurse));
+
+		assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), 10, Role::Nurse));
+		assert!(MedicalRecord::has_role(&10, Role::Nurse));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::RoleGranted {
+			who: 10,
+			role: Role::Nurse,
+		}));
+
+		assert_noop!(
+			MedicalRecord::grant_role(RuntimeOrigin::root(), 10, Role::Nurse),
+			Error::<Test>::RoleAlreadyGranted
+		);
+
+		assert_ok!(MedicalRecord::revoke_role(RuntimeOrigin::root(), 10, Role::Nurse));
+		assert!(!MedicalRecord::has_role(&10, Role::Nurse));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::RoleRevoked {
+			who: 10,
+			role: Role::Nurse,
+		}));
+
+		assert_noop!(
+			MedicalRecord::revoke_role(RuntimeOrigin::root(), 10, Role::Nurse),
+			Error::<Test>::RoleNotGranted
+		);
+	});
+}
+
+#[test]
+fn only_admin_origin_can_manage_roles() {
+	new_test_ext().execute_with(|| {
+		// Even an account holding the Admin role must go through the admin origin
+		assert_noop!(
+			MedicalRecord::grant_role(RuntimeOrigin::signed(1), 10, Role::Doctor),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MedicalRecord::revoke_role(RuntimeOrigin::signed(UNAUTHORIZED), 1, Role::Admin),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn unauthorized_account_cannot_touch_records() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MedicalRecord::create_patient(
+				RuntimeOrigin::signed(UNAUTHORIZED),
+				b"John Doe".to_vec(),
+				b"1990-01-01".to_vec(),
+				b"Male".to_vec(),
+				b"123 Main St".to_vec(),
+				b"555-1234".to_vec(),
+				b"Jane Doe - 555-5678".to_vec(),
+				name_index(b"John Doe"),
+			),
+			Error::<Test>::NotAuthorized
+		);
+
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(1),
+			b"John Doe".to_vec(),
+			b"1990-01-01".to_vec(),
+			b"Male".to_vec(),
+			b"123 Main St".to_vec(),
+			b"555-1234".to_vec(),
+			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
+		));
+
+		assert_noop!(
+			MedicalRecord::update_patient(
+				RuntimeOrigin::signed(UNAUTHORIZED),
+				0,
+				Some(b"John Smith".to_vec()),
+				None,
+				None,
+				None,
+				None,
+				None,
+				Some(name_index(b"John Smith")),
+			),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::delete_patient(RuntimeOrigin::signed(UNAUTHORIZED), 0),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::search_patient_by_name(RuntimeOrigin::signed(UNAUTHORIZED), name_index(b"John Doe")),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(UNAUTHORIZED),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				b"Normal".to_vec(),
+				b"All values within range".to_vec(),
+			),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::create_disease_progression(
+				RuntimeOrigin::signed(UNAUTHORIZED),
+				0,
+				b"2023-01-15".to_vec(),
+				b"Fever".to_vec(),
+				b"Cold".to_vec(),
+				b"Rest".to_vec(),
+				b"Medicine".to_vec(),
+				None,
+			),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::create_medical_record(
+				RuntimeOrigin::signed(UNAUTHORIZED),
+				0,
+				b"Flu diagnosis".to_vec(),
+				b"Rest and medication".to_vec(),
+				None,
+			),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::get_patient_complete_history(RuntimeOrigin::signed(UNAUTHORIZED), 0),
+			Error::<Test>::NotAuthorized
+		);
+	});
+}
+
+#[test]
+fn delete_patient_requires_admin_role() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(1),
+			b"John Doe".to_vec(),
+			b"1990-01-01".to_vec(),
+			b"Male".to_vec(),
+			b"123 Main St".to_vec(),
+			b"555-1234".to_vec(),
+			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
+		));
+
+		// Account 3 is a doctor but not an admin
+		assert_noop!(
+			MedicalRecord::delete_patient(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::NotAuthorized
+		);
+
+		assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), 3, Role::Admin));
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(3), 0));
+		assert_eq!(Patients::<Test>::get(0), None);
+	});
+}
+
+#[test]
+fn lab_technician_can_record_tests_but_not_progressions() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(1),
+			b"John Doe".to_vec(),
+			b"1990-01-01".to_vec(),
+			b"Male".to_vec(),
+			b"123 Main St".to_vec(),
+			b"555-1234".to_vec(),
+			b"Jane Doe - 555-5678".to_vec(),
+			name_index(b"John Doe"),
+		));
+		assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), 10, Role::LabTechnician));
+
+		give_consent(0, 10);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(10),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"All values within range".to_vec(),
+		));
+		assert_ok!(MedicalRecord::update_clinical_test(
+			RuntimeOrigin::signed(10),
+			0,
+			None,
+			None,
+			Some(b"Abnormal".to_vec()),
+			None,
+		));
+
+		assert_noop!(
+			MedicalRecord::create_disease_progression(
+				RuntimeOrigin::signed(10),
+				0,
+				b"2023-01-15".to_vec(),
+				b"Fever".to_vec(),
+				b"Cold".to_vec(),
+				b"Rest".to_vec(),
+				b"Medicine".to_vec(),
+				None,
+			),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::delete_clinical_test(RuntimeOrigin::signed(10), 0),
+			Error::<Test>::NotAuthorized
+		);
+	});
+}
+
+#[test]
+fn revoked_role_loses_access() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(MedicalRecord::revoke_role(RuntimeOrigin::root(), 1, Role::Doctor));
+		assert_ok!(MedicalRecord::revoke_role(RuntimeOrigin::root(), 1, Role::Admin));
+
+		assert_noop!(
+			MedicalRecord::create_patient(
+				RuntimeOrigin::signed(1),
+				b"John Doe".to_vec(),
+				b"1990-01-01".to_vec(),
+				b"Male".to_vec(),
+				b"123 Main St".to_vec(),
+				b"555-1234".to_vec(),
+				b"Jane Doe - 555-5678".to_vec(),
+				name_index(b"John Doe"),
+			),
+			Error::<Test>::NotAuthorized
+		);
+	});
+}
+
+// Create a patient with account 1 and link the patient's own account
+fn create_linked_patient() {
+	assert_ok!(MedicalRecord::create_patient(
+		RuntimeOrigin::signed(1),
+		b"John Doe".to_vec(),
+		b"1990-01-01".to_vec(),
+		b"Male".to_vec(),
+		b"123 Main St".to_vec(),
+		b"555-1234".to_vec(),
+		b"Jane Doe - 555-5678".to_vec(),
+		name_index(b"John Doe"),
+	));
+	assert_ok!(MedicalRecord::link_patient_account(RuntimeOrigin::signed(1), 0, patient_account(0)));
+}
+
+#[test]
+fn writes_without_consent_fail() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+
+		assert_noop!(
+			MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				b"Normal".to_vec(),
+				b"All values within range".to_vec(),
+			),
+			Error::<Test>::NoConsent
+		);
+		assert_noop!(
+			MedicalRecord::create_disease_progression(
+				RuntimeOrigin::signed(2),
+				0,
+				b"2023-01-15".to_vec(),
+				b"Fever".to_vec(),
+				b"Cold".to_vec(),
+				b"Rest".to_vec(),
+				b"Medicine".to_vec(),
+				None,
+			),
+			Error::<Test>::NoConsent
+		);
+		assert_noop!(
+			MedicalRecord::create_medical_record(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Flu diagnosis".to_vec(),
+				b"Rest and medication".to_vec(),
+				None,
+			),
+			Error::<Test>::NoConsent
+		);
+		assert_noop!(
+			MedicalRecord::get_patient_complete_history(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::NoConsent
+		);
+	});
+}
+
+#[test]
+fn update_by_other_doctor_requires_own_consent() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_ok!(MedicalRecord::create_disease_progression(
+			RuntimeOrigin::signed(2),
+			0,
+			b"2023-01-15".to_vec(),
+			b"Fever".to_vec(),
+			b"Cold".to_vec(),
+			b"Rest".to_vec(),
+			b"Medicine".to_vec(),
+			None,
+		));
+
+		// Doctor 3 has no grant of their own
+		assert_noop!(
+			MedicalRecord::update_disease_progression(
+				RuntimeOrigin::signed(3),
+				0,
+				None,
+				Some(b"Feeling better".to_vec()),
+				None,
+				None,
+				None,
+				None,
+			),
+			Error::<Test>::NoConsent
+		);
+		assert_noop!(
+			MedicalRecord::delete_disease_progression(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::NoConsent
+		);
+	});
+}
+
+#[test]
+fn consent_scope_is_enforced() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::ClinicalTest,
+			ConsentScope { read: true, append: true, amend: false },
+			None,
+		));
+
+		assert!(MedicalRecord::has_consent(0, &2, RecordType::ClinicalTest, AccessKind::Append));
+		assert!(!MedicalRecord::has_consent(0, &2, RecordType::ClinicalTest, AccessKind::Amend));
+		assert!(!MedicalRecord::has_consent(0, &2, RecordType::DiseaseProgression, AccessKind::Append));
+
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"All values within range".to_vec(),
+		));
+		assert_noop!(
+			MedicalRecord::update_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				None,
+				None,
+				Some(b"Abnormal".to_vec()),
+				None,
+			),
+			Error::<Test>::NoConsent
+		);
+	});
+}
+
+#[test]
+fn expired_consent_drops_patient_from_doctor_list() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::ClinicalTest,
+			ConsentScope { read: true, append: false, amend: false },
+			Some(5),
+		));
+		assert_eq!(MedicalRecord::get_doctor_patients(&2).len(), 1);
+
+		// The care link stays in storage, but the doctor no longer sees the patient
+		System::set_block_number(5);
+		assert_eq!(MedicalRecord::doctor_patients(2).into_inner(), vec![0]);
+		assert!(MedicalRecord::get_doctor_patients(&2).is_empty());
+
+		// A fresh grant lists the patient again
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::MedicalRecord,
+			ConsentScope { read: true, append: false, amend: false },
+			None,
+		));
+		assert_eq!(MedicalRecord::get_doctor_patients(&2).len(), 1);
+	});
+}
+
+#[test]
+fn consent_expires() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::ClinicalTest,
+			ConsentScope { read: true, append: true, amend: true },
+			Some(5),
+		));
+
+		System::set_block_number(4);
+		assert!(MedicalRecord::has_consent(0, &2, RecordType::ClinicalTest, AccessKind::Append));
+
+		System::set_block_number(5);
+		assert!(!MedicalRecord::has_consent(0, &2, RecordType::ClinicalTest, AccessKind::Append));
+		assert_noop!(
+			MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				b"Normal".to_vec(),
+				b"All values within range".to_vec(),
+			),
+			Error::<Test>::NoConsent
+		);
+
+		// An expiry in the past is rejected
+		assert_noop!(
+			MedicalRecord::grant_consent(
+				RuntimeOrigin::signed(patient_account(0)),
+				0,
+				2,
+				RecordType::ClinicalTest,
+				ConsentScope { read: true, append: true, amend: true },
+				Some(5),
+			),
+			Error::<Test>::InvalidConsentExpiry
+		);
+	});
+}
+
+#[test]
+fn only_patient_or_guardian_can_grant_consent() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		let scope = ConsentScope { read: true, append: true, amend: false };
+
+		// Not even an admin can grant on the patient's behalf
+		assert_noop!(
+			MedicalRecord::grant_consent(RuntimeOrigin::signed(1), 0, 2, RecordType::ClinicalTest, scope, None),
+			Error::<Test>::NotPatientOrGuardian
+		);
+
+		assert_ok!(MedicalRecord::set_guardian(RuntimeOrigin::signed(1), 0, Some(50)));
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(50),
+			0,
+			2,
+			RecordType::ClinicalTest,
+			scope,
+			None,
+		));
+		assert!(MedicalRecord::has_consent(0, &2, RecordType::ClinicalTest, AccessKind::Append));
+
+		// Clearing the guardian removes their ability to manage consent
+		assert_ok!(MedicalRecord::set_guardian(RuntimeOrigin::signed(1), 0, None));
+		assert_noop!(
+			MedicalRecord::revoke_consent(RuntimeOrigin::signed(50), 0, 2, RecordType::ClinicalTest),
+			Error::<Test>::NotPatientOrGuardian
+		);
+	});
+}
+
+#[test]
+fn grant_consent_validates_input() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+
+		assert_noop!(
+			MedicalRecord::grant_consent(
+				RuntimeOrigin::signed(patient_account(0)),
+				0,
+				2,
+				RecordType::ClinicalTest,
+				ConsentScope::default(),
+				None,
+			),
+			Error::<Test>::InvalidConsentScope
+		);
+		assert_noop!(
+			MedicalRecord::grant_consent(
+				RuntimeOrigin::signed(patient_account(0)),
+				0,
+				UNAUTHORIZED,
+				RecordType::ClinicalTest,
+				ConsentScope { read: true, append: false, amend: false },
+				None,
+			),
+			Error::<Test>::InvalidGrantee
+		);
+		assert_noop!(
+			MedicalRecord::revoke_consent(RuntimeOrigin::signed(patient_account(0)), 0, 2, RecordType::ClinicalTest),
+			Error::<Test>::ConsentNotFound
+		);
+	});
+}
+
+#[test]
+fn consent_drives_doctor_patient_links_and_audit_trail() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		let scope = ConsentScope { read: true, append: true, amend: true };
+
+		assert!(MedicalRecord::doctor_patients(2).is_empty());
+
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::ClinicalTest,
+			scope,
+			None,
+		));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::ConsentGranted {
+			patient_id: 0,
+			grantee: 2,
+			record_type: RecordType::ClinicalTest,
+			scope,
+			expires_at: None,
+		}));
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::DiseaseProgression,
+			scope,
+			None,
+		));
+		assert_eq!(MedicalRecord::doctor_patients(2), vec![0]);
+		assert_eq!(MedicalRecord::patient_doctors(0), vec![2]);
+
+		// Writing a test does not create links on its own
+		assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), 10, Role::LabTechnician));
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			10,
+			RecordType::ClinicalTest,
+			scope,
+			None,
+		));
+		assert_ok!(MedicalRecord::revoke_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			10,
+			RecordType::ClinicalTest,
+		));
+		assert!(MedicalRecord::doctor_patients(10).is_empty());
+
+		// Links stay while any grant remains
+		assert_ok!(MedicalRecord::revoke_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::ClinicalTest,
+		));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::ConsentRevoked {
+			patient_id: 0,
+			grantee: 2,
+			record_type: RecordType::ClinicalTest,
+		}));
+		assert_eq!(MedicalRecord::doctor_patients(2), vec![0]);
+
+		assert_ok!(MedicalRecord::revoke_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			2,
+			RecordType::DiseaseProgression,
+		));
+		assert!(MedicalRecord::doctor_patients(2).is_empty());
+		assert!(MedicalRecord::patient_doctors(0).is_empty());
+
+		// Every grant and revocation is in the audit trail
+		let history = MedicalRecord::get_record_history(RecordType::Consent, 0);
+		assert_eq!(history.len(), 6);
+		let creates = history.iter().filter(|h| matches!(h.operation, crate::OperationType::Create)).count();
+		let deletes = history.iter().filter(|h| matches!(h.operation, crate::OperationType::Delete)).count();
+		assert_eq!(creates, 3);
+		assert_eq!(deletes, 3);
+		assert!(history.iter().all(|h| h.changed_by == patient_account(0)));
+	});
+}
+
+#[test]
+fn oversize_fields_are_rejected() {
+	new_test_ext().execute_with(|| {
+		// MaxNameLength is 64 and MaxAddressLength is 128 in the mock
+		assert_noop!(
+			MedicalRecord::create_patient(
+				RuntimeOrigin::signed(1),
+				vec![b'a'; 65],
+				b"1990-01-01".to_vec(),
+				b"Male".to_vec(),
+				b"123 Main St".to_vec(),
+				b"555-1234".to_vec(),
+				b"Jane Doe - 555-5678".to_vec(),
+				name_index(&[b'a'; 65]),
+			),
+			Error::<Test>::PatientDataTooLong
+		);
+		assert_noop!(
+			MedicalRecord::create_patient(
+				RuntimeOrigin::signed(1),
+				b"John Doe".to_vec(),
+				b"1990-01-01".to_vec(),
+				b"Male".to_vec(),
+				vec![b'x'; 5 * 1024 * 1024],
+				b"555-1234".to_vec(),
+				b"Jane Doe - 555-5678".to_vec(),
+				name_index(b"John Doe"),
+			),
+			Error::<Test>::PatientDataTooLong
+		);
+
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_noop!(
+			MedicalRecord::update_patient(
+				RuntimeOrigin::signed(1),
+				0,
+				None,
+				None,
+				None,
+				Some(vec![b'x'; 129]),
+				None,
+				None,
+				None,
+			),
+			Error::<Test>::PatientDataTooLong
+		);
+		assert_noop!(
+			MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				vec![b'r'; 257],
+				b"All values within range".to_vec(),
+			),
+			Error::<Test>::ClinicalTestDataTooLong
+		);
+		assert_noop!(
+			MedicalRecord::create_disease_progression(
+				RuntimeOrigin::signed(2),
+				0,
+				b"2023-01-15".to_vec(),
+				vec![b's'; 257],
+				b"Cold".to_vec(),
+				b"Rest".to_vec(),
+				b"Medicine".to_vec(),
+				None,
+			),
+			Error::<Test>::DiseaseProgressionDataTooLong
+		);
+
+		// A value at the bound is accepted
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			Some(vec![b'x'; 128]),
+			None,
+			None,
+			None,
+		));
+		assert_eq!(MedicalRecord::patients(0).unwrap().address.len(), 128);
+	});
+}
+
+#[test]
+fn bounded_indexes_reject_new_entries_when_full() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		// MaxRecordsPerPatient is 20 in the mock
+		for _ in 0..20 {
+			assert_ok!(MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				b"Normal".to_vec(),
+				b"All values within range".to_vec(),
+			));
+		}
+		assert_noop!(
+			MedicalRecord::create_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				b"Blood Test".to_vec(),
+				b"2023-01-15".to_vec(),
+				b"Normal".to_vec(),
+				b"All values within range".to_vec(),
+			),
+			Error::<Test>::TooManyRecordsForPatient
+		);
+		assert_eq!(MedicalRecord::next_test_id(), 20);
+
+		// MaxDoctorsPerPatient is 8 in the mock, doctor 2 already holds one slot
+		for doctor in 10..17u64 {
+			assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), doctor, Role::Doctor));
+			give_consent(0, doctor);
+		}
+		assert_ok!(MedicalRecord::grant_role(RuntimeOrigin::root(), 17, Role::Doctor));
+		assert_noop!(
+			MedicalRecord::grant_consent(
+				RuntimeOrigin::signed(patient_account(0)),
+				0,
+				17,
+				RecordType::ClinicalTest,
+				ConsentScope { read: true, append: false, amend: false },
+				None,
+			),
+			Error::<Test>::TooManyDoctorsForPatient
+		);
+	});
+}
+
+// Run a multi-block migration to completion with `limit` weight per block, returning the number
+// of blocks it took
+fn run_migration<M: SteppedMigration>(limit: Weight) -> u32 {
+	let mut cursor = None;
+	let mut blocks = 0;
+	loop {
+		blocks += 1;
+		cursor = M::step(cursor, &mut WeightMeter::with_limit(limit)).unwrap();
+		if cursor.is_none() {
+			return blocks
+		}
+	}
+}
+
+#[test]
+fn migration_v1_bounds_legacy_storage() {
+	use crate::migrations::v1::{new, v0, MigrateV0ToV1};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<MedicalRecord>();
+
+		let legacy_name = vec![b'n'; 100];
+		v0::Patients::<Test>::insert(
+			0,
+			v0::PatientInfo::<Test> {
+				patient_id: 0,
+				patient_name: legacy_name.clone(),
+				date_of_birth: b"1990-01-01".to_vec(),
+				gender: b"Male".to_vec(),
+				address: vec![b'x'; 5 * 1024],
+				phone: b"555-1234".to_vec(),
+				emergency_contact: b"Jane Doe - 555-5678".to_vec(),
+				created_at: 1,
+				created_by: 1,
+				last_modified_at: 1,
+				last_modified_by: 1,
+			},
+		);
+		v0::Patients::<Test>::insert(
+			1,
+			v0::PatientInfo::<Test> {
+				patient_id: 1,
+				patient_name: b"Jane Doe".to_vec(),
+				date_of_birth: b"1985-05-05".to_vec(),
+				gender: b"Female".to_vec(),
+				address: b"456 Oak Ave".to_vec(),
+				phone: b"555-4321".to_vec(),
+				emergency_contact: b"John Doe - 555-1234".to_vec(),
+				created_at: 1,
+				created_by: 1,
+				last_modified_at: 1,
+				last_modified_by: 1,
+			},
+		);
+		v0::PatientNameToId::<Test>::insert(legacy_name, vec![0]);
+		v0::PatientNameToId::<Test>::insert(b"Jane Doe".to_vec(), vec![1]);
+		v0::PatientsByGender::<Test>::insert(b"Male".to_vec(), vec![0]);
+		v0::ChangeHistories::<Test>::insert(
+			0,
+			v0::ChangeHistory::<Test> {
+				change_id: 0,
+				record_type: RecordType::Patient,
+				record_id: 0,
+				field_name: b"address".to_vec(),
+				old_value: None,
+				new_value: vec![b'x'; 5 * 1024],
+				changed_by: 1,
+				changed_at: 1,
+				operation: crate::OperationType::Create,
+			},
+		);
+
+		// A few entries per block
+		let limit = <Test as frame_system::Config>::DbWeight::get().reads_writes(7, 7);
+		assert!(run_migration::<MigrateV0ToV1<Test>>(limit) > 1);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 1);
+		StorageVersion::new(2).put::<MedicalRecord>();
+
+		// Oversize fields are truncated, others are untouched
+		let patient = MedicalRecord::patients(0).unwrap();
+		assert_eq!(patient.patient_name.to_vec(), vec![b'n'; 64]);
+		assert_eq!(patient.address.to_vec(), vec![b'x'; 128]);
+		assert_eq!(patient.phone.to_vec(), b"555-1234".to_vec());
+		assert_eq!(MedicalRecord::patients(1).unwrap().address.to_vec(), b"456 Oak Ave".to_vec());
+
+		// The name index is rebuilt under the truncated key
+		let legacy_index = new::PatientNameToId::<Test>::get(
+			frame_support::BoundedVec::truncate_from(vec![b'n'; 64])
+		);
+		assert_eq!(legacy_index.map(|ids| ids.to_vec()), Some(vec![0]));
+		assert_eq!(MedicalRecord::patients_by_gender(
+			frame_support::BoundedVec::truncate_from(b"Male".to_vec())
+		).to_vec(), vec![0]);
+
+		let change = new::ChangeHistories::<Test>::get(0).unwrap();
+		assert_eq!(change.new_value.len(), 256);
+
+		// Running it again is a no-op
+		StorageVersion::new(1).put::<MedicalRecord>();
+		run_migration::<MigrateV0ToV1<Test>>(Weight::MAX);
+		assert_eq!(MedicalRecord::patients(0).unwrap(), patient);
+	});
+}
+
+#[test]
+fn migration_v1_indexes_every_patient_of_a_gender() {
+	use crate::migrations::v1::{v0, MigrateV0ToV1};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<MedicalRecord>();
+
+		// More patients of one gender than any legacy list used to be bounded to
+		let count = 150;
+		for patient_id in 0..count {
+			v0::Patients::<Test>::insert(
+				patient_id,
+				v0::PatientInfo::<Test> {
+					patient_id,
+					patient_name: format!("Patient {}", patient_id).into_bytes(),
+					date_of_birth: b"1990-01-01".to_vec(),
+					gender: b"Male".to_vec(),
+					address: b"123 Main St".to_vec(),
+					phone: b"555-1234".to_vec(),
+					emergency_contact: b"Jane Doe - 555-5678".to_vec(),
+					created_at: 1,
+					created_by: 1,
+					last_modified_at: 1,
+					last_modified_by: 1,
+				},
+			);
+		}
+		v0::PatientsByGender::<Test>::insert(b"Male".to_vec(), (0..count).collect::<Vec<_>>());
+		v0::PatientsByBirthYear::<Test>::insert(1990, (0..count).collect::<Vec<_>>());
+
+		let limit = <Test as frame_system::Config>::DbWeight::get().reads_writes(7, 7);
+		assert!(run_migration::<MigrateV0ToV1<Test>>(limit) > 1);
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 1);
+
+		let mut by_gender = MedicalRecord::patients_by_gender(
+			frame_support::BoundedVec::truncate_from(b"Male".to_vec())
+		);
+		by_gender.sort();
+		assert_eq!(by_gender, (0..count).collect::<Vec<_>>());
+		let mut by_birth_year = MedicalRecord::patients_by_birth_year(1990);
+		by_birth_year.sort();
+		assert_eq!(by_birth_year, (0..count).collect::<Vec<_>>());
+	});
+}
+
+// Create `count` patients with distinct names
+fn create_patients(count: u32) {
+	for i in 0..count {
+		assert_ok!(MedicalRecord::create_patient(
+			RuntimeOrigin::signed(1),
+			format!("Patient {}", i).into_bytes(),
+			b"1990-01-01".to_vec(),
+			b"Male".to_vec(),
+			b"123 Main St".to_vec(),
+			b"555-1234".to_vec(),
+			format!("Contact {}", i).into_bytes(),
+			name_index(&format!("Patient {}", i).into_bytes()),
+		));
+	}
+}
+
+#[test]
+fn active_patients_are_counted() {
+	new_test_ext().execute_with(|| {
+		create_patients(3);
+		assert_eq!(MedicalRecord::active_patient_count(), 3);
+
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 1));
+		assert_eq!(MedicalRecord::active_patient_count(), 2);
+		assert!(!crate::ActivePatients::<Test>::contains_key(1));
+	});
+}
+
+#[test]
+fn get_all_patients_walks_every_page() {
+	new_test_ext().execute_with(|| {
+		create_patients(10);
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 4));
+
+		let mut seen = Vec::new();
+		let mut cursor = None;
+		let mut pages = 0;
+		loop {
+			let page = MedicalRecord::get_all_patients(cursor, 4);
+			assert!(page.items.len() <= 4);
+			seen.extend(page.items.iter().map(|patient| patient.patient_id));
+			pages += 1;
+			match page.next_cursor {
+				Some(next) => cursor = Some(next),
+				None => break,
+			}
+		}
+
+		assert_eq!(pages, 3);
+		seen.sort();
+		assert_eq!(seen, vec![0, 1, 2, 3, 5, 6, 7, 8, 9]);
+
+		// An exact multiple of the page size ends without an empty trailing page
+		let first = MedicalRecord::get_all_patients(None, 9);
+		assert_eq!(first.items.len(), 9);
+		assert_eq!(first.next_cursor, None);
+	});
+}
+
+#[test]
+fn page_size_is_capped() {
+	new_test_ext().execute_with(|| {
+		// MaxPageSize is 10 in the mock
+		create_patients(11);
+
+		let page = MedicalRecord::get_all_patients(None, u32::MAX);
+		assert_eq!(page.items.len(), 10);
+		assert!(page.next_cursor.is_some());
+
+		let rest = MedicalRecord::get_all_patients(page.next_cursor, u32::MAX);
+		assert_eq!(rest.items.len(), 1);
+		assert_eq!(rest.next_cursor, None);
+	});
+}
+
+#[test]
+fn emergency_contacts_and_appointments_are_paginated() {
+	new_test_ext().execute_with(|| {
+		create_patients(3);
+
+		let contacts = MedicalRecord::get_emergency_contacts(None, 10);
+		assert_eq!(contacts.items.len(), 3);
+		assert_eq!(contacts.next_cursor, None);
+		assert!(contacts.items.contains(&(2, b"Patient 2".to_vec(), b"Contact 2".to_vec())));
+
+		give_consent(1, 2);
+		assert_ok!(MedicalRecord::create_disease_progression(
+			RuntimeOrigin::signed(2),
+			1,
+			b"2023-01-15".to_vec(),
+			b"Fever".to_vec(),
+			b"Cold".to_vec(),
+			b"Rest".to_vec(),
+			b"Medicine".to_vec(),
+			Some(FOLLOW_UP),
+		));
+
+		let mut appointments = Vec::new();
+		let mut cursor = None;
+		loop {
+			let page = MedicalRecord::get_patients_with_appointments(cursor, 1);
+			appointments.extend(page.items);
+			match page.next_cursor {
+				Some(next) => cursor = Some(next),
+				None => break,
+			}
+		}
+		assert_eq!(
+			appointments,
+			vec![(
+				1,
+				b"Patient 1".to_vec(),
+				ScheduledAppointment { appointment_id: 0, doctor_id: 2, scheduled_time: FOLLOW_UP },
+			)]
+		);
+	});
+}
+
+#[test]
+fn migration_v2_moves_active_patients_to_counted_map() {
+	use crate::migrations::v2::{v1, MigrateV1ToV2};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(1).put::<MedicalRecord>();
+		v1::ActivePatients::<Test>::put(vec![0, 1, 2, 2, 5]);
+
+		// Two ids per block, on top of reading the version and the list
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let limit = db.reads_writes(2, 1).saturating_add(db.reads_writes(2, 4));
+
+		// The list stays until every id is moved
+		let cursor = MigrateV1ToV2::<Test>::step(None, &mut WeightMeter::with_limit(limit)).unwrap();
+		assert_eq!(cursor, Some(2));
+		assert!(v1::ActivePatients::<Test>::exists());
+		assert_eq!(MedicalRecord::active_patient_count(), 2);
+
+		assert_eq!(run_migration::<MigrateV1ToV2<Test>>(limit), 3);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 2);
+		assert!(!v1::ActivePatients::<Test>::exists());
+		assert_eq!(MedicalRecord::active_patient_count(), 4);
+		let mut ids: Vec<u32> = crate::ActivePatients::<Test>::iter_keys().collect();
+		ids.sort();
+		assert_eq!(ids, vec![0, 1, 2, 5]);
+	});
+}
+
+#[test]
+fn records_are_anchored_into_the_proof_chain() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"".to_vec(),
+		));
+		let created = ClinicalTests::<Test>::get(0).unwrap();
+		assert_ok!(MedicalRecord::update_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			None,
+			None,
+			Some(b"Abnormal".to_vec()),
+			None,
+		));
+		let updated = ClinicalTests::<Test>::get(0).unwrap();
+
+		let proofs = MedicalRecord::proofs_for_record(RecordType::ClinicalTest, 0);
+		assert_eq!(proofs.len(), 2);
+		System::assert_has_event(RuntimeEvent::MedicalRecord(Event::RecordAnchored {
+			record_type: RecordType::ClinicalTest,
+			record_id: 0,
+			proof_hash: proofs[1],
+		}));
+
+		// An auditor can recompute the anchored data hash of each version from the record itself
+		for (version, (proof_hash, test)) in proofs.iter().zip([created, updated]).enumerate() {
+			let data_hash = BlakeTwo256::hash_of(&(
+				RecordType::ClinicalTest,
+				0u32,
+				version as u32,
+				BlakeTwo256::hash_of(&test),
+			));
+			let proof = PoH::proofs(proof_hash).unwrap();
+			assert_eq!(proof.data_hash, data_hash);
+			assert_eq!(PoH::anchored_proof(data_hash), Some(*proof_hash));
+		}
+
+		// Versions are chained in the order they were written
+		assert_eq!(PoH::proofs(proofs[1]).unwrap().previous_hash, proofs[0]);
+		assert_eq!(PoH::latest_proof(), Some(proofs[1]));
+	});
+}
+
+#[test]
+fn anchors_cannot_be_front_run() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		// The content hash of a pending record is known from the transaction, so anyone can
+		// submit the data hash its anchor will use before it is included
+		let test = crate::ClinicalTest::<Test> {
+			test_id: 0,
+			patient_id: 0,
+			doctor_id: 2,
+			test_type: b"Blood Test".to_vec().try_into().unwrap(),
+			test_date: b"2023-01-15".to_vec().try_into().unwrap(),
+			result: b"Normal".to_vec().try_into().unwrap(),
+			notes: Default::default(),
+			attachments: Default::default(),
+			created_at: 1,
+			created_by: 2,
+			last_modified_at: 1,
+			last_modified_by: 2,
+		};
+		let anchored = (RecordType::ClinicalTest, 0u32, 0u32, BlakeTwo256::hash_of(&test));
+		assert_ok!(PoH::submit_data(RuntimeOrigin::signed(3), anchored.encode()));
+		let data_hash = BlakeTwo256::hash_of(&anchored);
+		assert!(PoH::data_to_proof(data_hash).is_some());
+
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"".to_vec(),
+		));
+		assert_eq!(ClinicalTests::<Test>::get(0), Some(test));
+
+		// The record is anchored under its own proof, apart from the public submission
+		let proofs = MedicalRecord::proofs_for_record(RecordType::ClinicalTest, 0);
+		assert_eq!(PoH::anchored_proof(data_hash), Some(proofs[0]));
+		assert_ne!(PoH::data_to_proof(data_hash), Some(proofs[0]));
+		assert_eq!(PoH::proofs(proofs[0]).unwrap().submitter, 2);
+	});
+}
+
+#[test]
+fn progressions_and_medical_records_are_anchored() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_ok!(MedicalRecord::create_disease_progression(
+			RuntimeOrigin::signed(2),
+			0,
+			b"2023-01-15".to_vec(),
+			b"Fever".to_vec(),
+			b"Cold".to_vec(),
+			b"Rest".to_vec(),
+			b"Water".to_vec(),
+			None,
+		));
+		assert_ok!(MedicalRecord::set_next_appointment(RuntimeOrigin::signed(2), 0, FOLLOW_UP));
+		assert_ok!(MedicalRecord::clear_appointment(RuntimeOrigin::signed(2), 0));
+		assert_eq!(MedicalRecord::proofs_for_record(RecordType::DiseaseProgression, 0).len(), 3);
+
+		assert_ok!(MedicalRecord::create_medical_record(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Flu diagnosis".to_vec(),
+			b"Rest and medication".to_vec(),
+			None,
+		));
+		let record_hash = MedicalRecords::<Test>::get(0).unwrap().record_hash;
+		let proofs = MedicalRecord::proofs_for_record(RecordType::MedicalRecord, 0);
+		assert_eq!(proofs.len(), 1);
+		assert_eq!(
+			PoH::proofs(proofs[0]).unwrap().data_hash,
+			BlakeTwo256::hash_of(&(RecordType::MedicalRecord, 0u32, 0u32, record_hash)),
+		);
+		assert_eq!(PoH::proof_count(), 4);
+	});
+}
+
+#[test]
+fn anchored_versions_are_bounded() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Blood Test".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Normal".to_vec(),
+			b"".to_vec(),
+		));
+		// MaxProofsPerRecord is 8 in the mock
+		for i in 1..8u8 {
+			assert_ok!(MedicalRecord::update_clinical_test(
+				RuntimeOrigin::signed(2),
+				0,
+				None,
+				None,
+				None,
+				Some(vec![i]),
+			));
+		}
+		assert_noop!(
+			MedicalRecord::update_clinical_test(RuntimeOrigin::signed(2), 0, None, None, None, Some(vec![8])),
+			Error::<Test>::TooManyProofsForRecord
+		);
+	});
+}
+
+#[test]
+fn migration_v3_drops_free_text_appointments() {
+	use crate::migrations::v3::{new, v2, MigrateV2ToV3};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(2).put::<MedicalRecord>();
+		let progression = |progression_id: u32, next_appointment: &[u8]| v2::DiseaseProgression::<Test> {
+			progression_id,
+			patient_id: 0,
+			doctor_id: 2,
+			visit_date: b"2023-01-15".to_vec().try_into().unwrap(),
+			symptoms: b"Fever".to_vec().try_into().unwrap(),
+			diagnosis: b"Cold".to_vec().try_into().unwrap(),
+			treatment: b"Rest".to_vec().try_into().unwrap(),
+			prescription: b"Medicine".to_vec().try_into().unwrap(),
+			next_appointment: next_appointment.to_vec().try_into().unwrap(),
+			created_at: 1,
+			created_by: 2,
+			last_modified_at: 1,
+			last_modified_by: 2,
+		};
+		v2::DiseaseProgressions::<Test>::insert(0, progression(0, b"2023-01-22 10:00"));
+		v2::DiseaseProgressions::<Test>::insert(1, progression(1, b""));
+
+		run_migration::<MigrateV2ToV3<Test>>(Weight::MAX);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 3);
+		for progression_id in [0, 1] {
+			let progression = new::DiseaseProgressions::<Test>::get(progression_id).unwrap();
+			assert_eq!(progression.next_appointment, None);
+			assert_eq!(progression.diagnosis, b"Cold".to_vec());
+			assert_eq!(progression.last_modified_by, 2);
+		}
+	});
+}
+
+#[test]
+fn changes_are_hash_chained() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Ok(0));
+		create_patients(1);
+
+		// One change per patient field, each linked to the one before it
+		let mut prev_change_hash = H256::default();
+		for change_id in 0..6 {
+			let change = MedicalRecord::change_history(change_id).unwrap();
+			assert_eq!(change.prev_change_hash, prev_change_hash);
+			assert_eq!(change.change_hash, MedicalRecord::compute_change_hash(&change));
+			prev_change_hash = change.change_hash;
+		}
+		assert_eq!(MedicalRecord::audit_head(), prev_change_hash);
+		System::assert_has_event(RuntimeEvent::MedicalRecord(Event::ChangeRecorded {
+			change_id: 5,
+			record_type: RecordType::Patient,
+			record_id: 0,
+			changed_by: 1,
+			change_hash: prev_change_hash,
+		}));
+
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Ok(6));
+		assert_eq!(MedicalRecord::verify_audit_trail(2, Some(3)), Ok(2));
+		assert_eq!(MedicalRecord::verify_audit_trail(5, Some(5)), Ok(1));
+	});
+}
+
+#[test]
+fn audit_trail_detects_tampering() {
+	use crate::{AuditError, ChangeHistories, AuditHead};
+
+	new_test_ext().execute_with(|| {
+		create_patients(1);
+
+		// Rewriting a value breaks its own hash
+		let original = MedicalRecord::change_history(2).unwrap();
+		let mut forged = original.clone();
+		forged.new_value = b"Female".to_vec().try_into().unwrap();
+		ChangeHistories::<Test>::insert(2, forged.clone());
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::HashMismatch(2)));
+
+		// Rehashing the forged entry breaks the link of the next one
+		forged.change_hash = MedicalRecord::compute_change_hash(&forged);
+		ChangeHistories::<Test>::insert(2, forged);
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::BrokenLink(3)));
+		assert_eq!(MedicalRecord::verify_audit_trail(0, Some(2)), Ok(3));
+		ChangeHistories::<Test>::insert(2, original);
+
+		// Removing an entry leaves a gap
+		let removed = ChangeHistories::<Test>::take(4).unwrap();
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::Missing(4)));
+		assert_eq!(MedicalRecord::verify_audit_trail(5, None), Err(AuditError::Missing(4)));
+		ChangeHistories::<Test>::insert(4, removed);
+
+		// Dropping the latest entries is caught by the head
+		crate::NextChangeId::<Test>::put(5);
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::HeadMismatch));
+		crate::NextChangeId::<Test>::put(6);
+
+		AuditHead::<Test>::put(H256::repeat_byte(1));
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::HeadMismatch));
+	});
+}
+
+#[test]
+fn audit_trail_ranges_are_checked() {
+	use crate::AuditError;
+
+	new_test_ext().execute_with(|| {
+		assert_eq!(MedicalRecord::verify_audit_trail(0, Some(0)), Err(AuditError::InvalidRange));
+
+		// 18 changes, more than `MaxAuditRange`
+		create_patients(3);
+		assert_eq!(MedicalRecord::verify_audit_trail(3, Some(2)), Err(AuditError::InvalidRange));
+		assert_eq!(MedicalRecord::verify_audit_trail(0, Some(18)), Err(AuditError::InvalidRange));
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Err(AuditError::RangeTooLong));
+		assert_eq!(MedicalRecord::verify_audit_trail(0, Some(15)), Ok(16));
+		assert_eq!(MedicalRecord::verify_audit_trail(16, None), Ok(2));
+	});
+}
+
+#[test]
+fn migration_v4_chains_change_history() {
+	use crate::migrations::{
+		v4::{new, v3, MigrateV3ToV4},
+		v7::MigrateV6ToV7,
+	};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(3).put::<MedicalRecord>();
+		for change_id in 0..3 {
+			v3::ChangeHistories::<Test>::insert(
+				change_id,
+				v3::ChangeHistory::<Test> {
+					change_id,
+					record_type: RecordType::Patient,
+					record_id: 0,
+					field_name: b"phone".to_vec().try_into().unwrap(),
+					old_value: None,
+					new_value: format!("555-000{}", change_id).into_bytes().try_into().unwrap(),
+					changed_by: 1,
+					changed_at: 1,
+					operation: crate::OperationType::Update,
+				},
+			);
+		}
+		crate::NextChangeId::<Test>::put(3);
+
+		run_migration::<MigrateV3ToV4<Test>>(Weight::MAX);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 4);
+		assert_eq!(new::ChangeHistories::<Test>::get(1).unwrap().new_value.to_vec(), b"555-0001".to_vec());
+		assert_eq!(MedicalRecord::audit_head(), new::ChangeHistories::<Test>::get(2).unwrap().change_hash);
+
+		// The chain verifies in the current layout
+		StorageVersion::new(6).put::<MedicalRecord>();
+		run_migration::<MigrateV6ToV7<Test>>(Weight::MAX);
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Ok(3));
+
+		// New changes extend the migrated chain
+		create_patients(1);
+		assert_eq!(MedicalRecord::verify_audit_trail(0, None), Ok(9));
+	});
+}
+
+#[test]
+fn migration_v5_drops_plaintext_name_index() {
+	use crate::migrations::v5::{v4, MigrateV4ToV5};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		create_patients(3);
+		StorageVersion::new(4).put::<MedicalRecord>();
+		crate::PatientsByName::<Test>::remove(name_index(b"Patient 0"));
+		crate::PatientNameIndex::<Test>::remove(0);
+		for patient_id in 0..3 {
+			v4::PatientNameToId::<Test>::insert(
+				frame_support::BoundedVec::truncate_from(format!("Patient {}", patient_id).into_bytes()),
+				frame_support::BoundedVec::truncate_from(vec![patient_id]),
+			);
+		}
+
+		// One name per block, on top of reading and writing the version
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let limit = db.reads_writes(2, 2);
+
+		let cursor = MigrateV4ToV5::<Test>::step(None, &mut WeightMeter::with_limit(limit)).unwrap();
+		assert_eq!(cursor, Some(1));
+		assert_eq!(v4::PatientNameToId::<Test>::iter_keys().count(), 2);
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 4);
+
+		// The last block finds nothing left to drop
+		assert_eq!(run_migration::<MigrateV4ToV5<Test>>(limit), 3);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 5);
+		assert_eq!(v4::PatientNameToId::<Test>::iter_keys().count(), 0);
+		// Patients stay in place and are found again once re-indexed
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Patient 0")), None);
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			None,
+			None,
+			Some(name_index(b"Patient 0")),
+		));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Patient 0")), Some(0));
+	});
+}
+
+#[test]
+fn reindex_patient_names_works() {
+	new_test_ext().execute_with(|| {
+		create_patients(3);
+		let entries = frame_support::BoundedVec::truncate_from(vec![
+			(0, name_index(b"Blinded 0")),
+			(2, name_index(b"Blinded 2")),
+		]);
+
+		assert_ok!(MedicalRecord::reindex_patient_names(RuntimeOrigin::signed(1), entries));
+
+		System::assert_last_event(Event::PatientNamesReindexed { count: 2 }.into());
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Blinded 0")), Some(0));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Patient 0")), None);
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Patient 1")), Some(1));
+		assert_eq!(MedicalRecord::get_patient_id_by_name(&name_index(b"Blinded 2")), Some(2));
+	});
+}
+
+#[test]
+fn reindex_patient_names_validates_input() {
+	new_test_ext().execute_with(|| {
+		create_patients(1);
+		let entries = |patient_id| frame_support::BoundedVec::truncate_from(vec![(patient_id, name_index(b"Blinded"))]);
+
+		// Only admins re-index in bulk
+		assert_noop!(
+			MedicalRecord::reindex_patient_names(RuntimeOrigin::signed(3), entries(0)),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::reindex_patient_names(RuntimeOrigin::signed(1), entries(7)),
+			Error::<Test>::PatientNotFound
+		);
+	});
+}
+
+// Binary CIDv1 with a SHA2-256 multihash of `digest`
+fn cid_v1(codec: u8, digest: [u8; 32]) -> Vec<u8> {
+	[&[0x01, codec, 0x12, 0x20][..], &digest].concat()
+}
+
+fn blob_pointer(content: &[u8]) -> crate::DataPointerOf<Test> {
+	crate::DataPointer {
+		location: crate::DataLocation::Blob,
+		content_hash: sp_io::hashing::sha2_256(content),
+		size: content.len() as u64,
+	}
+}
+
+#[test]
+fn data_pointers_are_validated() {
+	let content_hash = sp_io::hashing::sha2_256(b"lab report");
+	let pointer = |location| crate::DataPointerOf::<Test> { location, content_hash, size: 10 };
+	let ipfs = |cid: Vec<u8>| pointer(crate::DataLocation::Ipfs(cid.try_into().unwrap()));
+	let url = |url: &[u8]| pointer(crate::DataLocation::Url(url.to_vec().try_into().unwrap()));
+
+	// A raw CID addresses the content itself, so its digest is the content hash
+	assert!(ipfs(cid_v1(0x55, content_hash)).is_valid());
+	assert!(!ipfs(cid_v1(0x55, [0; 32])).is_valid());
+	// A dag-pb CID addresses the UnixFS root, which is not the content hash
+	assert!(ipfs(cid_v1(0x70, [0; 32])).is_valid());
+	// Other CID versions, hash functions, digest lengths and trailing bytes are rejected
+	assert!(!ipfs([&[0x00][..], &cid_v1(0x55, content_hash)[1..]].concat()).is_valid());
+	assert!(!ipfs([&[0x01, 0x55, 0x13, 0x20][..], &content_hash].concat()).is_valid());
+	assert!(!ipfs(cid_v1(0x55, content_hash)[..35].to_vec()).is_valid());
+	assert!(!ipfs([cid_v1(0x55, content_hash), vec![0]].concat()).is_valid());
+	// Multi-byte codecs are read as varints
+	assert!(ipfs([&[0x01, 0x90, 0x01, 0x12, 0x20][..], &[0; 32]].concat()).is_valid());
+
+	assert!(url(b"https://lab.example/reports/1.pdf").is_valid());
+	assert!(!url(b"http://lab.example/reports/1.pdf").is_valid());
+	assert!(!url(b"https:///reports/1.pdf").is_valid());
+	assert!(!url(b"https://lab.example/reports 1.pdf").is_valid());
+
+	assert!(pointer(crate::DataLocation::Blob).is_valid());
+	assert!(!crate::DataPointerOf::<Test> { size: 0, ..pointer(crate::DataLocation::Blob) }.is_valid());
+}
+
+// Register `content` as a document of a patient, the way uploaders do before handing it to a node
+fn store_document(who: u64, patient_id: u32, content: &[u8]) -> sp_runtime::DispatchResult {
+	MedicalRecord::store_document(
+		RuntimeOrigin::signed(who),
+		patient_id,
+		sp_io::hashing::sha2_256(content),
+		content.len() as u32,
+	)
+}
+
+#[test]
+fn stored_documents_are_registered_by_hash() {
+	let mut ext = new_test_ext();
+	ext.execute_with(|| {
+		create_linked_patient();
+
+		// Registering needs consent on the patient's clinical records
+		assert_noop!(store_document(2, 0, b"lab report"), Error::<Test>::NoConsent);
+		give_consent(0, 2);
+		assert_noop!(store_document(2, 0, b""), Error::<Test>::EmptyDocument);
+		assert_noop!(store_document(2, 0, &[0; 1025]), Error::<Test>::DocumentTooLarge);
+
+		assert_ok!(store_document(2, 0, b"lab report"));
+		let content_hash = sp_io::hashing::sha2_256(b"lab report");
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::DocumentStored {
+			content_hash,
+			patient_id: 0,
+			size: 10,
+		}));
+		let document = MedicalRecord::documents(content_hash).unwrap();
+		assert_eq!((document.patient_id, document.size, document.uploaded_by), (0, 10, 2));
+		assert_noop!(store_document(2, 0, b"lab report"), Error::<Test>::DocumentAlreadyStored);
+
+		// Only the uploader hands the content to nodes, at the registered size
+		assert!(MedicalRecord::can_write_document(&2, &content_hash, 10));
+		assert!(!MedicalRecord::can_write_document(&2, &content_hash, 11));
+		assert!(!MedicalRecord::can_write_document(&patient_account(0), &content_hash, 10));
+		assert!(!MedicalRecord::can_write_document(&2, &[0; 32], 10));
+	});
+
+	// The content never goes through the runtime
+	ext.persist_offchain_overlay();
+	let content_hash = sp_io::hashing::sha2_256(b"lab report");
+	assert_eq!(ext.offchain_db().get(&crate::data_pointer::document_key(&content_hash)), None);
+}
+
+#[test]
+fn stored_documents_are_readable_by_authorised_accounts() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(store_document(2, 0, b"lab report"));
+		let content_hash = sp_io::hashing::sha2_256(b"lab report");
+
+		assert!(MedicalRecord::can_read_document(&2, &content_hash));
+		assert!(MedicalRecord::can_read_document(&patient_account(0), &content_hash));
+		assert!(!MedicalRecord::can_read_document(&3, &content_hash));
+		assert!(!MedicalRecord::can_read_document(&2, &[0; 32]));
+
+		// Read consent on any clinical record type is enough
+		assert_ok!(MedicalRecord::grant_consent(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			3,
+			RecordType::DiseaseProgression,
+			ConsentScope { read: true, append: false, amend: false },
+			None,
+		));
+		assert!(MedicalRecord::can_read_document(&3, &content_hash));
+	});
+}
+
+#[test]
+fn documents_are_attached_to_clinical_records() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		create_patients(1);
+		give_consent(1, 2);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"X-Ray".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Clear".to_vec(),
+			b"See report".to_vec(),
+		));
+		assert_ok!(store_document(2, 0, b"imaging report"));
+		assert_ok!(store_document(2, 1, b"other patient"));
+
+		// Stored documents have to exist, belong to the patient and match in size
+		let report = blob_pointer(b"imaging report");
+		assert_noop!(
+			MedicalRecord::add_attachment(RuntimeOrigin::signed(2), RecordType::ClinicalTest, 0, blob_pointer(b"missing")),
+			Error::<Test>::DocumentNotFound
+		);
+		assert_noop!(
+			MedicalRecord::add_attachment(
+				RuntimeOrigin::signed(2),
+				RecordType::ClinicalTest,
+				0,
+				blob_pointer(b"other patient")
+			),
+			Error::<Test>::InvalidDataPointer
+		);
+		assert_noop!(
+			MedicalRecord::add_attachment(
+				RuntimeOrigin::signed(2),
+				RecordType::ClinicalTest,
+				0,
+				crate::DataPointerOf::<Test> { size: 1, ..report.clone() }
+			),
+			Error::<Test>::InvalidDataPointer
+		);
+
+		assert_ok!(MedicalRecord::add_attachment(RuntimeOrigin::signed(2), RecordType::ClinicalTest, 0, report.clone()));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::AttachmentAdded {
+			record_type: RecordType::ClinicalTest,
+			record_id: 0,
+			content_hash: report.content_hash,
+		}));
+		assert_noop!(
+			MedicalRecord::add_attachment(RuntimeOrigin::signed(2), RecordType::ClinicalTest, 0, report.clone()),
+			Error::<Test>::AttachmentAlreadyExists
+		);
+		for url in [&b"https://lab.example/1"[..], b"https://lab.example/2", b"https://lab.example/3"] {
+			assert_ok!(MedicalRecord::add_attachment(RuntimeOrigin::signed(2), RecordType::ClinicalTest, 0, url_pointer(url)));
+		}
+		assert_noop!(
+			MedicalRecord::add_attachment(
+				RuntimeOrigin::signed(2),
+				RecordType::ClinicalTest,
+				0,
+				url_pointer(b"https://lab.example/4")
+			),
+			Error::<Test>::TooManyAttachments
+		);
+		assert_eq!(MedicalRecord::clinical_tests(0).unwrap().attachments[0], report);
+		assert_eq!(MedicalRecord::proofs_for_record(RecordType::ClinicalTest, 0).len(), 5);
+
+		assert_ok!(MedicalRecord::remove_attachment(
+			RuntimeOrigin::signed(2),
+			RecordType::ClinicalTest,
+			0,
+			report.content_hash
+		));
+		assert_eq!(MedicalRecord::clinical_tests(0).unwrap().attachments.len(), 3);
+		assert_noop!(
+			MedicalRecord::remove_attachment(RuntimeOrigin::signed(2), RecordType::ClinicalTest, 0, report.content_hash),
+			Error::<Test>::AttachmentNotFound
+		);
+
+		// Both changes are in the audit trail
+		let history = history_of(RecordType::ClinicalTest, 0);
+		assert_eq!(
+			history[4],
+			(b"attachments".to_vec(), None, report.encode(), 2, OperationType::Update)
+		);
+		assert_eq!(
+			history.last().unwrap(),
+			&(b"attachments".to_vec(), Some(report.encode()), b"removed".to_vec(), 2, OperationType::Update)
+		);
+
+		// Only clinical tests and disease progressions take attachments, under amend consent
+		assert_noop!(
+			MedicalRecord::add_attachment(RuntimeOrigin::signed(2), RecordType::MedicalRecord, 0, report.clone()),
+			Error::<Test>::AttachmentsNotSupported
+		);
+		assert_noop!(
+			MedicalRecord::add_attachment(RuntimeOrigin::signed(3), RecordType::ClinicalTest, 0, report),
+			Error::<Test>::NoConsent
+		);
+	});
+}
+
+#[test]
+fn documents_are_attached_to_disease_progressions() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(create_progression(None));
+		let pointer = crate::DataPointerOf::<Test> {
+			location: crate::DataLocation::Ipfs(cid_v1(0x70, [1; 32]).try_into().unwrap()),
+			content_hash: [2; 32],
+			size: 2048,
+		};
+
+		assert_ok!(MedicalRecord::add_attachment(
+			RuntimeOrigin::signed(2),
+			RecordType::DiseaseProgression,
+			0,
+			pointer.clone()
+		));
+		assert_eq!(MedicalRecord::disease_progressions(0).unwrap().attachments.to_vec(), vec![pointer]);
+		assert_noop!(
+			MedicalRecord::add_attachment(
+				RuntimeOrigin::signed(2),
+				RecordType::DiseaseProgression,
+				0,
+				url_pointer(b"ftp://lab.example/1")
+			),
+			Error::<Test>::InvalidDataPointer
+		);
+	});
+}
+
+#[test]
+fn migration_v6_types_data_pointers() {
+	use crate::migrations::v6::{v5, MigrateV5ToV6};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(5).put::<MedicalRecord>();
+		v5::ClinicalTests::<Test>::insert(
+			0,
+			v5::ClinicalTest::<Test> {
+				test_id: 0,
+				patient_id: 0,
+				doctor_id: 2,
+				test_type: b"Blood Test".to_vec().try_into().unwrap(),
+				test_date: b"2023-01-15".to_vec().try_into().unwrap(),
+				result: b"Normal".to_vec().try_into().unwrap(),
+				notes: b"".to_vec().try_into().unwrap(),
+				created_at: 1,
+				created_by: 2,
+				last_modified_at: 1,
+				last_modified_by: 2,
+			},
+		);
+		v5::DiseaseProgressions::<Test>::insert(
+			0,
+			v5::DiseaseProgression::<Test> {
+				progression_id: 0,
+				patient_id: 0,
+				doctor_id: 2,
+				visit_date: b"2023-01-15".to_vec().try_into().unwrap(),
+				symptoms: b"Fever".to_vec().try_into().unwrap(),
+				diagnosis: b"Cold".to_vec().try_into().unwrap(),
+				treatment: b"Rest".to_vec().try_into().unwrap(),
+				prescription: b"Medicine".to_vec().try_into().unwrap(),
+				next_appointment: Some(3),
+				created_at: 1,
+				created_by: 2,
+				last_modified_at: 1,
+				last_modified_by: 2,
+			},
+		);
+		let record = |record_id, data_pointer: Option<&[u8]>| v5::MedicalRecord::<Test> {
+			record_id,
+			patient_id: 0,
+			doctor_id: 2,
+			record_hash: H256::repeat_byte(1),
+			data_pointer: data_pointer.map(|pointer| pointer.to_vec().try_into().unwrap()),
+			diagnosis: b"Flu".to_vec().try_into().unwrap(),
+			treatment: b"Rest".to_vec().try_into().unwrap(),
+			created_at: 1,
+			created_by: 2,
+			last_modified_at: 1,
+			last_modified_by: 2,
+		};
+		v5::MedicalRecords::<Test>::insert(0, record(0, Some(b"ipfs://record-123")));
+		v5::MedicalRecords::<Test>::insert(1, record(1, None));
+
+		run_migration::<MigrateV5ToV6<Test>>(Weight::MAX);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 6);
+		let test = MedicalRecord::clinical_tests(0).unwrap();
+		assert_eq!((test.result.to_vec(), test.attachments.len()), (b"Normal".to_vec(), 0));
+		let progression = MedicalRecord::disease_progressions(0).unwrap();
+		assert_eq!((progression.next_appointment, progression.attachments.len()), (Some(3), 0));
+		// Untyped pointers carry no content hash, so they are dropped
+		for record_id in [0, 1] {
+			let record = MedicalRecord::medical_records(record_id).unwrap();
+			assert_eq!(record.data_pointer, None);
+			assert_eq!(record.record_hash, H256::repeat_byte(1));
+		}
+	});
+}
+
+// Run `on_initialize` for the blocks up to `block`
+fn run_to_block(block: u64) {
+	use frame_support::traits::Hooks;
+	while System::block_number() < block {
+		System::set_block_number(System::block_number() + 1);
+		MedicalRecord::on_initialize(System::block_number());
+	}
+}
+
+// Verify the whole audit trail in segments of `MaxAuditRange` changes
+fn verify_whole_audit_trail() {
+	let latest = MedicalRecord::next_change_id() - 1;
+	for from in (0..=latest).step_by(16) {
+		assert!(MedicalRecord::verify_audit_trail(from, Some((from + 15).min(latest))).is_ok());
+	}
+	assert!(MedicalRecord::verify_audit_trail(latest, None).is_ok());
+}
+
+#[test]
+fn erasure_is_requested_by_the_patient_and_approved() {
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		create_patients(2);
+		for patient_id in [1, 2] {
+			assert_ok!(MedicalRecord::link_patient_account(RuntimeOrigin::signed(1), patient_id, patient_account(patient_id)));
+		}
+
+		assert_noop!(
+			MedicalRecord::request_erasure(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NotPatientOrGuardian
+		);
+		assert_noop!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 0), Error::<Test>::ErasureNotRequested);
+
+		assert_ok!(MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::ErasureRequested {
+			patient_id: 0,
+			requested_by: patient_account(0),
+		}));
+		assert_noop!(
+			MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0),
+			Error::<Test>::ErasureAlreadyRequested
+		);
+
+		// Only the data-protection origin decides
+		assert_noop!(MedicalRecord::approve_erasure(RuntimeOrigin::signed(1), 0), sp_runtime::DispatchError::BadOrigin);
+		assert_ok!(MedicalRecord::reject_erasure(RuntimeOrigin::root(), 0));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::ErasureRejected { patient_id: 0 }));
+		assert!(MedicalRecord::erasure_request(0).is_none());
+		assert!(MedicalRecord::patients(0).is_some());
+
+		assert_ok!(MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0));
+		assert_ok!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 0));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::ErasureApproved { patient_id: 0 }));
+		assert_noop!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 0), Error::<Test>::ErasureAlreadyApproved);
+		assert_noop!(MedicalRecord::reject_erasure(RuntimeOrigin::root(), 0), Error::<Test>::ErasureAlreadyApproved);
+
+		// The patient is unlisted on approval, so nothing new can be recorded for them
+		assert!(MedicalRecord::patients(0).is_none());
+		assert!(!MedicalRecord::patient_name_exists(&name_index(b"John Doe")));
+		assert_eq!(MedicalRecord::active_patient_count(), 2);
+		assert_noop!(
+			MedicalRecord::grant_consent(
+				RuntimeOrigin::signed(patient_account(0)),
+				0,
+				2,
+				RecordType::ClinicalTest,
+				ConsentScope { read: true, append: true, amend: true },
+				None,
+			),
+			Error::<Test>::PatientNotFound
+		);
+
+		// Approved erasures wait in a bounded queue
+		for patient_id in [1, 2] {
+			assert_ok!(MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(patient_id)), patient_id));
+		}
+		assert_ok!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 1));
+		assert_noop!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 2), Error::<Test>::TooManyPendingErasures);
+		assert_eq!(MedicalRecord::erasure_queue().to_vec(), vec![0, 1]);
+	});
+}
+
+#[test]
+fn erasure_removes_personal_data_over_several_blocks() {
+	let mut ext = new_test_ext();
+	let content_hash = sp_io::hashing::sha2_256(b"imaging report");
+	// The uploader handed the document to this node
+	sp_core::offchain::OffchainStorage::set(
+		&mut ext.offchain_db(),
+		b"",
+		&crate::data_pointer::document_key(&content_hash),
+		b"imaging report",
+	);
+	ext.execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			0,
+			b"X-Ray".to_vec(),
+			b"2023-01-15".to_vec(),
+			b"Fracture".to_vec(),
+			b"Left wrist".to_vec(),
+		));
+		assert_ok!(create_progression(Some(FOLLOW_UP)));
+		assert_ok!(MedicalRecord::create_medical_record(
+			RuntimeOrigin::signed(2),
+			0,
+			b"Fracture".to_vec(),
+			b"Cast".to_vec(),
+			None,
+		));
+		assert_ok!(store_document(2, 0, b"imaging report"));
+		assert_ok!(MedicalRecord::add_attachment(
+			RuntimeOrigin::signed(2),
+			RecordType::ClinicalTest,
+			0,
+			blob_pointer(b"imaging report")
+		));
+
+		// Another patient of the same doctor is left alone
+		create_patients(1);
+		give_consent(1, 2);
+		assert_ok!(MedicalRecord::create_clinical_test(
+			RuntimeOrigin::signed(2),
+			1,
+			b"Blood Test".to_vec(),
+			b"2023-01-16".to_vec(),
+			b"Normal".to_vec(),
+			b"".to_vec(),
+		));
+
+		assert_ok!(MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0));
+		assert_ok!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 0));
+
+		// Erasure proceeds in bounded steps per block
+		run_to_block(2);
+		assert_eq!(MedicalRecord::erasure_request(0).unwrap().stage, crate::ErasureStage::Records);
+		let mut blocks = 1;
+		while MedicalRecord::erasure_request(0).is_some() {
+			run_to_block(System::block_number() + 1);
+			blocks += 1;
+		}
+		assert!(blocks > 2);
+		assert!(MedicalRecord::erasure_queue().is_empty());
+
+		// No record, document, grant, link or key of the patient is left
+		assert!(MedicalRecord::clinical_tests(0).is_none());
+		assert!(MedicalRecord::disease_progressions(0).is_none());
+		assert!(MedicalRecord::medical_records(0).is_none());
+		assert!(MedicalRecord::documents(content_hash).is_none());
+		assert_eq!(crate::PatientDocuments::<Test>::iter_prefix(0).count(), 0);
+		assert_eq!(crate::Consents::<Test>::iter_prefix(0).count(), 0);
+		assert!(!crate::PatientClinicalTests::<Test>::contains_key(0));
+		assert!(!crate::PatientDoctors::<Test>::contains_key(0));
+		assert_eq!(MedicalRecord::doctor_patients(2).to_vec(), vec![1]);
+		assert!(MedicalRecord::patient_account(0).is_none());
+		assert!(MedicalRecord::patient_data_keys(0).is_none());
+		assert!(crate::mock::BookedAppointments::get()[&0].cancelled);
+
+		// Audit entries keep only the hashes of their values
+		let erased = [
+			(RecordType::Patient, 0),
+			(RecordType::ClinicalTest, 0),
+			(RecordType::DiseaseProgression, 0),
+			(RecordType::MedicalRecord, 0),
+			(RecordType::Consent, 0),
+		];
+		let mut redacted = 0;
+		for (record_type, record_id) in erased.clone() {
+			for (field_name, old_value, new_value, _, _) in history_of(record_type, record_id) {
+				if field_name == b"erased".to_vec() {
+					continue;
+				}
+				assert!(old_value.is_none_or(|value| value.len() == 32));
+				assert_eq!(new_value.len(), 32);
+				redacted += 1;
+			}
+		}
+		assert!(crate::ChangeHistories::<Test>::iter_values().all(|change| {
+			change.redacted ==
+				(erased.contains(&(change.record_type.clone(), change.record_id)) &&
+					change.field_name.to_vec() != b"erased".to_vec())
+		}));
+		assert_eq!(history_of(RecordType::ClinicalTest, 1)[2].2, b"Normal".to_vec());
+		verify_whole_audit_trail();
+		assert_ok!(MedicalRecord::do_try_state());
+
+		// The tombstone is chained into the audit trail
+		let tombstone = MedicalRecord::erasure_tombstone(0).unwrap();
+		assert_eq!((tombstone.requested_by, tombstone.approved_at), (patient_account(0), 1));
+		assert!(tombstone.erased_items > redacted);
+		let last_change = MedicalRecord::change_history(MedicalRecord::next_change_id() - 1).unwrap();
+		assert_eq!(
+			(last_change.record_type, last_change.field_name.to_vec(), last_change.new_value.to_vec()),
+			(RecordType::Patient, b"erased".to_vec(), tombstone.digest.encode())
+		);
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::PatientErased {
+			patient_id: 0,
+			erased_items: tombstone.erased_items,
+			digest: tombstone.digest,
+		}));
+
+		// Nothing of the patient can be found any more
+		assert_noop!(
+			MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0),
+			Error::<Test>::NotPatientOrGuardian
+		);
+	});
+
+	// The document is gone from the offchain database as well
+	ext.persist_offchain_overlay();
+	assert_eq!(ext.offchain_db().get(&crate::data_pointer::document_key(&content_hash)), None);
+}
+
+#[test]
+fn redacted_changes_keep_their_hash() {
+	new_test_ext().execute_with(|| {
+		create_patients(1);
+		let change = MedicalRecord::change_history(0).unwrap();
+		let redacted = crate::ChangeHistory::<Test> {
+			old_value: None,
+			new_value: BlakeTwo256::hash(&change.new_value).encode().try_into().unwrap(),
+			redacted: true,
+			..change.clone()
+		};
+		assert_eq!(MedicalRecord::compute_change_hash(&redacted), change.change_hash);
+
+		// A redacted value cannot be swapped for another hash
+		let forged = crate::ChangeHistory::<Test> { new_value: vec![0; 32].try_into().unwrap(), ..redacted };
+		assert_ne!(MedicalRecord::compute_change_hash(&forged), change.change_hash);
+	});
+}
+
+#[test]
+fn migration_v7_adds_redaction_flag_and_indexes_documents() {
+	use crate::migrations::v7::{v6, MigrateV6ToV7};
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		create_linked_patient();
+		give_consent(0, 2);
+		assert_ok!(store_document(2, 0, b"lab report"));
+		let content_hash = sp_io::hashing::sha2_256(b"lab report");
+		crate::PatientDocuments::<Test>::remove(0, content_hash);
+
+		StorageVersion::new(6).put::<MedicalRecord>();
+		let changes = MedicalRecord::next_change_id();
+		for change_id in 0..changes {
+			let change = crate::ChangeHistories::<Test>::take(change_id).unwrap();
+			v6::ChangeHistories::<Test>::insert(
+				change_id,
+				v6::ChangeHistory::<Test> {
+					change_id,
+					record_type: change.record_type,
+					record_id: change.record_id,
+					field_name: change.field_name,
+					old_value: change.old_value,
+					new_value: change.new_value,
+					changed_by: change.changed_by,
+					changed_at: change.changed_at,
+					operation: change.operation,
+					prev_change_hash: change.prev_change_hash,
+					change_hash: change.change_hash,
+				},
+			);
+		}
+
+		run_migration::<MigrateV6ToV7<Test>>(Weight::MAX);
+
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 7);
+		assert_eq!(crate::ChangeHistories::<Test>::iter_values().filter(|change| !change.redacted).count() as u32, changes);
+		verify_whole_audit_trail();
+		assert!(crate::PatientDocuments::<Test>::contains_key(0, content_hash));
+	});
+}
+
+// A PII field sealed with the given version of the patient data key
+fn sealed(value: &[u8], key_version: u32) -> Vec<u8> {
+	crate::PiiEnvelope {
+		version: crate::envelope::ENVELOPE_VERSION,
+		key_version,
+		nonce: [7; 24],
+		ciphertext: [value, &[0; crate::envelope::TAG_LENGTH]].concat(),
+	}
+	.encode()
+}
+
+fn wrapped_key(seed: u8) -> crate::WrappedDataKey {
+	crate::WrappedDataKey { ephemeral_public: [seed; 32], nonce: [seed; 24], ciphertext: [seed; 48] }
+}
+
+fn create_encrypted_patient() {
+	assert_ok!(MedicalRecord::create_patient(
+		RuntimeOrigin::signed(1),
+		sealed(b"John Doe", 0),
+		sealed(b"1990-01-01", 0),
+		b"Male".to_vec(),
+		sealed(b"123 Main St", 0),
+		sealed(b"555-1234", 0),
+		sealed(b"Jane Doe - 555-5678", 0),
+		name_index(b"John Doe"),
+	));
+}
+
+#[test]
+fn encrypted_pii_must_be_sealed_in_envelopes() {
+	new_test_ext().execute_with(|| {
+		EncryptedPii::set(true);
+		let create = |name: Vec<u8>| {
+			MedicalRecord::create_patient(
+				RuntimeOrigin::signed(1),
+				name,
+				sealed(b"1990-01-01", 0),
+				b"Male".to_vec(),
+				sealed(b"123 Main St", 0),
+				sealed(b"555-1234", 0),
+				sealed(b"Jane Doe - 555-5678", 0),
+				name_index(b"John Doe"),
+			)
+		};
+
+		// Plaintext, trailing bytes, unknown versions and missing tags are rejected
+		assert_noop!(create(b"John Doe".to_vec()), Error::<Test>::InvalidEnvelope);
+		assert_noop!(create([sealed(b"John Doe", 0), vec![0]].concat()), Error::<Test>::InvalidEnvelope);
+		let mut unknown_version = sealed(b"John Doe", 0);
+		unknown_version[0] = 2;
+		assert_noop!(create(unknown_version), Error::<Test>::InvalidEnvelope);
+		let untagged = crate::PiiEnvelope {
+			version: crate::envelope::ENVELOPE_VERSION,
+			key_version: 0,
+			nonce: [0; 24],
+			ciphertext: vec![0; crate::envelope::TAG_LENGTH - 1],
+		};
+		assert_noop!(create(untagged.encode()), Error::<Test>::InvalidEnvelope);
+		assert_noop!(create(sealed(b"John Doe", 1)), Error::<Test>::DataKeyVersionMismatch);
+
+		create_encrypted_patient();
+
+		// Only ciphertext reaches state and the change history, and no birth year is indexed
+		let patient = Patients::<Test>::get(0).unwrap();
+		assert_eq!(patient.patient_name.to_vec(), sealed(b"John Doe", 0));
+		assert_eq!(patient.gender.to_vec(), b"Male".to_vec());
+		let history = MedicalRecord::get_record_history(RecordType::Patient, 0);
+		assert_eq!(history[1].new_value.to_vec(), sealed(b"1990-01-01", 0));
+		assert!(MedicalRecord::patients_by_birth_year(1990).is_empty());
+
+		assert_noop!(
+			MedicalRecord::update_patient(RuntimeOrigin::signed(1), 0, None, None, None, None, Some(b"555-0000".to_vec()), None, None),
+			Error::<Test>::InvalidEnvelope
+		);
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			Some(sealed(b"555-0000", 0)),
+			None,
+			None,
+		));
+	});
+}
+
+#[test]
+fn data_keys_are_wrapped_for_registered_accounts() {
+	new_test_ext().execute_with(|| {
+		EncryptedPii::set(true);
+		create_encrypted_patient();
+		assert_ok!(MedicalRecord::link_patient_account(RuntimeOrigin::signed(1), 0, patient_account(0)));
+		for account in [1, 3, patient_account(0)] {
+			assert_ok!(MedicalRecord::register_encryption_key(RuntimeOrigin::signed(account), [account as u8; 32]));
+		}
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::EncryptionKeyRegistered {
+			who: patient_account(0),
+		}));
+
+		// Only the patient, an admin or the registering account may share the key
+		assert_noop!(
+			MedicalRecord::set_data_keys(RuntimeOrigin::signed(3), 0, 0, vec![(3, wrapped_key(3))]),
+			Error::<Test>::NotAuthorized
+		);
+		assert_noop!(
+			MedicalRecord::set_data_keys(RuntimeOrigin::signed(1), 0, 0, vec![(4, wrapped_key(4))]),
+			Error::<Test>::EncryptionKeyNotRegistered
+		);
+		assert_noop!(
+			MedicalRecord::set_data_keys(RuntimeOrigin::signed(1), 0, 1, vec![(1, wrapped_key(1))]),
+			Error::<Test>::InvalidDataKeyVersion
+		);
+		assert_ok!(MedicalRecord::set_data_keys(
+			RuntimeOrigin::signed(1),
+			0,
+			0,
+			vec![(1, wrapped_key(1)), (patient_account(0), wrapped_key(2))],
+		));
+		assert_ok!(MedicalRecord::set_data_keys(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			0,
+			vec![(3, wrapped_key(3)), (1, wrapped_key(4))],
+		));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::DataKeysSet { patient_id: 0, key_version: 0 }));
+		let keyring = MedicalRecord::patient_data_keys(0).unwrap();
+		assert_eq!(
+			keyring.wrapped_keys.to_vec(),
+			vec![(1, wrapped_key(4)), (patient_account(0), wrapped_key(2)), (3, wrapped_key(3))]
+		);
+
+		assert_ok!(MedicalRecord::remove_data_key(RuntimeOrigin::signed(patient_account(0)), 0, 3));
+		assert_noop!(
+			MedicalRecord::remove_data_key(RuntimeOrigin::signed(patient_account(0)), 0, 3),
+			Error::<Test>::DataKeyNotFound
+		);
+
+		// Rotating drops every copy of the old key, and fields must be sealed with the new one
+		assert_ok!(MedicalRecord::set_data_keys(
+			RuntimeOrigin::signed(patient_account(0)),
+			0,
+			1,
+			vec![(patient_account(0), wrapped_key(5))],
+		));
+		assert_eq!(
+			MedicalRecord::patient_data_keys(0).unwrap().wrapped_keys.to_vec(),
+			vec![(patient_account(0), wrapped_key(5))]
+		);
+		assert_eq!(MedicalRecord::current_key_version(0), 1);
+		assert_noop!(
+			MedicalRecord::update_patient(RuntimeOrigin::signed(1), 0, None, None, None, None, Some(sealed(b"555-0000", 0)), None, None),
+			Error::<Test>::DataKeyVersionMismatch
+		);
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			None,
+			None,
+			None,
+			Some(sealed(b"555-0000", 1)),
+			None,
+			None,
+		));
+
+		// The keyring is bounded and goes away with the patient
+		for account in 10..14 {
+			assert_ok!(MedicalRecord::register_encryption_key(RuntimeOrigin::signed(account), [0; 32]));
+		}
+		assert_noop!(
+			MedicalRecord::set_data_keys(
+				RuntimeOrigin::signed(1),
+				0,
+				1,
+				(10..14).map(|account| (account, wrapped_key(6))).collect(),
+			),
+			Error::<Test>::TooManyDataKeyHolders
+		);
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 0));
+		assert!(MedicalRecord::patient_data_keys(0).is_none());
+	});
+}
+
+// Give patient 0 a clinical test, a progression with a follow-up, a medical record and a document
+fn create_patient_with_records() {
+	create_linked_patient();
+	give_consent(0, 2);
+	assert_ok!(MedicalRecord::create_clinical_test(
+		RuntimeOrigin::signed(2),
+		0,
+		b"X-Ray".to_vec(),
+		b"2023-01-15".to_vec(),
+		b"Fracture".to_vec(),
+		b"Left wrist".to_vec(),
+	));
+	assert_ok!(create_progression(Some(FOLLOW_UP)));
+	assert_ok!(MedicalRecord::create_medical_record(
+		RuntimeOrigin::signed(2),
+		0,
+		b"Fracture".to_vec(),
+		b"Cast".to_vec(),
+		None,
+	));
+	assert_ok!(store_document(2, 0, b"imaging report"));
+}
+
+#[test]
+fn deleting_a_patient_cascades_over_several_blocks() {
+	new_test_ext().execute_with(|| {
+		create_patient_with_records();
+		create_patients(3);
+		give_consent(1, 2);
+		assert_ok!(MedicalRecord::do_try_state());
+
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 0));
+		System::assert_last_event(RuntimeEvent::MedicalRecord(Event::PatientDeleted { patient_id: 0 }));
+		assert_eq!(MedicalRecord::deletion_queue().to_vec(), vec![(0, 1)]);
+		assert!(MedicalRecord::clinical_tests(0).is_some());
+		assert_ok!(MedicalRecord::do_try_state());
+
+		// Deletions are bounded as well
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 2));
+		assert_noop!(
+			MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 3),
+			Error::<Test>::TooManyPendingDeletions
+		);
+
+		run_to_block(2);
+		assert_eq!(MedicalRecord::deletion_queue().first(), Some(&(0, 1)));
+		while !MedicalRecord::deletion_queue().is_empty() {
+			run_to_block(System::block_number() + 1);
+			assert_ok!(MedicalRecord::do_try_state());
+		}
+		System::assert_has_event(RuntimeEvent::MedicalRecord(Event::PatientDeletionCompleted { patient_id: 0 }));
+		System::assert_has_event(RuntimeEvent::MedicalRecord(Event::MedicalRecordDeleted { record_id: 0 }));
+
+		// Nothing of the patient is left but their audit trail and account link
+		assert!(MedicalRecord::clinical_tests(0).is_none());
+		assert!(MedicalRecord::disease_progressions(0).is_none());
+		assert!(MedicalRecord::medical_records(0).is_none());
+		assert_eq!(crate::Documents::<Test>::iter().count(), 0);
+		assert_eq!(crate::Consents::<Test>::iter_prefix(0).count(), 0);
+		assert!(!crate::PatientClinicalTests::<Test>::contains_key(0));
+		assert!(!crate::PatientDoctors::<Test>::contains_key(0));
+		assert_eq!(MedicalRecord::doctor_patients(2).to_vec(), vec![1]);
+		assert!(crate::mock::BookedAppointments::get()[&0].cancelled);
+		assert_eq!(MedicalRecord::patient_account(0), Some(patient_account(0)));
+		assert_eq!(crate::PatientRecordIndex::<Test>::iter_prefix(0).count(), 3);
+
+		// Every deletion is recorded as done by the admin who deleted the patient
+		for record_type in [RecordType::ClinicalTest, RecordType::DiseaseProgression, RecordType::MedicalRecord] {
+			let last = history_of(record_type, 0).pop().unwrap();
+			assert_eq!((last.0, last.3, last.4), (b"deleted".to_vec(), 1, OperationType::Delete));
+		}
+		assert_eq!(
+			history_of(RecordType::Consent, 0).iter().filter(|change| change.2 == b"revoked".to_vec()).count(),
+			4
+		);
+		verify_whole_audit_trail();
+	});
+}
+
+#[test]
+fn deleting_records_keeps_indexes_consistent() {
+	new_test_ext().execute_with(|| {
+		create_patient_with_records();
+		assert_ok!(MedicalRecord::delete_clinical_test(RuntimeOrigin::signed(2), 0));
+		assert_ok!(MedicalRecord::delete_disease_progression(RuntimeOrigin::signed(2), 0));
+
+		// The care link follows consent, not records, and the follow-up goes with its progression
+		assert_eq!(MedicalRecord::doctor_patients(2).to_vec(), vec![0]);
+		assert_eq!(MedicalRecord::patient_doctors(0).to_vec(), vec![2]);
+		assert!(crate::mock::BookedAppointments::get()[&0].cancelled);
+		assert_ok!(MedicalRecord::do_try_state());
+
+		// Moving a patient to another gender or birth year moves them in the demographic indexes
+		assert_ok!(MedicalRecord::update_patient(
+			RuntimeOrigin::signed(1),
+			0,
+			None,
+			Some(b"1985-06-01".to_vec()),
+			Some(b"Female".to_vec()),
+			None,
+			None,
+			None,
+			None,
+		));
+		assert!(MedicalRecord::patients_by_gender(frame_support::BoundedVec::truncate_from(b"Male".to_vec())).is_empty());
+		assert_eq!(MedicalRecord::patients_by_gender(frame_support::BoundedVec::truncate_from(b"Female".to_vec())).to_vec(), vec![0]);
+		assert!(MedicalRecord::patients_by_birth_year(1990).is_empty());
+		assert_eq!(MedicalRecord::patients_by_birth_year(1985).to_vec(), vec![0]);
+		assert_ok!(MedicalRecord::do_try_state());
+
+		// Dangling entries are caught
+		crate::PatientDoctors::<Test>::remove(0);
+		assert!(MedicalRecord::do_try_state().is_err());
+	});
+}
+
+#[test]
+fn erasure_after_deletion_redacts_deleted_records() {
+	new_test_ext().execute_with(|| {
+		create_patient_with_records();
+		assert_ok!(MedicalRecord::delete_clinical_test(RuntimeOrigin::signed(2), 0));
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(1), 0));
+		run_to_block(10);
+		assert!(MedicalRecord::deletion_queue().is_empty());
+
+		// The account link outlives the deletion, so the patient can still ask for erasure
+		assert_ok!(MedicalRecord::request_erasure(RuntimeOrigin::signed(patient_account(0)), 0));
+		assert_ok!(MedicalRecord::approve_erasure(RuntimeOrigin::root(), 0));
+		while MedicalRecord::erasure_request(0).is_some() {
+			run_to_block(System::block_number() + 1);
+			assert_ok!(MedicalRecord::do_try_state());
+		}
+
+		for record_type in [
+			RecordType::Patient,
+			RecordType::ClinicalTest,
+			RecordType::DiseaseProgression,
+			RecordType::MedicalRecord,
+			RecordType::Consent,
+		] {
+			assert!(MedicalRecord::get_record_history(record_type, 0)
+				.iter()
+				.all(|change| change.redacted || change.field_name.to_vec() == b"erased".to_vec()));
+		}
+		assert_eq!(crate::PatientRecordIndex::<Test>::iter_prefix(0).count(), 0);
+		assert!(MedicalRecord::patient_account(0).is_none());
+		verify_whole_audit_trail();
+	});
+}
+
+#[test]
+fn migration_v8_indexes_records_and_queues_dangling_patients() {
+	use crate::migrations::v8::MigrateV7ToV8;
+	use frame_support::traits::{GetStorageVersion, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		create_patient_with_records();
+		create_patients(1);
+
+		// Patient 0 was deleted before deletions cascaded, leaving their records behind
+		assert_ok!(MedicalRecord::delete_patient(RuntimeOrigin::signed(2), 0));
+		crate::DeletionQueue::<Test>::kill();
+		let _ = crate::PatientRecordIndex::<Test>::clear(u32::MAX, None);
+		assert!(MedicalRecord::do_try_state().is_err());
+
+		StorageVersion::new(7).put::<MedicalRecord>();
+		run_migration::<MigrateV7ToV8<Test>>(Weight::MAX);
+		assert_eq!(MedicalRecord::on_chain_storage_version(), 8);
+
+		assert_eq!(crate::PatientRecordIndex::<Test>::iter_prefix(0).count(), 3);
+		assert_eq!(MedicalRecord::deletion_queue().to_vec(), vec![(0, 2)]);
+		assert_ok!(MedicalRecord::do_try_state());
+
+		run_to_block(10);
+		assert!(MedicalRecord::clinical_tests(0).is_none());
+		assert!(MedicalRecord::doctor_patients(2).is_empty());
+		assert_eq!(history_of(RecordType::ClinicalTest, 0).pop().unwrap().3, 2);
+		assert_ok!(MedicalRecord::do_try_state());
+	});
+}
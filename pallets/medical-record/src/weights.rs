@@ -42,9 +42,29 @@ pub trait WeightInfo {
 	fn create_disease_progression() -> Weight;
 	fn update_disease_progression() -> Weight;
 	fn delete_disease_progression() -> Weight;
+	fn set_next_appointment() -> Weight;
+	fn clear_appointment() -> Weight;
+	fn reindex_patient_names(n: u32) -> Weight;
 	fn create_medical_record() -> Weight;
 	fn search_patient_by_name() -> Weight;
+	fn get_patient_complete_history() -> Weight;
 	fn record_change() -> Weight;
+	fn grant_role() -> Weight;
+	fn revoke_role() -> Weight;
+	fn link_patient_account() -> Weight;
+	fn set_guardian() -> Weight;
+	fn grant_consent() -> Weight;
+	fn revoke_consent() -> Weight;
+	fn register_encryption_key() -> Weight;
+	fn set_data_keys(n: u32) -> Weight;
+	fn remove_data_key() -> Weight;
+	fn store_document() -> Weight;
+	fn add_attachment() -> Weight;
+	fn remove_attachment() -> Weight;
+	fn request_erasure() -> Weight;
+	fn approve_erasure() -> Weight;
+	fn reject_erasure() -> Weight;
+	fn on_initialize(s: u32) -> Weight;
 }
 
 /// Weights for pallet_medical_record using the Substrate node and recommended hardware.
@@ -53,61 +73,81 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	fn create_patient() -> Weight {
 		Weight::from_parts(15_000, 0) // Increased due to multiple change records
 			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(9)) // patient + name mapping + counter + 6 change records
+			.saturating_add(T::DbWeight::get().writes(10)) // patient + name index both ways + counter + 6 change records
 	}
 
 	fn update_patient() -> Weight {
 		Weight::from_parts(12_000, 0) // Increased due to change records
-			.saturating_add(T::DbWeight::get().reads(2))
-			.saturating_add(T::DbWeight::get().writes(4)) // patient + name mapping + change records
+			.saturating_add(T::DbWeight::get().reads(4)) // + current and new name index
+			.saturating_add(T::DbWeight::get().writes(6)) // patient + old and new name index both ways + change records
 	}
 
 	fn delete_patient() -> Weight {
 		Weight::from_parts(12_000, 0) // Increased due to change record
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(4)) // patient + name mapping + change record + counter
+			.saturating_add(T::DbWeight::get().reads(3)) // + name index and deletion queue
+			.saturating_add(T::DbWeight::get().writes(7)) // patient + name index both ways + change record + counter + data keys + deletion queue
 	}
 
 	fn create_clinical_test() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(2))
-			.saturating_add(T::DbWeight::get().writes(2))
+		Weight::from_parts(15_000, 0) // Increased due to multiple change records
+			.saturating_add(T::DbWeight::get().reads(8)) // + change counter and audit head
+			.saturating_add(T::DbWeight::get().writes(20)) // + 4 change records, counter, audit head and record index
 	}
 
 	fn update_clinical_test() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+		Weight::from_parts(15_000, 0) // Increased due to multiple change records
+			.saturating_add(T::DbWeight::get().reads(7)) // + change counter and audit head
+			.saturating_add(T::DbWeight::get().writes(18)) // + up to 4 change records, counter and audit head
 	}
 
 	fn delete_clinical_test() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+		Weight::from_parts(12_000, 0) // Increased due to change record
+			.saturating_add(T::DbWeight::get().reads(3))
+			.saturating_add(T::DbWeight::get().writes(6)) // test + test list + change record + counter + audit head
 	}
 
 	fn create_disease_progression() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(2))
-			.saturating_add(T::DbWeight::get().writes(2))
+		Weight::from_parts(25_000, 0) // Increased due to booking the next appointment
+			.saturating_add(T::DbWeight::get().reads(16)) // + patient account, doctor calendar, agenda and audit head
+			.saturating_add(T::DbWeight::get().writes(31)) // + appointment, its indexes, agenda, 6 change records and record index
 	}
 
 	fn update_disease_progression() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+		Weight::from_parts(25_000, 0) // Increased due to booking the next appointment
+			.saturating_add(T::DbWeight::get().reads(16)) // + linked appointment, doctor calendar, agenda and audit head
+			.saturating_add(T::DbWeight::get().writes(29)) // + appointment, its indexes, agenda and up to 6 change records
 	}
 
 	fn delete_disease_progression() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+		Weight::from_parts(20_000, 0) // Increased due to cancelling the follow-up appointment
+			.saturating_add(T::DbWeight::get().reads(5)) // + follow-up appointment and agenda
+			.saturating_add(T::DbWeight::get().writes(8)) // progression + progression list + change record + counter + audit head + appointment
+	}
+
+	fn set_next_appointment() -> Weight {
+		Weight::from_parts(20_000, 0) // Books a new follow-up or moves the linked one
+			.saturating_add(T::DbWeight::get().reads(14)) // progression + role + consent + patient account + linked appointment + doctor calendar + agenda + audit head + proofs
+			.saturating_add(T::DbWeight::get().writes(17)) // progression + appointment, counter, indexes, bookings, agenda and queued tasks + change record + counter + audit head + proofs
+	}
+
+	fn clear_appointment() -> Weight {
+		Weight::from_parts(15_000, 0) // Cancels the follow-up and returns held funds
+			.saturating_add(T::DbWeight::get().reads(11)) // progression + role + consent + appointment + doctor bookings + escrow + audit head + proofs
+			.saturating_add(T::DbWeight::get().writes(10)) // progression + appointment + doctor bookings + escrow + change record + counter + audit head + proofs
+	}
+
+	fn reindex_patient_names(n: u32) -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(Weight::from_parts(4_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(1)) // role
+			.saturating_add(T::DbWeight::get().reads((4 * n).into())) // patient + previous index + its patients + new index
+			.saturating_add(T::DbWeight::get().writes((3 * n).into())) // previous index + its patients + new index
 	}
 
 	fn create_medical_record() -> Weight {
-		Weight::from_parts(10_000, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(2))
+		Weight::from_parts(15_000, 0) // Increased due to multiple change records
+			.saturating_add(T::DbWeight::get().reads(8)) // + change counter, audit head and stored document
+			.saturating_add(T::DbWeight::get().writes(18)) // + 3 change records, counter, audit head and record index
 	}
 
 	fn search_patient_by_name() -> Weight {
@@ -116,11 +156,118 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 			.saturating_add(T::DbWeight::get().writes(0))
 	}
 
+	fn get_patient_complete_history() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(6))
+			.saturating_add(T::DbWeight::get().writes(0))
+	}
+
 	fn record_change() -> Weight {
 		Weight::from_parts(8_000, 0)
 			.saturating_add(T::DbWeight::get().reads(1))
 			.saturating_add(T::DbWeight::get().writes(3)) // change + record mapping + counter
 	}
+
+	fn grant_role() -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn revoke_role() -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn link_patient_account() -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn set_guardian() -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn grant_consent() -> Weight {
+		Weight::from_parts(12_000, 0)
+			.saturating_add(T::DbWeight::get().reads(7))
+			.saturating_add(T::DbWeight::get().writes(6)) // consent + doctor/patient links + change record
+	}
+
+	fn revoke_consent() -> Weight {
+		Weight::from_parts(12_000, 0)
+			.saturating_add(T::DbWeight::get().reads(6))
+			.saturating_add(T::DbWeight::get().writes(6)) // consent + doctor/patient links + change record
+	}
+
+	fn register_encryption_key() -> Weight {
+		Weight::from_parts(8_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn set_data_keys(n: u32) -> Weight {
+		Weight::from_parts(12_000, 0)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(5)) // patient, roles, patient account, guardian, keyring
+			.saturating_add(T::DbWeight::get().reads(n.into())) // encryption key of each holder
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn remove_data_key() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(5)) // patient, roles, patient account, guardian, keyring
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn store_document() -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(T::DbWeight::get().reads(8)) // roles, patient, consents, document
+			.saturating_add(T::DbWeight::get().writes(2)) // document + patient documents
+	}
+
+	fn add_attachment() -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(T::DbWeight::get().reads(8)) // roles, record, consent, document, change counter, audit head, proofs
+			.saturating_add(T::DbWeight::get().writes(10)) // record + change record, counter, audit head + proofs and anchor
+	}
+
+	fn remove_attachment() -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(T::DbWeight::get().reads(7)) // roles, record, consent, change counter, audit head, proofs
+			.saturating_add(T::DbWeight::get().writes(10)) // record + change record, counter, audit head + proofs and anchor
+	}
+
+	fn request_erasure() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(3)) // patient account, guardian, request
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn approve_erasure() -> Weight {
+		Weight::from_parts(15_000, 0)
+			.saturating_add(T::DbWeight::get().reads(7)) // request, queue, data keys, patient, name index, demographic indexes
+			.saturating_add(T::DbWeight::get().writes(9)) // request, queue, data keys, patient, name index both ways, active, demographic indexes
+	}
+
+	fn reject_erasure() -> Weight {
+		Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	fn on_initialize(s: u32) -> Weight {
+		Weight::from_parts(5_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2)) // erasure and deletion queues
+			.saturating_add(Weight::from_parts(35_000, 0).saturating_mul(s.into()))
+			// per step at worst the deletion of a progression with a paid follow-up: queues, progression + list,
+			// appointment, doctor bookings, queued tasks, agenda, escrow, holds and account, change record, audit head, proofs
+			.saturating_add(T::DbWeight::get().reads(17).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().writes(20).saturating_mul(s.into()))
+	}
 }
 
 // For backwards compatibility and tests
@@ -134,7 +281,33 @@ impl WeightInfo for () {
 	fn create_disease_progression() -> Weight { Weight::from_parts(10_000, 0) }
 	fn update_disease_progression() -> Weight { Weight::from_parts(10_000, 0) }
 	fn delete_disease_progression() -> Weight { Weight::from_parts(10_000, 0) }
+	fn set_next_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn clear_appointment() -> Weight { Weight::from_parts(10_000, 0) }
+	fn reindex_patient_names(n: u32) -> Weight {
+		Weight::from_parts(8_000, 0).saturating_add(Weight::from_parts(4_000, 0).saturating_mul(n.into()))
+	}
 	fn create_medical_record() -> Weight { Weight::from_parts(10_000, 0) }
 	fn search_patient_by_name() -> Weight { Weight::from_parts(10_000, 0) }
+	fn get_patient_complete_history() -> Weight { Weight::from_parts(10_000, 0) }
 	fn record_change() -> Weight { Weight::from_parts(8_000, 0) }
+	fn grant_role() -> Weight { Weight::from_parts(8_000, 0) }
+	fn revoke_role() -> Weight { Weight::from_parts(8_000, 0) }
+	fn link_patient_account() -> Weight { Weight::from_parts(8_000, 0) }
+	fn set_guardian() -> Weight { Weight::from_parts(8_000, 0) }
+	fn grant_consent() -> Weight { Weight::from_parts(12_000, 0) }
+	fn revoke_consent() -> Weight { Weight::from_parts(12_000, 0) }
+	fn register_encryption_key() -> Weight { Weight::from_parts(8_000, 0) }
+	fn set_data_keys(n: u32) -> Weight {
+		Weight::from_parts(12_000, 0).saturating_add(Weight::from_parts(3_000, 0).saturating_mul(n.into()))
+	}
+	fn remove_data_key() -> Weight { Weight::from_parts(10_000, 0) }
+	fn store_document() -> Weight { Weight::from_parts(15_000, 0) }
+	fn add_attachment() -> Weight { Weight::from_parts(15_000, 0) }
+	fn remove_attachment() -> Weight { Weight::from_parts(15_000, 0) }
+	fn request_erasure() -> Weight { Weight::from_parts(10_000, 0) }
+	fn approve_erasure() -> Weight { Weight::from_parts(15_000, 0) }
+	fn reject_erasure() -> Weight { Weight::from_parts(10_000, 0) }
+	fn on_initialize(s: u32) -> Weight {
+		Weight::from_parts(5_000, 0).saturating_add(Weight::from_parts(35_000, 0).saturating_mul(s.into()))
+	}
 }
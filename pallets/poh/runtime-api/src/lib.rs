@@ -0,0 +1,27 @@
+//! Runtime API definition for the proof of history pallet.
+//!
+//! Lets clients fetch Merkle inclusion proofs for data hashes anchored with `submit_batch`, walk
+//! the proof chain by index and verify segments of it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+
+pub use pallet_poh::{ChainError, InclusionProof};
+
+sp_api::decl_runtime_apis! {
+	pub trait PohApi<Hash>
+	where
+		Hash: Codec,
+	{
+		/// Inclusion proof for `leaf` in the batch anchored under `root`, if it is part of it.
+		fn inclusion_proof(root: Hash, leaf: Hash) -> Option<InclusionProof<Hash>>;
+
+		/// The proof hash at `index` in creation order.
+		fn proof_hash_at(index: u64) -> Option<Hash>;
+
+		/// Verify the chain from `from` back to `to`, or to the first proof if `to` is `None`,
+		/// and return the number of proofs checked.
+		fn verify_range(from: Hash, to: Option<Hash>) -> Result<u32, ChainError<Hash>>;
+	}
+}
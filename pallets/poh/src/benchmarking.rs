@@ -1,15 +1,61 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-poh
 
 use super::*;
 
 #[allow(unused)]
-use crate::PoH as PoH;
+use crate::Pallet as PoH;
 use frame_benchmarking::v2::*;
+use frame_support::{sp_runtime::traits::Hash, traits::Get};
 use frame_system::RawOrigin;
+use scale_info::prelude::{vec, vec::Vec};
 
 #[benchmarks]
 mod benchmarks {
 	use super::*;
 
+	#[benchmark]
+	fn submit_data() {
+		let caller: T::AccountId = whitelisted_caller();
+		// Chain onto an existing proof so the previous hash is read from storage
+		PoH::<T>::do_anchor(caller.clone(), T::Hasher::hash(b"previous")).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), vec![0u8; 1024]);
+
+		assert_eq!(PoH::<T>::proof_count(), 2);
+	}
+
+	#[benchmark]
+	fn verify_proof() {
+		let caller: T::AccountId = whitelisted_caller();
+		PoH::<T>::do_anchor(caller.clone(), T::Hasher::hash(b"previous")).unwrap();
+		let proof_hash = PoH::<T>::do_anchor(caller.clone(), T::Hasher::hash(b"data")).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), proof_hash);
+	}
+
+	#[benchmark]
+	fn get_proof_by_data() {
+		let caller: T::AccountId = whitelisted_caller();
+		let data_hash = T::Hasher::hash(b"data");
+		PoH::<T>::do_anchor(caller.clone(), data_hash).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), data_hash);
+	}
+
+	#[benchmark]
+	fn submit_batch(n: Linear<1, { T::MaxBatchSize::get() }>) {
+		let caller: T::AccountId = whitelisted_caller();
+		PoH::<T>::do_anchor(caller.clone(), T::Hasher::hash(b"previous")).unwrap();
+		let data_hashes: Vec<_> = (0..n).map(|i| T::Hasher::hash(&i.to_le_bytes())).collect();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), data_hashes);
+
+		assert_eq!(PoH::<T>::proof_count(), 2);
+	}
+
 	impl_benchmark_test_suite!(PoH, crate::mock::new_test_ext(), crate::mock::Test);
 }
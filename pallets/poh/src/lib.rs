@@ -4,6 +4,49 @@ pub use pallet::*;
 
 pub use frame_support::traits::Time;
 
+use codec::Encode;
+use frame_support::pallet_prelude::DispatchError;
+
+/// The hash a proof record commits to: its data hash, previous proof hash, block number and
+/// timestamp, SCALE encoded and concatenated in that order. Shared with offline verifiers so
+/// they recompute proof hashes exactly as the pallet does.
+pub fn proof_hash_of<H, BlockNumber, Moment, AccountId>(
+	proof: &ProofRecord<H::Output, BlockNumber, Moment, AccountId>,
+) -> H::Output
+where
+	H: frame_support::sp_runtime::traits::Hash,
+	BlockNumber: Encode,
+	Moment: Encode,
+{
+	let mut combined = proof.data_hash.encode();
+	combined.extend_from_slice(&proof.previous_hash.encode());
+	combined.extend_from_slice(&proof.block_number.encode());
+	combined.extend_from_slice(&proof.timestamp.encode());
+	<H as frame_support::sp_runtime::traits::Hash>::hash(&combined)
+}
+
+/// Check an inclusion proof against the Merkle root it claims, using the same hashing as the
+/// pallet. This does not look at chain state: callers still need to check that `proof.root` is
+/// the data hash of the `ProofRecord` stored under `proof.proof_hash`.
+pub fn verify_inclusion<H: frame_support::sp_runtime::traits::Hash>(
+	proof: &InclusionProof<H::Output>,
+) -> bool {
+	binary_merkle_tree::verify_proof::<H, _, _>(
+		&proof.root,
+		proof.proof.iter().copied(),
+		proof.number_of_leaves,
+		proof.leaf_index,
+		&proof.leaf,
+	)
+}
+
+/// Lets other pallets add entries to the proof chain without depending on this pallet's
+/// `Config`.
+pub trait ProofAnchor<AccountId, Hash> {
+	/// Append a proof for `data_hash` on behalf of `who` and return the new proof hash.
+	fn anchor(who: &AccountId, data_hash: Hash) -> Result<Hash, DispatchError>;
+}
+
 #[cfg(test)]
 mod mock;
 
@@ -23,6 +66,7 @@ pub mod pallet {
 		sp_runtime::traits::{Hash, Member},
 	};
 	use frame_system::pallet_prelude::*;
+	use serde::{Deserialize, Serialize};
 	use scale_info::{
 		TypeInfo,
 		prelude::vec::Vec,
@@ -43,13 +87,23 @@ pub mod pallet {
 		type Time: Time;
 		
 		/// Hash type used for the proof
-		type Hash: Member + Parameter + MaybeSerializeDeserialize + Ord + Default + Copy + TypeInfo + MaxEncodedLen;
+		type Hash: Member + Parameter + MaybeSerializeDeserialize + Ord + Default + Copy + TypeInfo + MaxEncodedLen + AsRef<[u8]>;
 
 		/// A type representing the weights required by the dispatchables of this pallet.
 		type WeightInfo: WeightInfo;
+
+		/// Maximum number of data hashes in a single batch
+		#[pallet::constant]
+		type MaxBatchSize: Get<u32>;
+
+		/// Maximum number of chain links checked by a single range verification
+		#[pallet::constant]
+		type MaxRangeLength: Get<u32>;
 	}
 
-	#[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen, Debug)]
+	#[derive(
+		Encode, Decode, Clone, PartialEq, Eq, TypeInfo, MaxEncodedLen, Debug, Serialize, Deserialize,
+	)]
 	pub struct ProofRecord<Hash, BlockNumber, Moment, AccountId> {
 		/// The data hash included in this proof
 		pub data_hash: Hash,
@@ -65,6 +119,48 @@ pub mod pallet {
 		pub submitter: AccountId,
 	}
 
+	/// Everything needed to check that a leaf is part of an anchored batch
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, Debug)]
+	pub struct InclusionProof<Hash> {
+		/// Proof hash of the chain link that anchored the batch root
+		pub proof_hash: Hash,
+		/// Merkle root of the batch
+		pub root: Hash,
+		/// Sibling hashes from the leaf up to the root
+		pub proof: Vec<Hash>,
+		/// Number of leaves in the batch
+		pub number_of_leaves: u32,
+		/// Position of the leaf in the batch
+		pub leaf_index: u32,
+		/// The data hash being proven
+		pub leaf: Hash,
+	}
+
+	/// Why a chain segment failed to verify
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, TypeInfo, Debug)]
+	pub enum ChainError<Hash> {
+		/// No proof is stored under this hash
+		Missing(Hash),
+		/// The stored proof does not rehash to its own proof hash
+		HashMismatch(Hash),
+		/// The proof is not at the position in `ProofByIndex` that follows its predecessor
+		Gap(Hash),
+		/// The proof was created in an earlier block than its predecessor
+		OutOfOrder(Hash),
+		/// The walk reached the first proof without meeting the requested end
+		EndNotFound,
+		/// The segment is longer than `MaxRangeLength`
+		RangeTooLong,
+	}
+
+	/// The proof record type stored by this pallet
+	pub type ProofRecordOf<T> = ProofRecord<
+		<T as pallet::Config>::Hash,
+		BlockNumberFor<T>,
+		<<T as Config>::Time as Time>::Moment,
+		<T as frame_system::Config>::AccountId,
+	>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn proof_count)]
 	/// The total number of proofs created
@@ -77,7 +173,7 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		<T as pallet::Config>::Hash, // Proof hash as key
-		ProofRecord<<T as pallet::Config>::Hash, BlockNumberFor<T>, <<T as Config>::Time as Time>::Moment, T::AccountId>,
+		ProofRecordOf<T>,
 		OptionQuery,
 	>;
 
@@ -97,6 +193,64 @@ pub mod pallet {
 		OptionQuery,
 	>;
 
+    #[pallet::storage]
+	#[pallet::getter(fn anchored_proof)]
+	/// Maps data hashes anchored by other pallets through [`ProofAnchor`] to their proof hashes.
+	/// Kept apart from `DataToProof`, which anyone can write to with `submit_data`, so a public
+	/// submission of the same hash cannot make an anchor fail.
+	pub type AnchoredData<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		<T as pallet::Config>::Hash, // Data hash
+		<T as pallet::Config>::Hash, // Proof hash
+		OptionQuery,
+	>;
+
+    #[pallet::storage]
+	#[pallet::getter(fn proof_by_index)]
+	/// Proof hashes in creation order, indexed from 0 up to `ProofCount`
+	pub type ProofByIndex<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		u64, // Position in the chain
+		<T as pallet::Config>::Hash, // Proof hash
+		OptionQuery,
+	>;
+
+    #[pallet::storage]
+	#[pallet::getter(fn proof_index)]
+	/// Position of each proof in `ProofByIndex`
+	pub type ProofIndex<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		<T as pallet::Config>::Hash, // Proof hash
+		u64, // Position in the chain
+		OptionQuery,
+	>;
+
+    #[pallet::storage]
+	#[pallet::getter(fn batch_proof)]
+	/// Maps the Merkle roots of anchored batches to their proof hashes. Kept apart from
+	/// `DataToProof`, so a public submission of data hashing to a root cannot block its batch.
+	pub type BatchRoots<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		<T as pallet::Config>::Hash, // Merkle root
+		<T as pallet::Config>::Hash, // Proof hash
+		OptionQuery,
+	>;
+
+    #[pallet::storage]
+	#[pallet::getter(fn batch_leaves)]
+	/// The data hashes of each anchored batch, in submission order, keyed by Merkle root
+	pub type BatchLeaves<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		<T as pallet::Config>::Hash, // Merkle root
+		BoundedVec<<T as pallet::Config>::Hash, T::MaxBatchSize>,
+		OptionQuery,
+	>;
+
 	/// Pallets use events to inform users when important changes are made.
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
@@ -112,6 +266,9 @@ pub mod pallet {
 		/// A proof has been verified successfully.
 		/// [proof_hash, verifier]
 		ProofVerified(<T as pallet::Config>::Hash, T::AccountId),
+		/// A batch of data hashes has been anchored under a single proof.
+		/// [proof_hash, merkle_root, leaf_count]
+		BatchAnchored(<T as pallet::Config>::Hash, <T as pallet::Config>::Hash, u32),
     }
 
 	/// Errors inform users that something went wrong.
@@ -125,6 +282,10 @@ pub mod pallet {
 		InvalidProofChain,
 		/// No previous proof exists to chain from
 		NoPreviousProof,
+		/// A batch must contain at least one data hash
+		EmptyBatch,
+		/// The batch holds more data hashes than `MaxBatchSize`
+		BatchTooLarge,
 	}
 
 	#[pallet::hooks]
@@ -144,48 +305,7 @@ pub mod pallet {
 			// Hash the input data
 			let data_hash = T::Hasher::hash(&data);
 			
-			// Check if this data has already been included in a proof
-			ensure!(!DataToProof::<T>::contains_key(data_hash), Error::<T>::ProofAlreadyExists);
-			
-			// Get the previous proof hash or create initial value if this is the first proof
-			let previous_hash = Self::latest_proof().unwrap_or_else(|| <T as pallet::Config>::Hash::default());
-			
-			// Get current block number and timestamp
-			let block_number = <frame_system::Pallet<T>>::block_number();
-			let now = T::Time::now();
-			
-			// Combine previous hash with data hash and other parameters to create a new proof
-			let mut combined = data_hash.encode();
-			combined.extend_from_slice(&previous_hash.encode());
-			combined.extend_from_slice(&block_number.encode());
-			combined.extend_from_slice(&now.encode());
-			
-			// Create the new proof hash
-			let proof_hash = T::Hasher::hash(&combined);
-			
-			// Create proof record
-			let proof_record = ProofRecord {
-				data_hash,
-				previous_hash,
-				proof_hash,
-				block_number,
-				timestamp: now,
-				submitter: submitter.clone(),
-			};
-			
-			// Increment proof count
-			let count = Self::proof_count();
-			ProofCount::<T>::put(count + 1);
-			
-			// Store proof and mappings
-			Proofs::<T>::insert(proof_hash, proof_record);
-			DataToProof::<T>::insert(data_hash, proof_hash);
-			LatestProof::<T>::put(proof_hash);
-			
-			// Emit event
-			Self::deposit_event(Event::ProofCreated(proof_hash, data_hash, block_number));
-			
-			Ok(())
+			Self::do_anchor(submitter, data_hash).map(|_| ())
 		}
 
         /// Verify a proof record
@@ -209,12 +329,7 @@ pub mod pallet {
 			}
 			
 			// Recreate the hash to validate
-			let mut combined = proof.data_hash.encode();
-			combined.extend_from_slice(&proof.previous_hash.encode());
-			combined.extend_from_slice(&proof.block_number.encode());
-			combined.extend_from_slice(&proof.timestamp.encode());
-			
-			let calculated_hash = T::Hasher::hash(&combined);
+			let calculated_hash = Self::compute_proof_hash(&proof);
 			
 			// Verify hash matches the stored proof hash
 			ensure!(
@@ -229,6 +344,9 @@ pub mod pallet {
 		}
 
         /// Get a data's proof history
+		///
+		/// Deprecated: use the `proof_by_data` view function instead. The runtime call filter
+		/// rejects this call; it is kept so existing call indices stay stable.
 		#[pallet::call_index(2)]
 		#[pallet::weight(T::WeightInfo::get_proof_by_data())]
 		pub fn get_proof_by_data(origin: OriginFor<T>, data_hash: <T as pallet::Config>::Hash) -> DispatchResult {
@@ -243,7 +361,227 @@ pub mod pallet {
 			// This function just verifies the data exists in the system
 			// The actual proof can be queried from storage
 			
+			Ok(())
+		}
+
+        /// Anchor many data hashes at once. The hashes become the leaves of a binary Merkle
+		/// tree and only its root gets a `ProofRecord`, so the whole batch costs one chain link.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::submit_batch(data_hashes.len() as u32))]
+		pub fn submit_batch(
+			origin: OriginFor<T>,
+			data_hashes: Vec<<T as pallet::Config>::Hash>,
+		) -> DispatchResult {
+			let submitter = ensure_signed(origin)?;
+
+			ensure!(!data_hashes.is_empty(), Error::<T>::EmptyBatch);
+			let leaves: BoundedVec<_, T::MaxBatchSize> =
+				data_hashes.try_into().map_err(|_| Error::<T>::BatchTooLarge)?;
+
+			let root = Self::batch_root(&leaves);
+			ensure!(!BatchRoots::<T>::contains_key(root), Error::<T>::ProofAlreadyExists);
+			let proof_hash = Self::append_proof(submitter, root);
+
+			let leaf_count = leaves.len() as u32;
+			BatchRoots::<T>::insert(root, proof_hash);
+			BatchLeaves::<T>::insert(root, leaves);
+
+			Self::deposit_event(Event::BatchAnchored(proof_hash, root, leaf_count));
+
 			Ok(())
 		}
     }
+
+	impl<T: Config> Pallet<T> {
+		/// Append a proof for an already hashed piece of data and return the new proof hash.
+		pub fn do_anchor(
+			submitter: T::AccountId,
+			data_hash: <T as pallet::Config>::Hash,
+		) -> Result<<T as pallet::Config>::Hash, DispatchError> {
+			// Check if this data has already been included in a proof
+			ensure!(!DataToProof::<T>::contains_key(data_hash), Error::<T>::ProofAlreadyExists);
+
+			let proof_hash = Self::append_proof(submitter, data_hash);
+			DataToProof::<T>::insert(data_hash, proof_hash);
+			Ok(proof_hash)
+		}
+
+		// Append a proof for `data_hash` to the chain, without recording it under its data hash
+		fn append_proof(
+			submitter: T::AccountId,
+			data_hash: <T as pallet::Config>::Hash,
+		) -> <T as pallet::Config>::Hash {
+			// Get the previous proof hash or create initial value if this is the first proof
+			let previous_hash = Self::latest_proof().unwrap_or_default();
+			
+			// Get current block number and timestamp
+			let block_number = <frame_system::Pallet<T>>::block_number();
+			let now = T::Time::now();
+			
+			// Create proof record, combining previous hash with data hash and other parameters
+			let mut proof_record = ProofRecord {
+				data_hash,
+				previous_hash,
+				proof_hash: Default::default(),
+				block_number,
+				timestamp: now,
+				submitter: submitter.clone(),
+			};
+			let proof_hash = Self::compute_proof_hash(&proof_record);
+			proof_record.proof_hash = proof_hash;
+			
+			// Increment proof count and record the position of the new proof
+			let count = Self::proof_count();
+			ProofCount::<T>::put(count + 1);
+			ProofByIndex::<T>::insert(count, proof_hash);
+			ProofIndex::<T>::insert(proof_hash, count);
+			
+			// Store proof and mappings
+			Proofs::<T>::insert(proof_hash, proof_record);
+			LatestProof::<T>::put(proof_hash);
+			
+			// Emit event
+			Self::deposit_event(Event::ProofCreated(proof_hash, data_hash, block_number));
+			
+			proof_hash
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The hash a proof record commits to, see [`proof_hash_of`]
+		pub fn compute_proof_hash(proof: &ProofRecordOf<T>) -> <T as pallet::Config>::Hash {
+			proof_hash_of::<T::Hasher, _, _, _>(proof)
+		}
+
+		/// Walk the chain back from `from` until `to`, or until the first proof if `to` is
+		/// `None`, and check every link on the way: each proof must rehash to its key, sit at
+		/// the `ProofByIndex` position right after its predecessor, and not be older than it.
+		/// Returns the number of proofs checked, `from` and `to` included.
+		pub fn do_verify_range(
+			from: <T as pallet::Config>::Hash,
+			to: Option<<T as pallet::Config>::Hash>,
+		) -> Result<u32, ChainError<<T as pallet::Config>::Hash>> {
+			let mut current = from;
+			let mut index = ProofIndex::<T>::get(from).ok_or(ChainError::Missing(from))?;
+			let mut newer_block: Option<BlockNumberFor<T>> = None;
+			let mut checked = 0u32;
+
+			loop {
+				if checked >= T::MaxRangeLength::get() {
+					return Err(ChainError::RangeTooLong);
+				}
+
+				let proof = Proofs::<T>::get(current).ok_or(ChainError::Missing(current))?;
+				if proof.proof_hash != current || Self::compute_proof_hash(&proof) != current {
+					return Err(ChainError::HashMismatch(current));
+				}
+				if ProofIndex::<T>::get(current) != Some(index) ||
+					ProofByIndex::<T>::get(index) != Some(current)
+				{
+					return Err(ChainError::Gap(current));
+				}
+				if newer_block.is_some_and(|newer| proof.block_number > newer) {
+					return Err(ChainError::OutOfOrder(current));
+				}
+				checked += 1;
+
+				if Some(current) == to {
+					return Ok(checked);
+				}
+				if proof.previous_hash == Default::default() {
+					// The first proof in the chain must also be the first by index
+					if index != 0 {
+						return Err(ChainError::Gap(current));
+					}
+					return match to {
+						None => Ok(checked),
+						Some(_) => Err(ChainError::EndNotFound),
+					};
+				}
+
+				index = index.checked_sub(1).ok_or(ChainError::Gap(current))?;
+				newer_block = Some(proof.block_number);
+				current = proof.previous_hash;
+			}
+		}
+
+		/// Merkle root of a batch of data hashes
+		pub fn batch_root(leaves: &[<T as pallet::Config>::Hash]) -> <T as pallet::Config>::Hash {
+			binary_merkle_tree::merkle_root::<T::Hasher, _>(leaves)
+		}
+
+		/// Inclusion proof for `leaf` in the batch anchored under `root`, or `None` if no such
+		/// batch exists or the leaf is not part of it.
+		pub fn inclusion_proof(
+			root: <T as pallet::Config>::Hash,
+			leaf: <T as pallet::Config>::Hash,
+		) -> Option<InclusionProof<<T as pallet::Config>::Hash>> {
+			let leaves = BatchLeaves::<T>::get(root)?;
+			let proof_hash = BatchRoots::<T>::get(root)?;
+			let leaf_index = leaves.iter().position(|l| *l == leaf)? as u32;
+
+			let merkle = binary_merkle_tree::merkle_proof::<T::Hasher, _, _>(leaves.iter(), leaf_index);
+			Some(InclusionProof {
+				proof_hash,
+				root: merkle.root,
+				proof: merkle.proof,
+				number_of_leaves: merkle.number_of_leaves,
+				leaf_index,
+				leaf,
+			})
+		}
+	}
+
+	impl<T: Config> ProofAnchor<T::AccountId, <T as pallet::Config>::Hash> for Pallet<T> {
+		fn anchor(
+			who: &T::AccountId,
+			data_hash: <T as pallet::Config>::Hash,
+		) -> Result<<T as pallet::Config>::Hash, DispatchError> {
+			ensure!(!AnchoredData::<T>::contains_key(data_hash), Error::<T>::ProofAlreadyExists);
+
+			let proof_hash = Self::append_proof(who.clone(), data_hash);
+			AnchoredData::<T>::insert(data_hash, proof_hash);
+			Ok(proof_hash)
+		}
+	}
+
+	#[pallet::view_functions_experimental]
+	impl<T: Config> Pallet<T> {
+		/// The proof covering `data_hash`, or `None` if the data was never submitted.
+		pub fn proof_by_data(data_hash: <T as pallet::Config>::Hash) -> Option<ProofRecordOf<T>> {
+			DataToProof::<T>::get(data_hash).and_then(Proofs::<T>::get)
+		}
+
+		/// The proof anchoring the batch with Merkle root `root`, or `None` if no such batch
+		/// was submitted.
+		pub fn proof_by_batch(root: <T as pallet::Config>::Hash) -> Option<ProofRecordOf<T>> {
+			BatchRoots::<T>::get(root).and_then(Proofs::<T>::get)
+		}
+
+		/// The proof covering `data_hash` anchored by another pallet through [`ProofAnchor`], or
+		/// `None` if the data was never anchored.
+		pub fn proof_by_anchor(data_hash: <T as pallet::Config>::Hash) -> Option<ProofRecordOf<T>> {
+			AnchoredData::<T>::get(data_hash).and_then(Proofs::<T>::get)
+		}
+
+		/// The proof at `index` in creation order, or `None` past the end of the chain.
+		pub fn proof_at(index: u64) -> Option<ProofRecordOf<T>> {
+			ProofByIndex::<T>::get(index).and_then(Proofs::<T>::get)
+		}
+
+		/// The proof created right after `proof_hash`, if any.
+		pub fn next_proof(proof_hash: <T as pallet::Config>::Hash) -> Option<<T as pallet::Config>::Hash> {
+			let index = ProofIndex::<T>::get(proof_hash)?;
+			ProofByIndex::<T>::get(index.checked_add(1)?)
+		}
+
+		/// Verify the chain segment from `from` back to `to`, or back to the first proof if
+		/// `to` is `None`. Returns the number of proofs checked.
+		pub fn verify_range(
+			from: <T as pallet::Config>::Hash,
+			to: Option<<T as pallet::Config>::Hash>,
+		) -> Result<u32, ChainError<<T as pallet::Config>::Hash>> {
+			Self::do_verify_range(from, to)
+		}
+	}
 }
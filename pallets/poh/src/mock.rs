@@ -1,9 +1,8 @@
 use crate as pallet_poh;
 
 use frame_support::{
-	pallet_prelude::*,
 	derive_impl,
-	traits::Time,
+	traits::{ConstU32, Time},
 };
 
 use sp_runtime::{
@@ -11,7 +10,7 @@ use sp_runtime::{
 	traits::BlakeTwo256,
 };
 
-use sp_core::{Hasher, H256};
+use sp_core::H256;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -73,6 +72,9 @@ impl pallet_poh::Config for Test {
 	
 	/// Hash type used for the proof
 	type Hash = H256;
+
+	type MaxBatchSize = ConstU32<16>;
+	type MaxRangeLength = ConstU32<8>;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -1,2 +1,250 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
\ No newline at end of file
+use crate::{mock::*, verify_inclusion, ChainError, Error, Event, ProofAnchor, ProofByIndex, Proofs};
+use frame_support::{assert_noop, assert_ok};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+#[test]
+fn proof_by_data_returns_the_stored_proof() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let data = b"lab result".to_vec();
+		let data_hash = <Test as crate::Config>::Hasher::hash(&data);
+
+		assert_eq!(PoH::proof_by_data(data_hash), None);
+
+		assert_ok!(PoH::submit_data(RuntimeOrigin::signed(1), data.clone()));
+
+		let proof = PoH::proof_by_data(data_hash).expect("proof was stored");
+		assert_eq!(proof.data_hash, data_hash);
+		assert_eq!(proof.submitter, 1);
+		assert_eq!(PoH::latest_proof(), Some(proof.proof_hash));
+		System::assert_last_event(RuntimeEvent::PoH(Event::ProofCreated(
+			proof.proof_hash,
+			data_hash,
+			1,
+		)));
+
+		assert_noop!(
+			PoH::submit_data(RuntimeOrigin::signed(1), data),
+			Error::<Test>::ProofAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn anchors_are_kept_apart_from_public_submissions() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let data = b"record version".to_vec();
+		let data_hash = <Test as crate::Config>::Hasher::hash(&data);
+
+		// Submitting the data publicly first does not block the anchor
+		assert_ok!(PoH::submit_data(RuntimeOrigin::signed(2), data.clone()));
+		let proof_hash = <PoH as ProofAnchor<_, _>>::anchor(&1, data_hash).unwrap();
+
+		let proof = PoH::proof_by_anchor(data_hash).expect("anchor was stored");
+		assert_eq!(proof.proof_hash, proof_hash);
+		assert_eq!(proof.submitter, 1);
+		assert_eq!(PoH::proof_by_data(data_hash).unwrap().submitter, 2);
+
+		// An anchor cannot be repeated
+		assert_noop!(
+			<PoH as ProofAnchor<_, _>>::anchor(&1, data_hash),
+			Error::<Test>::ProofAlreadyExists
+		);
+
+		// Nor does an anchor block a later public submission
+		let other = BlakeTwo256::hash(b"other version");
+		assert_ok!(<PoH as ProofAnchor<_, _>>::anchor(&1, other));
+		assert_ok!(PoH::submit_data(RuntimeOrigin::signed(2), b"other version".to_vec()));
+	});
+}
+
+fn batch(count: u8) -> Vec<H256> {
+	(0..count).map(|i| BlakeTwo256::hash(&[i])).collect()
+}
+
+#[test]
+fn submit_batch_anchors_the_merkle_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let leaves = batch(5);
+		let root = PoH::batch_root(&leaves);
+
+		assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), leaves.clone()));
+
+		// The whole batch costs a single chain link
+		assert_eq!(PoH::proof_count(), 1);
+		let proof = PoH::proof_by_batch(root).expect("root was anchored");
+		assert_eq!(proof.data_hash, root);
+		assert_eq!(PoH::batch_leaves(root).unwrap().into_inner(), leaves);
+		System::assert_last_event(RuntimeEvent::PoH(Event::BatchAnchored(proof.proof_hash, root, 5)));
+
+		assert_noop!(
+			PoH::submit_batch(RuntimeOrigin::signed(1), leaves),
+			Error::<Test>::ProofAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn batches_are_kept_apart_from_public_submissions() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let leaves = batch(2);
+		let root = PoH::batch_root(&leaves);
+
+		// The root of two leaves is the hash of their hashes, which anyone can submit as data
+		let data: Vec<u8> = leaves
+			.iter()
+			.flat_map(|leaf| BlakeTwo256::hash(leaf.as_bytes()).as_bytes().to_vec())
+			.collect();
+		assert_eq!(BlakeTwo256::hash(&data), root);
+		assert_ok!(PoH::submit_data(RuntimeOrigin::signed(2), data));
+
+		// The front-running submission does not block the batch
+		assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), leaves.clone()));
+		let proof = PoH::proof_by_batch(root).expect("root was anchored");
+		assert_eq!(proof.submitter, 1);
+		assert_eq!(PoH::proof_by_data(root).unwrap().submitter, 2);
+		assert_eq!(PoH::inclusion_proof(root, leaves[0]).unwrap().proof_hash, proof.proof_hash);
+	});
+}
+
+#[test]
+fn submit_batch_checks_its_size() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(PoH::submit_batch(RuntimeOrigin::signed(1), vec![]), Error::<Test>::EmptyBatch);
+		// MaxBatchSize is 16 in the mock
+		assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), batch(16)));
+		assert_noop!(PoH::submit_batch(RuntimeOrigin::signed(1), batch(17)), Error::<Test>::BatchTooLarge);
+	});
+}
+
+#[test]
+fn inclusion_proofs_verify_for_every_leaf() {
+	new_test_ext().execute_with(|| {
+		// An odd number of leaves exercises the promoted last node
+		for size in [1, 2, 7] {
+			let leaves = batch(size);
+			let root = PoH::batch_root(&leaves);
+			assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), leaves.clone()));
+
+			for (index, leaf) in leaves.iter().enumerate() {
+				let proof = PoH::inclusion_proof(root, *leaf).expect("leaf is in the batch");
+				assert_eq!(proof.leaf_index, index as u32);
+				assert_eq!(proof.number_of_leaves, size as u32);
+				assert_eq!(proof.proof_hash, PoH::batch_proof(root).unwrap());
+				assert!(verify_inclusion::<BlakeTwo256>(&proof));
+
+				// Tampering with the leaf or the path breaks the proof
+				let mut forged = proof.clone();
+				forged.leaf = BlakeTwo256::hash(b"forged");
+				assert!(!verify_inclusion::<BlakeTwo256>(&forged));
+				if let Some(sibling) = forged.proof.first_mut() {
+					*sibling = BlakeTwo256::hash(b"forged");
+					forged.leaf = *leaf;
+					assert!(!verify_inclusion::<BlakeTwo256>(&forged));
+				}
+			}
+		}
+	});
+}
+
+#[test]
+fn inclusion_proof_is_none_for_unknown_leaves_and_batches() {
+	new_test_ext().execute_with(|| {
+		let leaves = batch(3);
+		let root = PoH::batch_root(&leaves);
+		assert_eq!(PoH::inclusion_proof(root, leaves[0]), None);
+
+		assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), leaves));
+		assert_eq!(PoH::inclusion_proof(root, BlakeTwo256::hash(b"missing")), None);
+	});
+}
+
+fn submit(count: u8) -> Vec<H256> {
+	(0..count)
+		.map(|i| {
+			System::set_block_number(1 + i as u64);
+			assert_ok!(PoH::submit_data(RuntimeOrigin::signed(1), vec![i]));
+			PoH::latest_proof().unwrap()
+		})
+		.collect()
+}
+
+#[test]
+fn proofs_are_indexed_in_creation_order() {
+	new_test_ext().execute_with(|| {
+		let proofs = submit(3);
+		assert_ok!(PoH::submit_batch(RuntimeOrigin::signed(1), batch(4)));
+		let batch_proof = PoH::latest_proof().unwrap();
+
+		assert_eq!(PoH::proof_count(), 4);
+		for (index, proof_hash) in proofs.iter().chain([&batch_proof]).enumerate() {
+			assert_eq!(PoH::proof_by_index(index as u64), Some(*proof_hash));
+			assert_eq!(PoH::proof_index(proof_hash), Some(index as u64));
+			assert_eq!(PoH::proof_at(index as u64).unwrap().proof_hash, *proof_hash);
+		}
+		assert_eq!(PoH::proof_at(4), None);
+
+		// Walk forward with next pointers and back with previous hashes
+		assert_eq!(PoH::next_proof(proofs[0]), Some(proofs[1]));
+		assert_eq!(PoH::next_proof(proofs[2]), Some(batch_proof));
+		assert_eq!(PoH::next_proof(batch_proof), None);
+		assert_eq!(PoH::proofs(batch_proof).unwrap().previous_hash, proofs[2]);
+	});
+}
+
+#[test]
+fn verify_range_walks_the_chain() {
+	new_test_ext().execute_with(|| {
+		let proofs = submit(5);
+
+		assert_eq!(PoH::verify_range(proofs[4], None), Ok(5));
+		assert_eq!(PoH::verify_range(proofs[4], Some(proofs[1])), Ok(4));
+		assert_eq!(PoH::verify_range(proofs[2], Some(proofs[2])), Ok(1));
+		assert_eq!(PoH::verify_range(proofs[0], None), Ok(1));
+
+		// `to` must be behind `from`
+		assert_eq!(PoH::verify_range(proofs[1], Some(proofs[3])), Err(ChainError::EndNotFound));
+		let unknown = BlakeTwo256::hash(b"unknown");
+		assert_eq!(PoH::verify_range(unknown, None), Err(ChainError::Missing(unknown)));
+	});
+}
+
+#[test]
+fn verify_range_is_bounded() {
+	new_test_ext().execute_with(|| {
+		// MaxRangeLength is 8 in the mock
+		let proofs = submit(9);
+		assert_eq!(PoH::verify_range(proofs[8], Some(proofs[1])), Ok(8));
+		assert_eq!(PoH::verify_range(proofs[8], None), Err(ChainError::RangeTooLong));
+	});
+}
+
+#[test]
+fn verify_range_detects_tampering() {
+	new_test_ext().execute_with(|| {
+		let proofs = submit(4);
+
+		// A rewritten record no longer hashes to its key
+		Proofs::<Test>::mutate(proofs[1], |proof| proof.as_mut().unwrap().data_hash = H256::repeat_byte(1));
+		assert_eq!(PoH::verify_range(proofs[3], None), Err(ChainError::HashMismatch(proofs[1])));
+	});
+
+	new_test_ext().execute_with(|| {
+		let proofs = submit(4);
+
+		// A removed link shows up as a missing predecessor
+		Proofs::<Test>::remove(proofs[1]);
+		assert_eq!(PoH::verify_range(proofs[3], None), Err(ChainError::Missing(proofs[1])));
+	});
+
+	new_test_ext().execute_with(|| {
+		let proofs = submit(4);
+
+		// A hole in the index is a gap
+		ProofByIndex::<Test>::remove(2);
+		assert_eq!(PoH::verify_range(proofs[3], None), Err(ChainError::Gap(proofs[2])));
+	});
+}
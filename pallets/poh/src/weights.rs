@@ -37,13 +37,16 @@ pub trait WeightInfo {
 	fn submit_data() -> Weight;
     fn verify_proof() -> Weight;
     fn get_proof_by_data() -> Weight;
+    fn submit_batch(n: u32) -> Weight;
 }
 
 /// Weights for pallet_template using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	fn submit_data() -> Weight {
-        Weight::from_parts(10_000, 0).saturating_add(T::DbWeight::get().writes(1_u64))
+        Weight::from_parts(10_000, 0)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
     }
 
     fn verify_proof() -> Weight {
@@ -55,12 +58,21 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
         Weight::from_parts(5_000, 0)
 			.saturating_add(T::DbWeight::get().writes(1_u64))
     }
+
+    fn submit_batch(n: u32) -> Weight {
+        Weight::from_parts(10_000, 0)
+			.saturating_add(Weight::from_parts(2_000, 32).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+    }
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
 	fn submit_data() -> Weight {
-        Weight::from_parts(10_000, 0).saturating_add(RocksDbWeight::get().writes(1_u64))
+        Weight::from_parts(10_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
     }
 
     fn verify_proof() -> Weight {
@@ -72,4 +84,11 @@ impl WeightInfo for () {
         Weight::from_parts(5_000, 0)
 			.saturating_add(RocksDbWeight::get().writes(1_u64))
     }
+
+    fn submit_batch(n: u32) -> Weight {
+        Weight::from_parts(10_000, 0)
+			.saturating_add(Weight::from_parts(2_000, 32).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+    }
 }
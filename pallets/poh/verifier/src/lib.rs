@@ -0,0 +1,205 @@
+//! Offline verification of proof chains exported from `pallet-poh`.
+//!
+//! A bundle is a list of `ProofRecord`s, oldest first, encoded either as JSON or as a SCALE
+//! encoded `Vec<ProofRecord>`. [`verify_chain`] recomputes every proof hash with the same
+//! function the pallet uses and checks that each record points at the one before it, so a
+//! bundle can be checked without access to a node.
+
+use codec::DecodeAll;
+use core::fmt;
+use sp_core::{crypto::AccountId32, H256};
+use sp_runtime::traits::BlakeTwo256;
+
+/// A proof record as stored by the Healer Network runtime.
+pub type Proof = pallet_poh::ProofRecord<H256, u32, u64, AccountId32>;
+
+/// How an exported bundle is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	/// A JSON array of proof records.
+	Json,
+	/// A SCALE encoded `Vec` of proof records.
+	Scale,
+}
+
+/// The bundle could not be read.
+#[derive(Debug)]
+pub enum DecodeError {
+	/// The JSON is malformed or does not describe proof records.
+	Json(serde_json::Error),
+	/// The bytes are not a SCALE encoded list of proof records.
+	Scale(codec::Error),
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DecodeError::Json(e) => write!(f, "invalid JSON bundle: {}", e),
+			DecodeError::Scale(e) => write!(f, "invalid SCALE bundle: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode an exported bundle.
+pub fn decode_bundle(bytes: &[u8], format: Format) -> Result<Vec<Proof>, DecodeError> {
+	match format {
+		Format::Json => serde_json::from_slice(bytes).map_err(DecodeError::Json),
+		Format::Scale => Vec::<Proof>::decode_all(&mut &bytes[..]).map_err(DecodeError::Scale),
+	}
+}
+
+/// Recompute the proof hash of a record exactly as `pallet-poh` does.
+pub fn proof_hash(proof: &Proof) -> H256 {
+	pallet_poh::proof_hash_of::<BlakeTwo256, _, _, _>(proof)
+}
+
+/// What is wrong with a link in the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+	/// The record does not rehash to the proof hash it claims.
+	HashMismatch {
+		/// The hash recomputed from the record.
+		computed: H256,
+	},
+	/// The record's previous hash is not the proof hash of the record before it.
+	PreviousHashMismatch {
+		/// The proof hash of the record before it.
+		expected: H256,
+	},
+	/// The record was created in an earlier block than the record before it.
+	OutOfOrder {
+		/// The block number of the record before it.
+		previous_block: u32,
+	},
+}
+
+/// The first link that failed to verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+	/// Position of the record in the bundle.
+	pub index: usize,
+	/// The proof hash the record claims.
+	pub proof_hash: H256,
+	/// What is wrong with it.
+	pub fault: Fault,
+}
+
+impl fmt::Display for BrokenLink {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "record {} ({:?}): ", self.index, self.proof_hash)?;
+		match &self.fault {
+			Fault::HashMismatch { computed } =>
+				write!(f, "proof hash does not match its contents, recomputed {:?}", computed),
+			Fault::PreviousHashMismatch { expected } =>
+				write!(f, "previous hash does not point at the record before it ({:?})", expected),
+			Fault::OutOfOrder { previous_block } =>
+				write!(f, "created before the record before it (block {})", previous_block),
+		}
+	}
+}
+
+impl std::error::Error for BrokenLink {}
+
+/// Outcome of a successful verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+	/// Number of records checked.
+	pub proofs: usize,
+	/// Whether the bundle starts at the first proof of the chain rather than mid-chain.
+	pub starts_at_genesis: bool,
+	/// Proof hash of the newest record, if the bundle is not empty.
+	pub head: Option<H256>,
+}
+
+/// Verify a bundle of proof records, oldest first, and report the first broken link.
+pub fn verify_chain(proofs: &[Proof]) -> Result<Summary, BrokenLink> {
+	let mut previous: Option<&Proof> = None;
+
+	for (index, proof) in proofs.iter().enumerate() {
+		let broken = |fault| BrokenLink { index, proof_hash: proof.proof_hash, fault };
+
+		let computed = proof_hash(proof);
+		if computed != proof.proof_hash {
+			return Err(broken(Fault::HashMismatch { computed }));
+		}
+		if let Some(previous) = previous {
+			if proof.previous_hash != previous.proof_hash {
+				return Err(broken(Fault::PreviousHashMismatch { expected: previous.proof_hash }));
+			}
+			if proof.block_number < previous.block_number {
+				return Err(broken(Fault::OutOfOrder { previous_block: previous.block_number }));
+			}
+		}
+		previous = Some(proof);
+	}
+
+	Ok(Summary {
+		proofs: proofs.len(),
+		starts_at_genesis: proofs.first().is_some_and(|first| first.previous_hash == H256::zero()),
+		head: proofs.last().map(|last| last.proof_hash),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+
+	fn chain(len: u8) -> Vec<Proof> {
+		let mut proofs: Vec<Proof> = Vec::new();
+		for i in 0..len {
+			let mut proof = Proof {
+				data_hash: H256::repeat_byte(i),
+				previous_hash: proofs.last().map(|p| p.proof_hash).unwrap_or_default(),
+				proof_hash: H256::zero(),
+				block_number: i as u32,
+				timestamp: 6_000 * i as u64,
+				submitter: AccountId32::new([1; 32]),
+			};
+			proof.proof_hash = proof_hash(&proof);
+			proofs.push(proof);
+		}
+		proofs
+	}
+
+	#[test]
+	fn valid_chain_verifies() {
+		let proofs = chain(4);
+		assert_eq!(
+			verify_chain(&proofs),
+			Ok(Summary { proofs: 4, starts_at_genesis: true, head: Some(proofs[3].proof_hash) })
+		);
+		// A segment from the middle of the chain is fine too
+		assert!(!verify_chain(&proofs[1..]).unwrap().starts_at_genesis);
+	}
+
+	#[test]
+	fn reports_the_first_broken_link() {
+		let mut proofs = chain(4);
+		proofs[2].data_hash = H256::repeat_byte(9);
+		let err = verify_chain(&proofs).unwrap_err();
+		assert_eq!(err.index, 2);
+		assert!(matches!(err.fault, Fault::HashMismatch { .. }));
+
+		let mut proofs = chain(4);
+		proofs.remove(1);
+		assert_eq!(
+			verify_chain(&proofs).unwrap_err().fault,
+			Fault::PreviousHashMismatch { expected: proofs[0].proof_hash }
+		);
+	}
+
+	#[test]
+	fn decodes_json_and_scale_bundles() {
+		let proofs = chain(3);
+
+		let json = serde_json::to_vec(&proofs).unwrap();
+		assert_eq!(decode_bundle(&json, Format::Json).unwrap(), proofs);
+
+		let scale = proofs.encode();
+		assert_eq!(decode_bundle(&scale, Format::Scale).unwrap(), proofs);
+		assert!(decode_bundle(&scale[1..], Format::Scale).is_err());
+	}
+}
@@ -20,4 +20,5 @@ docker run -d \
   --validator \
   --alice \
   --unsafe-force-node-key-generation \
+  --enable-offchain-indexing true \
   --force-authoring
\ No newline at end of file
@@ -42,8 +42,9 @@ use sp_version::RuntimeVersion;
 
 // Local module imports
 use super::{
-	AccountId, Aura, Balance, Block, Executive, Grandpa, InherentDataExt, Nonce, Runtime,
-	RuntimeCall, RuntimeGenesisConfig, SessionKeys, System, TransactionPayment, VERSION,
+	AccountId, Aura, Balance, Block, BlockNumber, Executive, Grandpa, Hash, InherentDataExt,
+	MedicalRecord, Nonce, PoH, Runtime, RuntimeCall, RuntimeGenesisConfig, SessionKeys, System,
+	TransactionPayment, VERSION,
 };
 
 impl_runtime_apis! {
@@ -220,6 +221,97 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(2)]
+	impl pallet_medical_record_runtime_api::MedicalRecordApi<
+		Block,
+		AccountId,
+		BlockNumber,
+		pallet_medical_record::PatientInfo<Runtime>,
+		pallet_medical_record::ClinicalTest<Runtime>,
+		pallet_medical_record::ChangeHistory<Runtime>,
+	> for Runtime {
+		fn get_all_patients(
+			cursor: Option<u32>,
+			limit: u32,
+		) -> pallet_medical_record::Page<pallet_medical_record::PatientInfo<Runtime>> {
+			MedicalRecord::get_all_patients(cursor, limit)
+		}
+
+		fn get_patient_clinical_tests(patient_id: u32) -> Vec<pallet_medical_record::ClinicalTest<Runtime>> {
+			MedicalRecord::get_patient_clinical_tests(patient_id)
+		}
+
+		fn get_patient_timeline(patient_id: u32) -> Vec<(BlockNumber, Vec<u8>)> {
+			MedicalRecord::get_patient_timeline(patient_id)
+		}
+
+		fn get_record_history(
+			record_type: pallet_medical_record::RecordType,
+			record_id: u32,
+		) -> Vec<pallet_medical_record::ChangeHistory<Runtime>> {
+			MedicalRecord::get_record_history(record_type, record_id)
+		}
+
+		fn get_changes_by_user(
+			user: AccountId,
+			cursor: Option<u32>,
+			limit: u32,
+		) -> pallet_medical_record::Page<pallet_medical_record::ChangeHistory<Runtime>> {
+			MedicalRecord::get_changes_by_user(&user, cursor, limit)
+		}
+
+		fn get_changes_in_range(
+			start_block: BlockNumber,
+			end_block: BlockNumber,
+			cursor: Option<u32>,
+			limit: u32,
+		) -> pallet_medical_record::Page<pallet_medical_record::ChangeHistory<Runtime>> {
+			MedicalRecord::get_changes_in_range(start_block, end_block, cursor, limit)
+		}
+
+		fn get_latest_changes(
+			cursor: Option<u32>,
+			limit: u32,
+		) -> pallet_medical_record::Page<pallet_medical_record::ChangeHistory<Runtime>> {
+			MedicalRecord::get_latest_changes(cursor, limit)
+		}
+
+		fn verify_audit_trail(
+			from: u32,
+			to: Option<u32>,
+		) -> Result<u32, pallet_medical_record::AuditError> {
+			MedicalRecord::verify_audit_trail(from, to)
+		}
+	}
+
+	impl pallet_medical_record_runtime_api::MedicalDocumentsApi<Block, AccountId> for Runtime {
+		fn can_read_document(reader: AccountId, content_hash: pallet_medical_record::ContentHash) -> bool {
+			MedicalRecord::can_read_document(&reader, &content_hash)
+		}
+
+		fn can_write_document(
+			writer: AccountId,
+			content_hash: pallet_medical_record::ContentHash,
+			size: u32,
+		) -> bool {
+			MedicalRecord::can_write_document(&writer, &content_hash, size)
+		}
+	}
+
+	impl pallet_poh_runtime_api::PohApi<Block, Hash> for Runtime {
+		fn inclusion_proof(root: Hash, leaf: Hash) -> Option<pallet_poh::InclusionProof<Hash>> {
+			PoH::inclusion_proof(root, leaf)
+		}
+
+		fn proof_hash_at(index: u64) -> Option<Hash> {
+			PoH::proof_by_index(index)
+		}
+
+		fn verify_range(from: Hash, to: Option<Hash>) -> Result<u32, pallet_poh::ChainError<Hash>> {
+			PoH::do_verify_range(from, to)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -32,4 +32,8 @@ frame_benchmarking::define_benchmarks!(
 	[pallet_sudo, Sudo]
 	[pallet_template, Template]
 	[pallet_medical_record, MedicalRecord]
+	[pallet_medical_appointment, MedicalAppointment]
+	[pallet_poh, PoH]
+	[pallet_ai_prediction, AiPrediction]
+	[pallet_migrations, MultiBlockMigrations]
 );
@@ -26,25 +26,37 @@
 // Substrate and Polkadot dependencies
 use frame_support::{
 	derive_impl, parameter_types,
-	traits::{ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, VariantCountOf},
+	traits::{ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Contains, VariantCountOf},
 	weights::{
 		constants::{RocksDbWeight, WEIGHT_REF_TIME_PER_SECOND},
 		IdentityFee, Weight,
 	},
 };
-use frame_system::limits::{BlockLength, BlockWeights};
+use frame_system::{
+	limits::{BlockLength, BlockWeights},
+	EnsureRoot,
+};
 use pallet_transaction_payment::{ConstFeeMultiplier, FungibleAdapter, Multiplier};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_runtime::{traits::One, Perbill};
+use alloc::vec::Vec;
+use codec::Encode;
+use sp_runtime::{
+	generic,
+	traits::{BlakeTwo256, One, Verify},
+	DispatchError, DispatchResult, MultiAddress, Perbill, Permill, SaturatedConversion,
+};
 use sp_version::RuntimeVersion;
 // use sp_core::Hasher;
 // use crate::opaque::Hash,
 
 // Local module imports
 use super::{
-	AccountId, Aura, Balance, Balances, Block, BlockNumber, Hash, Nonce, PalletInfo, Runtime,
-	RuntimeCall, RuntimeEvent, RuntimeFreezeReason, RuntimeHoldReason, RuntimeOrigin, RuntimeTask,
-	System, EXISTENTIAL_DEPOSIT, SLOT_DURATION, VERSION,
+	AccountId, Aura, Balance, Balances, Block, BlockNumber, Hash, MedicalAppointment, MedicalRecord,
+	MultiBlockMigrations,
+	Nonce, PalletInfo,
+	PoH, Runtime, RuntimeCall, RuntimeEvent, RuntimeFreezeReason, RuntimeHoldReason, RuntimeOrigin,
+	RuntimeTask, Signature, SignedPayload, System, TxExtension, UncheckedExtrinsic,
+	EXISTENTIAL_DEPOSIT, MILLI_UNIT, SLOT_DURATION, VERSION,
 };
 
 // @note - Modified for measurements
@@ -65,6 +77,21 @@ parameter_types! {
 	pub const SS58Prefix: u8 = 42;
 }
 
+/// Rejects the query-only calls that have been replaced by view functions. They stay in the
+/// pallets so call indices do not shift, but can no longer be dispatched.
+pub struct BaseCallFilter;
+impl Contains<RuntimeCall> for BaseCallFilter {
+	fn contains(call: &RuntimeCall) -> bool {
+		!matches!(
+			call,
+			RuntimeCall::MedicalRecord(
+				pallet_medical_record::Call::search_patient_by_name { .. } |
+					pallet_medical_record::Call::get_patient_complete_history { .. }
+			) | RuntimeCall::PoH(pallet_poh::Call::get_proof_by_data { .. })
+		)
+	}
+}
+
 /// The default types are being injected by [`derive_impl`](`frame_support::derive_impl`) from
 /// [`SoloChainDefaultConfig`](`struct@frame_system::config_preludes::SolochainDefaultConfig`),
 /// but overridden as needed.
@@ -72,6 +99,8 @@ parameter_types! {
 impl frame_system::Config for Runtime {
 	/// The block type for the runtime.
 	type Block = Block;
+	/// The basic call filter to use in dispatchable.
+	type BaseCallFilter = BaseCallFilter;
 	/// Block & extrinsics weights: base values and limits.
 	type BlockWeights = RuntimeBlockWeights;
 	/// The maximum length of a block (in bytes).
@@ -93,6 +122,8 @@ impl frame_system::Config for Runtime {
 	/// This is used as an identifier of the chain. 42 is the generic substrate prefix.
 	type SS58Prefix = SS58Prefix;
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	/// Storage migrations run over several blocks, blocking transactions until they are done.
+	type MultiBlockMigrator = MultiBlockMigrations;
 }
 
 impl pallet_aura::Config for Runtime {
@@ -156,6 +187,36 @@ impl pallet_transaction_payment::Config for Runtime {
 	type WeightInfo = pallet_transaction_payment::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+	/// Migrations may use up to a tenth of the maximum block weight.
+	pub MbmServiceWeight: Weight = Perbill::from_percent(10) * RuntimeBlockWeights::get().max_block;
+}
+
+impl pallet_migrations::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	/// The storage migrations of the runtime's pallets, in the order they run.
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	type Migrations = (
+		pallet_medical_record::migrations::v1::MigrateV0ToV1<Runtime>,
+		pallet_medical_record::migrations::v2::MigrateV1ToV2<Runtime>,
+		pallet_medical_record::migrations::v3::MigrateV2ToV3<Runtime>,
+		pallet_medical_record::migrations::v4::MigrateV3ToV4<Runtime>,
+		pallet_medical_record::migrations::v5::MigrateV4ToV5<Runtime>,
+		pallet_medical_record::migrations::v6::MigrateV5ToV6<Runtime>,
+		pallet_medical_record::migrations::v7::MigrateV6ToV7<Runtime>,
+		pallet_medical_record::migrations::v8::MigrateV7ToV8<Runtime>,
+	);
+	// Benchmarks need mocked migrations to guarantee that they succeed.
+	#[cfg(feature = "runtime-benchmarks")]
+	type Migrations = pallet_migrations::mock_helpers::MockedMigrations;
+	type CursorMaxLen = ConstU32<65_536>;
+	type IdentifierMaxLen = ConstU32<256>;
+	type MigrationStatusHandler = ();
+	type FailedMigrationHandler = frame_support::migrations::FreezeChainOnFailedMigration;
+	type MaxServiceWeight = MbmServiceWeight;
+	type WeightInfo = pallet_migrations::weights::SubstrateWeight<Runtime>;
+}
+
 impl pallet_sudo::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type RuntimeCall = RuntimeCall;
@@ -172,22 +233,280 @@ impl pallet_template::Config for Runtime {
 impl pallet_medical_record::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = pallet_medical_record::weights::SubstrateWeight<Runtime>;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type DataProtectionOrigin = EnsureRoot<AccountId>;
+	type ProofAnchor = PoH;
+	type Appointments = MedicalAppointments;
+	type MaxNameLength = ConstU32<128>;
+	type MaxDateLength = ConstU32<96>;
+	type MaxGenderLength = ConstU32<16>;
+	type MaxAddressLength = ConstU32<256>;
+	type MaxPhoneLength = ConstU32<96>;
+	type MaxEmergencyContactLength = ConstU32<256>;
+	type MaxTestTypeLength = ConstU32<128>;
+	type MaxTestResultLength = ConstU32<1024>;
+	type MaxNotesLength = ConstU32<1024>;
+	type MaxClinicalTextLength = ConstU32<1024>;
+	type MaxDataPointerLength = ConstU32<256>;
+	type MaxAttachmentsPerRecord = ConstU32<16>;
+	type MaxDocumentSize = ConstU32<{ 4 * 1024 * 1024 }>;
+	type MaxFieldNameLength = ConstU32<32>;
+	type MaxChangeValueLength = ConstU32<1024>;
+	type MaxPatientsPerName = ConstU32<16>;
+	type MaxPageSize = ConstU32<1_000>;
+	type MaxReindexBatchSize = ConstU32<500>;
+	type MaxRecordsPerPatient = ConstU32<1_000>;
+	type MaxPatientsPerDoctor = ConstU32<1_000>;
+	type MaxDoctorsPerPatient = ConstU32<64>;
+	type MaxProofsPerRecord = ConstU32<256>;
+	type MaxAuditRange = ConstU32<10_000>;
+	type EncryptedPii = ConstBool<true>;
+	type MaxDataKeyHolders = ConstU32<32>;
+	type MaxPendingErasures = ConstU32<32>;
+	type MaxPendingDeletions = ConstU32<32>;
+	type MaxRemovalStepsPerBlock = ConstU32<50>;
 	// / The identifier used to distinguish between accounts.
 	// type AccountId = AccountId;
 }
 
+/// Lets pallet-medical-record book the follow-up visits of disease progressions in
+/// pallet-medical-appointment. Follow-ups are booked by the doctor for the patient's account, so
+/// no deposit is held, and only that doctor can move or cancel them.
+pub struct MedicalAppointments;
+
+impl pallet_medical_record::AppointmentScheduler<AccountId> for MedicalAppointments {
+	fn schedule(
+		patient: &AccountId,
+		doctor: &AccountId,
+		scheduled_time: u64,
+	) -> Result<u32, DispatchError> {
+		MedicalAppointment::do_create_appointment(doctor, patient.clone(), doctor.clone(), scheduled_time)
+	}
+
+	fn reschedule(who: &AccountId, appointment_id: u32, scheduled_time: u64) -> DispatchResult {
+		MedicalAppointment::do_reschedule(who, appointment_id, scheduled_time)
+	}
+
+	fn cancel(who: &AccountId, appointment_id: u32) -> DispatchResult {
+		MedicalAppointment::do_cancel(who, appointment_id, Default::default())
+	}
+
+	fn upcoming(
+		appointment_id: u32,
+	) -> Option<pallet_medical_record::ScheduledAppointment<AccountId>> {
+		MedicalAppointment::appointments(appointment_id)
+			.filter(|appointment| appointment.status().is_open())
+			.map(|appointment| pallet_medical_record::ScheduledAppointment {
+				appointment_id,
+				doctor_id: appointment.doctor_id().clone(),
+				scheduled_time: appointment.scheduled_time(),
+			})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_slot(doctor: &AccountId) -> u64 {
+		use frame_support::traits::UnixTime;
+		use pallet_medical_appointment::{AvailabilityWindow, SECONDS_PER_DAY};
+
+		// Hour slots all day, every day
+		let windows: Vec<_> = (0..7)
+			.map(|weekday| AvailabilityWindow { weekday, start: 0, end: SECONDS_PER_DAY as u32 })
+			.collect();
+		MedicalAppointment::set_availability(
+			RuntimeOrigin::signed(doctor.clone()),
+			windows.try_into().expect("a week fits MaxAvailabilityWindows"),
+			3_600,
+		)
+		.expect("the availability is valid");
+
+		// Midnight tomorrow
+		let today = pallet_timestamp::Pallet::<Runtime>::now().as_secs() / SECONDS_PER_DAY;
+		(today + 1) * SECONDS_PER_DAY
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_escrow(appointment_id: u32) {
+		use frame_support::traits::{
+			fungible::{Inspect, Mutate, MutateHold},
+			Get,
+		};
+		use pallet_medical_appointment::{Escrow, Escrows, HoldReason};
+
+		let Some(appointment) = MedicalAppointment::appointments(appointment_id) else {
+			return;
+		};
+		// The booking deposit and a consultation fee of the same amount
+		let deposit = <Runtime as pallet_medical_appointment::Config>::BookingDeposit::get();
+		let fee = deposit;
+		let payer = appointment.patient_id().clone();
+		Balances::set_balance(&payer, Balances::minimum_balance() + deposit + fee);
+		Balances::hold(&HoldReason::AppointmentDeposit.into(), &payer, deposit + fee)
+			.expect("the patient was just funded");
+		Escrows::<Runtime>::insert(appointment_id, Escrow { payer, deposit, fee });
+	}
+}
+
+parameter_types! {
+	pub const LateCancellationPenalty: Perbill = Perbill::from_percent(50);
+}
+
+/// Accounts holding the doctor role of pallet-medical-record, the only ones that may book
+/// appointments on behalf of a patient.
+pub struct RegisteredDoctors;
+impl Contains<AccountId> for RegisteredDoctors {
+	fn contains(who: &AccountId) -> bool {
+		MedicalRecord::has_role(who, pallet_medical_record::Role::Doctor)
+	}
+}
+
 /// Configure the pallet-medical-appointment in pallets/medical-appointment.
 impl pallet_medical_appointment::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = pallet_medical_appointment::weights::SubstrateWeight<Runtime>;
 	type TimeProvider = pallet_timestamp::Pallet<Runtime>;
 	type RuntimeAppointmentStatus = pallet_medical_appointment::pallet::AppointmentStatus;
+	type MaxReasonLength = ConstU32<256>;
+	type MaxAvailabilityWindows = ConstU32<28>;
+	type MaxUnavailablePeriods = ConstU32<32>;
+	type MaxBookingsPerDoctor = ConstU32<256>;
+	type ExpectedBlockTime = ConstU64<SLOT_DURATION>;
+	// A day before the appointment
+	type ReminderLeadTime = ConstU64<86_400>;
+	type NoShowGracePeriod = ConstU64<1_800>;
+	type MaxTasksPerBlock = ConstU32<64>;
+	type Currency = Balances;
+	type RuntimeHoldReason = RuntimeHoldReason;
+	type BookingDeposit = ConstU128<{ 100 * MILLI_UNIT }>;
+	type LateCancellationPeriod = ConstU64<86_400>;
+	type LateCancellationPenalty = LateCancellationPenalty;
+	type Doctors = RegisteredDoctors;
+}
+
+/// Configure the pallet-poh in pallets/poh.
+impl pallet_poh::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Hash = Hash;
+	type Hasher = BlakeTwo256;
+	type Time = pallet_timestamp::Pallet<Runtime>;
+	type WeightInfo = pallet_poh::weights::SubstrateWeight<Runtime>;
+	type MaxBatchSize = ConstU32<4_096>;
+	type MaxRangeLength = ConstU32<1_000>;
+}
+
+/// Lets pallet-ai-prediction look up patients in pallet-medical-record. A prediction can be
+/// requested by the patient, their guardian, or anyone the patient has given read consent on
+/// their clinical tests, disease progressions or medical records.
+pub struct MedicalRecordPatients;
+
+impl pallet_ai_prediction::PatientRecords<AccountId> for MedicalRecordPatients {
+	fn patient_exists(patient_id: u32) -> bool {
+		pallet_medical_record::Patients::<Runtime>::contains_key(patient_id)
+	}
+
+	// The complete history is read without checking consent; `can_request_prediction` decides
+	// who may have it run through the model.
+	fn record_payload(patient_id: u32) -> Option<Vec<u8>> {
+		MedicalRecord::patient_complete_history(patient_id).map(|history| history.encode())
+	}
+
+	fn can_request_prediction(patient_id: u32, who: &AccountId) -> bool {
+		use pallet_medical_record::{AccessKind, RecordType};
+
+		MedicalRecord::is_patient_or_guardian(patient_id, who) ||
+			[RecordType::ClinicalTest, RecordType::DiseaseProgression, RecordType::MedicalRecord]
+				.into_iter()
+				.any(|record_type| {
+					MedicalRecord::has_consent(patient_id, who, record_type, AccessKind::Read)
+				})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn register_patient(patient_id: u32, who: &AccountId) {
+		let block_number = System::block_number();
+		pallet_medical_record::Patients::<Runtime>::insert(
+			patient_id,
+			pallet_medical_record::PatientInfo {
+				patient_id,
+				patient_name: Default::default(),
+				date_of_birth: Default::default(),
+				gender: Default::default(),
+				address: Default::default(),
+				phone: Default::default(),
+				emergency_contact: Default::default(),
+				created_at: block_number,
+				created_by: who.clone(),
+				last_modified_at: block_number,
+				last_modified_by: who.clone(),
+			},
+		);
+		pallet_medical_record::PatientAccounts::<Runtime>::insert(patient_id, who);
+	}
 }
 
-// impl pallet_poh::Config for Runtime {
-// 	type RuntimeEvent = RuntimeEvent;
-// 	type Hash = Hash;
-// 	type Hasher = Hasher;
-// 	type Time = Time;
-// 	type WeightInfo = pallet_poh::weights::SubstrateWeight<Runtime>;
-// }
\ No newline at end of file
+/// Configure the pallet-ai-prediction in pallets/ai-prediction.
+parameter_types! {
+	pub const AiConfidenceTolerance: Permill = Permill::from_percent(2);
+}
+
+impl pallet_ai_prediction::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = pallet_ai_prediction::weights::SubstrateWeight<Runtime>;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type PatientRecords = MedicalRecordPatients;
+	type AuthorityId = pallet_ai_prediction::crypto::AiWorkerAuthId;
+	type MaxPredictionsPerBlock = ConstU32<8>;
+	type MaxWorkers = ConstU32<16>;
+	type QuorumThreshold = ConstU32<3>;
+	type ConfidenceTolerance = AiConfidenceTolerance;
+}
+
+impl frame_system::offchain::SigningTypes for Runtime {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::CreateTransactionBase<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type RuntimeCall = RuntimeCall;
+}
+
+/// Lets offchain workers, such as the AI prediction workers, sign and submit transactions with
+/// keys from the node's keystore.
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
+where
+	RuntimeCall: From<LocalCall>,
+{
+	fn create_signed_transaction<
+		C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>,
+	>(
+		call: RuntimeCall,
+		public: <Signature as Verify>::Signer,
+		account: AccountId,
+		nonce: Nonce,
+	) -> Option<UncheckedExtrinsic> {
+		let period =
+			BlockHashCount::get().checked_next_power_of_two().map(|c| c / 2).unwrap_or(2) as u64;
+		let current_block = System::block_number().saturated_into::<u64>().saturating_sub(1);
+		let tx_ext: TxExtension = (
+			frame_system::CheckNonZeroSender::<Runtime>::new(),
+			frame_system::CheckSpecVersion::<Runtime>::new(),
+			frame_system::CheckTxVersion::<Runtime>::new(),
+			frame_system::CheckGenesis::<Runtime>::new(),
+			frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
+			frame_system::CheckNonce::<Runtime>::from(nonce),
+			frame_system::CheckWeight::<Runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+			frame_metadata_hash_extension::CheckMetadataHash::<Runtime>::new(false),
+			frame_system::WeightReclaim::<Runtime>::new(),
+		);
+
+		let raw_payload = SignedPayload::new(call, tx_ext).ok()?;
+		let signature = raw_payload.using_encoded(|payload| C::sign(payload, public))?;
+		let (call, tx_ext, _) = raw_payload.deconstruct();
+
+		Some(UncheckedExtrinsic::new_signed(call, MultiAddress::Id(account), signature, tx_ext))
+	}
+}
\ No newline at end of file
@@ -69,7 +69,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: apis::RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -169,7 +169,8 @@ pub type SignedPayload = generic::SignedPayload<RuntimeCall, TxExtension>;
 
 /// All migrations of the runtime, aside from the ones declared in the pallets.
 ///
-/// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
+/// This can be a tuple of types, each implementing `OnRuntimeUpgrade`. Storage migrations of
+/// the pallets run over several blocks through `MultiBlockMigrations` instead.
 #[allow(unused_parens)]
 type Migrations = ();
 
@@ -232,7 +233,12 @@ mod runtime {
 	#[runtime::pallet_index(9)]
 	pub type MedicalAppointment = pallet_medical_appointment;
 
-	// #[runtime::pallet_index(8)]
-	// pub type PoH = pallet_poh;
+	#[runtime::pallet_index(10)]
+	pub type PoH = pallet_poh;
 
+	#[runtime::pallet_index(11)]
+	pub type AiPrediction = pallet_ai_prediction;
+
+	#[runtime::pallet_index(12)]
+	pub type MultiBlockMigrations = pallet_migrations;
 }
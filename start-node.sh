@@ -1,2 +1,2 @@
 cargo run --release -- purge-chain --database=paritydb --dev -d ./tmp/my-dev -y
-cargo run --release -- --dev -d ./tmp/my-dev --database=paritydb --pool-limit=1000000 --pool-kbytes=1000000 --tx-ban-seconds=0
\ No newline at end of file
+cargo run --release -- --dev -d ./tmp/my-dev --database=paritydb --pool-limit=1000000 --pool-kbytes=1000000 --tx-ban-seconds=0 --enable-offchain-indexing true
\ No newline at end of file